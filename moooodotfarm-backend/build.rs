@@ -1,7 +1,59 @@
+use base64::Engine;
+use sha2::{Digest, Sha384};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+const STATIC_DIR: &str = "src/ports/http/static";
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::configure()
         .build_server(true)
         .build_client(true)
         .compile_protos(&["proto/moooodotfarm.proto"], &["proto"])?;
+    generate_static_integrity_manifest()?;
+    Ok(())
+}
+
+fn generate_static_integrity_manifest() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed={STATIC_DIR}");
+
+    let mut files = vec![];
+    collect_files(Path::new(STATIC_DIR), &mut files)?;
+    files.sort();
+
+    let mut manifest = String::from("pub static STATIC_INTEGRITY: &[(&str, &str, &str)] = &[\n");
+    for file in files {
+        let relative_path = file
+            .strip_prefix(STATIC_DIR)?
+            .to_str()
+            .ok_or("static asset path is not valid utf-8")?
+            .replace('\\', "/");
+        let digest = Sha384::digest(std::fs::read(&file)?);
+        let integrity = format!(
+            "sha384-{}",
+            base64::engine::general_purpose::STANDARD.encode(digest)
+        );
+        let version: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+        writeln!(
+            manifest,
+            "    ({relative_path:?}, {integrity:?}, {version:?}),"
+        )?;
+    }
+    manifest.push_str("];\n");
+
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+    std::fs::write(out_dir.join("static_integrity.rs"), manifest)?;
+    Ok(())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
     Ok(())
 }
@@ -1,27 +1,228 @@
 use crate::domain::time::DateTime;
-use crate::errors::Result;
+use crate::errors::{Error, Result};
 use crate::{app, domain};
 use anyhow::{Context, anyhow};
 use redb;
 use redb::{ReadableDatabase, ReadableTable};
 use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 const COW_STATUS_TABLE: redb::TableDefinition<String, String> =
     redb::TableDefinition::new("cow_status");
+const METADATA_TABLE: redb::TableDefinition<String, String> =
+    redb::TableDefinition::new("metadata");
+const COW_BODY_TABLE: redb::TableDefinition<String, String> =
+    redb::TableDefinition::new("cow_body");
+// one row per change keyed by the cow and the position of the change in its history so that
+// recording a change doesn't rewrite the earlier ones
+const CHARACTER_AUDIT_TABLE: redb::TableDefinition<(String, u64), String> =
+    redb::TableDefinition::new("character_changes");
+
+const LAST_UPDATE_STARTED_KEY: &str = "last_update_started";
+const LAST_UPDATE_FINISHED_KEY: &str = "last_update_finished";
+const LAST_UPDATE_RESULT_KEY: &str = "last_update_result";
 
 #[derive(Clone)]
 pub struct Database {
     db: Arc<Mutex<redb::Database>>,
+    path: Option<String>,
 }
 
 impl Database {
     pub fn new(path: impl Into<String>) -> Result<Self> {
-        let db = redb::Database::create(path.into()).context("Failed to open database")?;
+        let path = path.into();
+        let db = redb::Database::create(&path).context("Failed to open database")?;
+        Ok(Self {
+            db: Arc::new(Mutex::new(db)),
+            path: Some(path),
+        })
+    }
+
+    pub fn open_copy(source_path: impl AsRef<Path>, dest_path: impl Into<String>) -> Result<Self> {
+        let dest_path = dest_path.into();
+        std::fs::copy(source_path, &dest_path).context("Failed to copy database")?;
+        Self::new(dest_path)
+    }
+
+    pub fn new_in_memory() -> Result<Self> {
+        let db = redb::Database::builder()
+            .create_with_backend(redb::backends::InMemoryBackend::new())
+            .context("Failed to create in-memory database")?;
         Ok(Self {
             db: Arc::new(Mutex::new(db)),
+            path: None,
         })
     }
+
+    // returns the number of bytes by which the database file shrunk
+    pub fn compact(&self) -> Result<u64> {
+        let mut db = self.db.lock().unwrap();
+
+        let size_before = self.file_size()?;
+        db.compact().context("Failed to compact database")?;
+        let size_after = self.file_size()?;
+        Ok(size_before.saturating_sub(size_after))
+    }
+
+    // rewrites all cows in the current format, returns the number of cows which had to be changed
+    pub fn migrate(&self) -> Result<usize> {
+        let db = self.db.lock().unwrap();
+
+        let write_txn = db.begin_write()?;
+        let mut migrated = 0;
+        {
+            let mut table = write_txn.open_table(COW_STATUS_TABLE)?;
+
+            let mut rows = vec![];
+            for row in table.iter()? {
+                let (key, value) = row?;
+                rows.push((key.value(), value.value()));
+            }
+
+            for (key, value) in rows {
+                let persisted: PersistedCow = serde_json::from_str(&value)?;
+                let cow: domain::Cow = persisted.try_into()?;
+                let j = serde_json::to_string(&PersistedCow::from(cow))?;
+                if j != value {
+                    table.insert(key, j)?;
+                    migrated += 1;
+                }
+            }
+        }
+        write_txn.commit()?;
+        Ok(migrated)
+    }
+
+    // writes one persisted cow per line, returns the number of exported cows
+    pub fn export_jsonl<W: Write>(&self, mut writer: W) -> Result<usize> {
+        let db = self.db.lock().unwrap();
+        let read_txn = db.begin_read()?;
+        let mut exported = 0;
+        match read_txn.open_table(COW_STATUS_TABLE) {
+            Ok(table) => {
+                for row in table.iter()? {
+                    let (_key, value) = row?;
+                    writeln!(writer, "{}", value.value())?;
+                    exported += 1;
+                }
+            }
+            Err(e) => match e {
+                redb::TableError::TableDoesNotExist(_a) => {}
+                other => return Err(other.into()),
+            },
+        }
+        writer.flush()?;
+        Ok(exported)
+    }
+
+    // reads one persisted cow per line and writes the cows as they are read, all of them in one
+    // transaction so that either all cows are imported or none of them are
+    pub fn import_jsonl<R: BufRead>(&self, reader: R) -> Result<usize> {
+        let db = self.db.lock().unwrap();
+
+        // returning early drops the transaction without committing it which aborts the import
+        let write_txn = db.begin_write()?;
+        let mut imported = 0;
+        {
+            let mut table = write_txn.open_table(COW_STATUS_TABLE)?;
+            let mut audit = write_txn.open_table(CHARACTER_AUDIT_TABLE)?;
+            for (i, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let cow = serde_json::from_str::<PersistedCow>(&line)
+                    .map_err(Error::from)
+                    .and_then(domain::Cow::try_from)
+                    .map_err(|err| {
+                        Error::ValidationError(anyhow!(
+                            "no cows were imported as line {} is invalid: {}",
+                            i + 1,
+                            err
+                        ))
+                    })?;
+                let key = cow.name().url().to_string();
+                let cow_before = read_cow(&table, &key)?;
+                write_cow(&mut table, &mut audit, &key, cow_before.as_ref(), cow)?;
+                imported += 1;
+            }
+        }
+        write_txn.commit()?;
+        Ok(imported)
+    }
+
+    fn file_size(&self) -> Result<u64> {
+        match &self.path {
+            Some(path) => Ok(std::fs::metadata(path)?.len()),
+            None => Ok(0),
+        }
+    }
+}
+
+impl Database {
+    fn put_metadata(&self, values: &[(&str, String)]) -> Result<()> {
+        let db = self.db.lock().unwrap();
+
+        let write_txn = db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(METADATA_TABLE)?;
+            for (key, value) in values {
+                table.insert(key.to_string(), value.clone())?;
+            }
+        }
+        Ok(write_txn.commit()?)
+    }
+
+    fn get_metadata(&self, key: &str) -> Result<Option<String>> {
+        let db = self.db.lock().unwrap();
+
+        let read_txn = db.begin_read()?;
+        match read_txn.open_table(METADATA_TABLE) {
+            Ok(table) => Ok(table.get(key.to_string())?.map(|v| v.value())),
+            Err(e) => match e {
+                redb::TableError::TableDoesNotExist(_a) => Ok(None),
+                other => Err(other.into()),
+            },
+        }
+    }
+}
+
+impl app::UpdateLog for Database {
+    fn record_update_started(&self, at: &DateTime) -> Result<()> {
+        self.put_metadata(&[(LAST_UPDATE_STARTED_KEY, at.into())])
+    }
+
+    fn record_update_finished(&self, at: &DateTime, result: app::UpdateResult) -> Result<()> {
+        self.put_metadata(&[
+            (LAST_UPDATE_FINISHED_KEY, at.into()),
+            (LAST_UPDATE_RESULT_KEY, (&result).into()),
+        ])
+    }
+
+    fn last_update(&self) -> Result<app::LastUpdate> {
+        Ok(app::LastUpdate::new(
+            match self.get_metadata(LAST_UPDATE_STARTED_KEY)? {
+                Some(dt_str) => Some(dt_str.try_into()?),
+                None => None,
+            },
+            match self.get_metadata(LAST_UPDATE_FINISHED_KEY)? {
+                Some(dt_str) => Some(dt_str.try_into()?),
+                None => None,
+            },
+            match self.get_metadata(LAST_UPDATE_RESULT_KEY)? {
+                Some(result) => Some(result.try_into()?),
+                None => None,
+            },
+        ))
+    }
+}
+
+impl app::DatabaseCompactor for Database {
+    fn compact(&self) -> Result<u64> {
+        Database::compact(self)
+    }
 }
 
 impl app::Inventory for Database {
@@ -70,28 +271,40 @@ impl app::Inventory for Database {
     fn update<F>(&self, name: &domain::VisibleName, f: F) -> Result<()>
     where
         F: FnOnce(Option<domain::Cow>) -> Result<Option<domain::Cow>>,
+    {
+        let mut f = Some(f);
+        self.update_many(std::slice::from_ref(name), |_, cow| match f.take() {
+            Some(f) => f(cow),
+            None => Err(anyhow!("update function called more than once").into()),
+        })
+    }
+
+    fn update_many<F>(&self, names: &[domain::VisibleName], mut f: F) -> Result<()>
+    where
+        F: FnMut(&domain::VisibleName, Option<domain::Cow>) -> Result<Option<domain::Cow>>,
     {
         let db = self.db.lock().unwrap();
 
+        // returning early drops the transaction without committing it which aborts the whole batch
         let write_txn = db.begin_write()?;
         {
             let mut table = write_txn.open_table(COW_STATUS_TABLE)?;
-            let key = name.url().to_string();
+            let mut audit = write_txn.open_table(CHARACTER_AUDIT_TABLE)?;
 
-            let cow_status: Option<domain::Cow> = match table.get(&key)? {
-                Some(v) => {
-                    let persisted: PersistedCow = serde_json::from_str(&v.value())?;
-                    Some(persisted.try_into()?)
-                }
-                None => None,
-            };
-
-            let cow_to_save = f(cow_status)?;
+            for name in names {
+                let key = name.url().to_string();
 
-            if let Some(cow_to_save) = cow_to_save {
-                let persisted: PersistedCow = cow_to_save.into();
-                let j = serde_json::to_string(&persisted)?;
-                table.insert(key, j)?;
+                let cow_status = read_cow(&table, &key)?;
+                let cow_before = cow_status.clone();
+                if let Some(cow_to_save) = f(name, cow_status)? {
+                    write_cow(
+                        &mut table,
+                        &mut audit,
+                        &key,
+                        cow_before.as_ref(),
+                        cow_to_save,
+                    )?;
+                }
             }
         }
         Ok(write_txn.commit()?)
@@ -108,20 +321,187 @@ impl app::Inventory for Database {
             match table.remove(&key)? {
                 Some(_) => {}
                 None => {
-                    return Err(anyhow!("cow does not exist").into());
+                    return Err(Error::NotFound("cow does not exist".to_string()));
                 }
             }
+
+            let mut bodies = write_txn.open_table(COW_BODY_TABLE)?;
+            bodies.remove(&key)?;
+
+            let mut audit = write_txn.open_table(CHARACTER_AUDIT_TABLE)?;
+            audit.retain_in(audit_range(&key), |_, _| false)?;
         }
         Ok(write_txn.commit()?)
     }
 }
+
+impl app::CowBodies for Database {
+    fn put(&self, name: &domain::VisibleName, body: &str) -> Result<()> {
+        let db = self.db.lock().unwrap();
+
+        let write_txn = db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(COW_BODY_TABLE)?;
+            table.insert(name.url().to_string(), body.to_string())?;
+        }
+        Ok(write_txn.commit()?)
+    }
+
+    fn get(&self, name: &domain::VisibleName) -> Result<Option<String>> {
+        let db = self.db.lock().unwrap();
+
+        let read_txn = db.begin_read()?;
+        match read_txn.open_table(COW_BODY_TABLE) {
+            Ok(table) => Ok(table.get(name.url().to_string())?.map(|v| v.value())),
+            Err(e) => match e {
+                redb::TableError::TableDoesNotExist(_a) => Ok(None),
+                other => Err(other.into()),
+            },
+        }
+    }
+}
+impl app::CharacterAudit for Database {
+    fn character_changes(
+        &self,
+        name: &domain::VisibleName,
+    ) -> Result<Vec<domain::CharacterChange>> {
+        let db = self.db.lock().unwrap();
+
+        let read_txn = db.begin_read()?;
+        match read_txn.open_table(CHARACTER_AUDIT_TABLE) {
+            Ok(table) => {
+                let mut changes = vec![];
+                for row in table.range(audit_range(name.url().as_str()))? {
+                    let (_key, value) = row?;
+                    let change: PersistedCharacterChange = serde_json::from_str(&value.value())?;
+                    changes.push(change.try_into()?);
+                }
+                Ok(changes)
+            }
+            Err(e) => match e {
+                redb::TableError::TableDoesNotExist(_a) => Ok(vec![]),
+                other => Err(other.into()),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedCharacterChange {
+    old_character: String,
+    new_character: String,
+    changed_at: String,
+}
+
+impl From<&domain::CharacterChange> for PersistedCharacterChange {
+    fn from(value: &domain::CharacterChange) -> Self {
+        Self {
+            old_character: value.old_character().into(),
+            new_character: value.new_character().into(),
+            changed_at: value.changed_at().into(),
+        }
+    }
+}
+
+impl TryFrom<PersistedCharacterChange> for domain::CharacterChange {
+    type Error = crate::errors::Error;
+
+    fn try_from(value: PersistedCharacterChange) -> std::result::Result<Self, Self::Error> {
+        Ok(domain::CharacterChange::new(
+            value.old_character.try_into()?,
+            value.new_character.try_into()?,
+            value.changed_at.try_into()?,
+        ))
+    }
+}
+
+fn read_cow(table: &redb::Table<String, String>, key: &String) -> Result<Option<domain::Cow>> {
+    match table.get(key)? {
+        Some(v) => {
+            let persisted: PersistedCow = serde_json::from_str(&v.value())?;
+            Ok(Some(persisted.try_into()?))
+        }
+        None => Ok(None),
+    }
+}
+
+// a change of the character is recorded together with the cow so that the audit can't miss it
+fn write_cow(
+    table: &mut redb::Table<String, String>,
+    audit: &mut redb::Table<(String, u64), String>,
+    key: &str,
+    cow_before: Option<&domain::Cow>,
+    cow: domain::Cow,
+) -> Result<()> {
+    if let Some(change) = domain::CharacterChange::new_from_update(cow_before, &cow) {
+        let position = match audit.range(audit_range(key))?.next_back() {
+            Some(row) => row?.0.value().1 + 1,
+            None => 0,
+        };
+        let persisted: PersistedCharacterChange = (&change).into();
+        audit.insert(
+            (key.to_string(), position),
+            serde_json::to_string(&persisted)?,
+        )?;
+    }
+
+    let persisted: PersistedCow = cow.into();
+    table.insert(key.to_string(), serde_json::to_string(&persisted)?)?;
+    Ok(())
+}
+
+fn audit_range(key: &str) -> std::ops::RangeInclusive<(String, u64)> {
+    (key.to_string(), 0)..=(key.to_string(), u64::MAX)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PersistedCow {
     name: String,
     character: String,
+    // cows persisted before expectations were introduced aren't expected to have any character
+    #[serde(default)]
+    expected_character: Option<String>,
+    // cows persisted before nicknames were introduced don't have one
+    #[serde(default)]
+    nickname: Option<String>,
+    // same goes for tags, those cows simply end up with none
+    #[serde(default)]
+    tags: Vec<String>,
+    // and notes, an empty string is the same as having none
+    #[serde(default)]
+    notes: String,
+    // cows persisted before this was tracked are treated as added when they were first seen, or
+    // when they are loaded if they were never seen, migrate makes that stick
+    #[serde(default)]
+    added_at: Option<String>,
     first_seen: Option<String>,
     last_seen: Option<String>,
     last_checked: Option<String>,
+    // cows persisted before bodies were hashed get a hash during their next successful check
+    #[serde(default)]
+    body_hash: Option<String>,
+    // cows persisted before failed checks were counted get a streak based on their last check
+    #[serde(default)]
+    failure_streak: Option<u32>,
+    // cows persisted before flapping was tracked start out stable
+    #[serde(default)]
+    flaps: u32,
+    #[serde(default)]
+    last_flapped_at: Option<String>,
+    #[serde(default)]
+    redirected_to: Option<String>,
+    // only read to migrate cows persisted before the missing reason was tracked
+    #[serde(default, skip_serializing)]
+    domain_not_found: bool,
+    #[serde(default)]
+    missing_reason: Option<String>,
+    // cows persisted before pausing was introduced are not paused
+    #[serde(default)]
+    paused: bool,
+    // cows persisted before this was tracked are taken over at startup if they are configured, see
+    // reload_config::adopt_configured_cows
+    #[serde(default)]
+    managed_by_config: bool,
 }
 
 impl From<domain::Cow> for PersistedCow {
@@ -129,9 +509,27 @@ impl From<domain::Cow> for PersistedCow {
         PersistedCow {
             name: value.name().into(),
             character: value.character().into(),
+            expected_character: value.expected_character().map(|v| v.into()),
+            nickname: value.nickname().map(|v| v.as_str().to_string()),
+            tags: value
+                .tags()
+                .iter()
+                .map(|v| v.as_str().to_string())
+                .collect(),
+            notes: value.notes().to_string(),
+            added_at: Some(value.added_at().into()),
             first_seen: value.first_seen().map(|dt| dt.into()),
             last_seen: value.last_seen().map(|dt| dt.into()),
             last_checked: value.last_checked().map(|dt| dt.into()),
+            body_hash: value.body_hash().map(|v| v.as_str().to_string()),
+            failure_streak: Some(value.failure_streak()),
+            flaps: value.flaps(),
+            last_flapped_at: value.last_flapped_at().map(|dt| dt.into()),
+            redirected_to: value.redirected_to().map(|v| v.into()),
+            domain_not_found: false,
+            missing_reason: value.missing_reason().as_ref().map(|v| v.into()),
+            paused: value.paused(),
+            managed_by_config: value.managed_by_config(),
         }
     }
 }
@@ -140,7 +538,17 @@ impl TryFrom<PersistedCow> for domain::Cow {
     type Error = crate::errors::Error;
 
     fn try_from(value: PersistedCow) -> std::result::Result<Self, Self::Error> {
-        Ok(domain::Cow::new_from_history(
+        let nickname = match value.nickname {
+            Some(nickname) => Some(domain::Nickname::new(nickname).map_err(corrupted)?),
+            None => None,
+        };
+        let tags = value
+            .tags
+            .into_iter()
+            .map(domain::Tag::new)
+            .collect::<Result<Vec<_>>>()
+            .map_err(corrupted)?;
+        let cow = domain::Cow::new_from_history(
             value.name.try_into()?,
             value.character.try_into()?,
             match value.first_seen {
@@ -155,7 +563,78 @@ impl TryFrom<PersistedCow> for domain::Cow {
                 Some(dt_str) => Some(dt_str.try_into()?),
                 None => None,
             },
-        ))
+        )
+        .with_expected_character(match value.expected_character {
+            Some(character) => Some(character.try_into()?),
+            None => None,
+        })
+        .with_nickname(nickname)
+        .with_tags(tags)
+        .with_notes(value.notes)
+        .with_body_hash(match value.body_hash {
+            Some(body_hash) => Some(domain::BodyHash::new(body_hash).map_err(corrupted)?),
+            None => None,
+        })
+        .with_flaps(
+            value.flaps,
+            match value.last_flapped_at {
+                Some(dt_str) => Some(dt_str.try_into()?),
+                None => None,
+            },
+        )
+        .with_redirected_to(match value.redirected_to {
+            Some(name) => Some(name.try_into()?),
+            None => None,
+        })
+        .with_missing_reason(match value.missing_reason {
+            Some(reason) => Some(reason.try_into()?),
+            None if value.domain_not_found => Some(domain::MissingReason::DnsError),
+            None => None,
+        })
+        .with_paused(value.paused)
+        .with_managed_by_config(value.managed_by_config);
+        let cow = match value.failure_streak {
+            Some(failure_streak) => cow.with_failure_streak(failure_streak),
+            None => cow.with_unrecorded_failure_streak(),
+        };
+        Ok(match value.added_at {
+            Some(dt_str) => cow.with_added_at(dt_str.try_into()?),
+            None => cow,
+        })
+    }
+}
+
+impl From<&domain::MissingReason> for String {
+    fn from(value: &domain::MissingReason) -> Self {
+        match value {
+            domain::MissingReason::HttpStatus(status) => format!("http_status:{status}"),
+            domain::MissingReason::Timeout => "timeout".to_string(),
+            domain::MissingReason::DnsError => "dns_error".to_string(),
+            domain::MissingReason::ValidationFailed => "validation_failed".to_string(),
+            domain::MissingReason::ConnectionRefused => "connection_refused".to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for domain::MissingReason {
+    type Error = crate::errors::Error;
+
+    fn try_from(value: String) -> std::result::Result<domain::MissingReason, Self::Error> {
+        if let Some(status) = value.strip_prefix("http_status:") {
+            return Ok(domain::MissingReason::HttpStatus(status.parse().map_err(
+                |err| Self::Error::StorageError(anyhow!("invalid http status {}: {}", status, err)),
+            )?));
+        }
+        match value.as_str() {
+            "timeout" => Ok(domain::MissingReason::Timeout),
+            "dns_error" => Ok(domain::MissingReason::DnsError),
+            "validation_failed" => Ok(domain::MissingReason::ValidationFailed),
+            "connection_refused" => Ok(domain::MissingReason::ConnectionRefused),
+            other => Err(Self::Error::StorageError(anyhow!(
+                "unknown missing reason: {}",
+                other
+            ))),
+        }
     }
 }
 
@@ -169,7 +648,7 @@ impl TryFrom<String> for domain::VisibleName {
     type Error = crate::errors::Error;
 
     fn try_from(value: String) -> std::result::Result<domain::VisibleName, Self::Error> {
-        domain::VisibleName::new(value)
+        domain::VisibleName::new(value).map_err(corrupted)
     }
 }
 
@@ -189,7 +668,7 @@ impl TryFrom<String> for domain::Character {
         match value.as_str() {
             "brave" => Ok(domain::Character::Brave),
             "shy" => Ok(domain::Character::Shy),
-            other => Err(Self::Error::Unknown(anyhow!(
+            other => Err(Self::Error::StorageError(anyhow!(
                 "unknown character: {}",
                 other
             ))),
@@ -197,10 +676,33 @@ impl TryFrom<String> for domain::Character {
     }
 }
 
-const DT_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+impl From<&app::UpdateResult> for String {
+    fn from(value: &app::UpdateResult) -> Self {
+        match value {
+            app::UpdateResult::Succeeded => "succeeded".to_string(),
+            app::UpdateResult::Failed => "failed".to_string(),
+        }
+    }
+}
+
+impl TryFrom<String> for app::UpdateResult {
+    type Error = crate::errors::Error;
+
+    fn try_from(value: String) -> std::result::Result<app::UpdateResult, Self::Error> {
+        match value.as_str() {
+            "succeeded" => Ok(app::UpdateResult::Succeeded),
+            "failed" => Ok(app::UpdateResult::Failed),
+            other => Err(Self::Error::StorageError(anyhow!(
+                "unknown update result: {}",
+                other
+            ))),
+        }
+    }
+}
+
 impl From<&DateTime> for String {
     fn from(value: &DateTime) -> Self {
-        value.format(DT_FORMAT)
+        value.to_storage_string()
     }
 }
 
@@ -208,6 +710,530 @@ impl TryFrom<String> for DateTime {
     type Error = crate::errors::Error;
 
     fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
-        DateTime::new_from_str(&value, DT_FORMAT)
+        DateTime::new_from_storage_str(&value).map_err(corrupted)
+    }
+}
+
+// a stored value which can't be read back was corrupted, unlike the same error for a value which
+// was just entered it isn't the caller's fault
+fn corrupted(err: Error) -> Error {
+    match err {
+        Error::StorageError(_) => err,
+        other => Error::StorageError(anyhow!(other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{CharacterAudit, Inventory};
+    use crate::domain::{Character, Cow, MissingReason, VisibleName};
+
+    fn characters(database: &Database) -> Result<Vec<(VisibleName, Character)>> {
+        let mut cows: Vec<_> = database
+            .list()?
+            .into_iter()
+            .map(|cow| (cow.name().clone(), cow.character().clone()))
+            .collect();
+        cows.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(cows)
+    }
+
+    #[test]
+    fn missing_reasons_are_persisted() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        for reason in [
+            domain::MissingReason::HttpStatus(404),
+            domain::MissingReason::Timeout,
+            domain::MissingReason::DnsError,
+            domain::MissingReason::ValidationFailed,
+            domain::MissingReason::ConnectionRefused,
+        ] {
+            database.update(&name, |cow| {
+                let mut cow = cow.unwrap();
+                cow.mark_as_missing_because(reason)?;
+                Ok(Some(cow))
+            })?;
+            assert_eq!(database.get(&name)?.unwrap().missing_reason(), Some(reason));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn corrupted_cows_are_storage_errors() -> Result<()> {
+        for json in [
+            r#"{ "name": "https://example.com/cow.txt", "character": "brave", "last_seen": "yesterday" }"#,
+            r#"{ "name": "not a url", "character": "brave" }"#,
+            r#"{ "name": "https://example.com/cow.txt", "character": "timid" }"#,
+        ] {
+            let persisted: PersistedCow = serde_json::from_str(json)?;
+            let result: Result<Cow> = persisted.try_into();
+            assert!(matches!(result, Err(Error::StorageError(_))), "{json}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cows_persisted_with_a_domain_which_was_not_found_keep_it() -> Result<()> {
+        let persisted: PersistedCow = serde_json::from_str(
+            r#"{ "name": "https://example.com/cow.txt", "character": "brave", "domain_not_found": true }"#,
+        )?;
+        let cow: Cow = persisted.try_into()?;
+        assert_eq!(cow.missing_reason(), Some(MissingReason::DnsError));
+        assert!(cow.domain_not_found());
+        Ok(())
+    }
+
+    #[test]
+    fn cows_persisted_before_pausing_are_not_paused() -> Result<()> {
+        let persisted: PersistedCow = serde_json::from_str(
+            r#"{ "name": "https://example.com/cow.txt", "character": "brave" }"#,
+        )?;
+        let cow: Cow = persisted.try_into()?;
+        assert!(!cow.paused());
+
+        let database = Database::new_in_memory()?;
+        database.update(cow.name(), |_| Ok(Some(cow.clone().with_paused(true))))?;
+        assert!(database.get(cow.name())?.unwrap().paused());
+        Ok(())
+    }
+
+    #[test]
+    fn cows_persisted_before_failed_checks_were_counted_still_ran_away() -> Result<()> {
+        let long_ago =
+            (DateTime::now() - domain::time::Duration::new_from_days(3)).to_storage_string();
+        let now = DateTime::now().to_storage_string();
+        for (last_checked, expected_status) in [
+            (&now, app::CowStatus::RanAway),
+            (&long_ago, app::CowStatus::HappilyGrazing),
+        ] {
+            let persisted: PersistedCow = serde_json::from_str(&format!(
+                r#"{{ "name": "https://example.com/cow.txt", "character": "brave", "first_seen": "{long_ago}", "last_seen": "{long_ago}", "last_checked": "{last_checked}" }}"#
+            ))?;
+            let cow: Cow = persisted.try_into()?;
+            let cow = app::Cow::try_from(&domain::CensoredCow::new(&cow)?)?;
+            assert!(cow.metrics_status() == &expected_status, "{last_checked}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cows_persisted_without_notes_have_none() -> Result<()> {
+        let persisted: PersistedCow = serde_json::from_str(
+            r#"{ "name": "https://example.com/cow.txt", "character": "brave" }"#,
+        )?;
+        let cow: Cow = persisted.try_into()?;
+        assert_eq!(cow.notes(), "");
+        assert_eq!(cow.expected_character(), None);
+
+        let database = Database::new_in_memory()?;
+        database.update(cow.name(), |_| {
+            Ok(Some(cow.clone().with_notes("ask bob\nor alice")))
+        })?;
+        assert_eq!(
+            database.get(cow.name())?.unwrap().notes(),
+            "ask bob\nor alice"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn expected_characters_are_persisted() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(
+                Cow::new(name.clone(), Character::Shy)
+                    .with_expected_character(Some(Character::Brave)),
+            ))
+        })?;
+        let cow = database.get(&name)?.unwrap();
+        assert_eq!(cow.expected_character(), Some(&Character::Brave));
+        assert!(cow.has_unexpected_character());
+        Ok(())
+    }
+
+    #[test]
+    fn character_changes_are_kept_until_the_cow_is_deleted() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| Ok(Some(Cow::new(name.clone(), Character::Shy))))?;
+        assert!(database.character_changes(&name)?.is_empty());
+
+        database.update(&name, Ok)?;
+        assert!(database.character_changes(&name)?.is_empty());
+
+        for character in [Character::Brave, Character::Shy] {
+            database.update(&name, |cow| {
+                let mut cow = cow.unwrap();
+                cow.change_character(character.clone())?;
+                Ok(Some(cow))
+            })?;
+        }
+        let characters: Vec<_> = database
+            .character_changes(&name)?
+            .iter()
+            .map(|change| {
+                (
+                    change.old_character().clone(),
+                    change.new_character().clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            characters,
+            vec![
+                (Character::Shy, Character::Brave),
+                (Character::Brave, Character::Shy),
+            ]
+        );
+
+        let neighbour = VisibleName::new("https://example.com/other/cow.txt")?;
+        database.update(&neighbour, |_| {
+            Ok(Some(Cow::new(neighbour.clone(), Character::Shy)))
+        })?;
+        database.update(&neighbour, |cow| {
+            let mut cow = cow.unwrap();
+            cow.change_character(Character::Brave)?;
+            Ok(Some(cow))
+        })?;
+
+        database.delete(&name)?;
+        assert!(database.character_changes(&name)?.is_empty());
+        assert_eq!(database.character_changes(&neighbour)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn flap_streaks_are_persisted() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let last_flapped_at = DateTime::now();
+        database.update(&name, |_| {
+            Ok(Some(
+                Cow::new(name.clone(), Character::Brave)
+                    .with_flaps(5, Some(last_flapped_at.clone())),
+            ))
+        })?;
+        let cow = database.get(&name)?.unwrap();
+        assert_eq!(cow.flaps(), 5);
+        assert_eq!(cow.recent_flaps(&last_flapped_at), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn body_hashes_are_persisted() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let body_hash = domain::BodyHash::of("moo");
+        database.update(&name, |_| {
+            Ok(Some(
+                Cow::new(name.clone(), Character::Brave).with_body_hash(Some(body_hash.clone())),
+            ))
+        })?;
+        assert_eq!(database.get(&name)?.unwrap().body_hash(), Some(&body_hash));
+        Ok(())
+    }
+
+    #[test]
+    fn update_many_applies_all_updates_or_none() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let names = vec![
+            VisibleName::new("https://a.example.com/cow.txt")?,
+            VisibleName::new("https://b.example.com/cow.txt")?,
+            VisibleName::new("https://c.example.com/cow.txt")?,
+        ];
+
+        database.update_many(&names, |name, _| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+        assert_eq!(
+            characters(&database)?,
+            names
+                .iter()
+                .map(|name| (name.clone(), Character::Brave))
+                .collect::<Vec<_>>()
+        );
+
+        let result = database.update_many(&names, |name, cow| {
+            if name == &names[2] {
+                return Err(anyhow!("injected error").into());
+            }
+            let mut cow = cow.unwrap();
+            cow.change_character(Character::Shy)?;
+            Ok(Some(cow))
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            characters(&database)?,
+            names
+                .iter()
+                .map(|name| (name.clone(), Character::Brave))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn migrating_a_copy_leaves_the_original_unchanged() -> Result<()> {
+        let source_path = std::env::temp_dir().join(format!(
+            "moooodotfarm-migration-source-{}.db",
+            std::process::id()
+        ));
+        let dest_path = std::env::temp_dir().join(format!(
+            "moooodotfarm-migration-dest-{}.db",
+            std::process::id()
+        ));
+
+        let outdated_cow = r#"{ "name": "https://b.example.com/cow.txt", "character": "shy" }"#;
+        {
+            let source = Database::new(source_path.to_string_lossy())?;
+            let name = VisibleName::new("https://a.example.com/cow.txt")?;
+            source.update(&name, |_| {
+                Ok(Some(Cow::new(name.clone(), Character::Brave)))
+            })?;
+            let db = source.db.lock().unwrap();
+            let write_txn = db.begin_write()?;
+            write_txn.open_table(COW_STATUS_TABLE)?.insert(
+                "https://b.example.com/cow.txt".to_string(),
+                outdated_cow.to_string(),
+            )?;
+            write_txn.commit()?;
+        }
+
+        let copy = Database::open_copy(&source_path, dest_path.to_string_lossy())?;
+        assert_eq!(copy.migrate()?, 1);
+        assert_eq!(copy.migrate()?, 0);
+        assert_eq!(characters(&copy)?.len(), 2);
+        drop(copy);
+
+        let source = Database::new(source_path.to_string_lossy())?;
+        let raw = {
+            let db = source.db.lock().unwrap();
+            let read_txn = db.begin_read()?;
+            read_txn
+                .open_table(COW_STATUS_TABLE)?
+                .get("https://b.example.com/cow.txt".to_string())?
+                .map(|v| v.value())
+        };
+        assert_eq!(raw.as_deref(), Some(outdated_cow));
+        drop(source);
+
+        std::fs::remove_file(source_path)?;
+        std::fs::remove_file(dest_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn cows_persisted_without_tags_are_migrated_to_empty_tags() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let key = "https://a.example.com/cow.txt".to_string();
+        {
+            let db = database.db.lock().unwrap();
+            let write_txn = db.begin_write()?;
+            write_txn.open_table(COW_STATUS_TABLE)?.insert(
+                key.clone(),
+                r#"{ "name": "https://a.example.com/cow.txt", "character": "brave" }"#.to_string(),
+            )?;
+            write_txn.commit()?;
+        }
+
+        let name = VisibleName::new(&key)?;
+        assert!(database.get(&name)?.unwrap().tags().is_empty());
+        assert_eq!(database.migrate()?, 1);
+
+        let raw = {
+            let db = database.db.lock().unwrap();
+            let read_txn = db.begin_read()?;
+            read_txn
+                .open_table(COW_STATUS_TABLE)?
+                .get(key)?
+                .map(|v| v.value())
+                .unwrap()
+        };
+        assert!(raw.contains(r#""tags":[]"#));
+        Ok(())
+    }
+
+    #[test]
+    fn added_at_is_preserved_across_reloads() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("moooodotfarm-added-at-{}.db", std::process::id()));
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let added_at = DateTime::new_from_unix_timestamp(1_700_000_000);
+        {
+            let database = Database::new(path.to_string_lossy())?;
+            database.update(&name, |_| {
+                Ok(Some(
+                    Cow::new(name.clone(), Character::Brave).with_added_at(added_at.clone()),
+                ))
+            })?;
+            database.update(&name, |cow| {
+                let mut cow = cow.unwrap();
+                cow.mark_as_ok()?;
+                Ok(Some(cow))
+            })?;
+        }
+
+        let database = Database::new(path.to_string_lossy())?;
+        assert_eq!(database.get(&name)?.unwrap().added_at(), &added_at);
+        drop(database);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn cows_persisted_before_added_at_are_migrated_to_first_seen() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let key = "https://a.example.com/cow.txt".to_string();
+        let first_seen = DateTime::new_from_unix_timestamp(1_700_000_000);
+        {
+            let db = database.db.lock().unwrap();
+            let write_txn = db.begin_write()?;
+            write_txn.open_table(COW_STATUS_TABLE)?.insert(
+                key.clone(),
+                format!(
+                    r#"{{ "name": "{key}", "character": "brave", "first_seen": "{}" }}"#,
+                    first_seen.to_storage_string()
+                ),
+            )?;
+            write_txn.commit()?;
+        }
+
+        assert_eq!(database.migrate()?, 1);
+        let name = VisibleName::new(&key)?;
+        assert_eq!(database.get(&name)?.unwrap().added_at(), &first_seen);
+        Ok(())
+    }
+
+    #[test]
+    fn cows_persisted_before_added_at_which_were_never_seen_keep_it_once_migrated() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let key = "https://a.example.com/cow.txt".to_string();
+        {
+            let db = database.db.lock().unwrap();
+            let write_txn = db.begin_write()?;
+            write_txn.open_table(COW_STATUS_TABLE)?.insert(
+                key.clone(),
+                format!(r#"{{ "name": "{key}", "character": "brave" }}"#),
+            )?;
+            write_txn.commit()?;
+        }
+
+        assert_eq!(database.migrate()?, 1);
+        let name = VisibleName::new(&key)?;
+        let added_at = database.get(&name)?.unwrap().added_at().clone();
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        assert_eq!(database.get(&name)?.unwrap().added_at(), &added_at);
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct RecordingWriter {
+        written: Vec<u8>,
+        largest_write: usize,
+    }
+
+    impl Write for RecordingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.largest_write = self.largest_write.max(buf.len());
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn jsonl_export_and_import_round_trip() -> Result<()> {
+        let source = Database::new_in_memory()?;
+        let names = (0..10_000)
+            .map(|i| VisibleName::new(format!("https://{i}.example.com/cow.txt")))
+            .collect::<Result<Vec<_>>>()?;
+        source.update_many(&names, |name, _| {
+            Ok(Some(Cow::new(name.clone(), Character::Shy)))
+        })?;
+
+        let mut writer = RecordingWriter::default();
+        assert_eq!(source.export_jsonl(&mut writer)?, names.len());
+        // cows are written out one by one instead of being buffered
+        assert!(writer.largest_write < 1024, "{}", writer.largest_write);
+
+        let destination = Database::new_in_memory()?;
+        assert_eq!(
+            destination.import_jsonl(writer.written.as_slice())?,
+            names.len()
+        );
+        assert_eq!(characters(&destination)?, characters(&source)?);
+        Ok(())
+    }
+
+    #[test]
+    fn jsonl_import_is_all_or_nothing() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let input = "{\"name\":\"https://example.com/cow.txt\",\"character\":\"brave\"}\n\
+            not a cow\n\
+            {\"name\":\"https://example.com/cow.txt\",\"character\":\"timid\"}\n";
+
+        let err = database.import_jsonl(input.as_bytes()).unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.starts_with("no cows were imported as line 2 is invalid: "),
+            "{message}"
+        );
+        assert!(database.list()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn jsonl_import_audits_character_changes() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        let input = "{\"name\":\"https://example.com/cow.txt\",\"character\":\"shy\"}\n";
+        assert_eq!(database.import_jsonl(input.as_bytes())?, 1);
+
+        let changes = database.character_changes(&name)?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_character(), &Character::Brave);
+        assert_eq!(changes[0].new_character(), &Character::Shy);
+        Ok(())
+    }
+
+    #[test]
+    fn compaction_keeps_the_data() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("moooodotfarm-compaction-{}.db", std::process::id()));
+        let database = Database::new(path.to_string_lossy())?;
+        let names = (0..100)
+            .map(|i| VisibleName::new(format!("https://{i}.example.com/cow.txt")))
+            .collect::<Result<Vec<_>>>()?;
+        database.update_many(&names, |name, _| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+        for name in &names[1..] {
+            database.delete(name)?;
+        }
+
+        database.compact()?;
+
+        assert_eq!(
+            characters(&database)?,
+            vec![(names[0].clone(), Character::Brave)]
+        );
+        drop(database);
+        std::fs::remove_file(path)?;
+        Ok(())
     }
 }
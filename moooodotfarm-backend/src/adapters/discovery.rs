@@ -0,0 +1,151 @@
+use crate::app;
+use crate::domain::{Tag, VisibleName};
+use crate::errors::Result;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use simple_dns::rdata::RData;
+use simple_dns::{CLASS, Name, Packet, Question, TYPE};
+use std::collections::BTreeSet;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+static MDNS_ADDRESS: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353));
+static MDNS_SERVICE: &str = "_http._tcp.local";
+static MDNS_ORIGIN_TAG: &str = "mdns";
+static LISTEN_FOR_RESPONSES: Duration = Duration::from_secs(2);
+static MAX_PACKET_SIZE: usize = 9000;
+
+// asks the local network for http servers and suggests a cow for each of them, the host names are
+// resolved like any other cow so the host policy applies and the system resolver has to know how
+// to resolve .local names
+#[derive(Clone)]
+pub struct MdnsDiscovery {
+    address: SocketAddr,
+}
+
+impl MdnsDiscovery {
+    pub fn new() -> Self {
+        Self {
+            address: MDNS_ADDRESS,
+        }
+    }
+
+    fn query() -> Result<Vec<u8>> {
+        let mut packet = Packet::new_query(0);
+        packet.questions.push(Question::new(
+            Name::new_unchecked(MDNS_SERVICE),
+            TYPE::PTR.into(),
+            CLASS::IN.into(),
+            false,
+        ));
+        Ok(packet.build_bytes_vec().map_err(|err| anyhow!(err))?)
+    }
+
+    // responders send the srv records of their services along with the pointers to them
+    fn hosts(response: &[u8]) -> Result<Vec<(String, u16)>> {
+        let packet = Packet::parse(response).map_err(|err| anyhow!(err))?;
+        Ok(packet
+            .answers
+            .iter()
+            .chain(packet.additional_records.iter())
+            .filter_map(|record| match &record.rdata {
+                RData::SRV(srv) => Some((srv.target.to_string(), srv.port)),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+impl Default for MdnsDiscovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl app::CowDiscovery for MdnsDiscovery {
+    fn origin(&self) -> Tag {
+        Tag::new(MDNS_ORIGIN_TAG).expect("origin tag is valid")
+    }
+
+    async fn discover(&self) -> Result<Vec<VisibleName>> {
+        // sending from a port other than 5353 makes responders answer directly
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        socket.send_to(&Self::query()?, self.address).await?;
+
+        let mut hosts = BTreeSet::new();
+        let mut buf = vec![0; MAX_PACKET_SIZE];
+        let deadline = tokio::time::Instant::now() + LISTEN_FOR_RESPONSES;
+        while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv(&mut buf)).await {
+            match Self::hosts(&buf[..received?]) {
+                Ok(found) => hosts.extend(found),
+                Err(err) => log::debug!("ignoring invalid mdns response: {}", err),
+            }
+        }
+
+        Ok(hosts
+            .iter()
+            .map(|(host, port)| (host.trim_end_matches('.'), port))
+            .filter(|(host, _)| host.ends_with(".local"))
+            .filter_map(|(host, port)| match port {
+                80 => VisibleName::new(format!("http://{host}/cow.txt")).ok(),
+                port => VisibleName::new(format!("http://{host}:{port}/cow.txt")).ok(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::CowDiscovery as _;
+    use simple_dns::ResourceRecord;
+    use simple_dns::rdata::{PTR, SRV};
+
+    #[tokio::test]
+    async fn discovers_a_cow_announced_by_a_responder() -> Result<()> {
+        let responder = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let address = responder.local_addr()?;
+        tokio::spawn(async move {
+            let mut buf = vec![0; MAX_PACKET_SIZE];
+            let (len, from) = responder.recv_from(&mut buf).await?;
+            let query = Packet::parse(&buf[..len]).map_err(|err| anyhow!(err))?;
+            assert_eq!(query.questions[0].qname.to_string(), MDNS_SERVICE);
+
+            let instance = Name::new_unchecked("bessie._http._tcp.local");
+            let mut response = Packet::new_reply(0);
+            response.answers.push(ResourceRecord::new(
+                Name::new_unchecked(MDNS_SERVICE),
+                CLASS::IN,
+                120,
+                RData::PTR(PTR(instance.clone())),
+            ));
+            response.additional_records.push(ResourceRecord::new(
+                instance,
+                CLASS::IN,
+                120,
+                RData::SRV(SRV {
+                    priority: 0,
+                    weight: 0,
+                    port: 80,
+                    target: Name::new_unchecked("bessie.local"),
+                }),
+            ));
+            let response = response.build_bytes_vec().map_err(|err| anyhow!(err))?;
+            responder.send_to(&response, from).await?;
+            Ok::<_, crate::errors::Error>(())
+        });
+
+        let discovery = MdnsDiscovery { address };
+        let names = discovery.discover().await?;
+
+        assert_eq!(
+            names,
+            vec![VisibleName::new("http://bessie.local/cow.txt")?]
+        );
+        assert_eq!(discovery.origin(), Tag::new("mdns")?);
+        Ok(())
+    }
+}
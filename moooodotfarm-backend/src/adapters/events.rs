@@ -0,0 +1,58 @@
+use crate::app;
+use crate::domain::events::DomainEvent;
+
+#[derive(Clone, Default)]
+pub struct NoopEventSink {}
+
+impl NoopEventSink {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl app::EventSink for NoopEventSink {
+    fn emit(&self, _event: DomainEvent) {}
+}
+
+#[derive(Clone, Default)]
+pub struct LoggingEventSink {}
+
+impl LoggingEventSink {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl app::EventSink for LoggingEventSink {
+    fn emit(&self, event: DomainEvent) {
+        match &event {
+            DomainEvent::CowAdded { name, character } => {
+                log::info!("cow added: {} ({:?})", name.url(), character)
+            }
+            DomainEvent::CowRemoved { name } => log::info!("cow removed: {}", name.url()),
+            DomainEvent::CharacterChanged {
+                name,
+                old_character,
+                new_character,
+            } => log::info!(
+                "cow character changed: {} ({:?} -> {:?})",
+                name.url(),
+                old_character,
+                new_character
+            ),
+            DomainEvent::CowRanAway { name } => log::warn!("cow ran away: {}", name.url()),
+            DomainEvent::CowReturned { name } => log::info!("cow returned: {}", name.url()),
+            DomainEvent::CowPaused { name } => log::info!("cow paused: {}", name.url()),
+            DomainEvent::CowResumed { name } => log::info!("cow resumed: {}", name.url()),
+            DomainEvent::CowContentChanged { name } => {
+                log::info!("cow content changed: {}", name.url())
+            }
+            DomainEvent::HerdDegraded { ran_away, checked } => {
+                log::error!("herd degraded: {} of {} cows ran away", ran_away, checked)
+            }
+            DomainEvent::HerdRecovered { ran_away, checked } => {
+                log::info!("herd recovered: {} of {} cows ran away", ran_away, checked)
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::app;
+use crate::config::{Config, LogFormat};
+use crate::domain::time::DateTime;
+use crate::errors::Result;
+use anyhow::anyhow;
+use env_logger::{DEFAULT_FILTER_ENV, Env};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const DT_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3f%z";
+
+// env_logger lets everything through unless RUST_LOG says otherwise so that the level can be
+// switched at runtime, RUST_LOG still has the last word though. the max level of the log crate
+// follows the current level so that disabled lines stay as cheap as they normally are
+pub fn init(config: &Config) -> Result<LogLevel> {
+    let format = config.log_format().clone();
+    let logger = env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Trace)
+        .parse_env(Env::default())
+        .format(move |buf, record| write_record(&format, buf, record))
+        .build();
+    let configured = match std::env::var_os(DEFAULT_FILTER_ENV) {
+        Some(_) => logger.filter(),
+        None => config.log_level(),
+    };
+    let log_level = LogLevel::new(configured);
+    log::set_boxed_logger(Box::new(SwitchableLogger::new(logger, log_level.clone())))
+        .map_err(|err| anyhow!(err))?;
+    log::set_max_level(log_level.current());
+    Ok(log_level)
+}
+
+pub fn init_default() {
+    env_logger::Builder::from_env(Env::default().filter_or("RUST_LOG", "info")).init();
+}
+
+// raising the level while debugging a live incident saves a restart which could make the problem go
+// away
+#[derive(Clone)]
+pub struct LogLevel {
+    configured: log::LevelFilter,
+    // zero if the level isn't switched, otherwise one more than the level it was switched to, every
+    // logged line reads it so it mustn't take a lock
+    switched_to: Arc<AtomicUsize>,
+}
+
+impl LogLevel {
+    pub fn new(configured: log::LevelFilter) -> Self {
+        Self {
+            configured,
+            switched_to: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn current(&self) -> log::LevelFilter {
+        match self.switched_to.load(Ordering::Relaxed) {
+            0 => self.configured,
+            switched_to => log::LevelFilter::iter()
+                .nth(switched_to - 1)
+                .unwrap_or(self.configured),
+        }
+    }
+}
+
+impl app::LogLevelSwitch for LogLevel {
+    fn switch(&self, level: Option<log::LevelFilter>) -> log::LevelFilter {
+        self.switched_to.store(
+            level.map_or(0, |level| level as usize + 1),
+            Ordering::Relaxed,
+        );
+        let current = self.current();
+        log::set_max_level(current);
+        current
+    }
+
+    fn is_switched(&self) -> bool {
+        self.switched_to.load(Ordering::Relaxed) != 0
+    }
+}
+
+struct SwitchableLogger<L> {
+    inner: L,
+    level: LogLevel,
+}
+
+impl<L> SwitchableLogger<L> {
+    fn new(inner: L, level: LogLevel) -> Self {
+        Self { inner, level }
+    }
+}
+
+impl<L: log::Log> log::Log for SwitchableLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.level.current() && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+fn write_record(
+    format: &LogFormat,
+    w: &mut impl Write,
+    record: &log::Record<'_>,
+) -> std::io::Result<()> {
+    match format {
+        LogFormat::Text => writeln!(
+            w,
+            "[{} {:<5} {}] {}",
+            DateTime::now().format(DT_FORMAT),
+            record.level(),
+            record.target(),
+            record.args()
+        ),
+        LogFormat::Compact => writeln!(w, "{:<5} {}", record.level(), record.args()),
+        LogFormat::Json => {
+            let line = serde_json::json!({
+                "timestamp": DateTime::now().format(DT_FORMAT),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(w, "{}", line)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::LogLevelSwitch;
+    use std::sync::Mutex;
+
+    fn render(format: &LogFormat) -> String {
+        let mut buf = Vec::new();
+        write_record(
+            format,
+            &mut buf,
+            &log::Record::builder()
+                .args(format_args!("cow ran away"))
+                .level(log::Level::Warn)
+                .target("moooodotfarm")
+                .build(),
+        )
+        .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn formats_records_according_to_the_configured_format() {
+        let text = render(&LogFormat::Text);
+        assert!(text.starts_with('['), "{text}");
+        assert!(
+            text.ends_with("WARN  moooodotfarm] cow ran away\n"),
+            "{text}"
+        );
+
+        assert_eq!(render(&LogFormat::Compact), "WARN  cow ran away\n");
+
+        let json: serde_json::Value = serde_json::from_str(&render(&LogFormat::Json)).unwrap();
+        assert_eq!(json["level"], "WARN");
+        assert_eq!(json["target"], "moooodotfarm");
+        assert_eq!(json["message"], "cow ran away");
+        assert!(json["timestamp"].is_string());
+    }
+
+    #[derive(Default)]
+    struct CollectingLogger {
+        records: Mutex<Vec<String>>,
+    }
+
+    impl log::Log for CollectingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.records
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", record.level(), record.args()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn debug_lines_are_only_logged_while_the_level_is_switched_to_debug() {
+        let log_level = LogLevel::new(log::LevelFilter::Info);
+        let logger = SwitchableLogger::new(CollectingLogger::default(), log_level.clone());
+        let log_debug_line = |message| {
+            log::Log::log(
+                &logger,
+                &log::Record::builder()
+                    .args(format_args!("{}", message))
+                    .level(log::Level::Debug)
+                    .build(),
+            )
+        };
+
+        log_debug_line("before");
+        log_level.switch(Some(log::LevelFilter::Debug));
+        log_debug_line("while switched");
+        log_level.switch(None);
+        log_debug_line("after");
+
+        assert_eq!(
+            *logger.inner.records.lock().unwrap(),
+            vec!["DEBUG while switched".to_string()]
+        );
+    }
+
+    #[test]
+    fn every_level_can_be_switched_to() {
+        let log_level = LogLevel::new(log::LevelFilter::Info);
+        for level in log::LevelFilter::iter() {
+            assert_eq!(log_level.switch(Some(level)), level);
+            assert!(log_level.is_switched());
+        }
+        assert_eq!(log_level.switch(None), log::LevelFilter::Info);
+        assert!(!log_level.is_switched());
+    }
+}
@@ -1,33 +1,80 @@
 pub mod database;
+pub mod discovery;
+pub mod events;
+pub mod logging;
+pub mod runtime;
 
 use crate::app;
-use crate::app::{ApplicationHandlerCallResult, Herd};
-use crate::config::{Config, Environment};
-use crate::domain::time::Duration;
-use crate::domain::{CowTxt, VisibleName};
-use crate::errors::Result;
+use crate::app::{ApplicationHandlerCallResult, Herd, Inventory};
+use crate::config::{
+    Config, CowConfig, CowCredentials, DEFAULT_METRICS_HANDLER_NAMES, DEFAULT_METRICS_NAMESPACE,
+    Environment, GrpcLimits, JsonFieldNaming, LogFormat, StorageBackend, TlsConfig,
+};
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::check_order::CheckOrder;
+use crate::domain::circuit_breaker::CircuitBreakerState;
+use crate::domain::flap_penalty::FlapPenalty;
+use crate::domain::host_policy::{HostPolicy, IpNetwork};
+use crate::domain::redirect_policy::{RedirectIdentity, RedirectPolicy};
+use crate::domain::shard::Shard;
+use crate::domain::time::{DateTime, DisplayFormat, Duration};
+use crate::domain::{
+    CharacterChange, Cow, CowTxt, HostCensoring, MissingReason, MustMatch, Nickname, Tag,
+    VisibleName,
+};
+use crate::errors::{Error, Result};
 use anyhow::anyhow;
 use async_trait::async_trait;
-use prometheus::{CounterVec, GaugeVec, HistogramOpts, HistogramVec, Opts, Registry, labels};
+use futures_util::future::BoxFuture;
+use prometheus::{
+    Counter, CounterVec, Gauge, GaugeVec, HistogramOpts, HistogramVec, Opts, Registry, labels,
+};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
+static STDIN_CONFIG_PATH: &str = "-";
+
+#[derive(Clone)]
 pub struct ConfigLoader {
     path: PathBuf,
+    stdin_content: Arc<Mutex<Option<String>>>,
 }
 
 impl ConfigLoader {
+    // "-" reads the config from stdin
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
-        Self { path: path.into() }
+        Self {
+            path: path.into(),
+            stdin_content: Arc::new(Mutex::new(None)),
+        }
     }
 
     pub fn load(&self) -> Result<Config> {
-        let content = fs::read_to_string(&self.path)?;
+        let content = if self.path.as_os_str() == STDIN_CONFIG_PATH {
+            self.read_stdin(std::io::stdin().lock())?
+        } else {
+            fs::read_to_string(&self.path)?
+        };
         let transport: TomlConfig = toml::from_str(&content)?;
         Config::try_from(transport)
     }
+
+    // stdin can only be read once so reloading the config keeps using whatever was read first
+    fn read_stdin(&self, mut stdin: impl Read) -> Result<String> {
+        let mut stdin_content = self.stdin_content.lock().unwrap();
+        if let Some(content) = stdin_content.as_ref() {
+            return Ok(content.clone());
+        }
+        let mut content = String::new();
+        stdin.read_to_string(&mut content)?;
+        *stdin_content = Some(content.clone());
+        Ok(content)
+    }
 }
 
 #[derive(Deserialize)]
@@ -36,18 +83,485 @@ struct TomlConfig {
     grpc_address: String,
     environment: String,
     database_path: String,
+    backend: Option<String>,
+    cows: Option<Vec<TomlCow>>,
+    log_level: Option<String>,
+    log_format: Option<String>,
+    hide_shy_from_public: Option<bool>,
+    security_headers: Option<bool>,
+    site_title: Option<String>,
+    favicon_path: Option<String>,
+    logo_path: Option<String>,
+    public_url: Option<String>,
+    host_censoring: Option<String>,
+    stable_herd_order: Option<bool>,
+    allowed_networks: Option<Vec<String>>,
+    denied_networks: Option<Vec<String>>,
+    compact_database_every_hours: Option<u64>,
+    check_cache_ttl_seconds: Option<u64>,
+    max_concurrent_downloads: Option<usize>,
+    max_requests_per_update: Option<usize>,
+    update_deadline_percent: Option<u32>,
+    herd_degraded_percent: Option<u32>,
+    flap_penalty_max_multiplier: Option<u32>,
+    max_first_update_delay_seconds: Option<u64>,
+    max_unchecked_age_minutes: Option<u64>,
+    active_hours: Option<String>,
+    active_hours_timezone: Option<String>,
+    check_order: Option<String>,
+    datetime_display_format: Option<String>,
+    json_field_naming: Option<String>,
+    fallback_encoding: Option<String>,
+    metrics_bearer_token: Option<String>,
+    owner_bearer_token: Option<String>,
+    metrics_namespace: Option<String>,
+    metrics_handler_names: Option<Vec<String>>,
+    shard: Option<TomlShard>,
+    redirects: Option<TomlRedirects>,
+    tls: Option<TomlTls>,
+    grpc: Option<TomlGrpc>,
+    mdns_discovery: Option<bool>,
+    store_cow_bodies: Option<bool>,
+    allow_indexing: Option<bool>,
+    serve_herd_txt: Option<bool>,
+    must_match: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlRedirects {
+    allow_cross_origin: Option<bool>,
+    identity: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlTls {
+    cert_path: String,
+    key_path: String,
+    redirect_http_address: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlGrpc {
+    concurrency_limit: Option<usize>,
+    max_concurrent_streams: Option<u32>,
+    // zero turns tcp keepalive off
+    tcp_keepalive_seconds: Option<u64>,
+    web_origins: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct TomlShard {
+    index: u32,
+    total: u32,
+}
+
+#[derive(Deserialize, Clone)]
+struct TomlCow {
+    name: String,
+    character: String,
+    expected_character: Option<String>,
+    nickname: Option<String>,
+    tags: Option<Vec<String>>,
+    credentials: Option<TomlCowCredentials>,
+    headers: Option<BTreeMap<String, String>>,
+    must_match: Option<String>,
+    check_via: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+struct TomlCowCredentials {
+    username: Option<String>,
+    password: Option<String>,
+    header: Option<String>,
+    value: Option<String>,
 }
 
 impl TryFrom<TomlConfig> for Config {
     type Error = crate::errors::Error;
 
     fn try_from(value: TomlConfig) -> std::result::Result<Self, Self::Error> {
-        Config::new(
+        let log_level = match value.log_level {
+            Some(log_level) => log_level
+                .parse::<log::LevelFilter>()
+                .map_err(|_| anyhow!("invalid log level: {}", log_level))?,
+            None => log::LevelFilter::Info,
+        };
+        let log_format = match value.log_format {
+            Some(log_format) => log_format.try_into()?,
+            None => LogFormat::Text,
+        };
+        let storage_backend = match value.backend {
+            Some(backend) => backend.try_into()?,
+            None => StorageBackend::Redb,
+        };
+        let host_policy = HostPolicy::new(
+            parse_networks(value.allowed_networks)?,
+            parse_networks(value.denied_networks)?,
+        );
+        let config = Config::new(
             value.http_address,
             value.grpc_address,
             value.environment.try_into()?,
-            value.database_path,
-        )
+            interpolate_env_vars(&value.database_path)?,
+        )?
+        .with_logging(log_level, log_format)
+        .with_storage_backend(storage_backend)
+        .with_hide_shy_from_public(value.hide_shy_from_public.unwrap_or(false))
+        .with_stable_herd_order(value.stable_herd_order.unwrap_or(false))
+        .with_mdns_discovery(value.mdns_discovery.unwrap_or(false))
+        .with_store_cow_bodies(value.store_cow_bodies.unwrap_or(false))
+        .with_allow_indexing(value.allow_indexing.unwrap_or(false))
+        .with_serve_herd_txt(value.serve_herd_txt.unwrap_or(false))
+        .with_host_policy(host_policy);
+        let config = match value.compact_database_every_hours {
+            Some(hours) => {
+                let seconds = hours.checked_mul(60 * 60).ok_or_else(|| {
+                    anyhow!("compact_database_every_hours is too large: {}", hours)
+                })?;
+                config.with_database_compaction_interval(std::time::Duration::from_secs(seconds))?
+            }
+            None => config,
+        };
+        let config = match value.security_headers {
+            Some(security_headers) => config.with_security_headers(security_headers),
+            None => config,
+        };
+        let config = match value.site_title {
+            Some(site_title) => config.with_site_title(site_title)?,
+            None => config,
+        };
+        let config = match value.favicon_path {
+            Some(path) => config.with_favicon_path(interpolate_env_vars(&path)?)?,
+            None => config,
+        };
+        let config = match value.logo_path {
+            Some(path) => config.with_logo_path(interpolate_env_vars(&path)?)?,
+            None => config,
+        };
+        let config = match value.public_url {
+            Some(public_url) => config.with_public_url(
+                url::Url::parse(&public_url)
+                    .map_err(|err| anyhow!("invalid public_url {}: {}", public_url, err))?,
+            )?,
+            None => config,
+        };
+        let config = match value.check_cache_ttl_seconds {
+            Some(seconds) => {
+                config.with_check_cache_ttl(std::time::Duration::from_secs(seconds))?
+            }
+            None => config,
+        };
+        let config = match value.host_censoring {
+            Some(host_censoring) => {
+                config.with_host_censoring(HostCensoring::try_from(host_censoring.as_str())?)
+            }
+            None => config,
+        };
+        let config = match value.datetime_display_format {
+            Some(format) => config.with_display_format(DisplayFormat::new(format)?),
+            None => config,
+        };
+        let config = match value.json_field_naming {
+            Some(naming) => config.with_json_field_naming(naming.try_into()?),
+            None => config,
+        };
+        let config = match value.max_concurrent_downloads {
+            Some(max_concurrent_downloads) => {
+                config.with_max_concurrent_downloads(max_concurrent_downloads)?
+            }
+            None => config,
+        };
+        let config = match value.fallback_encoding {
+            Some(label) => config.with_fallback_encoding(
+                encoding_rs::Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| anyhow!("unknown fallback encoding: {}", label))?,
+            ),
+            None => config,
+        };
+        let config = match value.metrics_bearer_token {
+            Some(token) => config.with_metrics_bearer_token(token)?,
+            None => config,
+        };
+        let config = match value.owner_bearer_token {
+            Some(token) => config.with_owner_bearer_token(token)?,
+            None => config,
+        };
+        // an empty namespace turns the prefix off
+        let config = match value.metrics_namespace {
+            Some(namespace) if namespace.is_empty() => config.with_metrics_namespace(None)?,
+            Some(namespace) => config.with_metrics_namespace(Some(namespace))?,
+            None => config,
+        };
+        let config = match value.metrics_handler_names {
+            Some(handler_names) => config.with_metrics_handler_names(handler_names)?,
+            None => config,
+        };
+        let config = match value.max_requests_per_update {
+            Some(max_requests_per_update) => {
+                config.with_max_requests_per_update(max_requests_per_update)?
+            }
+            None => config,
+        };
+        let config = match value.update_deadline_percent {
+            Some(update_deadline_percent) => {
+                config.with_update_deadline_percent(update_deadline_percent)?
+            }
+            None => config,
+        };
+        let config = match value.herd_degraded_percent {
+            Some(herd_degraded_percent) => {
+                config.with_herd_degraded_percent(herd_degraded_percent)?
+            }
+            None => config,
+        };
+        let config = match value.flap_penalty_max_multiplier {
+            Some(max_multiplier) => config.with_flap_penalty(FlapPenalty::new(max_multiplier)?),
+            None => config,
+        };
+        let config = match value.check_order {
+            Some(check_order) => {
+                config.with_check_order(CheckOrder::try_from(check_order.as_str())?)
+            }
+            None => config,
+        };
+        let config = match value.max_first_update_delay_seconds {
+            Some(seconds) => {
+                config.with_max_first_update_delay(std::time::Duration::from_secs(seconds))
+            }
+            None => config,
+        };
+        let config = match value.max_unchecked_age_minutes {
+            Some(minutes) => config.with_max_unchecked_age(std::time::Duration::from_secs(
+                minutes.checked_mul(60).ok_or_else(|| {
+                    anyhow!("max_unchecked_age_minutes is too large: {}", minutes)
+                })?,
+            ))?,
+            None => config,
+        };
+        let config = match value.active_hours {
+            Some(active_hours) => config.with_active_hours(ActiveHours::new(
+                &active_hours,
+                value.active_hours_timezone.as_deref(),
+            )?),
+            None => config,
+        };
+        let config = match value.shard {
+            Some(shard) => config.with_shard(Shard::new(shard.index, shard.total)?),
+            None => config,
+        };
+        let config = match value.must_match {
+            Some(pattern) => config.with_must_match(MustMatch::new(&pattern)?),
+            None => config,
+        };
+        let config = match value.redirects {
+            Some(redirects) => config.with_redirect_policy(RedirectPolicy::new(
+                redirects.allow_cross_origin.unwrap_or(false),
+                match redirects.identity {
+                    Some(identity) => RedirectIdentity::try_from(identity.as_str())?,
+                    None => RedirectIdentity::default(),
+                },
+            )),
+            None => config,
+        };
+        let config = match value.tls {
+            Some(tls) => {
+                let tls_config = TlsConfig::new(
+                    interpolate_env_vars(&tls.cert_path)?,
+                    interpolate_env_vars(&tls.key_path)?,
+                )?;
+                config.with_tls(match tls.redirect_http_address {
+                    Some(address) => tls_config.with_redirect_http_address(address)?,
+                    None => tls_config,
+                })
+            }
+            None => config,
+        };
+        let config = match value.grpc {
+            Some(grpc) => {
+                let grpc_limits = GrpcLimits::default();
+                let grpc_limits = match grpc.concurrency_limit {
+                    Some(limit) => grpc_limits.with_concurrency_limit(limit)?,
+                    None => grpc_limits,
+                };
+                let grpc_limits = match grpc.max_concurrent_streams {
+                    Some(max_streams) => grpc_limits.with_max_concurrent_streams(max_streams)?,
+                    None => grpc_limits,
+                };
+                let grpc_limits = match grpc.tcp_keepalive_seconds {
+                    Some(0) => grpc_limits.with_tcp_keepalive(None),
+                    Some(seconds) => grpc_limits
+                        .with_tcp_keepalive(Some(std::time::Duration::from_secs(seconds))),
+                    None => grpc_limits,
+                };
+                config
+                    .with_grpc_limits(grpc_limits)
+                    .with_grpc_web_origins(grpc.web_origins.unwrap_or_default())?
+            }
+            None => config,
+        };
+        match value.cows {
+            Some(cows) => {
+                let mut cow_configs = vec![];
+                for cow in cows {
+                    for name in expand_cow_name(&cow.name)? {
+                        cow_configs.push(CowConfig::try_from(TomlCow {
+                            name,
+                            ..cow.clone()
+                        })?);
+                    }
+                }
+                Ok(config.with_cows(cow_configs))
+            }
+            None => Ok(config),
+        }
+    }
+}
+
+// a single pattern is rarely meant to watch more cows than this, a bigger expansion is most likely
+// a typo
+static MAX_EXPANDED_COW_NAMES: usize = 64;
+
+// expands every `{a,b,c}` group in the name of a cow, each of the resulting cows is tracked on
+// its own
+fn expand_cow_name(pattern: &str) -> Result<Vec<String>> {
+    let invalid = |reason: &str| {
+        crate::errors::Error::ValidationError(anyhow!("invalid cow name {}: {}", pattern, reason))
+    };
+
+    let mut names = vec![String::new()];
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|i| start + i)
+            .ok_or_else(|| invalid("unclosed brace"))?;
+        let prefix = &rest[..start];
+        let group = &rest[start + 1..end];
+        if prefix.contains('}') || group.contains('{') {
+            return Err(invalid("unbalanced or nested braces"));
+        }
+
+        let alternatives: Vec<&str> = group.split(',').collect();
+        if names.len() * alternatives.len() > MAX_EXPANDED_COW_NAMES {
+            return Err(invalid(&format!(
+                "expands into more than {MAX_EXPANDED_COW_NAMES} cows"
+            )));
+        }
+        names = names
+            .iter()
+            .flat_map(|name| {
+                alternatives
+                    .iter()
+                    .map(move |alternative| format!("{name}{prefix}{alternative}"))
+            })
+            .collect();
+        rest = &rest[end + 1..];
+    }
+    if rest.contains('}') {
+        return Err(invalid("unbalanced or nested braces"));
+    }
+
+    Ok(names.into_iter().map(|name| name + rest).collect())
+}
+
+// paths often depend on where a container mounts its volumes, `${VAR}` is replaced with the value of
+// the environment variable
+fn interpolate_env_vars(value: &str) -> Result<String> {
+    interpolate(value, |name| std::env::var(name).ok())
+}
+
+fn interpolate(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let invalid = |reason: String| {
+        crate::errors::Error::ValidationError(anyhow!("invalid path {}: {}", value, reason))
+    };
+
+    let mut interpolated = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .map(|i| start + i)
+            .ok_or_else(|| invalid("unclosed variable".to_string()))?;
+        let name = &rest[start + 2..end];
+        if name.is_empty() {
+            return Err(invalid("empty variable name".to_string()));
+        }
+        let variable = lookup(name)
+            .ok_or_else(|| invalid(format!("environment variable {name} is not set")))?;
+        interpolated.push_str(&rest[..start]);
+        interpolated.push_str(&variable);
+        rest = &rest[end + 1..];
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+fn parse_networks(networks: Option<Vec<String>>) -> Result<Vec<IpNetwork>> {
+    networks
+        .unwrap_or_default()
+        .iter()
+        .map(|network| network.parse())
+        .collect()
+}
+
+impl TryFrom<TomlCow> for CowConfig {
+    type Error = crate::errors::Error;
+
+    fn try_from(value: TomlCow) -> std::result::Result<Self, Self::Error> {
+        let cow = CowConfig::new(value.name.try_into()?, value.character.try_into()?);
+        let cow = match value.expected_character {
+            Some(character) => cow.with_expected_character(character.try_into()?),
+            None => cow,
+        };
+        let cow = match value.nickname {
+            Some(nickname) => cow.with_nickname(Nickname::new(nickname)?),
+            None => cow,
+        };
+        let cow = match value.tags {
+            Some(tags) => {
+                cow.with_tags(tags.into_iter().map(Tag::new).collect::<Result<Vec<_>>>()?)
+            }
+            None => cow,
+        };
+        let mut cow = match value.credentials {
+            Some(credentials) => cow.with_credentials(credentials.try_into()?),
+            None => cow,
+        };
+        for (name, value) in value.headers.unwrap_or_default() {
+            cow = cow.with_header(&name, &value)?;
+        }
+        let cow = match value.must_match {
+            Some(pattern) => cow.with_must_match(MustMatch::new(&pattern)?),
+            None => cow,
+        };
+        match value.check_via {
+            Some(check_via) => cow.with_check_via(url::Url::parse(&check_via).map_err(|err| {
+                crate::errors::Error::ValidationError(anyhow!("invalid check_via: {}", err))
+            })?),
+            None => Ok(cow),
+        }
+    }
+}
+
+impl TryFrom<TomlCowCredentials> for CowCredentials {
+    type Error = crate::errors::Error;
+
+    fn try_from(value: TomlCowCredentials) -> std::result::Result<Self, Self::Error> {
+        match (value.username, value.password, value.header, value.value) {
+            (Some(username), Some(password), None, None) => {
+                CowCredentials::new_basic(username, password)
+            }
+            (None, None, Some(header), Some(value)) => CowCredentials::new_header(&header, &value),
+            _ => Err(crate::errors::Error::ValidationError(anyhow!(
+                "cow credentials need either a username and a password or a header and a value"
+            ))),
+        }
+    }
+}
+
+impl app::ConfigSource for ConfigLoader {
+    fn load(&self) -> Result<Config> {
+        ConfigLoader::load(self)
     }
 }
 
@@ -63,18 +577,349 @@ impl TryFrom<String> for Environment {
     }
 }
 
+impl TryFrom<String> for StorageBackend {
+    type Error = crate::errors::Error;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.as_str() {
+            "redb" => Ok(StorageBackend::Redb),
+            "memory" => Ok(StorageBackend::Memory),
+            other => Err(Error::ValidationError(anyhow!(
+                "invalid backend: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for LogFormat {
+    type Error = crate::errors::Error;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            "compact" => Ok(LogFormat::Compact),
+            other => Err(anyhow!("invalid log format: {}", other).into()),
+        }
+    }
+}
+
+impl TryFrom<String> for JsonFieldNaming {
+    type Error = crate::errors::Error;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.as_str() {
+            "snake_case" => Ok(JsonFieldNaming::SnakeCase),
+            "camelCase" => Ok(JsonFieldNaming::CamelCase),
+            other => Err(Error::ValidationError(anyhow!(
+                "invalid json field naming: {}",
+                other
+            ))),
+        }
+    }
+}
+
+// remembers nothing across restarts, the herd is ordered by name just like in the database
+#[derive(Clone, Default)]
+pub struct MemoryInventory {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    cows: BTreeMap<VisibleName, Cow>,
+    bodies: HashMap<VisibleName, String>,
+    character_changes: HashMap<VisibleName, Vec<CharacterChange>>,
+    last_update: app::LastUpdate,
+}
+
+impl MemoryInventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Inventory for MemoryInventory {
+    fn get(&self, name: &VisibleName) -> Result<Option<Cow>> {
+        Ok(self.state.lock().unwrap().cows.get(name).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<Cow>> {
+        Ok(self.state.lock().unwrap().cows.values().cloned().collect())
+    }
+
+    fn update<F>(&self, name: &VisibleName, f: F) -> Result<()>
+    where
+        F: FnOnce(Option<Cow>) -> Result<Option<Cow>>,
+    {
+        let mut f = Some(f);
+        self.update_many(std::slice::from_ref(name), |_, cow| match f.take() {
+            Some(f) => f(cow),
+            None => Err(anyhow!("update function called more than once").into()),
+        })
+    }
+
+    // changes are staged until all of them succeed, just like in a database transaction
+    fn update_many<F>(&self, names: &[VisibleName], mut f: F) -> Result<()>
+    where
+        F: FnMut(&VisibleName, Option<Cow>) -> Result<Option<Cow>>,
+    {
+        let mut state = self.state.lock().unwrap();
+
+        let mut staged: BTreeMap<VisibleName, Cow> = BTreeMap::new();
+        let mut staged_changes: Vec<(VisibleName, CharacterChange)> = vec![];
+        for name in names {
+            let cow = staged.get(name).or_else(|| state.cows.get(name)).cloned();
+            let cow_before = cow.clone();
+            if let Some(cow_to_save) = f(name, cow)? {
+                if let Some(change) =
+                    CharacterChange::new_from_update(cow_before.as_ref(), &cow_to_save)
+                {
+                    staged_changes.push((name.clone(), change));
+                }
+                staged.insert(name.clone(), cow_to_save);
+            }
+        }
+        state.cows.extend(staged);
+        for (name, change) in staged_changes {
+            state
+                .character_changes
+                .entry(name)
+                .or_default()
+                .push(change);
+        }
+        Ok(())
+    }
+
+    // the body and the audit go with the cow just like in the database
+    fn delete(&self, name: &VisibleName) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match state.cows.remove(name) {
+            Some(_) => {
+                state.bodies.remove(name);
+                state.character_changes.remove(name);
+                Ok(())
+            }
+            None => Err(Error::NotFound("cow does not exist".to_string())),
+        }
+    }
+}
+
+impl app::CowBodies for MemoryInventory {
+    fn put(&self, name: &VisibleName, body: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.bodies.insert(name.clone(), body.to_string());
+        Ok(())
+    }
+
+    fn get(&self, name: &VisibleName) -> Result<Option<String>> {
+        Ok(self.state.lock().unwrap().bodies.get(name).cloned())
+    }
+}
+
+impl app::CharacterAudit for MemoryInventory {
+    fn character_changes(&self, name: &VisibleName) -> Result<Vec<CharacterChange>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .character_changes
+            .get(name)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+impl app::UpdateLog for MemoryInventory {
+    fn record_update_started(&self, at: &DateTime) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.last_update = app::LastUpdate::new(
+            Some(at.clone()),
+            state.last_update.finished().cloned(),
+            state.last_update.result(),
+        );
+        Ok(())
+    }
+
+    fn record_update_finished(&self, at: &DateTime, result: app::UpdateResult) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.last_update = app::LastUpdate::new(
+            state.last_update.started().cloned(),
+            Some(at.clone()),
+            Some(result),
+        );
+        Ok(())
+    }
+
+    fn last_update(&self) -> Result<app::LastUpdate> {
+        Ok(self.state.lock().unwrap().last_update.clone())
+    }
+}
+
+// there is no file which could shrink
+impl app::DatabaseCompactor for MemoryInventory {
+    fn compact(&self) -> Result<u64> {
+        Ok(0)
+    }
+}
+
+// the storage can't be a trait object as updates are generic over the update function, every
+// port is backed by the same store so that e.g. deleting a cow can't leave its body behind
+#[derive(Clone)]
+pub enum ConfiguredInventory {
+    Redb(database::Database),
+    Memory(MemoryInventory),
+}
+
+impl ConfiguredInventory {
+    // the path is ignored by the memory backend
+    pub fn open(storage_backend: StorageBackend, path: impl Into<String>) -> Result<Self> {
+        Ok(match storage_backend {
+            StorageBackend::Redb => ConfiguredInventory::Redb(database::Database::new(path)?),
+            StorageBackend::Memory => ConfiguredInventory::Memory(MemoryInventory::new()),
+        })
+    }
+}
+
+impl Inventory for ConfiguredInventory {
+    fn get(&self, name: &VisibleName) -> Result<Option<Cow>> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.get(name),
+            ConfiguredInventory::Memory(inventory) => inventory.get(name),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<Cow>> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.list(),
+            ConfiguredInventory::Memory(inventory) => inventory.list(),
+        }
+    }
+
+    fn update<F>(&self, name: &VisibleName, f: F) -> Result<()>
+    where
+        F: FnOnce(Option<Cow>) -> Result<Option<Cow>>,
+    {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.update(name, f),
+            ConfiguredInventory::Memory(inventory) => inventory.update(name, f),
+        }
+    }
+
+    fn update_many<F>(&self, names: &[VisibleName], f: F) -> Result<()>
+    where
+        F: FnMut(&VisibleName, Option<Cow>) -> Result<Option<Cow>>,
+    {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.update_many(names, f),
+            ConfiguredInventory::Memory(inventory) => inventory.update_many(names, f),
+        }
+    }
+
+    fn delete(&self, name: &VisibleName) -> Result<()> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.delete(name),
+            ConfiguredInventory::Memory(inventory) => inventory.delete(name),
+        }
+    }
+}
+
+impl app::CowBodies for ConfiguredInventory {
+    fn put(&self, name: &VisibleName, body: &str) -> Result<()> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => app::CowBodies::put(inventory, name, body),
+            ConfiguredInventory::Memory(inventory) => app::CowBodies::put(inventory, name, body),
+        }
+    }
+
+    fn get(&self, name: &VisibleName) -> Result<Option<String>> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => app::CowBodies::get(inventory, name),
+            ConfiguredInventory::Memory(inventory) => app::CowBodies::get(inventory, name),
+        }
+    }
+}
+
+impl app::CharacterAudit for ConfiguredInventory {
+    fn character_changes(&self, name: &VisibleName) -> Result<Vec<CharacterChange>> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.character_changes(name),
+            ConfiguredInventory::Memory(inventory) => inventory.character_changes(name),
+        }
+    }
+}
+
+impl app::UpdateLog for ConfiguredInventory {
+    fn record_update_started(&self, at: &DateTime) -> Result<()> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.record_update_started(at),
+            ConfiguredInventory::Memory(inventory) => inventory.record_update_started(at),
+        }
+    }
+
+    fn record_update_finished(&self, at: &DateTime, result: app::UpdateResult) -> Result<()> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.record_update_finished(at, result),
+            ConfiguredInventory::Memory(inventory) => inventory.record_update_finished(at, result),
+        }
+    }
+
+    fn last_update(&self) -> Result<app::LastUpdate> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.last_update(),
+            ConfiguredInventory::Memory(inventory) => inventory.last_update(),
+        }
+    }
+}
+
+impl app::DatabaseCompactor for ConfiguredInventory {
+    fn compact(&self) -> Result<u64> {
+        match self {
+            ConfiguredInventory::Redb(inventory) => inventory.compact(),
+            ConfiguredInventory::Memory(inventory) => inventory.compact(),
+        }
+    }
+}
+
+static OTHER_HANDLER_NAME: &str = "other";
+
+// keeps the number of tag label values bounded no matter how many tags are configured
+static MAX_TAGS_IN_METRICS: usize = 50;
+
 #[derive(Clone)]
 pub struct Metrics {
     registry: Registry,
+    handler_names: Arc<Vec<String>>,
 
     metric_application_handler_calls_counter: CounterVec,
     metric_application_handler_calls_histogram: HistogramVec,
     metric_herd_numbers: GaugeVec,
+    metric_herd_numbers_by_tag: GaugeVec,
+    metric_cows_configured: Gauge,
+    metric_cows_tracked: Gauge,
+    metric_cows_with_unexpected_character: Gauge,
+    metric_circuit_breakers: GaugeVec,
+    metric_inflight_cow_downloads: Gauge,
+    metric_cow_check_failures: CounterVec,
+    metric_cow_update_failures: Counter,
+    metric_cow_content_changes: Counter,
+    metric_update_requests: Gauge,
+    metric_update_deferred_cows: Gauge,
+    metric_last_update_started: Gauge,
+    metric_last_update_finished: Gauge,
+    metric_last_update_succeeded: Gauge,
+    metric_cows_overdue_for_first_check: Gauge,
+    metric_herd_degraded: Gauge,
+    metric_config_reloads: CounterVec,
+    metric_config_last_reload: Gauge,
 }
 
 impl Metrics {
     pub fn new() -> Result<Self> {
-        let registry = Registry::new_custom(Some("moooodotfarm".into()), None)?;
+        Self::new_with_namespace(Some(DEFAULT_METRICS_NAMESPACE))
+    }
+
+    pub fn new_with_namespace(namespace: Option<&str>) -> Result<Self> {
+        let registry = Registry::new_custom(namespace.map(|v| v.to_string()), None)?;
 
         let metric_application_handler_calls_counter = CounterVec::new(
             Opts::new(
@@ -100,18 +945,176 @@ impl Metrics {
         )?;
         registry.register(Box::new(metric_herd_numbers.clone()))?;
 
+        let metric_herd_numbers_by_tag = GaugeVec::new(
+            Opts::new(
+                "herd_numbers_by_tag",
+                "number of cows grouped by tag and status",
+            ),
+            &["tag", "status"],
+        )?;
+        registry.register(Box::new(metric_herd_numbers_by_tag.clone()))?;
+
+        let metric_cows_configured = Gauge::with_opts(Opts::new(
+            "cows_configured",
+            "number of cows declared in the config",
+        ))?;
+        registry.register(Box::new(metric_cows_configured.clone()))?;
+
+        let metric_cows_tracked = Gauge::with_opts(Opts::new(
+            "cows_tracked",
+            "number of cows present in the database",
+        ))?;
+        registry.register(Box::new(metric_cows_tracked.clone()))?;
+
+        let metric_cows_with_unexpected_character = Gauge::with_opts(Opts::new(
+            "cows_with_unexpected_character",
+            "number of cows whose character differs from the one expected in the config",
+        ))?;
+        registry.register(Box::new(metric_cows_with_unexpected_character.clone()))?;
+
+        let metric_circuit_breakers = GaugeVec::new(
+            Opts::new(
+                "circuit_breakers",
+                "number of per-host circuit breakers grouped by state",
+            ),
+            &["state"],
+        )?;
+        registry.register(Box::new(metric_circuit_breakers.clone()))?;
+
+        let metric_inflight_cow_downloads = Gauge::with_opts(Opts::new(
+            "inflight_cow_downloads",
+            "number of cow.txt downloads currently in progress",
+        ))?;
+        registry.register(Box::new(metric_inflight_cow_downloads.clone()))?;
+
+        let metric_cow_check_failures = CounterVec::new(
+            Opts::new(
+                "cow_check_failures",
+                "number of failed cow checks grouped by reason",
+            ),
+            &["reason"],
+        )?;
+        registry.register(Box::new(metric_cow_check_failures.clone()))?;
+
+        let metric_cow_update_failures = Counter::with_opts(Opts::new(
+            "cow_update_failures",
+            "number of cows whose check result couldn't be stored",
+        ))?;
+        registry.register(Box::new(metric_cow_update_failures.clone()))?;
+
+        let metric_cow_content_changes = Counter::with_opts(Opts::new(
+            "cow_content_changes",
+            "number of times a cow was found with a different body than during its last successful check",
+        ))?;
+        registry.register(Box::new(metric_cow_content_changes.clone()))?;
+
+        let metric_update_requests = Gauge::with_opts(Opts::new(
+            "update_requests",
+            "number of cow.txt requests made during the last update",
+        ))?;
+        registry.register(Box::new(metric_update_requests.clone()))?;
+
+        let metric_update_deferred_cows = Gauge::with_opts(Opts::new(
+            "update_deferred_cows",
+            "number of cows deferred to the next update after exceeding the request budget",
+        ))?;
+        registry.register(Box::new(metric_update_deferred_cows.clone()))?;
+
+        let metric_last_update_started = Gauge::with_opts(Opts::new(
+            "last_update_started_timestamp_seconds",
+            "unix timestamp of the moment when the last update started",
+        ))?;
+        registry.register(Box::new(metric_last_update_started.clone()))?;
+
+        let metric_last_update_finished = Gauge::with_opts(Opts::new(
+            "last_update_finished_timestamp_seconds",
+            "unix timestamp of the moment when the last update finished",
+        ))?;
+        registry.register(Box::new(metric_last_update_finished.clone()))?;
+
+        let metric_last_update_succeeded = Gauge::with_opts(Opts::new(
+            "last_update_succeeded",
+            "1 if the last finished update succeeded, 0 otherwise",
+        ))?;
+        registry.register(Box::new(metric_last_update_succeeded.clone()))?;
+
+        let metric_cows_overdue_for_first_check = Gauge::with_opts(Opts::new(
+            "cows_overdue_for_first_check",
+            "number of cows which are still waiting for their first check long after being added",
+        ))?;
+        registry.register(Box::new(metric_cows_overdue_for_first_check.clone()))?;
+
+        let metric_herd_degraded = Gauge::with_opts(Opts::new(
+            "herd_degraded",
+            "1 if more cows ran away during the last update than the configured threshold allows, 0 otherwise",
+        ))?;
+        registry.register(Box::new(metric_herd_degraded.clone()))?;
+
+        let metric_config_reloads = CounterVec::new(
+            Opts::new(
+                "config_reloads_total",
+                "number of config reloads grouped by result",
+            ),
+            &["result"],
+        )?;
+        registry.register(Box::new(metric_config_reloads.clone()))?;
+
+        let metric_config_last_reload = Gauge::with_opts(Opts::new(
+            "config_last_reload_timestamp",
+            "unix timestamp of the moment when the config was last reloaded successfully",
+        ))?;
+        registry.register(Box::new(metric_config_last_reload.clone()))?;
+
         Ok(Self {
             registry,
+            handler_names: Arc::new(
+                DEFAULT_METRICS_HANDLER_NAMES
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect(),
+            ),
 
             metric_application_handler_calls_counter,
             metric_application_handler_calls_histogram,
             metric_herd_numbers,
+            metric_herd_numbers_by_tag,
+            metric_cows_configured,
+            metric_cows_tracked,
+            metric_cows_with_unexpected_character,
+            metric_circuit_breakers,
+            metric_inflight_cow_downloads,
+            metric_cow_check_failures,
+            metric_cow_update_failures,
+            metric_cow_content_changes,
+            metric_update_requests,
+            metric_update_deferred_cows,
+            metric_last_update_started,
+            metric_last_update_finished,
+            metric_last_update_succeeded,
+            metric_cows_overdue_for_first_check,
+            metric_herd_degraded,
+            metric_config_reloads,
+            metric_config_last_reload,
         })
     }
 
+    // see Config::with_metrics_handler_names
+    pub fn with_handler_names(mut self, handler_names: &[String]) -> Self {
+        self.handler_names = Arc::new(handler_names.to_vec());
+        self
+    }
+
     pub fn registry(&self) -> &Registry {
         &self.registry
     }
+
+    fn normalize_handler_name<'a>(&self, handler_name: &'a str) -> &'a str {
+        if self.handler_names.iter().any(|v| v == handler_name) {
+            handler_name
+        } else {
+            OTHER_HANDLER_NAME
+        }
+    }
 }
 
 impl app::Metrics for Metrics {
@@ -121,6 +1124,7 @@ impl app::Metrics for Metrics {
         result: ApplicationHandlerCallResult,
         duration: Duration,
     ) {
+        let handler_name = self.normalize_handler_name(handler_name);
         let labels = labels! {
             "handler_name" => handler_name,
             "result" => match result {
@@ -142,7 +1146,7 @@ impl app::Metrics for Metrics {
         let mut counts: HashMap<&str, i64> = HashMap::new();
 
         for cow in herd.cows() {
-            let status_key = cow_status_as_str(cow.status());
+            let status_key = cow_status_as_str(cow.metrics_status());
             *counts.entry(status_key).or_insert(0) += 1;
         }
 
@@ -154,6 +1158,155 @@ impl app::Metrics for Metrics {
                 .with(&labels! { "status" => status_str })
                 .set(count as f64);
         }
+
+        let mut counts_by_tag: BTreeMap<&Tag, HashMap<&str, i64>> = BTreeMap::new();
+        for cow in herd.cows() {
+            let status_key = cow_status_as_str(cow.metrics_status());
+            for tag in cow.tags() {
+                *counts_by_tag
+                    .entry(tag)
+                    .or_default()
+                    .entry(status_key)
+                    .or_insert(0) += 1;
+            }
+        }
+        if counts_by_tag.len() > MAX_TAGS_IN_METRICS {
+            log::warn!(
+                "only the first {} out of {} tags are reported in metrics",
+                MAX_TAGS_IN_METRICS,
+                counts_by_tag.len()
+            );
+        }
+
+        // tags which are no longer used by any cow shouldn't linger around
+        self.metric_herd_numbers_by_tag.reset();
+        for (tag, counts) in counts_by_tag.iter().take(MAX_TAGS_IN_METRICS) {
+            for status in app::CowStatus::all_variants() {
+                let status_str = cow_status_as_str(status);
+                let count = counts.get(status_str).copied().unwrap_or(0);
+
+                self.metric_herd_numbers_by_tag
+                    .with(&labels! { "tag" => tag.as_str(), "status" => status_str })
+                    .set(count as f64);
+            }
+        }
+
+        let unexpected_character = herd
+            .cows()
+            .iter()
+            .filter(|cow| cow.unexpected_character())
+            .count();
+        // the herd numbers are updated after every update, only a change is worth a warning
+        let changed =
+            self.metric_cows_with_unexpected_character.get() != unexpected_character as f64;
+        if changed && unexpected_character > 0 {
+            log::warn!(
+                "{} cows have a different character than the config expects",
+                unexpected_character
+            );
+        }
+        self.metric_cows_with_unexpected_character
+            .set(unexpected_character as f64);
+    }
+
+    fn update_cow_counts(&self, configured: usize, tracked: usize) {
+        self.metric_cows_configured.set(configured as f64);
+        self.metric_cows_tracked.set(tracked as f64);
+    }
+
+    fn record_cow_download_started(&self) {
+        self.metric_inflight_cow_downloads.inc();
+    }
+
+    fn record_cow_download_finished(&self) {
+        self.metric_inflight_cow_downloads.dec();
+    }
+
+    fn record_cow_check_failure(&self, reason: Option<MissingReason>) {
+        self.metric_cow_check_failures
+            .with(&labels! { "reason" => check_failure_reason_as_str(reason) })
+            .inc();
+    }
+
+    fn record_cow_update_failure(&self) {
+        self.metric_cow_update_failures.inc();
+    }
+
+    fn record_cow_content_change(&self) {
+        self.metric_cow_content_changes.inc();
+    }
+
+    fn update_request_budget_usage(&self, requests: usize, deferred: usize) {
+        self.metric_update_requests.set(requests as f64);
+        self.metric_update_deferred_cows.set(deferred as f64);
+    }
+
+    fn update_circuit_breaker_states(&self, states: &[CircuitBreakerState]) {
+        for state in CircuitBreakerState::all_variants() {
+            let state_str = circuit_breaker_state_as_str(state);
+            let count = states.iter().filter(|v| *v == state).count();
+
+            self.metric_circuit_breakers
+                .with(&labels! { "state" => state_str })
+                .set(count as f64);
+        }
+    }
+
+    fn update_cows_overdue_for_first_check(&self, count: usize) {
+        self.metric_cows_overdue_for_first_check.set(count as f64);
+    }
+
+    fn update_herd_degraded(&self, degraded: bool) {
+        self.metric_herd_degraded
+            .set(if degraded { 1.0 } else { 0.0 });
+    }
+
+    fn update_last_update(&self, last_update: &app::LastUpdate) {
+        if let Some(started) = last_update.started() {
+            self.metric_last_update_started
+                .set(started.unix_timestamp() as f64);
+        }
+        if let Some(finished) = last_update.finished() {
+            self.metric_last_update_finished
+                .set(finished.unix_timestamp() as f64);
+        }
+        if let Some(result) = last_update.result() {
+            self.metric_last_update_succeeded.set(match result {
+                app::UpdateResult::Succeeded => 1.0,
+                app::UpdateResult::Failed => 0.0,
+            });
+        }
+    }
+
+    fn record_config_reload(&self, result: ApplicationHandlerCallResult) {
+        let succeeded = matches!(result, ApplicationHandlerCallResult::Ok);
+        self.metric_config_reloads
+            .with_label_values(&[if succeeded { "ok" } else { "error" }])
+            .inc();
+        if succeeded {
+            self.metric_config_last_reload
+                .set(DateTime::now().unix_timestamp() as f64);
+        }
+    }
+}
+
+// the status code is left out so that the number of label values stays bounded
+fn check_failure_reason_as_str(reason: Option<MissingReason>) -> &'static str {
+    match reason {
+        Some(MissingReason::HttpStatus(_)) => "http_error",
+        Some(MissingReason::Timeout) => "timeout",
+        Some(MissingReason::DnsError) => "dns_error",
+        Some(MissingReason::ValidationFailed) => "validation_failed",
+        Some(MissingReason::ConnectionRefused) => "connection_refused",
+        None => "other",
+    }
+}
+
+fn circuit_breaker_state_as_str(state: &CircuitBreakerState) -> &'static str {
+    match state {
+        CircuitBreakerState::Closed => "closed",
+        CircuitBreakerState::Open => "open",
+        CircuitBreakerState::HalfOpen => "half_open",
     }
 }
 
@@ -162,29 +1315,417 @@ fn cow_status_as_str(status: &app::CowStatus) -> &'static str {
         app::CowStatus::HappilyGrazing => "happily_grazing",
         app::CowStatus::RanAway => "ran_away",
         app::CowStatus::HaveNotCheckedYet => "have_not_checked_yet",
+        app::CowStatus::Paused => "paused",
     }
 }
 
-#[derive(Clone)]
-pub struct CowTxtDownloader {}
+static MAX_REDIRECTS: usize = 10;
+// a server which takes longer than this is reported as timing out instead of holding up the update
+static DEFAULT_DOWNLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
-impl Default for CowTxtDownloader {
-    fn default() -> Self {
-        Self::new()
-    }
+type LookupHost = fn(String) -> BoxFuture<'static, std::io::Result<Vec<SocketAddr>>>;
+
+#[derive(Clone)]
+pub struct CowTxtDownloader {
+    client: reqwest::Client,
+    same_host_client: reqwest::Client,
+    host_policy: Arc<HostPolicy>,
+    lookup_host: LookupHost,
+    redirect_policy: RedirectPolicy,
+    fallback_encoding: &'static encoding_rs::Encoding,
+    credentials: Arc<HashMap<VisibleName, CowCredentials>>,
+    headers: Arc<HashMap<VisibleName, http::HeaderMap>>,
+    must_match: Option<MustMatch>,
+    must_match_per_cow: Arc<HashMap<VisibleName, MustMatch>>,
+    check_via: Arc<HashMap<VisibleName, url::Url>>,
+    check_cache: Option<CheckCache>,
+    timeout: std::time::Duration,
 }
 
 impl CowTxtDownloader {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(
+        host_policy: HostPolicy,
+        fallback_encoding: &'static encoding_rs::Encoding,
+    ) -> Result<Self> {
+        let host_policy = Arc::new(host_policy);
+        let redirect_policy = RedirectPolicy::default();
+        Ok(Self {
+            client: build_client(host_policy.clone(), lookup_host, redirect_policy)?,
+            same_host_client: build_client(
+                host_policy.clone(),
+                lookup_host,
+                RedirectPolicy::default(),
+            )?,
+            host_policy,
+            lookup_host,
+            redirect_policy,
+            fallback_encoding,
+            credentials: Arc::new(HashMap::new()),
+            headers: Arc::new(HashMap::new()),
+            must_match: None,
+            must_match_per_cow: Arc::new(HashMap::new()),
+            check_via: Arc::new(HashMap::new()),
+            check_cache: None,
+            timeout: DEFAULT_DOWNLOAD_TIMEOUT,
+        })
+    }
+
+    // credentials are only read at startup, changing them requires a restart
+    pub fn with_credentials(mut self, credentials: HashMap<VisibleName, CowCredentials>) -> Self {
+        self.credentials = Arc::new(credentials);
+        self
+    }
+
+    // same as credentials, custom headers are only read at startup
+    pub fn with_headers(mut self, headers: HashMap<VisibleName, http::HeaderMap>) -> Self {
+        self.headers = Arc::new(headers);
+        self
+    }
+
+    // a pattern of a cow replaces the global one instead of being checked on top of it
+    pub fn with_must_match(
+        mut self,
+        must_match: Option<MustMatch>,
+        must_match_per_cow: HashMap<VisibleName, MustMatch>,
+    ) -> Self {
+        self.must_match = must_match;
+        self.must_match_per_cow = Arc::new(must_match_per_cow);
+        self
+    }
+
+    // the override is only used to reach the cow, it never shows up as the name of the cow
+    pub fn with_check_via(mut self, check_via: HashMap<VisibleName, url::Url>) -> Self {
+        self.check_via = Arc::new(check_via);
+        self
+    }
+
+    // None disables the cache, every check downloads the cow again
+    pub fn with_check_cache_ttl(mut self, ttl: Option<std::time::Duration>) -> Self {
+        self.check_cache = ttl.map(CheckCache::new);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_redirect_policy(mut self, redirect_policy: RedirectPolicy) -> Result<Self> {
+        self.client = build_client(self.host_policy.clone(), self.lookup_host, redirect_policy)?;
+        self.redirect_policy = redirect_policy;
+        Ok(self)
+    }
+
+    #[cfg(test)]
+    fn with_lookup_host(mut self, lookup_host: LookupHost) -> Result<Self> {
+        self.client = build_client(self.host_policy.clone(), lookup_host, self.redirect_policy)?;
+        self.same_host_client = build_client(
+            self.host_policy.clone(),
+            lookup_host,
+            RedirectPolicy::default(),
+        )?;
+        self.lookup_host = lookup_host;
+        Ok(self)
     }
 }
 
 #[async_trait]
 impl app::CowTxtDownloader for CowTxtDownloader {
     async fn download(&self, name: &VisibleName) -> Result<CowTxt<'_>> {
-        let cow_body = reqwest::get(name.url().to_string()).await?.text().await?;
-        CowTxt::new(cow_body)
+        let check_via = self.check_via.get(name);
+        let url = check_via.unwrap_or(name.url());
+        check_literal_host(&self.host_policy, url)?;
+        let credentials = self.credentials.get(name);
+        let headers = self.headers.get(name);
+        // whatever is served to a cow with credentials or headers is never shared with other cows
+        let check_cache = self
+            .check_cache
+            .as_ref()
+            .filter(|_| credentials.is_none() && headers.is_none());
+        let (cow_txt, final_url) = match check_cache {
+            Some(check_cache) => {
+                check_cache
+                    .get_or_fetch(url, || self.fetch(name, url, credentials, headers))
+                    .await?
+            }
+            None => self.fetch(name, url, credentials, headers).await?,
+        };
+        let redirected_to = match self.redirect_policy.identity() {
+            RedirectIdentity::FinalUrl if check_via.is_none() && &final_url != url => {
+                VisibleName::new(final_url.as_str())
+                    .inspect_err(|err| {
+                        log::debug!("cow {} redirected to an invalid name: {}", name.url(), err)
+                    })
+                    .ok()
+            }
+            _ => None,
+        };
+        if let Some(must_match) = self
+            .must_match_per_cow
+            .get(name)
+            .or(self.must_match.as_ref())
+        {
+            cow_txt.ensure_matches(must_match)?;
+        }
+        Ok(cow_txt.with_redirected_to(redirected_to))
+    }
+}
+
+impl CowTxtDownloader {
+    // returns the cow together with the url it was found at after following redirects
+    async fn fetch(
+        &self,
+        name: &VisibleName,
+        url: &url::Url,
+        credentials: Option<&CowCredentials>,
+        headers: Option<&http::HeaderMap>,
+    ) -> Result<(CowTxt<'static>, url::Url)> {
+        let client = if credentials.is_some() || headers.is_some() {
+            &self.same_host_client
+        } else {
+            &self.client
+        };
+
+        let mut request = client.get(url.to_string()).timeout(self.timeout);
+        if let Some(headers) = headers {
+            log::debug!(
+                "sending custom headers to {}: {:?}",
+                name.url(),
+                headers.keys().collect::<Vec<_>>()
+            );
+            request = request.headers(headers.clone());
+        }
+        request = match credentials {
+            Some(CowCredentials::Basic { username, password }) => {
+                request.basic_auth(username, Some(password))
+            }
+            Some(CowCredentials::Header {
+                name: header_name,
+                value,
+            }) => request.header(header_name.clone(), value.clone()),
+            None => request,
+        };
+        let response = request.send().await.map_err(classify_request_error)?;
+        let final_url = response.url().clone();
+        let charset = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(charset_from_content_type)
+            .map(|v| v.to_string());
+        let status = response.status();
+        let cow_body = response.bytes().await?;
+        // servers answering with an error page are reported by their status, a cow served with an
+        // unusual status is still a cow
+        let cow_txt = match CowTxt::new(decode_cow_body(
+            &cow_body,
+            charset.as_deref(),
+            self.fallback_encoding,
+        )) {
+            Ok(cow_txt) => cow_txt,
+            Err(_) if !status.is_success() => {
+                return Err(Error::UnexpectedHttpStatus(status.as_u16()));
+            }
+            Err(err) => return Err(err),
+        };
+        Ok((cow_txt, final_url))
+    }
+}
+
+// only cows which were found are cached so that a cow which ran away is retried on the next check,
+// the patterns are checked on every use as cows sharing a url may be given different ones. cows
+// sharing a url which are checked at the same time wait for a single download
+#[derive(Clone)]
+struct CheckCache {
+    ttl: std::time::Duration,
+    entries: Arc<Mutex<HashMap<url::Url, Arc<tokio::sync::OnceCell<CachedCheck>>>>>,
+}
+
+struct CachedCheck {
+    cow_txt: CowTxt<'static>,
+    final_url: url::Url,
+    checked_at: std::time::Instant,
+}
+
+impl CheckCache {
+    fn new(ttl: std::time::Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // a failed download leaves the entry empty so whoever waited for it downloads the cow again
+    async fn get_or_fetch<F, Fut>(
+        &self,
+        url: &url::Url,
+        fetch: F,
+    ) -> Result<(CowTxt<'static>, url::Url)>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(CowTxt<'static>, url::Url)>>,
+    {
+        let entry = {
+            let mut entries = self.entries.lock().unwrap();
+            // expired entries are dropped here so that cows which are gone don't stay around
+            // forever, entries which are still being downloaded are kept for whoever waits for them
+            entries.retain(|_, entry| match entry.get() {
+                Some(cached) => cached.checked_at.elapsed() < self.ttl,
+                None => Arc::strong_count(entry) > 1,
+            });
+            entries.entry(Self::key(url)).or_default().clone()
+        };
+        let mut fetched = false;
+        let cached = entry
+            .get_or_try_init(|| async {
+                fetched = true;
+                let (cow_txt, final_url) = fetch().await?;
+                Ok::<_, Error>(CachedCheck {
+                    cow_txt,
+                    final_url,
+                    checked_at: std::time::Instant::now(),
+                })
+            })
+            .await?;
+        if !fetched {
+            log::debug!("reusing the recent check of {}", url);
+        }
+        Ok((cached.cow_txt.clone(), cached.final_url.clone()))
+    }
+
+    // parsing already normalizes the scheme, host and port, fragments are never sent to the server
+    fn key(url: &url::Url) -> url::Url {
+        let mut url = url.clone();
+        url.set_fragment(None);
+        url
+    }
+}
+
+// reqwest only strips the standard authorization headers when redirected to a different host, so
+// requests carrying credentials or custom headers are built with the default redirect policy which
+// never leaves the host of the cow
+fn build_client(
+    host_policy: Arc<HostPolicy>,
+    lookup_host: LookupHost,
+    redirect_policy: RedirectPolicy,
+) -> Result<reqwest::Client> {
+    let redirect_host_policy = host_policy.clone();
+    Ok(reqwest::Client::builder()
+        .dns_resolver(HostPolicyResolver {
+            host_policy,
+            lookup_host,
+        })
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("too many redirects");
+            }
+            let allowed = attempt
+                .previous()
+                .last()
+                .is_none_or(|previous| redirect_policy.allows(previous, attempt.url()));
+            if !allowed {
+                return attempt.error("refusing to follow a redirect to a different host");
+            }
+            match check_literal_host(&redirect_host_policy, attempt.url()) {
+                Ok(_) => attempt.follow(),
+                Err(err) => attempt.error(err),
+            }
+        }))
+        .build()?)
+}
+
+fn charset_from_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|parameter| {
+        let (key, value) = parameter.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+// some cow servers mislabel the encoding or don't declare it at all, a body which can't be decoded
+// using the declared charset (or utf-8 if there is none) is decoded using the fallback encoding
+fn decode_cow_body(
+    body: &[u8],
+    charset: Option<&str>,
+    fallback_encoding: &'static encoding_rs::Encoding,
+) -> String {
+    let declared_encoding = charset
+        .and_then(|charset| encoding_rs::Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    if let Some(decoded) =
+        declared_encoding.decode_without_bom_handling_and_without_replacement(body)
+    {
+        return decoded.into_owned();
+    }
+    let (decoded, _, _) = fallback_encoding.decode(body);
+    decoded.into_owned()
+}
+
+// hosts which are domain names are checked by the resolver but addresses never reach it
+fn check_literal_host(host_policy: &HostPolicy, url: &url::Url) -> Result<()> {
+    match url.host() {
+        Some(url::Host::Ipv4(ip)) => host_policy.check(&IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => host_policy.check(&IpAddr::V6(ip)),
+        Some(url::Host::Domain(_)) => Ok(()),
+        None => Err(anyhow!("url has no host").into()),
+    }
+}
+
+// reqwest wraps errors of the resolver in a few layers of its own
+fn classify_request_error(err: reqwest::Error) -> Error {
+    let mut source = std::error::Error::source(&err);
+    while let Some(cause) = source {
+        if let Some(unresolved) = cause.downcast_ref::<UnresolvedHost>() {
+            return Error::DomainNotFound(unresolved.host.clone());
+        }
+        if let Some(io_err) = cause.downcast_ref::<std::io::Error>()
+            && io_err.kind() == std::io::ErrorKind::ConnectionRefused
+        {
+            let host = err.url().and_then(|url| url.host_str()).unwrap_or_default();
+            return Error::ConnectionRefused(host.to_string());
+        }
+        source = cause.source();
+    }
+    if err.is_timeout() {
+        let url = err.url().map(|url| url.to_string()).unwrap_or_default();
+        return Error::Timeout(url);
+    }
+    err.into()
+}
+
+fn lookup_host(host: String) -> BoxFuture<'static, std::io::Result<Vec<SocketAddr>>> {
+    Box::pin(async move { Ok(tokio::net::lookup_host((host.as_str(), 0)).await?.collect()) })
+}
+
+// kept apart from the host policy refusing an address, which is not a problem with the domain
+#[derive(Debug, thiserror::Error)]
+#[error("failed to resolve {host}: {source}")]
+struct UnresolvedHost {
+    host: String,
+    source: std::io::Error,
+}
+
+struct HostPolicyResolver {
+    host_policy: Arc<HostPolicy>,
+    lookup_host: LookupHost,
+}
+
+impl reqwest::dns::Resolve for HostPolicyResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host_policy = self.host_policy.clone();
+        let lookup = (self.lookup_host)(name.as_str().to_string());
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = lookup
+                .await
+                .map_err(|source| UnresolvedHost { host, source })?;
+            for addr in &addrs {
+                host_policy.check(&addr.ip())?;
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(addrs)
+        })
     }
 }
 
@@ -192,7 +1733,9 @@ impl app::CowTxtDownloader for CowTxtDownloader {
 mod tests {
     use super::*;
     use crate::config::Config;
+    use crate::domain;
     use crate::fixtures;
+    use axum::response::IntoResponse;
 
     #[test]
     fn loads_config_from_file_successfully() -> Result<()> {
@@ -209,4 +1752,1205 @@ mod tests {
         assert_eq!(expected_config, config);
         Ok(())
     }
+
+    #[test]
+    fn config_can_be_read_from_stdin() -> Result<()> {
+        let content = fs::read_to_string(fixtures::test_file_path(
+            "src/adapters/testdata/config_with_cows.toml",
+        ))?;
+        let loader = ConfigLoader::new("-");
+        assert_eq!(loader.read_stdin(content.as_bytes())?, content);
+        assert_eq!(loader.read_stdin(&b""[..])?, content);
+
+        let config = loader.load()?;
+        assert_eq!(config.cows().map(|cows| cows.len()), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn updates_configured_and_tracked_cow_counts() -> Result<()> {
+        let config = ConfigLoader::new(fixtures::test_file_path(
+            "src/adapters/testdata/config_with_cows.toml",
+        ))
+        .load()?;
+        let database = database::Database::new_in_memory()?;
+        let name: VisibleName = "https://example.com/cow.txt".to_string().try_into()?;
+        app::Inventory::update(&database, &name, |_| {
+            Ok(Some(domain::Cow::new(
+                name.clone(),
+                domain::Character::Brave,
+            )))
+        })?;
+        let metrics = Metrics::new()?;
+
+        app::update_cow_counts(&config, &database, &metrics)?;
+
+        let gauge = |name: &str| {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == format!("moooodotfarm_{name}"))
+                .map(|family| family.get_metric()[0].get_gauge().value())
+        };
+        assert_eq!(gauge("cows_configured"), Some(2.0));
+        assert_eq!(gauge("cows_tracked"), Some(1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn metric_names_carry_the_configured_namespace() -> Result<()> {
+        let names = |metrics: &Metrics| {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .map(|family| family.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let metrics = Metrics::new_with_namespace(Some("farm"))?;
+        assert!(names(&metrics).contains(&"farm_cows_tracked".to_string()));
+
+        let metrics = Metrics::new_with_namespace(None)?;
+        assert!(names(&metrics).contains(&"cows_tracked".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn herd_numbers_ignore_a_single_failed_check() -> Result<()> {
+        let metrics = Metrics::new()?;
+        let mut cow = domain::Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            domain::Character::Brave,
+        );
+        let ran_away = |cow: &domain::Cow| -> Result<f64> {
+            let herd: Herd =
+                domain::CensoredHerd::new(vec![domain::CensoredCow::new(cow)?]).try_into()?;
+            app::Metrics::update_herd_numbers(&metrics, &herd);
+            Ok(metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == "moooodotfarm_herd_numbers")
+                .and_then(|family| {
+                    family.get_metric().iter().find(|metric| {
+                        metric
+                            .get_label()
+                            .iter()
+                            .any(|label| label.value() == "ran_away")
+                    })
+                })
+                .map(|metric| metric.get_gauge().value())
+                .unwrap_or_default())
+        };
+
+        cow.mark_as_missing()?;
+        assert_eq!(ran_away(&cow)?, 0.0);
+
+        cow.mark_as_missing()?;
+        cow.mark_as_missing()?;
+        assert_eq!(ran_away(&cow)?, 1.0);
+
+        cow.mark_as_ok()?;
+        assert_eq!(ran_away(&cow)?, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn paused_cows_are_counted_apart_from_ran_away_ones() -> Result<()> {
+        let metrics = Metrics::new()?;
+        let mut cow = domain::Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            domain::Character::Brave,
+        );
+        for _ in 0..3 {
+            cow.mark_as_missing()?;
+        }
+        cow.pause()?;
+
+        let herd: Herd =
+            domain::CensoredHerd::new(vec![domain::CensoredCow::new(&cow)?]).try_into()?;
+        app::Metrics::update_herd_numbers(&metrics, &herd);
+
+        let herd_numbers: HashMap<String, f64> = metrics
+            .registry()
+            .gather()
+            .iter()
+            .filter(|family| family.name() == "moooodotfarm_herd_numbers")
+            .flat_map(|family| family.get_metric().iter())
+            .map(|metric| {
+                (
+                    metric.get_label()[0].value().to_string(),
+                    metric.get_gauge().value(),
+                )
+            })
+            .collect();
+        assert_eq!(herd_numbers.get("paused"), Some(&1.0));
+        assert_eq!(herd_numbers.get("ran_away"), Some(&0.0));
+        Ok(())
+    }
+
+    #[test]
+    fn cows_with_an_unexpected_character_are_counted() -> Result<()> {
+        let metrics = Metrics::new()?;
+        let mut cow = domain::Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            domain::Character::Shy,
+        )
+        .with_expected_character(Some(domain::Character::Brave));
+        let unexpected_character = |cow: &domain::Cow| -> Result<f64> {
+            let herd: Herd =
+                domain::CensoredHerd::new(vec![domain::CensoredCow::new(cow)?]).try_into()?;
+            assert_eq!(
+                herd.cows()[0].unexpected_character(),
+                cow.has_unexpected_character()
+            );
+            app::Metrics::update_herd_numbers(&metrics, &herd);
+            Ok(metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == "moooodotfarm_cows_with_unexpected_character")
+                .map(|family| family.get_metric()[0].get_gauge().value())
+                .unwrap_or_default())
+        };
+
+        assert_eq!(unexpected_character(&cow)?, 1.0);
+
+        cow.change_character(domain::Character::Brave)?;
+        assert_eq!(unexpected_character(&cow)?, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn herd_numbers_are_labelled_with_tags() -> Result<()> {
+        let metrics = Metrics::new()?;
+        let dairy = Tag::new("dairy")?;
+        let beef = Tag::new("beef")?;
+        let mut grazing = domain::Cow::new(
+            VisibleName::new("https://a.example.com/cow.txt")?,
+            domain::Character::Brave,
+        )
+        .with_tags(vec![dairy.clone(), beef.clone()]);
+        grazing.mark_as_ok()?;
+        let not_checked = domain::Cow::new(
+            VisibleName::new("https://b.example.com/cow.txt")?,
+            domain::Character::Brave,
+        )
+        .with_tags(vec![dairy.clone()]);
+        // shy cows don't reveal their tags, not even in the metrics
+        let mut shy = domain::Cow::new(
+            VisibleName::new("https://c.example.com/cow.txt")?,
+            domain::Character::Shy,
+        )
+        .with_tags(vec![dairy.clone()]);
+        shy.mark_as_ok()?;
+
+        let herd_of = |cows: &[&domain::Cow]| -> Result<Herd> {
+            domain::CensoredHerd::new(
+                cows.iter()
+                    .map(|cow| domain::CensoredCow::new(cow))
+                    .collect::<Result<Vec<_>>>()?,
+            )
+            .try_into()
+        };
+        let tagged = || -> Vec<(String, String, f64)> {
+            let mut values: Vec<_> = metrics
+                .registry()
+                .gather()
+                .iter()
+                .filter(|family| family.name() == "moooodotfarm_herd_numbers_by_tag")
+                .flat_map(|family| family.get_metric().to_vec())
+                .filter(|metric| metric.get_gauge().value() > 0.0)
+                .map(|metric| {
+                    let label = |name: &str| {
+                        metric
+                            .get_label()
+                            .iter()
+                            .find(|label| label.name() == name)
+                            .map(|label| label.value().to_string())
+                            .unwrap_or_default()
+                    };
+                    (label("tag"), label("status"), metric.get_gauge().value())
+                })
+                .collect();
+            values.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+            values
+        };
+
+        app::Metrics::update_herd_numbers(&metrics, &herd_of(&[&grazing, &not_checked, &shy])?);
+        assert_eq!(
+            tagged(),
+            vec![
+                ("beef".to_string(), "happily_grazing".to_string(), 1.0),
+                ("dairy".to_string(), "happily_grazing".to_string(), 1.0),
+                ("dairy".to_string(), "have_not_checked_yet".to_string(), 1.0),
+            ]
+        );
+
+        app::Metrics::update_herd_numbers(&metrics, &herd_of(&[&not_checked])?);
+        assert_eq!(
+            tagged(),
+            vec![("dairy".to_string(), "have_not_checked_yet".to_string(), 1.0)]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_refuses_to_contact_blocked_addresses() -> Result<()> {
+        let downloader = CowTxtDownloader::new(HostPolicy::default(), encoding_rs::UTF_8)?;
+        let name = VisibleName::new("http://169.254.169.254/cow.txt")?;
+
+        let result = app::CowTxtDownloader::download(&downloader, &name).await;
+
+        match result {
+            Err(err) => assert!(err.to_string().contains("not allowed"), "{err}"),
+            Ok(_) => panic!("download should have been refused"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_tells_unresolvable_domains_apart() -> Result<()> {
+        let downloader = CowTxtDownloader::new(HostPolicy::default(), encoding_rs::UTF_8)?
+            .with_lookup_host(|_| {
+                Box::pin(async { Err(std::io::Error::other("name or service not known")) })
+            })?;
+        let name = VisibleName::new("http://gone.example.com/cow.txt")?;
+
+        let result = app::CowTxtDownloader::download(&downloader, &name).await;
+
+        match result {
+            Err(Error::DomainNotFound(host)) => assert_eq!(host, "gone.example.com"),
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("download should have failed"),
+        }
+
+        let downloader = downloader.with_lookup_host(|_| {
+            Box::pin(async { Ok(vec![SocketAddr::from(([169, 254, 169, 254], 0))]) })
+        })?;
+        let result = app::CowTxtDownloader::download(&downloader, &name).await;
+        assert!(
+            matches!(result, Err(ref err) if !matches!(err, Error::DomainNotFound(_))),
+            "blocked addresses aren't a dns error"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_tells_refused_connections_apart() -> Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        drop(listener);
+        let downloader = CowTxtDownloader::new(
+            HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]),
+            encoding_rs::UTF_8,
+        )?;
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+
+        let result = app::CowTxtDownloader::download(&downloader, &name).await;
+
+        match result {
+            Err(Error::ConnectionRefused(host)) => assert_eq!(host, "127.0.0.1"),
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("download should have failed"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_reports_the_status_of_error_pages() -> Result<()> {
+        let cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let router = axum::Router::new()
+            .route(
+                "/gone/cow.txt",
+                axum::routing::get(|| async { (http::StatusCode::NOT_FOUND, "not found") }),
+            )
+            .route(
+                "/cow.txt",
+                axum::routing::get(move || async move {
+                    (http::StatusCode::INTERNAL_SERVER_ERROR, cow_body)
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let downloader = CowTxtDownloader::new(
+            HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]),
+            encoding_rs::UTF_8,
+        )?;
+
+        let name = VisibleName::new(format!("http://{address}/gone/cow.txt"))?;
+        let result = app::CowTxtDownloader::download(&downloader, &name).await;
+        match result {
+            Err(Error::UnexpectedHttpStatus(status)) => assert_eq!(status, 404),
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("download should have failed"),
+        }
+
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+        app::CowTxtDownloader::download(&downloader, &name).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn cow_names_are_brace_expanded() -> Result<()> {
+        assert_eq!(
+            expand_cow_name("https://{a,b,c}.example.com/cow.txt")?,
+            vec![
+                "https://a.example.com/cow.txt",
+                "https://b.example.com/cow.txt",
+                "https://c.example.com/cow.txt",
+            ]
+        );
+        assert_eq!(
+            expand_cow_name("https://{a,b}.example.{com,org}/cow.txt")?,
+            vec![
+                "https://a.example.com/cow.txt",
+                "https://a.example.org/cow.txt",
+                "https://b.example.com/cow.txt",
+                "https://b.example.org/cow.txt",
+            ]
+        );
+        assert_eq!(
+            expand_cow_name("https://example.com/cow.txt")?,
+            vec!["https://example.com/cow.txt"]
+        );
+
+        for pattern in [
+            "https://{a,b.example.com/cow.txt",
+            "https://a}.example.com/cow.txt",
+            "https://{a,{b,c}}.example.com/cow.txt",
+        ] {
+            assert!(expand_cow_name(pattern).is_err(), "{pattern}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn environment_variables_are_interpolated_into_paths() -> Result<()> {
+        let lookup = |name: &str| (name == "DATA_DIR").then(|| "/var/lib/farm".to_string());
+
+        assert_eq!(
+            interpolate("${DATA_DIR}/moooodotfarm.db", lookup)?,
+            "/var/lib/farm/moooodotfarm.db"
+        );
+        assert_eq!(
+            interpolate("${DATA_DIR}/${DATA_DIR}.db", lookup)?,
+            "/var/lib/farm//var/lib/farm.db"
+        );
+        assert_eq!(
+            interpolate("/data/moooodotfarm.db", lookup)?,
+            "/data/moooodotfarm.db"
+        );
+        assert_eq!(
+            interpolate("/data/$DATA_DIR.db", lookup)?,
+            "/data/$DATA_DIR.db"
+        );
+
+        for value in ["${DATA_DIR/moooodotfarm.db", "${}/moooodotfarm.db"] {
+            assert!(interpolate(value, lookup).is_err(), "{value}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn undefined_environment_variables_are_reported_by_name() -> Result<()> {
+        match interpolate("${MOOOODOTFARM_UNDEFINED}/moooodotfarm.db", |_| None) {
+            Err(err) => assert!(
+                err.to_string()
+                    .contains("MOOOODOTFARM_UNDEFINED is not set"),
+                "{err}"
+            ),
+            Ok(path) => panic!("interpolated into {path}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn over_large_cow_name_expansions_are_rejected() -> Result<()> {
+        let group = format!(
+            "{{{}}}",
+            (0..10).map(|i| i.to_string()).collect::<Vec<_>>().join(",")
+        );
+        let pattern = format!("https://{group}{group}.example.com/cow.txt");
+
+        match expand_cow_name(&pattern) {
+            Err(crate::errors::Error::ValidationError(err)) => {
+                assert!(err.to_string().contains("more than"), "{err}")
+            }
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(names) => panic!("expanded into {} cows", names.len()),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_outcomes_tell_bad_bodies_server_errors_and_timeouts_apart() -> Result<()> {
+        let router = axum::Router::new()
+            .route(
+                "/bad-body/cow.txt",
+                axum::routing::get(|| async { "not a cow" }),
+            )
+            .route(
+                "/server-error/cow.txt",
+                axum::routing::get(|| async { (http::StatusCode::INTERNAL_SERVER_ERROR, "oops") }),
+            )
+            .route(
+                "/slow/cow.txt",
+                axum::routing::get(|| async {
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    "too late"
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let downloader = CowTxtDownloader::new(
+            HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]),
+            encoding_rs::UTF_8,
+        )?
+        .with_timeout(std::time::Duration::from_millis(200));
+
+        for (path, expected_reason, expected_label) in [
+            (
+                "bad-body",
+                domain::MissingReason::ValidationFailed,
+                "validation_failed",
+            ),
+            (
+                "server-error",
+                domain::MissingReason::HttpStatus(500),
+                "http_error",
+            ),
+            ("slow", domain::MissingReason::Timeout, "timeout"),
+        ] {
+            let name = VisibleName::new(format!("http://{address}/{path}/cow.txt"))?;
+            let result = app::CowTxtDownloader::download(&downloader, &name).await;
+
+            let reason = domain::MissingReason::from_error(&result.err().unwrap());
+            assert_eq!(reason, Some(expected_reason), "{path}");
+            assert_eq!(
+                check_failure_reason_as_str(reason),
+                expected_label,
+                "{path}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn loads_config_with_logging_from_file_successfully() -> Result<()> {
+        let loader = ConfigLoader::new(fixtures::test_file_path(
+            "src/adapters/testdata/config_with_logging.toml",
+        ));
+        let config = loader.load()?;
+        assert_eq!(config.log_level(), log::LevelFilter::Debug);
+        assert_eq!(config.log_format(), &LogFormat::Json);
+        Ok(())
+    }
+
+    #[test]
+    fn loads_config_with_cows_from_file_successfully() -> Result<()> {
+        let loader = ConfigLoader::new(fixtures::test_file_path(
+            "src/adapters/testdata/config_with_cows.toml",
+        ));
+        let config = loader.load()?;
+        assert_eq!(
+            config.cows(),
+            Some(
+                &[
+                    CowConfig::new(
+                        "https://example.com/cow.txt".to_string().try_into()?,
+                        domain::Character::Brave,
+                    )
+                    .with_nickname(domain::Nickname::new("Bessie")?)
+                    .with_header("X-Api-Key", "moo")?,
+                    CowConfig::new(
+                        "https://example.org/cow.txt".to_string().try_into()?,
+                        domain::Character::Shy,
+                    )
+                    .with_expected_character(domain::Character::Shy)
+                    .with_credentials(CowCredentials::new_basic("farmer", "hunter2")?),
+                ][..]
+            )
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_decodes_mislabelled_cows_using_the_fallback_encoding() -> Result<()> {
+        let caption = "Grüß Gott, ich bin die Kuh aus Köln!";
+        let mut cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let (encoded_caption, _, _) = encoding_rs::WINDOWS_1252.encode(caption);
+        cow_body.extend_from_slice(&encoded_caption);
+
+        for content_type in ["text/plain", "text/plain; charset=utf-8"] {
+            let cow_body = cow_body.clone();
+            let router = axum::Router::new().route(
+                "/cow.txt",
+                axum::routing::get(move || async move {
+                    ([(http::header::CONTENT_TYPE, content_type)], cow_body)
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+            let address = listener.local_addr()?;
+            tokio::spawn(async move { axum::serve(listener, router).await });
+
+            let downloader = CowTxtDownloader::new(
+                HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]),
+                encoding_rs::WINDOWS_1252,
+            )?;
+            let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+
+            let cow_txt = app::CowTxtDownloader::download(&downloader, &name).await?;
+
+            assert!(
+                cow_txt.content().ends_with(caption),
+                "Failed for content type: {content_type}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_uses_basic_auth_credentials_of_the_cow() -> Result<()> {
+        let cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let router = axum::Router::new().route(
+            "/cow.txt",
+            axum::routing::get(move |headers: http::HeaderMap| async move {
+                // "farmer:hunter2"
+                let expected = "Basic ZmFybWVyOmh1bnRlcjI=";
+                match headers.get(http::header::AUTHORIZATION) {
+                    Some(value) if value == expected => {
+                        (http::StatusCode::OK, cow_body).into_response()
+                    }
+                    _ => http::StatusCode::UNAUTHORIZED.into_response(),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let host_policy = HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]);
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+
+        let downloader = CowTxtDownloader::new(host_policy.clone(), encoding_rs::UTF_8)?;
+        assert!(
+            app::CowTxtDownloader::download(&downloader, &name)
+                .await
+                .is_err()
+        );
+
+        for (password, expected_ok) in [("wrong", false), ("hunter2", true)] {
+            let downloader =
+                CowTxtDownloader::new(host_policy.clone(), encoding_rs::UTF_8)?.with_credentials(
+                    HashMap::from([(name.clone(), CowCredentials::new_basic("farmer", password)?)]),
+                );
+            assert_eq!(
+                app::CowTxtDownloader::download(&downloader, &name)
+                    .await
+                    .is_ok(),
+                expected_ok,
+                "Failed for password: {password}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cow_credentials_are_not_revealed_when_printed() -> Result<()> {
+        let basic = CowCredentials::new_basic("farmer", "hunter2")?;
+        let header = CowCredentials::new_header("x-api-key", "hunter2")?;
+        for credentials in [basic, header] {
+            let printed = format!(
+                "{:?}",
+                CowConfig::new(
+                    VisibleName::new("https://example.com/cow.txt")?,
+                    domain::Character::Brave,
+                )
+                .with_credentials(credentials)
+            );
+            assert!(!printed.contains("hunter2"), "{printed}");
+        }
+        assert!(CowCredentials::new_header("x api key", "value").is_err());
+        assert!(CowCredentials::new_header("x-api-key", "line\nbreak").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_sends_custom_headers_of_the_cow() -> Result<()> {
+        let cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let router = axum::Router::new().route(
+            "/cow.txt",
+            axum::routing::get(move |headers: http::HeaderMap| async move {
+                match headers.get("x-api-key") {
+                    Some(value) if value == "moo" => {
+                        (http::StatusCode::OK, cow_body).into_response()
+                    }
+                    _ => http::StatusCode::FORBIDDEN.into_response(),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let host_policy = HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]);
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+
+        for (value, expected_ok) in [(None, false), (Some("oink"), false), (Some("moo"), true)] {
+            let mut cow = CowConfig::new(name.clone(), domain::Character::Brave);
+            if let Some(value) = value {
+                cow = cow.with_header("X-Api-Key", value)?;
+            }
+            let downloader = CowTxtDownloader::new(host_policy.clone(), encoding_rs::UTF_8)?
+                .with_headers(HashMap::from([(name.clone(), cow.headers().clone())]));
+            assert_eq!(
+                app::CowTxtDownloader::download(&downloader, &name)
+                    .await
+                    .is_ok(),
+                expected_ok,
+                "Failed for value: {value:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_checks_cows_via_their_override() -> Result<()> {
+        let cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let router = axum::Router::new().route(
+            "/internal/cow.txt",
+            axum::routing::get(move || async move { cow_body }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let name = VisibleName::new("https://cow.example.com/cow.txt")?;
+        let cow = CowConfig::new(name.clone(), domain::Character::Brave).with_check_via(
+            url::Url::parse(&format!("http://{address}/internal/cow.txt"))?,
+        )?;
+        let downloader = CowTxtDownloader::new(
+            HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]),
+            encoding_rs::UTF_8,
+        )?
+        .with_redirect_policy(RedirectPolicy::new(false, RedirectIdentity::FinalUrl))?
+        .with_check_via(HashMap::from([(
+            name.clone(),
+            cow.check_via().unwrap().clone(),
+        )]));
+
+        let cow_txt = app::CowTxtDownloader::download(&downloader, &name).await?;
+
+        assert_eq!(cow_txt.redirected_to(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_reuses_recent_checks_of_the_same_url() -> Result<()> {
+        let cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let router = axum::Router::new().route(
+            "/cow.txt",
+            axum::routing::get({
+                let requests = requests.clone();
+                move || async move {
+                    requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    cow_body
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let host_policy = HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]);
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+
+        let downloader = CowTxtDownloader::new(host_policy.clone(), encoding_rs::UTF_8)?;
+        app::CowTxtDownloader::download(&downloader, &name).await?;
+        app::CowTxtDownloader::download(&downloader, &name).await?;
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let ttl = std::time::Duration::from_millis(200);
+        let downloader =
+            CowTxtDownloader::new(host_policy, encoding_rs::UTF_8)?.with_check_cache_ttl(Some(ttl));
+        app::CowTxtDownloader::download(&downloader, &name).await?;
+        app::CowTxtDownloader::download(&downloader, &name).await?;
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        tokio::time::sleep(ttl).await;
+        app::CowTxtDownloader::download(&downloader, &name).await?;
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_shares_concurrent_checks_of_the_same_url() -> Result<()> {
+        let cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let router = axum::Router::new().route(
+            "/cow.txt",
+            axum::routing::get({
+                let requests = requests.clone();
+                move || async move {
+                    requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    cow_body
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let host_policy = HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]);
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+        let downloader = CowTxtDownloader::new(host_policy, encoding_rs::UTF_8)?
+            .with_check_cache_ttl(Some(std::time::Duration::from_secs(60)));
+
+        let (first, second) = tokio::join!(
+            app::CowTxtDownloader::download(&downloader, &name),
+            app::CowTxtDownloader::download(&downloader, &name),
+        );
+        first?;
+        second?;
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_checks_the_must_match_pattern_of_the_cow() -> Result<()> {
+        let mut cow_body =
+            fs::read_to_string(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        cow_body.push_str("\nmoo from bessie\n");
+        let router = axum::Router::new().route(
+            "/cow.txt",
+            axum::routing::get(move || async move { cow_body }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let host_policy = HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]);
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+
+        for (global, per_cow, expected_ok) in [
+            (Some("moo from daisy"), None, false),
+            (Some("moo from daisy"), Some(r"moo from \w+"), true),
+            (None, Some("moo from daisy"), false),
+            (Some("bessie"), None, true),
+        ] {
+            let downloader = CowTxtDownloader::new(host_policy.clone(), encoding_rs::UTF_8)?
+                .with_must_match(
+                    global.map(MustMatch::new).transpose()?,
+                    match per_cow {
+                        Some(pattern) => HashMap::from([(name.clone(), MustMatch::new(pattern)?)]),
+                        None => HashMap::new(),
+                    },
+                );
+            assert_eq!(
+                app::CowTxtDownloader::download(&downloader, &name)
+                    .await
+                    .is_ok(),
+                expected_ok,
+                "Failed for patterns: {global:?} {per_cow:?}"
+            );
+        }
+        Ok(())
+    }
+
+    async fn serve_redirect(to: String) -> Result<SocketAddr> {
+        let router = axum::Router::new().route(
+            "/cow.txt",
+            axum::routing::get(move || async move { axum::response::Redirect::permanent(&to) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Ok(address)
+    }
+
+    #[tokio::test]
+    async fn downloader_follows_redirects_within_the_same_host() -> Result<()> {
+        let cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let router = axum::Router::new()
+            .route(
+                "/cow.txt",
+                axum::routing::get(|| async {
+                    axum::response::Redirect::permanent("/moved/cow.txt")
+                }),
+            )
+            .route(
+                "/moved/cow.txt",
+                axum::routing::get(move || async move { cow_body }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let downloader = CowTxtDownloader::new(
+            HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]),
+            encoding_rs::UTF_8,
+        )?;
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+
+        let cow_txt = app::CowTxtDownloader::download(&downloader, &name).await?;
+
+        assert!(cow_txt.redirected_to().is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn downloader_refuses_redirects_to_other_hosts_unless_allowed() -> Result<()> {
+        let cow_body = fs::read(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let router = axum::Router::new().route(
+            "/cow.txt",
+            axum::routing::get(move || async move { cow_body }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let cdn_address = listener.local_addr()?;
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        // only the port would differ if the redirect went to the same address
+        let final_url = format!("http://localhost:{}/cow.txt", cdn_address.port());
+        let address = serve_redirect(final_url.clone()).await?;
+        let host_policy = HostPolicy::new(vec!["127.0.0.0/8".parse()?], vec![]);
+        let name = VisibleName::new(format!("http://{address}/cow.txt"))?;
+
+        let downloader = CowTxtDownloader::new(host_policy.clone(), encoding_rs::UTF_8)?;
+        assert!(
+            app::CowTxtDownloader::download(&downloader, &name)
+                .await
+                .is_err()
+        );
+
+        let downloader = CowTxtDownloader::new(host_policy.clone(), encoding_rs::UTF_8)?
+            .with_redirect_policy(RedirectPolicy::new(true, RedirectIdentity::ConfiguredUrl))?;
+        let cow_txt = app::CowTxtDownloader::download(&downloader, &name).await?;
+        assert!(cow_txt.redirected_to().is_none());
+
+        let downloader = CowTxtDownloader::new(host_policy, encoding_rs::UTF_8)?
+            .with_redirect_policy(RedirectPolicy::new(true, RedirectIdentity::FinalUrl))?;
+        let cow_txt = app::CowTxtDownloader::download(&downloader, &name).await?;
+        assert_eq!(cow_txt.redirected_to(), Some(&VisibleName::new(final_url)?));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handlers_work_with_the_memory_inventory() -> Result<()> {
+        use crate::app::{AddCowHandler as _, GetHerdHandler as _, UpdateHandler as _};
+
+        let inventory = MemoryInventory::new();
+        let downloader = fixtures::MockCowTxtDownloader::new();
+        let metrics = Metrics::new()?;
+        let events = fixtures::RecordingEventSink::new();
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+
+        crate::app::add_cow::AddCowHandler::new(
+            inventory.clone(),
+            downloader.clone(),
+            metrics.clone(),
+            events.clone(),
+            4,
+        )
+        .handle(&app::AddCow::new(name.clone(), domain::Character::Brave))
+        .await?;
+
+        let herd = crate::app::get_herd::GetHerdHandler::new(
+            inventory.clone(),
+            metrics.clone(),
+            crate::app::get_herd::ShyCows::Censored,
+            domain::HerdShuffle::PerRequest,
+        )
+        .handle()
+        .await?;
+        assert_eq!(herd.cows().len(), 1);
+
+        inventory.update(&name, |cow| {
+            let cow = cow.unwrap();
+            let long_ago = domain::time::DateTime::now() - Duration::new_from_days(1);
+            Ok(Some(Cow::new_from_history(
+                name.clone(),
+                domain::Character::Brave,
+                cow.first_seen().cloned(),
+                Some(long_ago.clone()),
+                Some(long_ago),
+            )))
+        })?;
+        downloader.set_missing(&name, true);
+        crate::app::update::UpdateHandler::new(
+            inventory.clone(),
+            inventory.clone(),
+            downloader,
+            metrics,
+            events,
+            4,
+            None,
+        )
+        .handle()
+        .await?;
+        assert_eq!(inventory.get(&name)?.unwrap().failure_streak(), 1);
+        assert!(
+            app::UpdateLog::last_update(&inventory)?
+                .finished()
+                .is_some()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn memory_inventory_applies_all_updates_or_none() -> Result<()> {
+        let inventory = MemoryInventory::new();
+        let names = [
+            VisibleName::new("https://a.example.com/cow.txt")?,
+            VisibleName::new("https://b.example.com/cow.txt")?,
+        ];
+        inventory.update_many(&names, |name, _| {
+            Ok(Some(Cow::new(name.clone(), domain::Character::Brave)))
+        })?;
+
+        let result = inventory.update_many(&names, |name, cow| {
+            if name == &names[1] {
+                return Err(anyhow!("injected error").into());
+            }
+            let mut cow = cow.unwrap();
+            cow.change_character(domain::Character::Shy)?;
+            Ok(Some(cow))
+        });
+
+        assert!(result.is_err());
+        let characters: Vec<_> = inventory
+            .list()?
+            .iter()
+            .map(|cow| cow.character().clone())
+            .collect();
+        assert_eq!(
+            characters,
+            vec![domain::Character::Brave, domain::Character::Brave]
+        );
+        inventory.delete(&names[0])?;
+        assert!(inventory.delete(&names[0]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn deleting_a_cow_from_memory_forgets_its_body_and_audit() -> Result<()> {
+        use crate::app::{CharacterAudit as _, CowBodies as _};
+
+        let inventory = MemoryInventory::new();
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        inventory.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), domain::Character::Shy)))
+        })?;
+        inventory.put(&name, "moo")?;
+        inventory.update(&name, |cow| {
+            let mut cow = cow.unwrap();
+            cow.change_character(domain::Character::Brave)?;
+            Ok(Some(cow))
+        })?;
+        assert_eq!(
+            crate::app::CowBodies::get(&inventory, &name)?,
+            Some("moo".to_string())
+        );
+        assert_eq!(inventory.character_changes(&name)?.len(), 1);
+
+        inventory.delete(&name)?;
+
+        assert_eq!(crate::app::CowBodies::get(&inventory, &name)?, None);
+        assert!(inventory.character_changes(&name)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_handler_names_are_reported_as_other() -> Result<()> {
+        let metrics = Metrics::new()?;
+        for handler_name in ["get_herd", "GetHerdHandler<Database, Metrics>", "surprise"] {
+            app::Metrics::record_application_handler_call(
+                &metrics,
+                handler_name,
+                ApplicationHandlerCallResult::Ok,
+                Duration::new_from_seconds(1),
+            );
+        }
+
+        let mut handler_names: Vec<(String, f64)> = metrics
+            .registry()
+            .gather()
+            .iter()
+            .find(|family| family.name() == "moooodotfarm_application_handler_calls_counter")
+            .map(|family| {
+                family
+                    .get_metric()
+                    .iter()
+                    .map(|metric| {
+                        let handler_name = metric
+                            .get_label()
+                            .iter()
+                            .find(|label| label.name() == "handler_name")
+                            .map(|label| label.value().to_string())
+                            .unwrap_or_default();
+                        (handler_name, metric.get_counter().value())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        handler_names.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            handler_names,
+            vec![("get_herd".to_string(), 1.0), ("other".to_string(), 2.0)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn configured_handler_names_replace_the_default_ones() -> Result<()> {
+        let toml: TomlConfig = toml::from_str(
+            r#"
+http_address = "0.0.0.0:8080"
+grpc_address = "0.0.0.0:9090"
+environment = "development"
+database_path = "/moooodotfarm.db"
+metrics_handler_names = ["get_herd", "custom"]
+"#,
+        )?;
+        let config: Config = toml.try_into()?;
+        assert_eq!(config.metrics_handler_names(), ["get_herd", "custom"]);
+
+        let metrics = Metrics::new()?.with_handler_names(config.metrics_handler_names());
+        assert_eq!(metrics.normalize_handler_name("get_herd"), "get_herd");
+        assert_eq!(metrics.normalize_handler_name("custom"), "custom");
+        assert_eq!(metrics.normalize_handler_name("update"), "other");
+
+        assert!(
+            Config::new(
+                "0.0.0.0:8080",
+                "0.0.0.0:9090",
+                Environment::Production,
+                "/moooodotfarm.db"
+            )?
+            .with_metrics_handler_names(vec![" ".to_string()])
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn grpc_web_origins_must_be_origins() -> Result<()> {
+        let config = |origin: &str| -> Result<Config> {
+            let toml: TomlConfig = toml::from_str(&format!(
+                r#"
+http_address = "0.0.0.0:8080"
+grpc_address = "0.0.0.0:9090"
+environment = "development"
+database_path = "/moooodotfarm.db"
+
+[grpc]
+web_origins = ["{origin}"]
+"#
+            ))?;
+            toml.try_into()
+        };
+
+        assert_eq!(
+            config("https://moooo.farm")?.grpc_web_origins(),
+            ["https://moooo.farm"]
+        );
+        assert_eq!(
+            config("http://localhost:3000")?.grpc_web_origins(),
+            ["http://localhost:3000"]
+        );
+        for origin in [
+            "*",
+            "https://moooo.farm/",
+            "https://moooo.farm/herd",
+            "moooo.farm",
+        ] {
+            assert!(config(origin).is_err(), "{origin}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn storage_backend_is_parsed() -> Result<()> {
+        assert_eq!(
+            StorageBackend::try_from("memory".to_string())?,
+            StorageBackend::Memory
+        );
+        assert_eq!(
+            StorageBackend::try_from("redb".to_string())?,
+            StorageBackend::Redb
+        );
+        assert!(StorageBackend::try_from("postgres".to_string()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_custom_headers_are_rejected_when_loading_the_config() -> Result<()> {
+        let cow = || {
+            CowConfig::new(
+                VisibleName::new("https://example.com/cow.txt").unwrap(),
+                domain::Character::Brave,
+            )
+        };
+        assert!(cow().with_header("x api key", "moo").is_err());
+        assert!(cow().with_header("x-api-key", "m\noo").is_err());
+
+        let printed = format!("{:?}", cow().with_header("x-api-key", "hunter2")?);
+        assert!(!printed.contains("hunter2"), "{printed}");
+        Ok(())
+    }
+
+    #[test]
+    fn over_large_compaction_intervals_are_rejected() -> Result<()> {
+        let config = |hours: u64| -> Result<Config> {
+            let toml: TomlConfig = toml::from_str(&format!(
+                r#"
+http_address = "0.0.0.0:8080"
+grpc_address = "0.0.0.0:9090"
+environment = "development"
+database_path = "/moooodotfarm.db"
+compact_database_every_hours = {hours}
+"#
+            ))?;
+            toml.try_into()
+        };
+
+        assert_eq!(
+            config(24)?.database_compaction_interval(),
+            Some(std::time::Duration::from_secs(24 * 60 * 60))
+        );
+        assert!(config(u64::MAX).is_err());
+        assert!(config(100 * 365 * 24).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn over_large_max_unchecked_ages_are_rejected() -> Result<()> {
+        let config = |minutes: u64| -> Result<Config> {
+            let toml: TomlConfig = toml::from_str(&format!(
+                r#"
+http_address = "0.0.0.0:8080"
+grpc_address = "0.0.0.0:9090"
+environment = "development"
+database_path = "/moooodotfarm.db"
+max_unchecked_age_minutes = {minutes}
+"#
+            ))?;
+            toml.try_into()
+        };
+
+        assert_eq!(
+            config(30)?.max_unchecked_age(),
+            std::time::Duration::from_secs(30 * 60)
+        );
+        assert!(config(u64::MAX).is_err());
+        assert!(config(100 * 365 * 24 * 60).is_err());
+        Ok(())
+    }
 }
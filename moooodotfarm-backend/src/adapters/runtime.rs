@@ -0,0 +1,76 @@
+use crate::errors::Result;
+use anyhow::anyhow;
+
+const WORKER_THREADS_ENV: &str = "MOOOODOTFARM_WORKER_THREADS";
+const CURRENT_THREAD: &str = "current_thread";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeFlavor {
+    CurrentThread,
+    MultiThread { worker_threads: usize },
+}
+
+impl RuntimeFlavor {
+    // the runtime has to exist before the config file can be loaded which is why it is configured
+    // using an environment variable
+    pub fn from_env() -> Result<Self> {
+        Self::new(std::env::var(WORKER_THREADS_ENV).ok().as_deref())
+    }
+
+    pub fn new(worker_threads: Option<&str>) -> Result<Self> {
+        match worker_threads {
+            None => Ok(RuntimeFlavor::MultiThread {
+                worker_threads: std::thread::available_parallelism()?.get(),
+            }),
+            Some(CURRENT_THREAD) => Ok(RuntimeFlavor::CurrentThread),
+            Some(worker_threads) => match worker_threads.parse::<usize>() {
+                Ok(worker_threads) if worker_threads > 0 => {
+                    Ok(RuntimeFlavor::MultiThread { worker_threads })
+                }
+                _ => Err(anyhow!(
+                    "{} must be a positive number or '{}', got '{}'",
+                    WORKER_THREADS_ENV,
+                    CURRENT_THREAD,
+                    worker_threads
+                )
+                .into()),
+            },
+        }
+    }
+
+    pub fn build(&self) -> Result<tokio::runtime::Runtime> {
+        let mut builder = match self {
+            RuntimeFlavor::CurrentThread => tokio::runtime::Builder::new_current_thread(),
+            RuntimeFlavor::MultiThread { worker_threads } => {
+                let mut builder = tokio::runtime::Builder::new_multi_thread();
+                builder.worker_threads(*worker_threads);
+                builder
+            }
+        };
+        Ok(builder.enable_all().build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_a_runtime_with_the_requested_number_of_workers() -> Result<()> {
+        let runtime = RuntimeFlavor::new(Some("3"))?.build()?;
+        assert_eq!(runtime.metrics().num_workers(), 3);
+
+        let runtime = RuntimeFlavor::new(Some("current_thread"))?.build()?;
+        assert_eq!(runtime.metrics().num_workers(), 1);
+
+        assert_eq!(
+            RuntimeFlavor::new(None)?,
+            RuntimeFlavor::MultiThread {
+                worker_threads: std::thread::available_parallelism()?.get()
+            }
+        );
+        assert!(RuntimeFlavor::new(Some("0")).is_err());
+        assert!(RuntimeFlavor::new(Some("many")).is_err());
+        Ok(())
+    }
+}
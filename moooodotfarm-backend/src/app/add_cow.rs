@@ -1,52 +1,146 @@
-use crate::app::{CowTxtDownloader, Inventory, Metrics};
+use crate::app::{CowTxtDownloader, EventSink, Inventory, Metrics};
+use crate::domain::events::DomainEvent;
 use crate::errors::{Error, Result};
 use crate::{app, domain};
 use anyhow::anyhow;
 use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 
 #[derive(Clone)]
-pub struct AddCowHandler<I, D, M> {
+pub struct AddCowHandler<I, D, M, E> {
     inventory: I,
     downloader: D,
     metrics: M,
+    events: E,
+    downloads: Arc<Semaphore>,
 }
 
-impl<I, D, M> AddCowHandler<I, D, M>
+impl<I, D, M, E> AddCowHandler<I, D, M, E>
 where
     I: Inventory,
     D: CowTxtDownloader,
     M: Metrics,
+    E: EventSink,
 {
-    pub fn new(inventory: I, downloader: D, metrics: M) -> Self {
+    pub fn new(
+        inventory: I,
+        downloader: D,
+        metrics: M,
+        events: E,
+        max_concurrent_downloads: usize,
+    ) -> Self {
         Self {
             inventory,
             downloader,
             metrics,
+            events,
+            downloads: Arc::new(Semaphore::new(max_concurrent_downloads)),
         }
     }
 
     async fn handle_inner(&self, v: &app::AddCow) -> Result<()> {
-        self.downloader.download(v.name()).await?;
+        let body_hash = {
+            let _permit = self
+                .downloads
+                .acquire()
+                .await
+                .map_err(|err| Error::Unknown(anyhow!(err)))?;
+            self.downloader.download(v.name()).await?.body_hash()
+        };
         self.inventory
             .update(v.name(), |status: Option<domain::Cow>| {
                 if status.is_some() {
-                    return Err(Error::Unknown(anyhow!("cow already exists")));
+                    return Err(Error::Conflict("cow already exists".to_string()));
                 }
-                let cow = domain::Cow::new(v.name().clone(), v.character().clone());
+                // the cow was just downloaded so there is no need to wait for the timer to check it
+                let mut cow = domain::Cow::new(v.name().clone(), v.character().clone())
+                    .with_tags(v.tags().to_vec());
+                cow.mark_as_ok()?;
+                cow.record_body_hash(body_hash);
                 Ok(Some(cow))
             })?;
+        self.events.emit(DomainEvent::CowAdded {
+            name: v.name().clone(),
+            character: v.character().clone(),
+        });
         Ok::<(), Error>(())
     }
 }
 
 #[async_trait]
-impl<I, D, M> app::AddCowHandler for AddCowHandler<I, D, M>
+impl<I, D, M, E> app::AddCowHandler for AddCowHandler<I, D, M, E>
 where
     I: Inventory + Send + Sync,
     D: CowTxtDownloader + Send + Sync,
     M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
 {
     async fn handle(&self, v: &app::AddCow) -> Result<()> {
         crate::record_application_handler_call!(self.metrics, "add_cow", self.handle_inner(v).await)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::AddCowHandler as _;
+    use crate::domain::{Character, VisibleName};
+    use crate::fixtures::{MockCowTxtDownloader, RecordingEventSink};
+
+    #[tokio::test]
+    async fn emits_cow_added() -> Result<()> {
+        let events = RecordingEventSink::new();
+        let handler = AddCowHandler::new(
+            Database::new_in_memory()?,
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            events.clone(),
+            4,
+        );
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+
+        handler
+            .handle(&app::AddCow::new(name.clone(), Character::Shy))
+            .await?;
+
+        assert_eq!(
+            events.events(),
+            vec![DomainEvent::CowAdded {
+                name,
+                character: Character::Shy,
+            }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn added_cow_is_checked_right_away() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let handler = AddCowHandler::new(
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+        );
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+
+        handler
+            .handle(&app::AddCow::new(name.clone(), Character::Brave))
+            .await?;
+
+        let cow = database.get(&name)?.unwrap();
+        assert_eq!(cow.last_check_succeeded(), Some(true));
+        assert!(cow.first_seen().is_some());
+        assert!(cow.added_at() <= cow.first_seen().unwrap());
+        let censored_cow = domain::CensoredCow::new(&cow)?;
+        assert!(matches!(
+            app::Cow::try_from(&censored_cow)?.status(),
+            app::CowStatus::HappilyGrazing
+        ));
+        Ok(())
+    }
+}
@@ -1,42 +1,66 @@
-use crate::app::{Inventory, Metrics};
+use crate::app::{EventSink, Inventory, Metrics};
+use crate::domain::events::DomainEvent;
 use crate::errors::{Error, Result};
 use crate::{app, domain};
-use anyhow::anyhow;
 use async_trait::async_trait;
 
 #[derive(Clone)]
-pub struct ChangeCowCharacterHandler<I, M> {
+pub struct ChangeCowCharacterHandler<I, M, E> {
     inventory: I,
     metrics: M,
+    events: E,
 }
 
-impl<I, M> ChangeCowCharacterHandler<I, M>
+impl<I, M, E> ChangeCowCharacterHandler<I, M, E>
 where
     I: Inventory,
     M: Metrics,
+    E: EventSink,
 {
-    pub fn new(inventory: I, metrics: M) -> Self {
-        Self { inventory, metrics }
+    pub fn new(inventory: I, metrics: M, events: E) -> Self {
+        Self {
+            inventory,
+            metrics,
+            events,
+        }
     }
 
     async fn handle_inner(&self, v: &app::ChangeCowCharacter) -> Result<()> {
+        let peeked_cow = self.inventory.get(v.name())?;
+        if peeked_cow.is_some_and(|cow| cow.character() == v.character()) {
+            return Ok(());
+        }
+
+        let mut old_character = None;
         self.inventory
             .update(v.name(), |cow: Option<domain::Cow>| match cow {
                 Some(mut cow) => {
+                    if cow.character() == v.character() {
+                        return Ok(None);
+                    }
+                    old_character = Some(cow.character().clone());
                     cow.change_character(v.character().clone())?;
                     Ok(Some(cow))
                 }
-                None => Err(Error::Unknown(anyhow!("cow does not exist"))),
+                None => Err(Error::NotFound("cow does not exist".to_string())),
             })?;
+        if let Some(old_character) = old_character {
+            self.events.emit(DomainEvent::CharacterChanged {
+                name: v.name().clone(),
+                old_character,
+                new_character: v.character().clone(),
+            });
+        }
         Ok::<(), Error>(())
     }
 }
 
 #[async_trait]
-impl<I, M> app::ChangeCowCharacterHandler for ChangeCowCharacterHandler<I, M>
+impl<I, M, E> app::ChangeCowCharacterHandler for ChangeCowCharacterHandler<I, M, E>
 where
     I: Inventory + Send + Sync,
     M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
 {
     async fn handle(&self, v: &app::ChangeCowCharacter) -> Result<()> {
         crate::record_application_handler_call!(
@@ -46,3 +70,146 @@ where
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::ChangeCowCharacterHandler as _;
+    use crate::app::CharacterAudit as _;
+    use crate::domain::time::{DateTime, Duration};
+    use crate::domain::{Character, Cow, VisibleName};
+    use crate::fixtures::RecordingEventSink;
+
+    #[tokio::test]
+    async fn emits_character_changed() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        let events = RecordingEventSink::new();
+        let handler =
+            ChangeCowCharacterHandler::new(database, adapters::Metrics::new()?, events.clone());
+
+        handler
+            .handle(&app::ChangeCowCharacter::new(name.clone(), Character::Shy))
+            .await?;
+
+        assert_eq!(
+            events.events(),
+            vec![DomainEvent::CharacterChanged {
+                name,
+                old_character: Character::Brave,
+                new_character: Character::Shy,
+            }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn character_changes_are_audited() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        let handler = ChangeCowCharacterHandler::new(
+            database.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+        );
+
+        // timestamps are stored with a precision of a second
+        let before = DateTime::now() - &Duration::new_from_seconds(1);
+        for character in [Character::Shy, Character::Shy, Character::Brave] {
+            handler
+                .handle(&app::ChangeCowCharacter::new(name.clone(), character))
+                .await?;
+        }
+
+        let changes = database.character_changes(&name)?;
+        let characters: Vec<_> = changes
+            .iter()
+            .map(|change| (change.old_character(), change.new_character()))
+            .collect();
+        assert_eq!(
+            characters,
+            vec![
+                (&Character::Brave, &Character::Shy),
+                (&Character::Shy, &Character::Brave),
+            ]
+        );
+        assert!(changes.iter().all(|change| change.changed_at() >= &before));
+        Ok(())
+    }
+
+    #[derive(Clone)]
+    struct ReadOnlyInventory {
+        database: Database,
+    }
+
+    impl Inventory for ReadOnlyInventory {
+        fn get(&self, name: &VisibleName) -> Result<Option<Cow>> {
+            self.database.get(name)
+        }
+
+        fn list(&self) -> Result<Vec<Cow>> {
+            self.database.list()
+        }
+
+        fn update<F>(&self, _name: &VisibleName, _f: F) -> Result<()>
+        where
+            F: FnOnce(Option<Cow>) -> Result<Option<Cow>>,
+        {
+            panic!("unexpected write")
+        }
+
+        fn update_many<F>(&self, _names: &[VisibleName], _f: F) -> Result<()>
+        where
+            F: FnMut(&VisibleName, Option<Cow>) -> Result<Option<Cow>>,
+        {
+            panic!("unexpected write")
+        }
+
+        fn delete(&self, _name: &VisibleName) -> Result<()> {
+            panic!("unexpected write")
+        }
+    }
+
+    #[tokio::test]
+    async fn changing_to_the_current_character_is_a_no_op() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        let events = RecordingEventSink::new();
+        let handler = ChangeCowCharacterHandler::new(
+            ReadOnlyInventory {
+                database: database.clone(),
+            },
+            adapters::Metrics::new()?,
+            events.clone(),
+        );
+
+        handler
+            .handle(&app::ChangeCowCharacter::new(
+                name.clone(),
+                Character::Brave,
+            ))
+            .await?;
+
+        assert!(events.events().is_empty());
+        assert!(database.character_changes(&name)?.is_empty());
+        assert_eq!(
+            database.get(&name)?.map(|cow| cow.character().clone()),
+            Some(Character::Brave)
+        );
+        Ok(())
+    }
+}
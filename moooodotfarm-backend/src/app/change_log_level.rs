@@ -0,0 +1,93 @@
+use crate::app;
+use crate::app::{ChangeLogLevel, LogLevelSwitch, Metrics};
+use crate::errors::Result;
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct ChangeLogLevelHandler<S, M> {
+    switch: S,
+    metrics: M,
+}
+
+impl<S, M> ChangeLogLevelHandler<S, M>
+where
+    S: LogLevelSwitch,
+    M: Metrics,
+{
+    pub fn new(switch: S, metrics: M) -> Self {
+        Self { switch, metrics }
+    }
+
+    async fn handle_inner(&self, v: &ChangeLogLevel) -> Result<log::LevelFilter> {
+        let level = match v {
+            ChangeLogLevel::Set(level) => Some(*level),
+            ChangeLogLevel::Revert => None,
+            ChangeLogLevel::ToggleDebug if self.switch.is_switched() => None,
+            ChangeLogLevel::ToggleDebug => Some(log::LevelFilter::Debug),
+        };
+        let level = self.switch.switch(level);
+        // logged as a warning so that the change shows up whatever the new level is
+        log::warn!("log level changed to {}", level);
+        Ok(level)
+    }
+}
+
+#[async_trait]
+impl<S, M> app::ChangeLogLevelHandler for ChangeLogLevelHandler<S, M>
+where
+    S: LogLevelSwitch + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self, v: &ChangeLogLevel) -> Result<log::LevelFilter> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "change_log_level",
+            self.handle_inner(v).await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::logging::LogLevel;
+    use crate::app::ChangeLogLevelHandler as _;
+
+    #[tokio::test]
+    async fn toggling_switches_between_the_configured_level_and_debug() -> Result<()> {
+        let log_level = LogLevel::new(log::LevelFilter::Info);
+        let handler = ChangeLogLevelHandler::new(log_level.clone(), adapters::Metrics::new()?);
+
+        assert_eq!(
+            handler.handle(&ChangeLogLevel::ToggleDebug).await?,
+            log::LevelFilter::Debug
+        );
+        assert_eq!(
+            handler.handle(&ChangeLogLevel::ToggleDebug).await?,
+            log::LevelFilter::Info
+        );
+        assert_eq!(
+            handler
+                .handle(&ChangeLogLevel::Set(log::LevelFilter::Trace))
+                .await?,
+            log::LevelFilter::Trace
+        );
+        assert_eq!(
+            handler.handle(&ChangeLogLevel::ToggleDebug).await?,
+            log::LevelFilter::Info
+        );
+        assert_eq!(
+            handler
+                .handle(&ChangeLogLevel::Set(log::LevelFilter::Error))
+                .await?,
+            log::LevelFilter::Error
+        );
+        assert_eq!(
+            handler.handle(&ChangeLogLevel::Revert).await?,
+            log::LevelFilter::Info
+        );
+        assert_eq!(log_level.current(), log::LevelFilter::Info);
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use crate::app;
+use crate::app::{DatabaseCompactor, Metrics};
+use crate::errors::Result;
+use async_trait::async_trait;
+use log::info;
+
+#[derive(Clone)]
+pub struct CompactDatabaseHandler<C, M> {
+    compactor: C,
+    metrics: M,
+}
+
+impl<C, M> CompactDatabaseHandler<C, M>
+where
+    C: DatabaseCompactor,
+    M: Metrics,
+{
+    pub fn new(compactor: C, metrics: M) -> Self {
+        Self { compactor, metrics }
+    }
+
+    async fn handle_inner(&self) -> Result<u64> {
+        let reclaimed_bytes = self.compactor.compact()?;
+        info!(
+            "compacted the database, reclaimed {} bytes",
+            reclaimed_bytes
+        );
+        Ok(reclaimed_bytes)
+    }
+}
+
+#[async_trait]
+impl<C, M> app::CompactDatabaseHandler for CompactDatabaseHandler<C, M>
+where
+    C: DatabaseCompactor + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self) -> Result<u64> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "compact_database",
+            self.handle_inner().await
+        )
+    }
+}
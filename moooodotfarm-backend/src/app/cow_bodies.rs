@@ -0,0 +1,91 @@
+use crate::app::{CowBodies, CowTxtDownloader};
+use crate::domain::{CowTxt, VisibleName};
+use crate::errors::Result;
+use async_trait::async_trait;
+
+// anything larger is most likely a whole web page which happens to contain a cow
+static MAX_STORED_COW_BODY_BYTES: usize = 16 * 1024;
+
+// remembers the last body of each cow which passed validation so that the actual cow can be shown,
+// storing them is opt-in as they take up far more space than everything else known about a cow
+#[derive(Clone)]
+pub struct StoringCowTxtDownloader<D, B> {
+    downloader: D,
+    cow_bodies: B,
+    enabled: bool,
+}
+
+impl<D, B> StoringCowTxtDownloader<D, B> {
+    pub fn new(downloader: D, cow_bodies: B, enabled: bool) -> Self {
+        Self {
+            downloader,
+            cow_bodies,
+            enabled,
+        }
+    }
+}
+
+#[async_trait]
+impl<D, B> CowTxtDownloader for StoringCowTxtDownloader<D, B>
+where
+    D: CowTxtDownloader,
+    B: CowBodies + Send + Sync,
+{
+    async fn download(&self, name: &VisibleName) -> Result<CowTxt<'_>> {
+        let cow_txt = self.downloader.download(name).await?;
+        if !self.enabled {
+            return Ok(cow_txt);
+        }
+        if cow_txt.content().len() > MAX_STORED_COW_BODY_BYTES {
+            log::debug!("not storing the body of {}, it is too large", name.url());
+            return Ok(cow_txt);
+        }
+        // losing a body only makes the cow a little less fun to look at
+        if let Err(err) = self.cow_bodies.put(name, cow_txt.content()) {
+            log::warn!("failed to store the body of {}: {}", name.url(), err);
+        }
+        Ok(cow_txt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::database::Database;
+    use crate::fixtures::MockCowTxtDownloader;
+
+    #[tokio::test]
+    async fn bodies_are_only_stored_when_enabled() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+
+        let downloader =
+            StoringCowTxtDownloader::new(MockCowTxtDownloader::new(), database.clone(), false);
+        downloader.download(&name).await?;
+        assert_eq!(CowBodies::get(&database, &name)?, None);
+
+        let downloader =
+            StoringCowTxtDownloader::new(MockCowTxtDownloader::new(), database.clone(), true);
+        let cow_txt = downloader.download(&name).await?;
+        assert_eq!(
+            CowBodies::get(&database, &name)?.as_deref(),
+            Some(cow_txt.content())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_downloads_keep_the_last_good_body() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let mock = MockCowTxtDownloader::new();
+        let downloader = StoringCowTxtDownloader::new(mock.clone(), database.clone(), true);
+        downloader.download(&name).await?;
+
+        mock.set_missing(&name, true);
+        assert!(downloader.download(&name).await.is_err());
+
+        assert!(CowBodies::get(&database, &name)?.is_some());
+        Ok(())
+    }
+}
@@ -1,34 +1,45 @@
 use crate::app;
-use crate::app::{Inventory, Metrics};
+use crate::app::{EventSink, Inventory, Metrics};
+use crate::domain::events::DomainEvent;
 use crate::errors::Result;
 use async_trait::async_trait;
 
 #[derive(Clone)]
-pub struct DeleteCowHandler<I, M> {
+pub struct DeleteCowHandler<I, M, E> {
     inventory: I,
     metrics: M,
+    events: E,
 }
 
-impl<I, M> DeleteCowHandler<I, M>
+impl<I, M, E> DeleteCowHandler<I, M, E>
 where
     I: Inventory,
     M: Metrics,
+    E: EventSink,
 {
-    pub fn new(inventory: I, metrics: M) -> Self {
-        Self { inventory, metrics }
+    pub fn new(inventory: I, metrics: M, events: E) -> Self {
+        Self {
+            inventory,
+            metrics,
+            events,
+        }
     }
 
     async fn handle_inner(&self, v: &app::DeleteCow) -> Result<()> {
         self.inventory.delete(v.name())?;
+        self.events.emit(DomainEvent::CowRemoved {
+            name: v.name().clone(),
+        });
         Ok(())
     }
 }
 
 #[async_trait]
-impl<I, M> app::DeleteCowHandler for DeleteCowHandler<I, M>
+impl<I, M, E> app::DeleteCowHandler for DeleteCowHandler<I, M, E>
 where
     I: Inventory + Send + Sync,
     M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
 {
     async fn handle(&self, v: &app::DeleteCow) -> Result<()> {
         crate::record_application_handler_call!(
@@ -38,3 +49,30 @@ where
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::DeleteCowHandler as _;
+    use crate::domain::{Character, Cow, VisibleName};
+    use crate::fixtures::RecordingEventSink;
+
+    #[tokio::test]
+    async fn emits_cow_removed() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        let events = RecordingEventSink::new();
+        let handler = DeleteCowHandler::new(database, adapters::Metrics::new()?, events.clone());
+
+        handler.handle(&app::DeleteCow::new(name.clone())).await?;
+
+        assert_eq!(events.events(), vec![DomainEvent::CowRemoved { name }]);
+        Ok(())
+    }
+}
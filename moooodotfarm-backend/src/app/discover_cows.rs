@@ -0,0 +1,132 @@
+use crate::app::{AddCowHandler, CowDiscovery, Inventory, Metrics};
+use crate::errors::Result;
+use crate::{app, domain};
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct DiscoverCowsHandler<C, I, A, M> {
+    discovery: C,
+    inventory: I,
+    add_cow_handler: A,
+    metrics: M,
+}
+
+impl<C, I, A, M> DiscoverCowsHandler<C, I, A, M>
+where
+    C: CowDiscovery,
+    I: Inventory,
+    A: AddCowHandler,
+    M: Metrics,
+{
+    pub fn new(discovery: C, inventory: I, add_cow_handler: A, metrics: M) -> Self {
+        Self {
+            discovery,
+            inventory,
+            add_cow_handler,
+            metrics,
+        }
+    }
+
+    async fn handle_inner(&self) -> Result<usize> {
+        let mut added = 0;
+        for name in self.discovery.discover().await? {
+            if self.inventory.get(&name)?.is_some() {
+                continue;
+            }
+            // host names in a home network say a lot about who lives there
+            let add_cow = app::AddCow::new(name.clone(), domain::Character::Shy)
+                .with_tags(vec![self.discovery.origin()]);
+            match self.add_cow_handler.handle(&add_cow).await {
+                Ok(_) => {
+                    log::info!("discovered a cow {}", name.url());
+                    added += 1;
+                }
+                Err(err) => {
+                    log::debug!("discovered host has no cow {}: {}", name.url(), err);
+                }
+            }
+        }
+        Ok(added)
+    }
+}
+
+#[async_trait]
+impl<C, I, A, M> app::DiscoverCowsHandler for DiscoverCowsHandler<C, I, A, M>
+where
+    C: CowDiscovery + Send + Sync,
+    I: Inventory + Send + Sync,
+    A: AddCowHandler + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self) -> Result<usize> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "discover_cows",
+            self.handle_inner().await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::DiscoverCowsHandler as _;
+    use crate::app::add_cow;
+    use crate::domain::{Character, Cow, Tag, VisibleName};
+    use crate::fixtures::{MockCowTxtDownloader, RecordingEventSink};
+
+    struct StaticCowDiscovery {
+        names: Vec<VisibleName>,
+    }
+
+    #[async_trait]
+    impl CowDiscovery for StaticCowDiscovery {
+        fn origin(&self) -> Tag {
+            Tag::new("test").unwrap()
+        }
+
+        async fn discover(&self) -> Result<Vec<VisibleName>> {
+            Ok(self.names.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn discovered_cows_are_added_with_their_origin() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let metrics = adapters::Metrics::new()?;
+        let known = VisibleName::new("http://known.local/cow.txt")?;
+        let found = VisibleName::new("http://bessie.local/cow.txt")?;
+        let missing = VisibleName::new("http://printer.local/cow.txt")?;
+        downloader.set_missing(&missing, true);
+        database.update(&known, |_| {
+            Ok(Some(Cow::new(known.clone(), Character::Brave)))
+        })?;
+
+        let handler = DiscoverCowsHandler::new(
+            StaticCowDiscovery {
+                names: vec![known.clone(), found.clone(), missing.clone()],
+            },
+            database.clone(),
+            add_cow::AddCowHandler::new(
+                database.clone(),
+                downloader,
+                metrics.clone(),
+                RecordingEventSink::new(),
+                4,
+            ),
+            metrics,
+        );
+
+        assert_eq!(handler.handle().await?, 1);
+
+        let cow = database.get(&found)?.unwrap();
+        assert_eq!(cow.character(), &Character::Shy);
+        assert_eq!(cow.tags(), &[Tag::new("test")?]);
+        assert!(database.get(&known)?.unwrap().tags().is_empty());
+        assert!(database.get(&missing)?.is_none());
+        Ok(())
+    }
+}
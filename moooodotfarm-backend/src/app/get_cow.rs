@@ -0,0 +1,75 @@
+use crate::app::get_herd::ShyCows;
+use crate::app::{Cow, Inventory, Metrics};
+use crate::domain::HostCensoring;
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::flap_penalty::FlapPenalty;
+use crate::errors::Result;
+use crate::{app, domain};
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct GetCowHandler<I, M> {
+    inventory: I,
+    metrics: M,
+    shy_cows: ShyCows,
+    active_hours: Option<ActiveHours>,
+    host_censoring: HostCensoring,
+    flap_penalty: FlapPenalty,
+}
+
+impl<I, M> GetCowHandler<I, M>
+where
+    I: Inventory,
+    M: Metrics,
+{
+    pub fn new(inventory: I, metrics: M, shy_cows: ShyCows) -> Self {
+        Self {
+            inventory,
+            metrics,
+            shy_cows,
+            active_hours: None,
+            host_censoring: HostCensoring::default(),
+            flap_penalty: FlapPenalty::default(),
+        }
+    }
+
+    pub fn with_active_hours(mut self, active_hours: Option<ActiveHours>) -> Self {
+        self.active_hours = active_hours;
+        self
+    }
+
+    pub fn with_host_censoring(mut self, host_censoring: HostCensoring) -> Self {
+        self.host_censoring = host_censoring;
+        self
+    }
+
+    pub fn with_flap_penalty(mut self, flap_penalty: FlapPenalty) -> Self {
+        self.flap_penalty = flap_penalty;
+        self
+    }
+
+    async fn handle_inner(&self, v: &app::GetCow) -> Result<Option<Cow>> {
+        let cow = self.inventory.get(v.name())?.filter(|cow| {
+            self.shy_cows == ShyCows::Censored || cow.character() != &domain::Character::Shy
+        });
+        match cow {
+            Some(cow) => Ok(Some(Cow::try_from(
+                &domain::CensoredCow::new_with_host_censoring(&cow, self.host_censoring)?
+                    .with_active_hours(self.active_hours.as_ref())
+                    .with_flap_penalty(&self.flap_penalty),
+            )?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl<I, M> app::GetCowHandler for GetCowHandler<I, M>
+where
+    I: Inventory + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self, v: &app::GetCow) -> Result<Option<Cow>> {
+        crate::record_application_handler_call!(self.metrics, "get_cow", self.handle_inner(v).await)
+    }
+}
@@ -0,0 +1,107 @@
+use crate::app::{CharacterAudit, Inventory, Metrics};
+use crate::errors::Result;
+use crate::{app, domain};
+use async_trait::async_trait;
+
+// the audit names the characters a cow had, it is only ever shown to the owner of the farm so shy
+// cows aren't censored here
+#[derive(Clone)]
+pub struct GetCowAuditHandler<I, A, M> {
+    inventory: I,
+    audit: A,
+    metrics: M,
+}
+
+impl<I, A, M> GetCowAuditHandler<I, A, M>
+where
+    I: Inventory,
+    A: CharacterAudit,
+    M: Metrics,
+{
+    pub fn new(inventory: I, audit: A, metrics: M) -> Self {
+        Self {
+            inventory,
+            audit,
+            metrics,
+        }
+    }
+
+    async fn handle_inner(
+        &self,
+        v: &app::GetCowAudit,
+    ) -> Result<Option<Vec<domain::CharacterChange>>> {
+        if self.inventory.get(v.name())?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.audit.character_changes(v.name())?))
+    }
+}
+
+#[async_trait]
+impl<I, A, M> app::GetCowAuditHandler for GetCowAuditHandler<I, A, M>
+where
+    I: Inventory + Send + Sync,
+    A: CharacterAudit + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self, v: &app::GetCowAudit) -> Result<Option<Vec<domain::CharacterChange>>> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "get_cow_audit",
+            self.handle_inner(v).await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::ChangeCowCharacterHandler as _;
+    use crate::app::GetCowAuditHandler as _;
+    use crate::app::change_cow_character::ChangeCowCharacterHandler;
+    use crate::domain::{Character, Cow, VisibleName};
+    use crate::fixtures::RecordingEventSink;
+
+    #[tokio::test]
+    async fn returns_the_character_changes_of_a_cow() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+        let metrics = adapters::Metrics::new()?;
+        let change_handler = ChangeCowCharacterHandler::new(
+            database.clone(),
+            metrics.clone(),
+            RecordingEventSink::new(),
+        );
+        let handler = GetCowAuditHandler::new(database.clone(), database.clone(), metrics);
+
+        let audit = handler.handle(&app::GetCowAudit::new(name.clone())).await?;
+        assert_eq!(audit, Some(vec![]));
+
+        change_handler
+            .handle(&app::ChangeCowCharacter::new(name.clone(), Character::Shy))
+            .await?;
+
+        let audit = handler
+            .handle(&app::GetCowAudit::new(name.clone()))
+            .await?
+            .unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].old_character(), &Character::Brave);
+        assert_eq!(audit[0].new_character(), &Character::Shy);
+
+        assert_eq!(
+            handler
+                .handle(&app::GetCowAudit::new(VisibleName::new(
+                    "https://absent.example.com/cow.txt"
+                )?))
+                .await?,
+            None
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use crate::app::{CowBodies, Inventory, Metrics};
+use crate::errors::Result;
+use crate::{app, domain};
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct GetCowBodyHandler<I, B, M> {
+    inventory: I,
+    cow_bodies: B,
+    metrics: M,
+}
+
+impl<I, B, M> GetCowBodyHandler<I, B, M>
+where
+    I: Inventory,
+    B: CowBodies,
+    M: Metrics,
+{
+    pub fn new(inventory: I, cow_bodies: B, metrics: M) -> Self {
+        Self {
+            inventory,
+            cow_bodies,
+            metrics,
+        }
+    }
+
+    // a body can't be censored and may well say who is behind a shy cow, shy cows don't exist as
+    // far as anyone asking is concerned as otherwise their urls could be probed for
+    async fn handle_inner(&self, v: &app::GetCowBody) -> Result<Option<String>> {
+        let Some(cow) = self.inventory.get(v.name())? else {
+            return Ok(None);
+        };
+        if cow.character() == &domain::Character::Shy {
+            return Ok(None);
+        }
+        self.cow_bodies.get(v.name())
+    }
+}
+
+#[async_trait]
+impl<I, B, M> app::GetCowBodyHandler for GetCowBodyHandler<I, B, M>
+where
+    I: Inventory + Send + Sync,
+    B: CowBodies + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self, v: &app::GetCowBody) -> Result<Option<String>> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "get_cow_body",
+            self.handle_inner(v).await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::GetCowBodyHandler as _;
+    use crate::domain::{Character, Cow, VisibleName};
+
+    #[tokio::test]
+    async fn only_bodies_of_brave_cows_are_returned() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let brave = VisibleName::new("https://brave.example.com/cow.txt")?;
+        let shy = VisibleName::new("https://shy.example.com/cow.txt")?;
+        for (name, character) in [(&brave, Character::Brave), (&shy, Character::Shy)] {
+            database.update(name, |_| Ok(Some(Cow::new(name.clone(), character))))?;
+            CowBodies::put(&database, name, "moo")?;
+        }
+        let handler = GetCowBodyHandler::new(
+            database.clone(),
+            database.clone(),
+            adapters::Metrics::new()?,
+        );
+
+        assert_eq!(
+            handler.handle(&app::GetCowBody::new(brave)).await?,
+            Some("moo".to_string())
+        );
+        assert_eq!(handler.handle(&app::GetCowBody::new(shy)).await?, None);
+        assert_eq!(
+            handler
+                .handle(&app::GetCowBody::new(VisibleName::new(
+                    "https://absent.example.com/cow.txt"
+                )?))
+                .await?,
+            None
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use crate::app;
+use crate::app::{Inventory, Metrics};
+use crate::errors::Result;
+use async_trait::async_trait;
+
+// notes are written by the owner of the farm for themselves and may well say who is behind a shy
+// cow, they are only ever shown to the owner so nothing is censored here
+#[derive(Clone)]
+pub struct GetCowNotesHandler<I, M> {
+    inventory: I,
+    metrics: M,
+}
+
+impl<I, M> GetCowNotesHandler<I, M>
+where
+    I: Inventory,
+    M: Metrics,
+{
+    pub fn new(inventory: I, metrics: M) -> Self {
+        Self { inventory, metrics }
+    }
+
+    async fn handle_inner(&self, v: &app::GetCowNotes) -> Result<Option<String>> {
+        Ok(self
+            .inventory
+            .get(v.name())?
+            .map(|cow| cow.notes().to_string()))
+    }
+}
+
+#[async_trait]
+impl<I, M> app::GetCowNotesHandler for GetCowNotesHandler<I, M>
+where
+    I: Inventory + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self, v: &app::GetCowNotes) -> Result<Option<String>> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "get_cow_notes",
+            self.handle_inner(v).await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::GetCowNotesHandler as _;
+    use crate::domain::{Character, Cow, VisibleName};
+
+    #[tokio::test]
+    async fn returns_the_notes_of_shy_cows_too() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://shy.example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(
+                Cow::new(name.clone(), Character::Shy).with_notes("ask bob"),
+            ))
+        })?;
+        let handler = GetCowNotesHandler::new(database, adapters::Metrics::new()?);
+
+        assert_eq!(
+            handler.handle(&app::GetCowNotes::new(name)).await?,
+            Some("ask bob".to_string())
+        );
+        assert_eq!(
+            handler
+                .handle(&app::GetCowNotes::new(VisibleName::new(
+                    "https://absent.example.com/cow.txt"
+                )?))
+                .await?,
+            None
+        );
+        Ok(())
+    }
+}
@@ -1,13 +1,29 @@
 use crate::app::{Herd, Inventory, Metrics};
-use crate::domain::CensoredHerd;
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::flap_penalty::FlapPenalty;
+use crate::domain::herd_health::UncheckedCows;
+use crate::domain::time::DateTime;
+use crate::domain::{CensoredHerd, Character, HerdShuffle, HostCensoring};
 use crate::errors::Result;
 use crate::{app, domain};
 use async_trait::async_trait;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShyCows {
+    Censored,
+    Hidden,
+}
+
 #[derive(Clone)]
 pub struct GetHerdHandler<I, M> {
     inventory: I,
     metrics: M,
+    shy_cows: ShyCows,
+    shuffle: HerdShuffle,
+    active_hours: Option<ActiveHours>,
+    unchecked_cows: Option<UncheckedCows>,
+    host_censoring: HostCensoring,
+    flap_penalty: FlapPenalty,
 }
 
 impl<I, M> GetHerdHandler<I, M>
@@ -15,17 +31,63 @@ where
     I: Inventory,
     M: Metrics,
 {
-    pub fn new(inventory: I, metrics: M) -> Self {
-        Self { inventory, metrics }
+    pub fn new(inventory: I, metrics: M, shy_cows: ShyCows, shuffle: HerdShuffle) -> Self {
+        Self {
+            inventory,
+            metrics,
+            shy_cows,
+            shuffle,
+            active_hours: None,
+            unchecked_cows: None,
+            host_censoring: HostCensoring::default(),
+            flap_penalty: FlapPenalty::default(),
+        }
+    }
+
+    pub fn with_active_hours(mut self, active_hours: Option<ActiveHours>) -> Self {
+        self.active_hours = active_hours;
+        self
+    }
+
+    // the metric is kept up to date by the updates, see UpdateHandler::with_unchecked_cows
+    pub fn with_unchecked_cows(mut self, unchecked_cows: Option<UncheckedCows>) -> Self {
+        self.unchecked_cows = unchecked_cows;
+        self
+    }
+
+    pub fn with_host_censoring(mut self, host_censoring: HostCensoring) -> Self {
+        self.host_censoring = host_censoring;
+        self
+    }
+
+    pub fn with_flap_penalty(mut self, flap_penalty: FlapPenalty) -> Self {
+        self.flap_penalty = flap_penalty;
+        self
     }
 
     async fn handle_inner(&self) -> Result<Herd> {
         let cows = self.inventory.list()?;
-        let censored_cows = cows
+        let now = DateTime::now();
+        let herd: domain::Herd = cows
             .into_iter()
-            .map(|cow| domain::CensoredCow::new(&cow))
+            .filter(|cow| self.shy_cows == ShyCows::Censored || cow.character() != &Character::Shy)
+            .collect();
+        let censored_cows = herd
+            .iter()
+            .map(|cow| {
+                Ok(
+                    domain::CensoredCow::new_with_host_censoring(cow, self.host_censoring)?
+                        .with_active_hours(self.active_hours.as_ref())
+                        .with_flap_penalty(&self.flap_penalty)
+                        .with_first_check_overdue(
+                            self.unchecked_cows
+                                .as_ref()
+                                .is_some_and(|v| v.is_overdue(cow, &now)),
+                        ),
+                )
+            })
             .collect::<Result<Vec<domain::CensoredCow>>>()?;
-        CensoredHerd::new(censored_cows).try_into()
+        CensoredHerd::new_with_shuffle(censored_cows, self.shuffle).try_into()
     }
 }
 
@@ -39,3 +101,176 @@ where
         crate::record_application_handler_call!(self.metrics, "get_herd", self.handle_inner().await)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::GetHerdHandler as _;
+    use crate::domain::time::Duration;
+    use crate::domain::{Cow, Name, VisibleName};
+
+    fn database_with_brave_and_shy_cow() -> Result<Database> {
+        let database = Database::new_in_memory()?;
+        for (url, character) in [
+            ("https://brave.example.com/cow.txt", Character::Brave),
+            ("https://shy.example.com/cow.txt", Character::Shy),
+        ] {
+            let name = VisibleName::new(url)?;
+            database.update(&name, |_| Ok(Some(Cow::new(name.clone(), character))))?;
+        }
+        Ok(database)
+    }
+
+    #[tokio::test]
+    async fn shows_shy_cows_censored() -> Result<()> {
+        let handler = GetHerdHandler::new(
+            database_with_brave_and_shy_cow()?,
+            adapters::Metrics::new()?,
+            ShyCows::Censored,
+            HerdShuffle::PerRequest,
+        );
+
+        let herd = handler.handle().await?;
+
+        assert_eq!(herd.cows().len(), 2);
+        assert!(matches!(herd.cows()[0].name(), Name::Visible(_)));
+        assert!(matches!(herd.cows()[1].name(), Name::Censored(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hides_shy_cows() -> Result<()> {
+        let handler = GetHerdHandler::new(
+            database_with_brave_and_shy_cow()?,
+            adapters::Metrics::new()?,
+            ShyCows::Hidden,
+            HerdShuffle::PerRequest,
+        );
+
+        let herd = handler.handle().await?;
+
+        assert_eq!(herd.cows().len(), 1);
+        assert!(matches!(
+            herd.cows()[0].name(),
+            Name::Visible(name) if name.url().as_str() == "https://brave.example.com/cow.txt"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hours_outside_of_the_active_hours_dont_count_towards_running_away() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://brave.example.com/cow.txt")?;
+        let long_ago = DateTime::now() - Duration::new_from_hours(30);
+        database.update(&name, |_| {
+            Ok(Some(Cow::new_from_history(
+                name.clone(),
+                Character::Brave,
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+            )))
+        })?;
+        let handler = GetHerdHandler::new(
+            database,
+            adapters::Metrics::new()?,
+            ShyCows::Censored,
+            HerdShuffle::PerRequest,
+        );
+
+        let herd = handler.handle().await?;
+        assert!(matches!(herd.cows()[0].status(), app::CowStatus::RanAway));
+
+        let handler = handler.with_active_hours(Some(ActiveHours::new("08:00-20:00", None)?));
+        let herd = handler.handle().await?;
+        assert!(matches!(
+            herd.cows()[0].status(),
+            app::CowStatus::HappilyGrazing
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_the_active_hours_of_every_day_count_towards_running_away() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let handler = GetHerdHandler::new(
+            database.clone(),
+            adapters::Metrics::new()?,
+            ShyCows::Censored,
+            HerdShuffle::PerRequest,
+        )
+        .with_active_hours(Some(ActiveHours::new("08:00-10:00", None)?));
+
+        // two hours a day add up to a whole day of checks after twelve days
+        for (days, expected_status) in [
+            (3, app::CowStatus::HappilyGrazing),
+            (11, app::CowStatus::HappilyGrazing),
+            (13, app::CowStatus::RanAway),
+        ] {
+            let name = VisibleName::new("https://brave.example.com/cow.txt")?;
+            let long_ago = DateTime::now() - Duration::new_from_days(days);
+            database.update(&name, |_| {
+                Ok(Some(Cow::new_from_history(
+                    name.clone(),
+                    Character::Brave,
+                    Some(long_ago.clone()),
+                    Some(long_ago.clone()),
+                    Some(long_ago.clone()),
+                )))
+            })?;
+
+            let herd = handler.handle().await?;
+            assert!(
+                herd.cows()[0].status() == &expected_status,
+                "unseen for {days} days"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn marks_cows_waiting_for_their_first_check_for_too_long_as_overdue() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        for (url, waiting_for) in [
+            (
+                "https://new.example.com/cow.txt",
+                Duration::new_from_minutes(1),
+            ),
+            (
+                "https://old.example.com/cow.txt",
+                Duration::new_from_hours(1),
+            ),
+        ] {
+            let name = VisibleName::new(url)?;
+            database.update(&name, |_| {
+                Ok(Some(
+                    Cow::new(name.clone(), Character::Brave)
+                        .with_added_at(DateTime::now() - waiting_for.clone()),
+                ))
+            })?;
+        }
+        let handler = GetHerdHandler::new(
+            database,
+            adapters::Metrics::new()?,
+            ShyCows::Censored,
+            HerdShuffle::PerRequest,
+        )
+        .with_unchecked_cows(Some(UncheckedCows::new(Duration::new_from_minutes(10))));
+
+        let herd = handler.handle().await?;
+
+        let overdue: Vec<String> = herd
+            .cows()
+            .iter()
+            .filter(|cow| cow.first_check_overdue())
+            .map(|cow| match cow.name() {
+                Name::Visible(name) => name.url().to_string(),
+                Name::Censored(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(overdue, vec!["https://old.example.com/cow.txt".to_string()]);
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use crate::app::get_herd::ShyCows;
+use crate::app::{Cow, Insights, Inventory, Metrics};
+use crate::domain::HostCensoring;
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::flap_penalty::FlapPenalty;
+use crate::errors::Result;
+use crate::{app, domain};
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct GetInsightsHandler<I, M> {
+    inventory: I,
+    metrics: M,
+    shy_cows: ShyCows,
+    active_hours: Option<ActiveHours>,
+    host_censoring: HostCensoring,
+    flap_penalty: FlapPenalty,
+}
+
+impl<I, M> GetInsightsHandler<I, M>
+where
+    I: Inventory,
+    M: Metrics,
+{
+    pub fn new(inventory: I, metrics: M, shy_cows: ShyCows) -> Self {
+        Self {
+            inventory,
+            metrics,
+            shy_cows,
+            active_hours: None,
+            host_censoring: HostCensoring::default(),
+            flap_penalty: FlapPenalty::default(),
+        }
+    }
+
+    pub fn with_active_hours(mut self, active_hours: Option<ActiveHours>) -> Self {
+        self.active_hours = active_hours;
+        self
+    }
+
+    pub fn with_host_censoring(mut self, host_censoring: HostCensoring) -> Self {
+        self.host_censoring = host_censoring;
+        self
+    }
+
+    pub fn with_flap_penalty(mut self, flap_penalty: FlapPenalty) -> Self {
+        self.flap_penalty = flap_penalty;
+        self
+    }
+
+    // hidden shy cows are left out before picking so that the insights don't give away that they
+    // exist
+    async fn handle_inner(&self) -> Result<Insights> {
+        let herd: domain::Herd = self
+            .inventory
+            .list()?
+            .into_iter()
+            .filter(|cow| {
+                self.shy_cows == ShyCows::Censored || cow.character() != &domain::Character::Shy
+            })
+            .collect();
+        let stats = herd.stats();
+        Ok(Insights::new(
+            stats
+                .longest_lived()
+                .map(|cow| self.to_app_cow(cow))
+                .transpose()?,
+            stats.newest().map(|cow| self.to_app_cow(cow)).transpose()?,
+            stats
+                .flakiest()
+                .map(|cow| self.to_app_cow(cow))
+                .transpose()?,
+        ))
+    }
+
+    fn to_app_cow(&self, cow: &domain::Cow) -> Result<Cow> {
+        Cow::try_from(
+            &domain::CensoredCow::new_with_host_censoring(cow, self.host_censoring)?
+                .with_active_hours(self.active_hours.as_ref())
+                .with_flap_penalty(&self.flap_penalty),
+        )
+    }
+}
+
+#[async_trait]
+impl<I, M> app::GetInsightsHandler for GetInsightsHandler<I, M>
+where
+    I: Inventory + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self) -> Result<Insights> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "get_insights",
+            self.handle_inner().await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::GetInsightsHandler as _;
+    use crate::domain::time::DateTime;
+    use crate::domain::{Character, Name, VisibleName};
+
+    fn database_with_seeded_herd() -> Result<Database> {
+        let database = Database::new_in_memory()?;
+        for (url, character, first_seen, failure_streak) in [
+            ("https://old.example.com/cow.txt", Character::Brave, 2020, 0),
+            (
+                "https://flaky.example.com/cow.txt",
+                Character::Brave,
+                2021,
+                3,
+            ),
+            ("https://shy.example.com/cow.txt", Character::Shy, 2019, 7),
+        ] {
+            let name = VisibleName::new(url)?;
+            let cow = domain::Cow::new_from_history(
+                name.clone(),
+                character,
+                Some(DateTime::new_from_ymdhms(first_seen, 1, 1, 0, 0, 0)),
+                None,
+                None,
+            )
+            .with_failure_streak(failure_streak);
+            database.update(&name, |_| Ok(Some(cow.clone())))?;
+        }
+        Ok(database)
+    }
+
+    #[tokio::test]
+    async fn insights_show_shy_cows_censored() -> Result<()> {
+        let handler = GetInsightsHandler::new(
+            database_with_seeded_herd()?,
+            adapters::Metrics::new()?,
+            ShyCows::Censored,
+        );
+
+        let insights = handler.handle().await?;
+
+        for cow in [insights.longest_lived(), insights.flakiest()] {
+            assert!(matches!(cow.map(|cow| cow.name()), Some(Name::Censored(_))));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn insights_leave_out_hidden_shy_cows() -> Result<()> {
+        let handler = GetInsightsHandler::new(
+            database_with_seeded_herd()?,
+            adapters::Metrics::new()?,
+            ShyCows::Hidden,
+        );
+
+        let insights = handler.handle().await?;
+
+        for (cow, expected) in [
+            (insights.longest_lived(), "https://old.example.com/cow.txt"),
+            (insights.newest(), "https://flaky.example.com/cow.txt"),
+            (insights.flakiest(), "https://flaky.example.com/cow.txt"),
+        ] {
+            assert!(
+                matches!(
+                    cow.map(|cow| cow.name()),
+                    Some(Name::Visible(name)) if name.url().as_str() == expected
+                ),
+                "Failed for: {expected}"
+            );
+        }
+        Ok(())
+    }
+}
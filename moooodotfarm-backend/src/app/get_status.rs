@@ -0,0 +1,42 @@
+use crate::app;
+use crate::app::{LastUpdate, Metrics, UpdateLog};
+use crate::errors::Result;
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct GetStatusHandler<L, M> {
+    update_log: L,
+    metrics: M,
+}
+
+impl<L, M> GetStatusHandler<L, M>
+where
+    L: UpdateLog,
+    M: Metrics,
+{
+    pub fn new(update_log: L, metrics: M) -> Self {
+        Self {
+            update_log,
+            metrics,
+        }
+    }
+
+    async fn handle_inner(&self) -> Result<LastUpdate> {
+        self.update_log.last_update()
+    }
+}
+
+#[async_trait]
+impl<L, M> app::GetStatusHandler for GetStatusHandler<L, M>
+where
+    L: UpdateLog + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self) -> Result<LastUpdate> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "get_status",
+            self.handle_inner().await
+        )
+    }
+}
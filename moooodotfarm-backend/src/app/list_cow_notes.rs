@@ -0,0 +1,94 @@
+use crate::app;
+use crate::app::{Inventory, Metrics};
+use crate::errors::Result;
+use async_trait::async_trait;
+
+// see GetCowNotesHandler, cows without notes are left out
+#[derive(Clone)]
+pub struct ListCowNotesHandler<I, M> {
+    inventory: I,
+    metrics: M,
+}
+
+impl<I, M> ListCowNotesHandler<I, M>
+where
+    I: Inventory,
+    M: Metrics,
+{
+    pub fn new(inventory: I, metrics: M) -> Self {
+        Self { inventory, metrics }
+    }
+
+    fn handle_inner(&self) -> Result<Vec<app::CowNotes>> {
+        let mut notes: Vec<app::CowNotes> = self
+            .inventory
+            .list()?
+            .into_iter()
+            .filter(|cow| !cow.notes().is_empty())
+            .map(|cow| app::CowNotes::new(cow.name().clone(), cow.notes().to_string()))
+            .collect();
+        notes.sort_by(|a, b| a.name().url().cmp(b.name().url()));
+        Ok(notes)
+    }
+}
+
+#[async_trait]
+impl<I, M> app::ListCowNotesHandler for ListCowNotesHandler<I, M>
+where
+    I: Inventory + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self) -> Result<Vec<app::CowNotes>> {
+        crate::record_application_handler_call!(self.metrics, "list_cow_notes", self.handle_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::ListCowNotesHandler as _;
+    use crate::domain::{Character, Cow, VisibleName};
+
+    #[tokio::test]
+    async fn lists_the_notes_of_every_cow_which_has_some() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        for (url, character, notes) in [
+            ("https://shy.example.com/cow.txt", Character::Shy, "ask bob"),
+            (
+                "https://brave.example.com/cow.txt",
+                Character::Brave,
+                "ask alice",
+            ),
+            ("https://quiet.example.com/cow.txt", Character::Brave, ""),
+        ] {
+            let name = VisibleName::new(url)?;
+            database.update(&name, |_| {
+                Ok(Some(Cow::new(name.clone(), character).with_notes(notes)))
+            })?;
+        }
+        let handler = ListCowNotesHandler::new(database, adapters::Metrics::new()?);
+
+        let notes: Vec<(String, String)> = handler
+            .handle()
+            .await?
+            .iter()
+            .map(|v| (v.name().url().to_string(), v.notes().to_string()))
+            .collect();
+        assert_eq!(
+            notes,
+            vec![
+                (
+                    "https://brave.example.com/cow.txt".to_string(),
+                    "ask alice".to_string()
+                ),
+                (
+                    "https://shy.example.com/cow.txt".to_string(),
+                    "ask bob".to_string()
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
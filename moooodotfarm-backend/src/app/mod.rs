@@ -1,25 +1,90 @@
 pub mod add_cow;
 pub mod change_cow_character;
+pub mod change_log_level;
+pub mod compact_database;
+pub mod cow_bodies;
 pub mod delete_cow;
+pub mod discover_cows;
+pub mod get_cow;
+pub mod get_cow_audit;
+pub mod get_cow_body;
+pub mod get_cow_notes;
 pub mod get_herd;
+pub mod get_insights;
+pub mod get_status;
+pub mod list_cow_notes;
+pub mod pause_cow;
+pub mod refresh_cow;
+pub mod reload_config;
+pub mod resume_cow;
+pub mod set_cow_notes;
 pub mod update;
 
+use crate::config::Config;
 use crate::domain;
 use crate::domain::Character;
 use crate::domain::time::{DateTime, Duration};
 use crate::errors::{Error, Result};
 use async_trait::async_trait;
+use std::sync::{Arc, RwLock};
+
+static UPDATE_PROGRESS_CHANNEL_CAPACITY: usize = 64;
 
 #[async_trait]
 pub trait UpdateHandler {
     async fn handle(&self) -> Result<()>;
 }
 
+#[async_trait]
+pub trait CompactDatabaseHandler {
+    async fn handle(&self) -> Result<u64>;
+}
+
+#[async_trait]
+pub trait DiscoverCowsHandler {
+    async fn handle(&self) -> Result<usize>;
+}
+
 #[async_trait]
 pub trait GetHerdHandler {
     async fn handle(&self) -> Result<Herd>;
 }
 
+#[async_trait]
+pub trait GetInsightsHandler: Send + Sync {
+    async fn handle(&self) -> Result<Insights>;
+}
+
+#[async_trait]
+pub trait GetStatusHandler: Send + Sync {
+    async fn handle(&self) -> Result<LastUpdate>;
+}
+
+#[async_trait]
+pub trait GetCowHandler: Send + Sync {
+    async fn handle(&self, v: &GetCow) -> Result<Option<Cow>>;
+}
+
+#[async_trait]
+pub trait GetCowBodyHandler: Send + Sync {
+    async fn handle(&self, v: &GetCowBody) -> Result<Option<String>>;
+}
+
+#[async_trait]
+pub trait GetCowAuditHandler: Send + Sync {
+    async fn handle(&self, v: &GetCowAudit) -> Result<Option<Vec<domain::CharacterChange>>>;
+}
+
+#[async_trait]
+pub trait GetCowNotesHandler: Send + Sync {
+    async fn handle(&self, v: &GetCowNotes) -> Result<Option<String>>;
+}
+
+#[async_trait]
+pub trait ListCowNotesHandler: Send + Sync {
+    async fn handle(&self) -> Result<Vec<CowNotes>>;
+}
+
 #[async_trait]
 pub trait AddCowHandler: Send + Sync {
     async fn handle(&self, v: &AddCow) -> Result<()>;
@@ -35,14 +100,110 @@ pub trait DeleteCowHandler: Send + Sync {
     async fn handle(&self, v: &DeleteCow) -> Result<()>;
 }
 
+#[async_trait]
+pub trait PauseCowHandler: Send + Sync {
+    async fn handle(&self, v: &PauseCow) -> Result<()>;
+}
+
+#[async_trait]
+pub trait ResumeCowHandler: Send + Sync {
+    async fn handle(&self, v: &ResumeCow) -> Result<()>;
+}
+
+#[async_trait]
+pub trait SetCowNotesHandler: Send + Sync {
+    async fn handle(&self, v: &SetCowNotes) -> Result<()>;
+}
+
+#[async_trait]
+pub trait RefreshCowHandler: Send + Sync {
+    async fn handle(&self, v: &RefreshCow) -> Result<Cow>;
+}
+
+#[async_trait]
+pub trait ReloadConfigHandler: Send + Sync {
+    async fn handle(&self) -> Result<ReloadSummary>;
+}
+
+#[async_trait]
+pub trait ChangeLogLevelHandler: Send + Sync {
+    async fn handle(&self, v: &ChangeLogLevel) -> Result<log::LevelFilter>;
+}
+
+pub struct GetCow {
+    name: domain::VisibleName,
+}
+
+impl GetCow {
+    pub fn new(name: domain::VisibleName) -> Self {
+        Self { name }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+}
+
+pub struct GetCowBody {
+    name: domain::VisibleName,
+}
+
+impl GetCowBody {
+    pub fn new(name: domain::VisibleName) -> Self {
+        Self { name }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+}
+
+pub struct GetCowAudit {
+    name: domain::VisibleName,
+}
+
+impl GetCowAudit {
+    pub fn new(name: domain::VisibleName) -> Self {
+        Self { name }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+}
+
+pub struct GetCowNotes {
+    name: domain::VisibleName,
+}
+
+impl GetCowNotes {
+    pub fn new(name: domain::VisibleName) -> Self {
+        Self { name }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+}
+
 pub struct AddCow {
     name: domain::VisibleName,
     character: Character,
+    tags: Vec<domain::Tag>,
 }
 
 impl AddCow {
     pub fn new(name: domain::VisibleName, character: Character) -> Self {
-        Self { name, character }
+        Self {
+            name,
+            character,
+            tags: vec![],
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<domain::Tag>) -> Self {
+        self.tags = tags;
+        self
     }
 
     pub fn name(&self) -> &domain::VisibleName {
@@ -52,6 +213,10 @@ impl AddCow {
     pub fn character(&self) -> &Character {
         &self.character
     }
+
+    pub fn tags(&self) -> &[domain::Tag] {
+        &self.tags
+    }
 }
 
 pub struct ChangeCowCharacter {
@@ -90,6 +255,126 @@ impl DeleteCow {
     }
 }
 
+pub struct PauseCow {
+    name: domain::VisibleName,
+}
+
+impl PauseCow {
+    pub fn new(name: domain::VisibleName) -> Self {
+        Self { name }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+}
+
+pub struct ResumeCow {
+    name: domain::VisibleName,
+}
+
+impl ResumeCow {
+    pub fn new(name: domain::VisibleName) -> Self {
+        Self { name }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+}
+
+pub struct SetCowNotes {
+    name: domain::VisibleName,
+    notes: String,
+}
+
+impl SetCowNotes {
+    pub fn new(name: domain::VisibleName, notes: impl Into<String>) -> Self {
+        Self {
+            name,
+            notes: notes.into(),
+        }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+}
+
+pub struct RefreshCow {
+    name: domain::VisibleName,
+}
+
+impl RefreshCow {
+    pub fn new(name: domain::VisibleName) -> Self {
+        Self { name }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeLogLevel {
+    Set(log::LevelFilter),
+    // goes back to the configured level
+    Revert,
+    // switches between the configured level and debug, meant for signals which can't carry a level
+    ToggleDebug,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReloadSummary {
+    added: Vec<domain::VisibleName>,
+    removed: Vec<domain::VisibleName>,
+    character_changed: Vec<domain::VisibleName>,
+    nickname_changed: Vec<domain::VisibleName>,
+    tags_changed: Vec<domain::VisibleName>,
+}
+
+impl ReloadSummary {
+    pub fn new(
+        added: Vec<domain::VisibleName>,
+        removed: Vec<domain::VisibleName>,
+        character_changed: Vec<domain::VisibleName>,
+        nickname_changed: Vec<domain::VisibleName>,
+        tags_changed: Vec<domain::VisibleName>,
+    ) -> Self {
+        Self {
+            added,
+            removed,
+            character_changed,
+            nickname_changed,
+            tags_changed,
+        }
+    }
+
+    pub fn added(&self) -> &[domain::VisibleName] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[domain::VisibleName] {
+        &self.removed
+    }
+
+    pub fn character_changed(&self) -> &[domain::VisibleName] {
+        &self.character_changed
+    }
+
+    pub fn nickname_changed(&self) -> &[domain::VisibleName] {
+        &self.nickname_changed
+    }
+
+    pub fn tags_changed(&self) -> &[domain::VisibleName] {
+        &self.tags_changed
+    }
+}
+
 pub trait Metrics {
     fn record_application_handler_call(
         &self,
@@ -99,6 +384,49 @@ pub trait Metrics {
     );
 
     fn update_herd_numbers(&self, herd: &Herd);
+
+    fn update_cow_counts(&self, configured: usize, tracked: usize);
+
+    fn record_cow_download_started(&self);
+
+    fn record_cow_download_finished(&self);
+
+    // none for failures which don't have a missing reason
+    fn record_cow_check_failure(&self, reason: Option<domain::MissingReason>);
+
+    fn record_cow_update_failure(&self);
+
+    fn record_cow_content_change(&self);
+
+    fn update_request_budget_usage(&self, requests: usize, deferred: usize);
+
+    fn update_circuit_breaker_states(
+        &self,
+        states: &[domain::circuit_breaker::CircuitBreakerState],
+    );
+
+    fn update_cows_overdue_for_first_check(&self, count: usize);
+
+    fn update_herd_degraded(&self, degraded: bool);
+
+    fn update_last_update(&self, last_update: &LastUpdate);
+
+    fn record_config_reload(&self, result: ApplicationHandlerCallResult);
+}
+
+pub fn update_cow_counts(
+    config: &Config,
+    inventory: &impl Inventory,
+    metrics: &impl Metrics,
+) -> Result<()> {
+    let configured = config.cows().map(|cows| cows.len()).unwrap_or(0);
+    let tracked = inventory.list()?.len();
+    metrics.update_cow_counts(configured, tracked);
+    Ok(())
+}
+
+pub trait EventSink {
+    fn emit(&self, event: domain::events::DomainEvent);
 }
 
 pub trait Inventory {
@@ -107,14 +435,59 @@ pub trait Inventory {
     fn update<F>(&self, name: &domain::VisibleName, f: F) -> Result<()>
     where
         F: FnOnce(Option<domain::Cow>) -> Result<Option<domain::Cow>>;
+    fn update_many<F>(&self, names: &[domain::VisibleName], f: F) -> Result<()>
+    where
+        F: FnMut(&domain::VisibleName, Option<domain::Cow>) -> Result<Option<domain::Cow>>;
     fn delete(&self, name: &domain::VisibleName) -> Result<()>;
 }
 
+pub trait UpdateLog {
+    fn record_update_started(&self, at: &DateTime) -> Result<()>;
+    fn record_update_finished(&self, at: &DateTime, result: UpdateResult) -> Result<()>;
+    fn last_update(&self) -> Result<LastUpdate>;
+}
+
+// the last body of each cow which passed validation
+pub trait CowBodies {
+    fn put(&self, name: &domain::VisibleName, body: &str) -> Result<()>;
+    fn get(&self, name: &domain::VisibleName) -> Result<Option<String>>;
+}
+
+// character changes of each cow, oldest first. they are recorded by Inventory::update together with
+// the cow whose character changed
+pub trait CharacterAudit {
+    fn character_changes(&self, name: &domain::VisibleName)
+    -> Result<Vec<domain::CharacterChange>>;
+}
+
+pub trait DatabaseCompactor {
+    fn compact(&self) -> Result<u64>;
+}
+
+pub trait ConfigSource {
+    fn load(&self) -> Result<Config>;
+}
+
+pub trait LogLevelSwitch {
+    // None goes back to the configured level, returns the level which is active afterwards
+    fn switch(&self, level: Option<log::LevelFilter>) -> log::LevelFilter;
+
+    fn is_switched(&self) -> bool;
+}
+
 #[async_trait]
 pub trait CowTxtDownloader: Send + Sync {
     async fn download(&self, name: &domain::VisibleName) -> Result<domain::CowTxt<'_>>;
 }
 
+// finds hosts which might have a cow, whether they really do is only known once the cow is added
+#[async_trait]
+pub trait CowDiscovery: Send + Sync {
+    // every discovered cow is tagged with it so that it is clear where it came from
+    fn origin(&self) -> domain::Tag;
+    async fn discover(&self) -> Result<Vec<domain::VisibleName>>;
+}
+
 pub enum ApplicationHandlerCallResult {
     Ok,
     Error,
@@ -129,6 +502,137 @@ impl<T> From<&Result<T>> for ApplicationHandlerCallResult {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateResult {
+    Succeeded,
+    Failed,
+}
+
+impl<T> From<&Result<T>> for UpdateResult {
+    fn from(result: &Result<T>) -> Self {
+        match result {
+            Ok(_) => UpdateResult::Succeeded,
+            Err(_) => UpdateResult::Failed,
+        }
+    }
+}
+
+// survives restarts so that a stuck update timer can be spotted, the finished timestamp and the
+// result are those of the last update which actually completed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LastUpdate {
+    started: Option<DateTime>,
+    finished: Option<DateTime>,
+    result: Option<UpdateResult>,
+}
+
+impl LastUpdate {
+    pub fn new(
+        started: Option<DateTime>,
+        finished: Option<DateTime>,
+        result: Option<UpdateResult>,
+    ) -> Self {
+        Self {
+            started,
+            finished,
+            result,
+        }
+    }
+
+    pub fn started(&self) -> Option<&DateTime> {
+        self.started.as_ref()
+    }
+
+    pub fn finished(&self) -> Option<&DateTime> {
+        self.finished.as_ref()
+    }
+
+    pub fn result(&self) -> Option<UpdateResult> {
+        self.result
+    }
+}
+
+// published after every check so the name of a shy cow is censored just like everywhere else
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateProgress {
+    checked: usize,
+    total: usize,
+    current_cow: domain::Name,
+}
+
+impl UpdateProgress {
+    pub fn new(checked: usize, total: usize, current_cow: domain::Name) -> Self {
+        Self {
+            checked,
+            total,
+            current_cow,
+        }
+    }
+
+    pub fn checked(&self) -> usize {
+        self.checked
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn current_cow(&self) -> &domain::Name {
+        &self.current_cow
+    }
+}
+
+// subscribers which fall behind miss some of the progress rather than slowing the update down,
+// progress published while nobody is subscribed is dropped
+#[derive(Clone)]
+pub struct UpdateProgressChannel {
+    sender: tokio::sync::broadcast::Sender<UpdateProgress>,
+}
+
+impl UpdateProgressChannel {
+    pub fn new() -> Self {
+        let (sender, _) = tokio::sync::broadcast::channel(UPDATE_PROGRESS_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, progress: UpdateProgress) {
+        let _ = self.sender.send(progress);
+    }
+
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<UpdateProgress> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for UpdateProgressChannel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the cows in the order in which they appear in the config, shared by the update and the reload of
+// the config so that the update follows the reloaded config
+#[derive(Clone, Default)]
+pub struct ConfiguredOrder {
+    names: Arc<RwLock<Vec<domain::VisibleName>>>,
+}
+
+impl ConfiguredOrder {
+    pub fn new(names: Vec<domain::VisibleName>) -> Self {
+        Self {
+            names: Arc::new(RwLock::new(names)),
+        }
+    }
+
+    pub fn replace(&self, names: Vec<domain::VisibleName>) {
+        *self.names.write().unwrap() = names;
+    }
+
+    pub fn names(&self) -> Vec<domain::VisibleName> {
+        self.names.read().unwrap().clone()
+    }
+}
+
 pub struct Herd {
     cows: Vec<Cow>,
 }
@@ -152,12 +656,73 @@ impl TryFrom<domain::CensoredHerd> for Herd {
     }
 }
 
+// notes are only ever shown to the owner so the cow isn't censored
+pub struct CowNotes {
+    name: domain::VisibleName,
+    notes: String,
+}
+
+impl CowNotes {
+    pub fn new(name: domain::VisibleName, notes: String) -> Self {
+        Self { name, notes }
+    }
+
+    pub fn name(&self) -> &domain::VisibleName {
+        &self.name
+    }
+
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+}
+
+// see domain::HerdStats, the cows are censored just like everywhere else
+pub struct Insights {
+    longest_lived: Option<Cow>,
+    newest: Option<Cow>,
+    flakiest: Option<Cow>,
+}
+
+impl Insights {
+    pub fn new(longest_lived: Option<Cow>, newest: Option<Cow>, flakiest: Option<Cow>) -> Self {
+        Self {
+            longest_lived,
+            newest,
+            flakiest,
+        }
+    }
+
+    pub fn longest_lived(&self) -> Option<&Cow> {
+        self.longest_lived.as_ref()
+    }
+
+    pub fn newest(&self) -> Option<&Cow> {
+        self.newest.as_ref()
+    }
+
+    pub fn flakiest(&self) -> Option<&Cow> {
+        self.flakiest.as_ref()
+    }
+}
+
 pub struct Cow {
     name: domain::Name,
+    censor_reason: domain::CensorReason,
     character: Character,
+    unexpected_character: bool,
+    nickname: Option<domain::Nickname>,
+    tags: Vec<domain::Tag>,
+    notes: String,
+    added_at: DateTime,
     first_seen: Option<DateTime>,
     last_seen: Option<DateTime>,
+    next_check_at: Option<DateTime>,
+    check_interval: Duration,
     status: CowStatus,
+    metrics_status: CowStatus,
+    domain_not_found: bool,
+    missing_reason: Option<domain::MissingReason>,
+    first_check_overdue: bool,
 }
 
 impl Cow {
@@ -165,10 +730,35 @@ impl Cow {
         &self.name
     }
 
+    pub fn censor_reason(&self) -> domain::CensorReason {
+        self.censor_reason
+    }
+
     pub fn character(&self) -> &Character {
         &self.character
     }
 
+    // set when the config expects the cow to have a different character
+    pub fn unexpected_character(&self) -> bool {
+        self.unexpected_character
+    }
+
+    pub fn nickname(&self) -> Option<&domain::Nickname> {
+        self.nickname.as_ref()
+    }
+
+    pub fn tags(&self) -> &[domain::Tag] {
+        &self.tags
+    }
+
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    pub fn added_at(&self) -> &DateTime {
+        &self.added_at
+    }
+
     pub fn first_seen(&self) -> Option<&DateTime> {
         self.first_seen.as_ref()
     }
@@ -177,9 +767,35 @@ impl Cow {
         self.last_seen.as_ref()
     }
 
+    // none while the cow is paused
+    pub fn next_check_at(&self) -> Option<&DateTime> {
+        self.next_check_at.as_ref()
+    }
+
+    // lengthened for cows which keep running away and coming back
+    pub fn check_interval(&self) -> &Duration {
+        &self.check_interval
+    }
+
     pub fn status(&self) -> &CowStatus {
         &self.status
     }
+
+    pub fn metrics_status(&self) -> &CowStatus {
+        &self.metrics_status
+    }
+
+    pub fn domain_not_found(&self) -> bool {
+        self.domain_not_found
+    }
+
+    pub fn missing_reason(&self) -> Option<domain::MissingReason> {
+        self.missing_reason
+    }
+
+    pub fn first_check_overdue(&self) -> bool {
+        self.first_check_overdue
+    }
 }
 
 impl TryFrom<&domain::CensoredCow> for Cow {
@@ -188,18 +804,32 @@ impl TryFrom<&domain::CensoredCow> for Cow {
     fn try_from(value: &domain::CensoredCow) -> Result<Self> {
         Ok(Self {
             name: value.name().clone(),
+            censor_reason: value.censor_reason(),
             character: value.character().clone(),
+            unexpected_character: value.unexpected_character(),
+            nickname: value.nickname().cloned(),
+            tags: value.tags().to_vec(),
+            notes: value.notes().to_string(),
+            added_at: value.added_at().clone(),
             first_seen: value.first_seen().cloned(),
             last_seen: value.last_seen().cloned(),
+            next_check_at: value.next_check_at().cloned(),
+            check_interval: value.check_interval().clone(),
             status: CowStatus::new(value),
+            metrics_status: CowStatus::new_for_metrics(value),
+            domain_not_found: value.domain_not_found(),
+            missing_reason: value.missing_reason(),
+            first_check_overdue: value.first_check_overdue(),
         })
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum CowStatus {
     HappilyGrazing,
     RanAway,
     HaveNotCheckedYet,
+    Paused,
 }
 
 impl CowStatus {
@@ -208,17 +838,30 @@ impl CowStatus {
             CowStatus::HappilyGrazing,
             CowStatus::RanAway,
             CowStatus::HaveNotCheckedYet,
+            CowStatus::Paused,
         ]
     }
 
+    // a paused cow isn't checked so whatever the last check said is likely out of date
     fn new(cow_status: &domain::CensoredCow) -> Self {
+        if cow_status.paused() {
+            return CowStatus::Paused;
+        }
         if cow_status.last_checked().is_none() {
             return CowStatus::HaveNotCheckedYet;
         }
 
+        // a cow can't be seen while updates are outside of the active hours
+        let now = DateTime::now();
         let seen_in_last_24h = cow_status
             .last_seen()
-            .map(|v| DateTime::now() - v < Duration::new_from_hours(24))
+            .map(|v| {
+                let unseen_for = match cow_status.active_hours() {
+                    Some(active_hours) => active_hours.active_between(v, &now),
+                    None => &now - v,
+                };
+                unseen_for < Duration::new_from_hours(24)
+            })
             .unwrap_or(false);
         if seen_in_last_24h {
             return CowStatus::HappilyGrazing;
@@ -226,6 +869,21 @@ impl CowStatus {
 
         CowStatus::RanAway
     }
+
+    // unlike the status shown to people the metrics only count cows which failed several checks in
+    // a row as ran away so that a brief network problem doesn't make the gauges swing
+    fn new_for_metrics(cow_status: &domain::CensoredCow) -> Self {
+        match CowStatus::new(cow_status) {
+            CowStatus::RanAway if !cow_status.is_consistently_missing() => {
+                if cow_status.last_seen().is_some() {
+                    CowStatus::HappilyGrazing
+                } else {
+                    CowStatus::HaveNotCheckedYet
+                }
+            }
+            status => status,
+        }
+    }
 }
 
 #[macro_export]
@@ -0,0 +1,113 @@
+use crate::app::{EventSink, Inventory, Metrics};
+use crate::domain::events::DomainEvent;
+use crate::errors::{Error, Result};
+use crate::{app, domain};
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct PauseCowHandler<I, M, E> {
+    inventory: I,
+    metrics: M,
+    events: E,
+}
+
+impl<I, M, E> PauseCowHandler<I, M, E>
+where
+    I: Inventory,
+    M: Metrics,
+    E: EventSink,
+{
+    pub fn new(inventory: I, metrics: M, events: E) -> Self {
+        Self {
+            inventory,
+            metrics,
+            events,
+        }
+    }
+
+    async fn handle_inner(&self, v: &app::PauseCow) -> Result<()> {
+        let mut paused = false;
+        self.inventory
+            .update(v.name(), |cow: Option<domain::Cow>| match cow {
+                Some(mut cow) => {
+                    if cow.paused() {
+                        return Ok(None);
+                    }
+                    cow.pause()?;
+                    paused = true;
+                    Ok(Some(cow))
+                }
+                None => Err(Error::NotFound("cow does not exist".to_string())),
+            })?;
+        if paused {
+            self.events.emit(DomainEvent::CowPaused {
+                name: v.name().clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<I, M, E> app::PauseCowHandler for PauseCowHandler<I, M, E>
+where
+    I: Inventory + Send + Sync,
+    M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
+{
+    async fn handle(&self, v: &app::PauseCow) -> Result<()> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "pause_cow",
+            self.handle_inner(v).await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::PauseCowHandler as _;
+    use crate::domain::{Character, Cow, VisibleName};
+    use crate::fixtures::RecordingEventSink;
+
+    #[tokio::test]
+    async fn pauses_the_cow_once() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        let events = RecordingEventSink::new();
+        let handler =
+            PauseCowHandler::new(database.clone(), adapters::Metrics::new()?, events.clone());
+
+        handler.handle(&app::PauseCow::new(name.clone())).await?;
+        handler.handle(&app::PauseCow::new(name.clone())).await?;
+
+        assert!(database.get(&name)?.unwrap().paused());
+        assert_eq!(events.events(), vec![DomainEvent::CowPaused { name }]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pausing_a_missing_cow_fails() -> Result<()> {
+        let handler = PauseCowHandler::new(
+            Database::new_in_memory()?,
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+        );
+
+        let result = handler
+            .handle(&app::PauseCow::new(VisibleName::new(
+                "https://example.com/cow.txt",
+            )?))
+            .await;
+
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+use crate::app::get_herd::ShyCows;
+use crate::app::update::{Found, apply_check_result};
+use crate::app::{CowTxtDownloader, EventSink, Inventory, Metrics};
+use crate::domain::HostCensoring;
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::flap_penalty::FlapPenalty;
+use crate::errors::{Error, Result};
+use crate::{app, domain};
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct RefreshCowHandler<I, D, M, E> {
+    inventory: I,
+    downloader: D,
+    metrics: M,
+    events: E,
+    shy_cows: ShyCows,
+    host_censoring: HostCensoring,
+    active_hours: Option<ActiveHours>,
+    flap_penalty: FlapPenalty,
+}
+
+impl<I, D, M, E> RefreshCowHandler<I, D, M, E>
+where
+    I: Inventory,
+    D: CowTxtDownloader,
+    M: Metrics,
+    E: EventSink,
+{
+    pub fn new(inventory: I, downloader: D, metrics: M, events: E, shy_cows: ShyCows) -> Self {
+        Self {
+            inventory,
+            downloader,
+            metrics,
+            events,
+            shy_cows,
+            host_censoring: HostCensoring::default(),
+            active_hours: None,
+            flap_penalty: FlapPenalty::default(),
+        }
+    }
+
+    pub fn with_host_censoring(mut self, host_censoring: HostCensoring) -> Self {
+        self.host_censoring = host_censoring;
+        self
+    }
+
+    pub fn with_active_hours(mut self, active_hours: Option<ActiveHours>) -> Self {
+        self.active_hours = active_hours;
+        self
+    }
+
+    pub fn with_flap_penalty(mut self, flap_penalty: FlapPenalty) -> Self {
+        self.flap_penalty = flap_penalty;
+        self
+    }
+
+    async fn handle_inner(&self, v: &app::RefreshCow) -> Result<app::Cow> {
+        let peeked_cow = self
+            .inventory
+            .get(v.name())?
+            .filter(|cow| {
+                self.shy_cows == ShyCows::Censored || cow.character() != &domain::Character::Shy
+            })
+            .ok_or_else(|| Error::NotFound("cow does not exist".to_string()))?;
+        if !peeked_cow.can_refresh_on_demand() {
+            return self.to_app_cow(&peeked_cow);
+        }
+
+        let result = self
+            .downloader
+            .download(v.name())
+            .await
+            .map(|cow_txt| Found::new(&cow_txt));
+        if let Err(err) = &result {
+            self.metrics
+                .record_cow_check_failure(domain::MissingReason::from_error(err));
+        }
+        let mut cows = None;
+
+        self.inventory.update(v.name(), |cow| match cow {
+            Some(mut cow) => {
+                let cow_before = cow.clone();
+                apply_check_result(&mut cow, result)?;
+                cows = Some((cow_before, cow.clone()));
+                Ok(Some(cow))
+            }
+            None => Err(Error::NotFound("cow does not exist".to_string())),
+        })?;
+
+        let (cow_before, refreshed_cow) =
+            cows.ok_or_else(|| Error::NotFound("cow does not exist".to_string()))?;
+        let diff = domain::diff_herds(&[cow_before], std::slice::from_ref(&refreshed_cow));
+        for _ in diff.content_changed() {
+            self.metrics.record_cow_content_change();
+        }
+        for event in diff.events() {
+            self.events.emit(event);
+        }
+
+        self.to_app_cow(&refreshed_cow)
+    }
+
+    // the cow is shown the same way as in the herd
+    fn to_app_cow(&self, cow: &domain::Cow) -> Result<app::Cow> {
+        app::Cow::try_from(
+            &domain::CensoredCow::new_with_host_censoring(cow, self.host_censoring)?
+                .with_active_hours(self.active_hours.as_ref())
+                .with_flap_penalty(&self.flap_penalty),
+        )
+    }
+}
+
+#[async_trait]
+impl<I, D, M, E> app::RefreshCowHandler for RefreshCowHandler<I, D, M, E>
+where
+    I: Inventory + Send + Sync,
+    D: CowTxtDownloader + Send + Sync,
+    M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
+{
+    async fn handle(&self, v: &app::RefreshCow) -> Result<app::Cow> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "refresh_cow",
+            self.handle_inner(v).await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::RefreshCowHandler as _;
+    use crate::domain::events::DomainEvent;
+    use crate::domain::time::{DateTime, Duration};
+    use crate::domain::{Character, Cow, VisibleName};
+    use crate::fixtures::{MockCowTxtDownloader, RecordingEventSink};
+
+    #[tokio::test]
+    async fn refresh_shows_a_just_recovered_cow_as_grazing() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let events = RecordingEventSink::new();
+        let handler = RefreshCowHandler::new(
+            database.clone(),
+            downloader.clone(),
+            adapters::Metrics::new()?,
+            events.clone(),
+            ShyCows::Censored,
+        );
+
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let long_ago = DateTime::now() - Duration::new_from_days(2);
+        let a_while_ago = DateTime::now() - Duration::new_from_minutes(10);
+        database.update(&name, |_| {
+            Ok(Some(Cow::new_from_history(
+                name.clone(),
+                Character::Brave,
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+                Some(a_while_ago.clone()),
+            )))
+        })?;
+
+        let cow = handler.handle(&app::RefreshCow::new(name.clone())).await?;
+
+        assert!(matches!(cow.status(), app::CowStatus::HappilyGrazing));
+        assert_eq!(events.events(), vec![DomainEvent::CowReturned { name }]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refresh_is_skipped_for_a_cow_which_was_just_checked() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let handler = RefreshCowHandler::new(
+            database.clone(),
+            downloader.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            ShyCows::Censored,
+        );
+
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let long_ago = DateTime::now() - Duration::new_from_days(2);
+        database.update(&name, |_| {
+            Ok(Some(Cow::new_from_history(
+                name.clone(),
+                Character::Brave,
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+                Some(DateTime::now()),
+            )))
+        })?;
+
+        let cow = handler.handle(&app::RefreshCow::new(name.clone())).await?;
+
+        assert!(matches!(cow.status(), app::CowStatus::RanAway));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refresh_shows_the_cow_with_the_configured_active_hours_and_flap_penalty() -> Result<()>
+    {
+        let database = Database::new_in_memory()?;
+        let handler = RefreshCowHandler::new(
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            ShyCows::Censored,
+        );
+
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let long_ago = DateTime::now() - Duration::new_from_days(2);
+        database.update(&name, |_| {
+            Ok(Some(
+                Cow::new_from_history(
+                    name.clone(),
+                    Character::Brave,
+                    Some(long_ago.clone()),
+                    Some(long_ago.clone()),
+                    Some(DateTime::now()),
+                )
+                .with_flaps(5, Some(DateTime::now())),
+            ))
+        })?;
+
+        let cow = handler.handle(&app::RefreshCow::new(name.clone())).await?;
+        assert!(matches!(cow.status(), app::CowStatus::RanAway));
+        let penalized_interval = cow.check_interval().clone();
+
+        let handler = handler
+            .with_active_hours(Some(ActiveHours::new("08:00-10:00", None)?))
+            .with_flap_penalty(FlapPenalty::new(1)?);
+        let cow = handler.handle(&app::RefreshCow::new(name.clone())).await?;
+        assert!(matches!(cow.status(), app::CowStatus::HappilyGrazing));
+        assert_eq!(penalized_interval, cow.check_interval().clone() * 8);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refresh_pretends_hidden_shy_cows_do_not_exist() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let handler = RefreshCowHandler::new(
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            ShyCows::Hidden,
+        );
+
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| Ok(Some(Cow::new(name.clone(), Character::Shy))))?;
+
+        let result = handler.handle(&app::RefreshCow::new(name.clone())).await;
+
+        assert!(result.is_err());
+        assert!(database.get(&name)?.unwrap().last_checked().is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,764 @@
+use crate::app::{ConfigSource, EventSink, Inventory, Metrics, ReloadSummary};
+use crate::config::{Config, CowConfig, CowCredentials};
+use crate::domain::events::DomainEvent;
+use crate::errors::{Error, Result};
+use crate::{app, domain};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone)]
+pub struct ReloadConfigHandler<C, I, M, E> {
+    http_address: String,
+    grpc_address: String,
+    must_match: Option<domain::MustMatch>,
+    downloader_settings: BTreeMap<domain::VisibleName, DownloaderSettings>,
+    configured_order: app::ConfiguredOrder,
+    config_source: C,
+    inventory: I,
+    metrics: M,
+    events: E,
+}
+
+impl<C, I, M, E> ReloadConfigHandler<C, I, M, E>
+where
+    C: ConfigSource,
+    I: Inventory,
+    M: Metrics,
+    E: EventSink,
+{
+    pub fn new(
+        running_config: &Config,
+        config_source: C,
+        inventory: I,
+        metrics: M,
+        events: E,
+    ) -> Self {
+        Self {
+            http_address: running_config.http_address().to_string(),
+            grpc_address: running_config.grpc_address().to_string(),
+            must_match: running_config.must_match().cloned(),
+            downloader_settings: downloader_settings(running_config),
+            configured_order: app::ConfiguredOrder::default(),
+            config_source,
+            inventory,
+            metrics,
+            events,
+        }
+    }
+
+    // the order is replaced once the herd follows the reloaded config
+    pub fn with_configured_order(mut self, configured_order: app::ConfiguredOrder) -> Self {
+        self.configured_order = configured_order;
+        self
+    }
+
+    async fn handle_inner(&self) -> Result<ReloadSummary> {
+        let config = self.config_source.load()?;
+        self.ensure_bind_addresses_unchanged(&config)?;
+        self.ensure_downloader_settings_unchanged(&config)?;
+
+        let configured_cows = config
+            .cows()
+            .ok_or_else(|| Error::Unknown(anyhow!("config doesn't declare any cows")))?;
+        let changes = reconcile(configured_cows, &self.inventory.list()?)?;
+
+        let summary = self.apply(changes)?;
+        self.configured_order.replace(
+            configured_cows
+                .iter()
+                .map(|cow| cow.name().clone())
+                .collect(),
+        );
+        app::update_cow_counts(&config, &self.inventory, &self.metrics)?;
+        Ok(summary)
+    }
+
+    fn ensure_bind_addresses_unchanged(&self, config: &Config) -> Result<()> {
+        if config.http_address() != self.http_address {
+            return Err(Error::ConfigChangeRequiresRestart(
+                "http_address".to_string(),
+            ));
+        }
+        if config.grpc_address() != self.grpc_address {
+            return Err(Error::ConfigChangeRequiresRestart(
+                "grpc_address".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // the downloader is built from these when the farm starts so it would keep using the old ones
+    fn ensure_downloader_settings_unchanged(&self, config: &Config) -> Result<()> {
+        if config.must_match() != self.must_match.as_ref() {
+            return Err(Error::ConfigChangeRequiresRestart("must_match".to_string()));
+        }
+        // a cow which is removed doesn't need its settings anymore
+        let settings = downloader_settings(config);
+        for cow in config.cows().unwrap_or_default() {
+            if settings.get(cow.name()) != self.downloader_settings.get(cow.name()) {
+                return Err(Error::ConfigChangeRequiresRestart(format!(
+                    "the credentials, headers, must_match or check_via of {}",
+                    cow.name().url()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // every change is applied in a single transaction, the removed cows are deleted afterwards as a
+    // cow which is already gone can't fail the reload anymore
+    fn apply(&self, changes: Vec<Change>) -> Result<ReloadSummary> {
+        let (removed, changed): (Vec<Change>, Vec<Change>) = changes
+            .into_iter()
+            .partition(|change| matches!(change, Change::Remove(_)));
+
+        let mut changes_by_name: BTreeMap<domain::VisibleName, Vec<&Change>> = BTreeMap::new();
+        for change in &changed {
+            changes_by_name
+                .entry(change.name().clone())
+                .or_default()
+                .push(change);
+        }
+        let names: Vec<domain::VisibleName> = changes_by_name.keys().cloned().collect();
+        self.inventory.update_many(&names, |name, mut cow| {
+            for change in &changes_by_name[name] {
+                cow = Some(apply_change(change, cow)?);
+            }
+            Ok(cow)
+        })?;
+
+        for change in &removed {
+            match self.inventory.delete(change.name()) {
+                Ok(()) | Err(Error::NotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut summary = ReloadSummary::default();
+        for change in changed.into_iter().chain(removed) {
+            self.record(change, &mut summary);
+        }
+        Ok(summary)
+    }
+
+    fn record(&self, change: Change, summary: &mut ReloadSummary) {
+        match change {
+            Change::Add(cow) => {
+                self.events.emit(DomainEvent::CowAdded {
+                    name: cow.name().clone(),
+                    character: cow.character().clone(),
+                });
+                summary.added.push(cow.name().clone());
+            }
+            Change::Remove(name) => {
+                self.events
+                    .emit(DomainEvent::CowRemoved { name: name.clone() });
+                summary.removed.push(name);
+            }
+            Change::Character { cow, old_character } => {
+                self.events.emit(DomainEvent::CharacterChanged {
+                    name: cow.name().clone(),
+                    old_character,
+                    new_character: cow.character().clone(),
+                });
+                summary.character_changed.push(cow.name().clone());
+            }
+            Change::Adopt(_) | Change::ExpectedCharacter(_) => {}
+            Change::Nickname(cow) => summary.nickname_changed.push(cow.name().clone()),
+            Change::Tags(cow) => summary.tags_changed.push(cow.name().clone()),
+        }
+    }
+}
+
+fn apply_change(change: &Change, cow: Option<domain::Cow>) -> Result<domain::Cow> {
+    let existing = |cow: Option<domain::Cow>| {
+        cow.ok_or_else(|| Error::NotFound("cow does not exist".to_string()))
+    };
+    match change {
+        Change::Add(cow_config) => {
+            if cow.is_some() {
+                return Err(Error::Conflict("cow already exists".to_string()));
+            }
+            Ok(
+                domain::Cow::new(cow_config.name().clone(), cow_config.character().clone())
+                    .with_expected_character(cow_config.expected_character().cloned())
+                    .with_nickname(cow_config.nickname().cloned())
+                    .with_tags(cow_config.tags().to_vec())
+                    .with_managed_by_config(true),
+            )
+        }
+        Change::Adopt(_) => Ok(existing(cow)?.with_managed_by_config(true)),
+        Change::Remove(name) => Err(Error::Unknown(anyhow!(
+            "a removed cow can't be updated: {}",
+            name.url()
+        ))),
+        Change::Character {
+            cow: cow_config, ..
+        } => {
+            let mut cow = existing(cow)?;
+            cow.change_character(cow_config.character().clone())?;
+            Ok(cow)
+        }
+        // only an expectation, the cow keeps whatever character it has
+        Change::ExpectedCharacter(cow_config) => {
+            let mut cow = existing(cow)?;
+            cow.set_expected_character(cow_config.expected_character().cloned());
+            Ok(cow)
+        }
+        Change::Nickname(cow_config) => {
+            let mut cow = existing(cow)?;
+            cow.change_nickname(cow_config.nickname().cloned())?;
+            Ok(cow)
+        }
+        Change::Tags(cow_config) => {
+            let mut cow = existing(cow)?;
+            cow.change_tags(cow_config.tags().to_vec())?;
+            Ok(cow)
+        }
+    }
+}
+
+// what the downloader was configured with for every cow which was configured with anything
+fn downloader_settings(config: &Config) -> BTreeMap<domain::VisibleName, DownloaderSettings> {
+    config
+        .cows()
+        .unwrap_or_default()
+        .iter()
+        .map(|cow| {
+            (
+                cow.name().clone(),
+                DownloaderSettings {
+                    credentials: cow.credentials().cloned(),
+                    headers: cow.headers().clone(),
+                    must_match: cow.must_match().cloned(),
+                    check_via: cow.check_via().cloned(),
+                },
+            )
+        })
+        .filter(|(_, settings)| settings != &DownloaderSettings::default())
+        .collect()
+}
+
+#[derive(Clone, Default, PartialEq, Eq)]
+struct DownloaderSettings {
+    credentials: Option<CowCredentials>,
+    headers: http::HeaderMap,
+    must_match: Option<domain::MustMatch>,
+    check_via: Option<url::Url>,
+}
+
+#[async_trait]
+impl<C, I, M, E> app::ReloadConfigHandler for ReloadConfigHandler<C, I, M, E>
+where
+    C: ConfigSource + Send + Sync,
+    I: Inventory + Send + Sync,
+    M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
+{
+    async fn handle(&self) -> Result<ReloadSummary> {
+        let result = crate::record_application_handler_call!(
+            self.metrics,
+            "reload_config",
+            self.handle_inner().await
+        );
+        self.metrics.record_config_reload((&result).into());
+        result
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Change {
+    Add(CowConfig),
+    // a cow which was added over grpc or discovered before it was configured
+    Adopt(CowConfig),
+    Remove(domain::VisibleName),
+    Character {
+        cow: CowConfig,
+        old_character: domain::Character,
+    },
+    ExpectedCharacter(CowConfig),
+    Nickname(CowConfig),
+    Tags(CowConfig),
+}
+
+impl Change {
+    fn name(&self) -> &domain::VisibleName {
+        match self {
+            Change::Add(cow)
+            | Change::Adopt(cow)
+            | Change::Character { cow, .. }
+            | Change::ExpectedCharacter(cow)
+            | Change::Nickname(cow)
+            | Change::Tags(cow) => cow.name(),
+            Change::Remove(name) => name,
+        }
+    }
+}
+
+// cows persisted before it was tracked whether they came from the config are loaded as if they
+// didn't, the configured ones are taken over when the farm starts so that removing them from the
+// config later removes them from the herd
+pub fn adopt_configured_cows<I: Inventory>(config: &Config, inventory: &I) -> Result<()> {
+    let configured: BTreeSet<&domain::VisibleName> = config
+        .cows()
+        .unwrap_or_default()
+        .iter()
+        .map(|cow| cow.name())
+        .collect();
+    let names: Vec<domain::VisibleName> = inventory
+        .list()?
+        .into_iter()
+        .filter(|cow| !cow.managed_by_config() && configured.contains(cow.name()))
+        .map(|cow| cow.name().clone())
+        .collect();
+    inventory.update_many(&names, |_, cow| {
+        Ok(cow.map(|cow| cow.with_managed_by_config(true)))
+    })
+}
+
+// the whole plan is computed before anything is applied so that an invalid config never leaves
+// the herd partially updated
+fn reconcile(configured: &[CowConfig], tracked: &[domain::Cow]) -> Result<Vec<Change>> {
+    let mut configured_by_name: BTreeMap<&domain::VisibleName, &CowConfig> = BTreeMap::new();
+    for cow in configured {
+        if configured_by_name.insert(cow.name(), cow).is_some() {
+            return Err(Error::Unknown(anyhow!(
+                "cow is configured more than once: {}",
+                cow.name().url()
+            )));
+        }
+    }
+
+    let configured_herd: Vec<domain::Cow> = configured_by_name
+        .values()
+        .map(|cow| domain::Cow::new(cow.name().clone(), cow.character().clone()))
+        .collect();
+    // cows which didn't come from the config are never removed by it
+    let managed: Vec<domain::Cow> = tracked
+        .iter()
+        .filter(|cow| cow.managed_by_config())
+        .cloned()
+        .collect();
+    let diff = domain::diff_herds(&managed, &configured_herd);
+
+    let mut changes = vec![];
+    for name in diff.added() {
+        let cow = configured_by_name[name].clone();
+        if tracked.iter().any(|tracked| tracked.name() == name) {
+            changes.push(Change::Adopt(cow));
+        } else {
+            changes.push(Change::Add(cow));
+        }
+    }
+    for tracked in tracked {
+        let Some(cow) = configured_by_name.get(tracked.name()) else {
+            continue;
+        };
+        if tracked.character() != cow.character() {
+            changes.push(Change::Character {
+                cow: (*cow).clone(),
+                old_character: tracked.character().clone(),
+            });
+        }
+        if tracked.expected_character() != cow.expected_character() {
+            changes.push(Change::ExpectedCharacter((*cow).clone()));
+        }
+        if tracked.nickname() != cow.nickname() {
+            changes.push(Change::Nickname((*cow).clone()));
+        }
+        if tracked.tags() != cow.tags() {
+            changes.push(Change::Tags((*cow).clone()));
+        }
+    }
+    for name in diff.removed() {
+        changes.push(Change::Remove(name.clone()));
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::ConfigLoader;
+    use crate::adapters::database::Database;
+    use crate::app::CharacterAudit as _;
+    use crate::app::ReloadConfigHandler as _;
+    use crate::domain::{Character, VisibleName};
+    use crate::fixtures::RecordingEventSink;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const HEADER: &str = r#"
+http_address = "0.0.0.0:8080"
+grpc_address = "0.0.0.0:9090"
+environment = "development"
+database_path = "/moooodotfarm.db"
+"#;
+
+    fn config_file(test_name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "moooodotfarm-{}-{}.toml",
+            test_name,
+            std::process::id()
+        ))
+    }
+
+    fn herd(database: &Database) -> Result<Vec<(VisibleName, Character)>> {
+        let mut cows: Vec<_> = database
+            .list()?
+            .into_iter()
+            .map(|cow| (cow.name().clone(), cow.character().clone()))
+            .collect();
+        cows.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(cows)
+    }
+
+    #[tokio::test]
+    async fn reload_applies_changes_to_the_herd() -> Result<()> {
+        let path = config_file("reload-applies-changes");
+        fs::write(
+            &path,
+            format!(
+                r#"{HEADER}
+[[cows]]
+name = "https://a.example.com/cow.txt"
+character = "brave"
+
+[[cows]]
+name = "https://b.example.com/cow.txt"
+character = "brave"
+"#
+            ),
+        )?;
+
+        let loader = ConfigLoader::new(&path);
+        let running_config = loader.load()?;
+        let database = Database::new_in_memory()?;
+        let events = RecordingEventSink::new();
+        let configured_order = app::ConfiguredOrder::default();
+        let handler = ReloadConfigHandler::new(
+            &running_config,
+            loader,
+            database.clone(),
+            adapters::Metrics::new()?,
+            events.clone(),
+        )
+        .with_configured_order(configured_order.clone());
+
+        let a = VisibleName::new("https://a.example.com/cow.txt")?;
+        let b = VisibleName::new("https://b.example.com/cow.txt")?;
+        let c = VisibleName::new("https://c.example.com/cow.txt")?;
+
+        handler.handle().await?;
+        assert_eq!(
+            herd(&database)?,
+            vec![(a.clone(), Character::Brave), (b.clone(), Character::Brave)]
+        );
+
+        fs::write(
+            &path,
+            format!(
+                r#"{HEADER}
+[[cows]]
+name = "https://b.example.com/cow.txt"
+character = "shy"
+nickname = "Bessie"
+tags = ["dairy"]
+
+[[cows]]
+name = "https://c.example.com/cow.txt"
+character = "brave"
+"#
+            ),
+        )?;
+
+        let summary = handler.handle().await?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(
+            summary,
+            ReloadSummary::new(
+                vec![c.clone()],
+                vec![a.clone()],
+                vec![b.clone()],
+                vec![b.clone()],
+                vec![b.clone()]
+            )
+        );
+        assert_eq!(
+            database.get(&b)?.unwrap().tags(),
+            &[domain::Tag::new("dairy")?]
+        );
+        assert_eq!(
+            database.get(&b)?.unwrap().nickname(),
+            Some(&domain::Nickname::new("Bessie")?)
+        );
+        assert_eq!(
+            herd(&database)?,
+            vec![(b.clone(), Character::Shy), (c.clone(), Character::Brave)]
+        );
+        assert_eq!(configured_order.names(), vec![b.clone(), c.clone()]);
+        let changes = database.character_changes(&b)?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_character(), &Character::Brave);
+        assert_eq!(changes[0].new_character(), &Character::Shy);
+        assert!(events.events().contains(&DomainEvent::CharacterChanged {
+            name: b,
+            old_character: Character::Brave,
+            new_character: Character::Shy,
+        }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reload_leaves_cows_which_didnt_come_from_the_config_alone() -> Result<()> {
+        let path = config_file("reload-leaves-other-cows-alone");
+        let configured = |names: &[&str]| {
+            let cows: String = names
+                .iter()
+                .map(|name| format!("[[cows]]\nname = \"{name}\"\ncharacter = \"brave\"\n"))
+                .collect();
+            format!("{HEADER}\n{cows}")
+        };
+        let a = VisibleName::new("https://a.example.com/cow.txt")?;
+        let added = VisibleName::new("https://added.example.com/cow.txt")?;
+        let adopted = VisibleName::new("https://adopted.example.com/cow.txt")?;
+        let b = VisibleName::new("https://b.example.com/cow.txt")?;
+        fs::write(&path, configured(&[a.url().as_str()]))?;
+
+        let loader = ConfigLoader::new(&path);
+        let running_config = loader.load()?;
+        let database = Database::new_in_memory()?;
+        let handler = ReloadConfigHandler::new(
+            &running_config,
+            loader,
+            database.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+        );
+        // e.g. added over grpc or discovered
+        for name in [&added, &adopted] {
+            database.update(name, |_| {
+                Ok(Some(domain::Cow::new(name.clone(), Character::Brave)))
+            })?;
+        }
+
+        handler.handle().await?;
+        fs::write(
+            &path,
+            configured(&[a.url().as_str(), adopted.url().as_str()]),
+        )?;
+        let adopting = handler.handle().await?;
+        fs::write(&path, configured(&[b.url().as_str()]))?;
+        let removing = handler.handle().await?;
+        fs::remove_file(&path)?;
+
+        assert!(adopting.added().is_empty());
+        assert_eq!(removing.removed(), &[a, adopted]);
+        assert_eq!(
+            herd(&database)?,
+            vec![(added, Character::Brave), (b, Character::Brave)]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn configured_cows_persisted_before_they_were_managed_are_removed_by_a_reload()
+    -> Result<()> {
+        let path = config_file("reload-removes-legacy-cows");
+        let a = VisibleName::new("https://a.example.com/cow.txt")?;
+        let b = VisibleName::new("https://b.example.com/cow.txt")?;
+        let configured = |name: &VisibleName| {
+            format!(
+                "{HEADER}\n[[cows]]\nname = \"{}\"\ncharacter = \"brave\"\n",
+                name.url()
+            )
+        };
+        fs::write(&path, configured(&a))?;
+
+        let loader = ConfigLoader::new(&path);
+        let running_config = loader.load()?;
+        let database = Database::new_in_memory()?;
+        database.import_jsonl(
+            r#"{ "name": "https://a.example.com/cow.txt", "character": "brave" }"#.as_bytes(),
+        )?;
+        assert!(!database.get(&a)?.unwrap().managed_by_config());
+
+        adopt_configured_cows(&running_config, &database)?;
+        let handler = ReloadConfigHandler::new(
+            &running_config,
+            loader,
+            database.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+        );
+        fs::write(&path, configured(&b))?;
+        let summary = handler.handle().await?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(summary.removed(), &[a]);
+        assert_eq!(herd(&database)?, vec![(b, Character::Brave)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reloads_are_counted_by_result() -> Result<()> {
+        let path = config_file("reloads-are-counted-by-result");
+        let cows = r#"
+[[cows]]
+name = "https://a.example.com/cow.txt"
+character = "brave"
+"#;
+        fs::write(&path, format!("{HEADER}{cows}"))?;
+
+        let loader = ConfigLoader::new(&path);
+        let running_config = loader.load()?;
+        let metrics = adapters::Metrics::new()?;
+        let handler = ReloadConfigHandler::new(
+            &running_config,
+            loader,
+            Database::new_in_memory()?,
+            metrics.clone(),
+            RecordingEventSink::new(),
+        );
+        let reloads = |result: &str| {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == "moooodotfarm_config_reloads_total")
+                .and_then(|family| {
+                    family
+                        .get_metric()
+                        .iter()
+                        .find(|metric| metric.get_label()[0].value() == result)
+                        .map(|metric| metric.get_counter().value())
+                })
+                .unwrap_or_default()
+        };
+
+        handler.handle().await?;
+        assert_eq!((reloads("ok"), reloads("error")), (1.0, 0.0));
+
+        fs::write(
+            &path,
+            format!("{}{cows}", HEADER.replace("0.0.0.0:8080", "0.0.0.0:8081")),
+        )?;
+        let result = handler.handle().await;
+        fs::remove_file(&path)?;
+
+        assert!(result.is_err());
+        assert_eq!((reloads("ok"), reloads("error")), (1.0, 1.0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reload_rejects_bind_address_changes() -> Result<()> {
+        let path = config_file("reload-rejects-bind-address-changes");
+        fs::write(
+            &path,
+            format!(
+                r#"{HEADER}
+[[cows]]
+name = "https://a.example.com/cow.txt"
+character = "brave"
+"#
+            ),
+        )?;
+
+        let loader = ConfigLoader::new(&path);
+        let running_config = loader.load()?;
+        let database = Database::new_in_memory()?;
+        let handler = ReloadConfigHandler::new(
+            &running_config,
+            loader,
+            database.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+        );
+
+        fs::write(
+            &path,
+            format!(
+                r#"{}
+[[cows]]
+name = "https://a.example.com/cow.txt"
+character = "brave"
+"#,
+                HEADER.replace("0.0.0.0:8080", "0.0.0.0:8081")
+            ),
+        )?;
+
+        let result = handler.handle().await;
+        fs::remove_file(&path)?;
+
+        assert!(matches!(
+            result,
+            Err(Error::ConfigChangeRequiresRestart(ref field)) if field == "http_address"
+        ));
+        assert!(herd(&database)?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reload_rejects_changes_the_downloader_wouldnt_pick_up() -> Result<()> {
+        let path = config_file("reload-rejects-downloader-changes");
+        let cows = |header: &str| {
+            format!(
+                r#"{HEADER}
+[[cows]]
+name = "https://a.example.com/cow.txt"
+character = "brave"
+
+[cows.headers]
+x-moo = "{header}"
+"#
+            )
+        };
+        fs::write(&path, cows("moo"))?;
+
+        let loader = ConfigLoader::new(&path);
+        let running_config = loader.load()?;
+        let database = Database::new_in_memory()?;
+        let handler = ReloadConfigHandler::new(
+            &running_config,
+            loader,
+            database.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+        );
+        handler.handle().await?;
+
+        fs::write(&path, cows("mooo"))?;
+        let result = handler.handle().await;
+        fs::write(
+            &path,
+            format!(
+                r#"{HEADER}
+[[cows]]
+name = "https://b.example.com/cow.txt"
+character = "brave"
+check_via = "https://mirror.example.com/b/cow.txt"
+"#
+            ),
+        )?;
+        let added_result = handler.handle().await;
+        fs::remove_file(&path)?;
+
+        assert!(matches!(result, Err(Error::ConfigChangeRequiresRestart(_))));
+        assert!(matches!(
+            added_result,
+            Err(Error::ConfigChangeRequiresRestart(_))
+        ));
+        assert_eq!(
+            herd(&database)?,
+            vec![(
+                VisibleName::new("https://a.example.com/cow.txt")?,
+                Character::Brave
+            )]
+        );
+        Ok(())
+    }
+}
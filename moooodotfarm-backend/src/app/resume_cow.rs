@@ -0,0 +1,99 @@
+use crate::app::{EventSink, Inventory, Metrics};
+use crate::domain::events::DomainEvent;
+use crate::errors::{Error, Result};
+use crate::{app, domain};
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct ResumeCowHandler<I, M, E> {
+    inventory: I,
+    metrics: M,
+    events: E,
+}
+
+impl<I, M, E> ResumeCowHandler<I, M, E>
+where
+    I: Inventory,
+    M: Metrics,
+    E: EventSink,
+{
+    pub fn new(inventory: I, metrics: M, events: E) -> Self {
+        Self {
+            inventory,
+            metrics,
+            events,
+        }
+    }
+
+    async fn handle_inner(&self, v: &app::ResumeCow) -> Result<()> {
+        let mut resumed = false;
+        self.inventory
+            .update(v.name(), |cow: Option<domain::Cow>| match cow {
+                Some(mut cow) => {
+                    if !cow.paused() {
+                        return Ok(None);
+                    }
+                    cow.resume()?;
+                    resumed = true;
+                    Ok(Some(cow))
+                }
+                None => Err(Error::NotFound("cow does not exist".to_string())),
+            })?;
+        if resumed {
+            self.events.emit(DomainEvent::CowResumed {
+                name: v.name().clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<I, M, E> app::ResumeCowHandler for ResumeCowHandler<I, M, E>
+where
+    I: Inventory + Send + Sync,
+    M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
+{
+    async fn handle(&self, v: &app::ResumeCow) -> Result<()> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "resume_cow",
+            self.handle_inner(v).await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::ResumeCowHandler as _;
+    use crate::domain::flap_penalty::FlapPenalty;
+    use crate::domain::{Character, Cow, VisibleName};
+    use crate::fixtures::RecordingEventSink;
+
+    #[tokio::test]
+    async fn resumes_a_paused_cow() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(
+                Cow::new(name.clone(), Character::Brave).with_paused(true),
+            ))
+        })?;
+
+        let events = RecordingEventSink::new();
+        let handler =
+            ResumeCowHandler::new(database.clone(), adapters::Metrics::new()?, events.clone());
+
+        handler.handle(&app::ResumeCow::new(name.clone())).await?;
+
+        let cow = database.get(&name)?.unwrap();
+        assert!(!cow.paused());
+        assert!(cow.should_check(&FlapPenalty::default()));
+        assert_eq!(events.events(), vec![DomainEvent::CowResumed { name }]);
+        Ok(())
+    }
+}
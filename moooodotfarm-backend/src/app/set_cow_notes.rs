@@ -0,0 +1,119 @@
+use crate::app::{Inventory, Metrics};
+use crate::errors::{Error, Result};
+use crate::{app, domain};
+use async_trait::async_trait;
+
+#[derive(Clone)]
+pub struct SetCowNotesHandler<I, M> {
+    inventory: I,
+    metrics: M,
+}
+
+impl<I, M> SetCowNotesHandler<I, M>
+where
+    I: Inventory,
+    M: Metrics,
+{
+    pub fn new(inventory: I, metrics: M) -> Self {
+        Self { inventory, metrics }
+    }
+
+    async fn handle_inner(&self, v: &app::SetCowNotes) -> Result<()> {
+        self.inventory
+            .update(v.name(), |cow: Option<domain::Cow>| match cow {
+                Some(mut cow) => {
+                    if cow.notes() == v.notes().trim() {
+                        return Ok(None);
+                    }
+                    cow.change_notes(v.notes())?;
+                    Ok(Some(cow))
+                }
+                None => Err(Error::NotFound("cow does not exist".to_string())),
+            })?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<I, M> app::SetCowNotesHandler for SetCowNotesHandler<I, M>
+where
+    I: Inventory + Send + Sync,
+    M: Metrics + Send + Sync,
+{
+    async fn handle(&self, v: &app::SetCowNotes) -> Result<()> {
+        crate::record_application_handler_call!(
+            self.metrics,
+            "set_cow_notes",
+            self.handle_inner(v).await
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::SetCowNotesHandler as _;
+    use crate::domain::{Character, Cow, VisibleName};
+
+    #[tokio::test]
+    async fn sets_and_clears_the_notes() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        let handler = SetCowNotesHandler::new(database.clone(), adapters::Metrics::new()?);
+
+        handler
+            .handle(&app::SetCowNotes::new(name.clone(), " ask bob "))
+            .await?;
+        handler
+            .handle(&app::SetCowNotes::new(name.clone(), "ask bob"))
+            .await?;
+        assert_eq!(database.get(&name)?.unwrap().notes(), "ask bob");
+
+        handler
+            .handle(&app::SetCowNotes::new(name.clone(), ""))
+            .await?;
+        assert_eq!(database.get(&name)?.unwrap().notes(), "");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_notes_are_rejected() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(Cow::new(name.clone(), Character::Brave)))
+        })?;
+
+        let handler = SetCowNotesHandler::new(database.clone(), adapters::Metrics::new()?);
+
+        let result = handler
+            .handle(&app::SetCowNotes::new(name.clone(), "ask\u{7}bob"))
+            .await;
+
+        assert!(matches!(result, Err(Error::ValidationError(_))));
+        assert_eq!(database.get(&name)?.unwrap().notes(), "");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn setting_notes_of_a_missing_cow_fails() -> Result<()> {
+        let handler =
+            SetCowNotesHandler::new(Database::new_in_memory()?, adapters::Metrics::new()?);
+
+        let result = handler
+            .handle(&app::SetCowNotes::new(
+                VisibleName::new("https://example.com/cow.txt")?,
+                "ask bob",
+            ))
+            .await;
+
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        Ok(())
+    }
+}
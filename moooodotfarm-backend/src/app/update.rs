@@ -1,8 +1,21 @@
-use crate::app::{CowTxtDownloader, Inventory, Metrics};
-use crate::domain::CensoredHerd;
+use crate::app::{CowTxtDownloader, EventSink, Inventory, Metrics, UpdateLog};
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::check_order::CheckOrder;
+use crate::domain::circuit_breaker::{CircuitBreaker, CircuitBreakerState};
+use crate::domain::flap_penalty::FlapPenalty;
+use crate::domain::herd_health::{DownRatioAlert, UncheckedCows};
+use crate::domain::shard::Shard;
+use crate::domain::time::{DateTime, Duration};
+use crate::domain::{CensoredHerd, HostCensoring};
 use crate::errors::{Error, Result};
 use crate::{app, domain};
+use anyhow::anyhow;
 use async_trait::async_trait;
+use futures_util::{StreamExt, stream};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+static WARN_IF_CHECKS_QUEUED_FOR_SECONDS: u64 = 60;
 
 macro_rules! record_application_handler_call {
     ($metrics:expr, $handler_name:expr, $expr:expr) => {{
@@ -18,78 +31,1395 @@ macro_rules! record_application_handler_call {
 }
 
 #[derive(Clone)]
-pub struct UpdateHandler<I, D, M> {
+pub struct UpdateHandler<I, L, D, M, E> {
     inventory: I,
+    update_log: L,
     downloader: D,
     metrics: M,
+    events: E,
+    circuit_breakers: Arc<Mutex<HashMap<String, CircuitBreaker>>>,
+    max_concurrent_downloads: usize,
+    max_requests_per_update: Option<usize>,
+    shard: Option<Shard>,
+    deadline: Option<std::time::Duration>,
+    check_order: CheckOrder,
+    configured: app::ConfiguredOrder,
+    active_hours: Option<ActiveHours>,
+    flap_penalty: FlapPenalty,
+    down_ratio_alert: Arc<Mutex<Option<DownRatioAlert>>>,
+    unchecked_cows: Option<UncheckedCows>,
+    host_censoring: HostCensoring,
+    progress: app::UpdateProgressChannel,
+    running: Arc<tokio::sync::Mutex<()>>,
 }
 
-impl<I, D, M> UpdateHandler<I, D, M>
+impl<I, L, D, M, E> UpdateHandler<I, L, D, M, E>
 where
     I: Inventory + Send + Sync,
+    L: UpdateLog + Send + Sync,
     D: CowTxtDownloader + Send + Sync,
     M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
 {
-    pub fn new(inventory: I, downloader: D, metrics: M) -> Self {
+    pub fn new(
+        inventory: I,
+        update_log: L,
+        downloader: D,
+        metrics: M,
+        events: E,
+        max_concurrent_downloads: usize,
+        max_requests_per_update: Option<usize>,
+    ) -> Self {
         Self {
             inventory,
+            update_log,
             downloader,
             metrics,
+            events,
+            circuit_breakers: Arc::new(Mutex::new(HashMap::new())),
+            max_concurrent_downloads,
+            max_requests_per_update,
+            shard: None,
+            deadline: None,
+            check_order: CheckOrder::default(),
+            configured: app::ConfiguredOrder::default(),
+            active_hours: None,
+            flap_penalty: FlapPenalty::default(),
+            down_ratio_alert: Arc::new(Mutex::new(None)),
+            unchecked_cows: None,
+            host_censoring: HostCensoring::default(),
+            progress: app::UpdateProgressChannel::new(),
+            running: Arc::new(tokio::sync::Mutex::new(())),
         }
     }
 
+    pub fn with_shard(mut self, shard: Option<Shard>) -> Self {
+        self.shard = shard;
+        self
+    }
+
+    // checks still running when the deadline passes are abandoned so that a few slow cows can't
+    // make the update run into the next one, those cows are checked again during the next update
+    pub fn with_deadline(mut self, deadline: Option<std::time::Duration>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_check_order(
+        mut self,
+        check_order: CheckOrder,
+        configured: app::ConfiguredOrder,
+    ) -> Self {
+        self.check_order = check_order;
+        self.configured = configured;
+        self
+    }
+
+    // the update itself runs whenever it is asked to, the timer is what skips updates outside of
+    // the active hours
+    pub fn with_active_hours(mut self, active_hours: Option<ActiveHours>) -> Self {
+        self.active_hours = active_hours;
+        self
+    }
+
+    pub fn with_flap_penalty(mut self, flap_penalty: FlapPenalty) -> Self {
+        self.flap_penalty = flap_penalty;
+        self
+    }
+
+    pub fn with_down_ratio_alert(mut self, down_ratio_alert: Option<DownRatioAlert>) -> Self {
+        self.down_ratio_alert = Arc::new(Mutex::new(down_ratio_alert));
+        self
+    }
+
+    pub fn with_unchecked_cows(mut self, unchecked_cows: Option<UncheckedCows>) -> Self {
+        self.unchecked_cows = unchecked_cows;
+        self
+    }
+
+    pub fn with_host_censoring(mut self, host_censoring: HostCensoring) -> Self {
+        self.host_censoring = host_censoring;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: app::UpdateProgressChannel) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    // the update log only tells about the updates, failing to write it mustn't fail the update or
+    // hide why it failed
     async fn handle_inner(&self) -> Result<()> {
-        let mut cows: Vec<domain::Cow> = vec![];
+        if let Err(err) = self.update_log.record_update_started(&DateTime::now()) {
+            log::error!("failed to record the start of the update: {}", err);
+        }
+        let result = self.update_herd().await;
+        if let Err(err) = self.record_update_finished(&result) {
+            log::error!("failed to record the end of the update: {}", err);
+        }
+        result
+    }
 
-        for peeked_cow in self.inventory.list()? {
-            if !peeked_cow.should_check() {
-                cows.push(peeked_cow.clone());
+    fn record_update_finished(&self, result: &Result<()>) -> Result<()> {
+        self.update_log
+            .record_update_finished(&DateTime::now(), result.into())?;
+        self.metrics
+            .update_last_update(&self.update_log.last_update()?);
+        Ok(())
+    }
+
+    async fn update_herd(&self) -> Result<()> {
+        let mut herd = domain::Herd::new();
+        let mut to_check = vec![];
+        let mut pending = vec![];
+        let mut deferred = 0;
+
+        let mut peeked_cows = self.inventory.list()?;
+        self.check_order
+            .sort(&mut peeked_cows, &self.configured.names());
+        for peeked_cow in peeked_cows {
+            // cows from other shards are left out of the metrics as well so that they can be
+            // summed up across all instances
+            if self
+                .shard
+                .is_some_and(|shard| !shard.contains(peeked_cow.name()))
+            {
                 continue;
             }
 
-            let result = self.downloader.download(peeked_cow.name()).await;
+            if !peeked_cow.should_check(&self.flap_penalty) {
+                herd.add(peeked_cow.clone());
+                continue;
+            }
+
+            let host = circuit_breaker_key(peeked_cow.name());
+            if !self.circuit_breaker_allows_check(&host) {
+                log::debug!("circuit breaker is open, not checking {}", peeked_cow);
+                herd.add(peeked_cow.clone());
+                continue;
+            }
 
-            self.inventory.update(peeked_cow.name(), |cow| {
-                if let Some(mut cow) = cow {
-                    match result {
-                        Ok(_) => {
-                            cow.mark_as_ok();
-                        }
-                        Err(err) => {
-                            log::warn!("cow is missing {}: {}", cow, err);
-                            cow.mark_as_missing();
-                        }
-                    }
+            if self
+                .max_requests_per_update
+                .is_some_and(|max_requests| to_check.len() >= max_requests)
+            {
+                log::info!("request budget exceeded, deferring {}", peeked_cow);
+                deferred += 1;
+                herd.add(peeked_cow.clone());
+                continue;
+            }
 
-                    cows.push(cow.clone());
+            to_check.push((peeked_cow.name().clone(), host));
+            pending.push(peeked_cow);
+        }
+        self.metrics
+            .update_request_budget_usage(to_check.len(), deferred);
+        // names are censored up front, the progress is shown to whoever watches the update
+        let progress_names: HashMap<domain::VisibleName, domain::Name> = pending
+            .iter()
+            .map(|cow| {
+                Ok((
+                    cow.name().clone(),
+                    domain::Name::new_with_host_censoring(cow, self.host_censoring)?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        let total = to_check.len();
+        let mut checked_so_far = 0;
 
-                    return Ok(Some(cow));
+        let started_at = DateTime::now();
+        let deadline = self.deadline;
+        let deadline_passed = async move {
+            match deadline {
+                Some(deadline) => tokio::time::sleep(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        let mut logged_saturation = false;
+        let results: HashMap<domain::VisibleName, Result<Found>> =
+            stream::iter(to_check)
+            .map(|(name, host)| {
+                let queued_for = &DateTime::now() - &started_at;
+                if !logged_saturation
+                    && queued_for > Duration::new_from_seconds(WARN_IF_CHECKS_QUEUED_FOR_SECONDS)
+                {
+                    log::warn!(
+                        "checks are waiting for more than {} seconds for one of the {} download slots",
+                        WARN_IF_CHECKS_QUEUED_FOR_SECONDS,
+                        self.max_concurrent_downloads
+                    );
+                    logged_saturation = true;
                 }
+                self.check(name, host)
+            })
+            .buffer_unordered(self.max_concurrent_downloads)
+            .inspect(|(name, _)| {
+                checked_so_far += 1;
+                self.progress.publish(app::UpdateProgress::new(
+                    checked_so_far,
+                    total,
+                    progress_names[name].clone(),
+                ));
+            })
+            .take_until(deadline_passed)
+            .collect()
+            .await;
 
-                Ok(None)
-            })?;
+        let (checked, abandoned): (Vec<domain::Cow>, Vec<domain::Cow>) = pending
+            .into_iter()
+            .partition(|cow| results.contains_key(cow.name()));
+        if !abandoned.is_empty() {
+            log::warn!(
+                "update deadline passed, {} cows were not checked during this update",
+                abandoned.len()
+            );
+        }
+        for cow in abandoned {
+            herd.add(cow);
+        }
+
+        let outcomes: HashMap<domain::VisibleName, CheckOutcome> = results
+            .into_iter()
+            .map(|(name, result)| {
+                let outcome = CheckOutcome::new(&name, result);
+                (name, outcome)
+            })
+            .collect();
+        let (changes, failed) = self.store_outcomes(&outcomes);
+        // a cow which couldn't be stored keeps its state from before the check
+        for cow in checked
+            .into_iter()
+            .filter(|cow| failed.contains(cow.name()))
+        {
+            herd.add(cow);
+        }
+        let (checked_before, checked_after): (Vec<domain::Cow>, Vec<domain::Cow>) =
+            changes.into_iter().unzip();
+        for cow in &checked_after {
+            herd.add(cow.clone());
+        }
+
+        let diff = domain::diff_herds(&checked_before, &checked_after);
+        for _ in diff.content_changed() {
+            self.metrics.record_cow_content_change();
+        }
+        for event in diff.events() {
+            self.events.emit(event);
+        }
+
+        if let Some(unchecked_cows) = &self.unchecked_cows {
+            let now = DateTime::now();
+            self.metrics.update_cows_overdue_for_first_check(
+                herd.iter()
+                    .filter(|cow| unchecked_cows.is_overdue(cow, &now))
+                    .count(),
+            );
         }
 
-        let censored_cows: Vec<domain::CensoredCow> =
-            cows.iter()
-                .map(domain::CensoredCow::new)
-                .collect::<Result<Vec<domain::CensoredCow>>>()?;
+        let censored_cows: Vec<domain::CensoredCow> = herd
+            .iter()
+            .map(|cow| {
+                Ok(domain::CensoredCow::new(cow)?
+                    .with_active_hours(self.active_hours.as_ref())
+                    .with_flap_penalty(&self.flap_penalty))
+            })
+            .collect::<Result<Vec<domain::CensoredCow>>>()?;
         let censored_herd = CensoredHerd::new(censored_cows);
         let herd: app::Herd = censored_herd.try_into()?;
         self.metrics.update_herd_numbers(&herd);
+        self.metrics
+            .update_circuit_breaker_states(&self.circuit_breaker_states());
+        self.check_down_ratio(&herd);
+
+        // the rest of the herd was updated regardless, the error only marks the update as failed
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "failed to store {} of the {} checked cows: {}",
+                failed.len(),
+                outcomes.len(),
+                failed
+                    .iter()
+                    .map(|name| name.url().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .into());
+        }
 
         Ok::<(), Error>(())
     }
+
+    // the cows are stored together so that either the whole update is stored or none of it, if that
+    // fails they are stored one by one so that a single cow can't hold back the rest of the herd.
+    // the cows which weren't stored are checked again during the next update
+    fn store_outcomes(
+        &self,
+        outcomes: &HashMap<domain::VisibleName, CheckOutcome>,
+    ) -> (Vec<(domain::Cow, domain::Cow)>, Vec<domain::VisibleName>) {
+        let names: Vec<domain::VisibleName> = outcomes.keys().cloned().collect();
+        let mut changes = vec![];
+        let stored_together = self.inventory.update_many(&names, |name, cow| {
+            store_outcome(outcomes, name, cow, &mut changes)
+        });
+        let Err(err) = stored_together else {
+            return (changes, vec![]);
+        };
+
+        log::error!(
+            "failed to store the update, storing {} cows one by one instead: {}",
+            names.len(),
+            err
+        );
+        changes.clear();
+        let mut failed = vec![];
+        let mut last_stored: Option<&domain::VisibleName> = None;
+        for name in &names {
+            match self
+                .inventory
+                .update(name, |cow| store_outcome(outcomes, name, cow, &mut changes))
+            {
+                Ok(()) => last_stored = Some(name),
+                Err(err) => {
+                    log::error!(
+                        "failed to store {}, the last cow stored was {}: {}",
+                        name.url(),
+                        last_stored
+                            .map(|v| v.url().to_string())
+                            .unwrap_or_else(|| "none".to_string()),
+                        err
+                    );
+                    self.metrics.record_cow_update_failure();
+                    failed.push(name.clone());
+                }
+            }
+        }
+        (changes, failed)
+    }
+
+    // cows which weren't checked yet or are paused say nothing about whether something is down
+    fn check_down_ratio(&self, herd: &app::Herd) {
+        let mut down_ratio_alert = self.down_ratio_alert.lock().unwrap();
+        let Some(down_ratio_alert) = down_ratio_alert.as_mut() else {
+            return;
+        };
+        let statuses = herd.cows().iter().map(|cow| *cow.status());
+        let ran_away = statuses
+            .clone()
+            .filter(|status| *status == app::CowStatus::RanAway)
+            .count();
+        let checked = statuses
+            .filter(|status| {
+                matches!(
+                    status,
+                    app::CowStatus::RanAway | app::CowStatus::HappilyGrazing
+                )
+            })
+            .count();
+        if let Some(event) = down_ratio_alert.observe(ran_away, checked) {
+            self.events.emit(event);
+        }
+        self.metrics
+            .update_herd_degraded(down_ratio_alert.degraded());
+    }
+
+    async fn check(
+        &self,
+        name: domain::VisibleName,
+        host: String,
+    ) -> (domain::VisibleName, Result<Found>) {
+        let download = InflightDownload::start(&self.metrics);
+        let result = self
+            .downloader
+            .download(&name)
+            .await
+            .map(|cow_txt| Found::new(&cow_txt));
+        drop(download);
+        if let Err(err) = &result {
+            self.metrics
+                .record_cow_check_failure(domain::MissingReason::from_error(err));
+        }
+        self.record_circuit_breaker_result(&host, result.is_ok());
+        (name, result)
+    }
+
+    fn circuit_breaker_allows_check(&self, host: &str) -> bool {
+        self.circuit_breakers
+            .lock()
+            .unwrap()
+            .get(host)
+            .map(|breaker| breaker.allows_check(&DateTime::now()))
+            .unwrap_or(true)
+    }
+
+    fn record_circuit_breaker_result(&self, host: &str, succeeded: bool) {
+        let mut circuit_breakers = self.circuit_breakers.lock().unwrap();
+        let breaker = circuit_breakers.entry(host.to_string()).or_default();
+        if succeeded {
+            breaker.record_success();
+        } else {
+            breaker.record_failure(&DateTime::now());
+        }
+    }
+
+    fn circuit_breaker_states(&self) -> Vec<CircuitBreakerState> {
+        let now = DateTime::now();
+        self.circuit_breakers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|breaker| breaker.state(&now))
+            .collect()
+    }
+}
+
+// a download abandoned at the deadline is dropped halfway through and has to be counted as finished
+struct InflightDownload<'a, M: Metrics> {
+    metrics: &'a M,
+}
+
+impl<'a, M: Metrics> InflightDownload<'a, M> {
+    fn start(metrics: &'a M) -> Self {
+        metrics.record_cow_download_started();
+        Self { metrics }
+    }
+}
+
+impl<M: Metrics> Drop for InflightDownload<'_, M> {
+    fn drop(&mut self) {
+        self.metrics.record_cow_download_finished();
+    }
+}
+
+fn circuit_breaker_key(name: &domain::VisibleName) -> String {
+    name.url().host_str().unwrap_or_default().to_string()
+}
+
+fn store_outcome(
+    outcomes: &HashMap<domain::VisibleName, CheckOutcome>,
+    name: &domain::VisibleName,
+    cow: Option<domain::Cow>,
+    changes: &mut Vec<(domain::Cow, domain::Cow)>,
+) -> Result<Option<domain::Cow>> {
+    let Some(mut cow) = cow else {
+        return Ok(None);
+    };
+    let outcome = outcomes
+        .get(name)
+        .ok_or_else(|| anyhow!("no check result for {}", name.url()))?;
+    let cow_before = cow.clone();
+    outcome.apply(&mut cow)?;
+    changes.push((cow_before, cow.clone()));
+    Ok(Some(cow))
+}
+
+// all that is kept of a downloaded cow.txt once it was validated
+#[derive(Debug, Clone)]
+pub(super) struct Found {
+    // the url the cow was found at if it should be shown under it
+    redirected_to: Option<domain::VisibleName>,
+    body_hash: domain::BodyHash,
+}
+
+impl Found {
+    pub(super) fn new(cow_txt: &domain::CowTxt<'_>) -> Self {
+        Self {
+            redirected_to: cow_txt.redirected_to().cloned(),
+            body_hash: cow_txt.body_hash(),
+        }
+    }
+}
+
+// unlike the result of a check its outcome can be applied more than once, e.g. when the cows are
+// stored one by one after storing them together failed
+#[derive(Debug, Clone)]
+enum CheckOutcome {
+    Found(Found),
+    DomainNotFound,
+    Missing(Option<domain::MissingReason>),
+}
+
+impl CheckOutcome {
+    fn new(name: &domain::VisibleName, result: Result<Found>) -> Self {
+        match result {
+            Ok(found) => CheckOutcome::Found(found),
+            Err(Error::DomainNotFound(host)) => {
+                log::warn!("cow is missing {}: domain {} not found", name.url(), host);
+                CheckOutcome::DomainNotFound
+            }
+            Err(err) => {
+                log::warn!("cow is missing {}: {}", name.url(), err);
+                CheckOutcome::Missing(domain::MissingReason::from_error(&err))
+            }
+        }
+    }
+
+    fn apply(&self, cow: &mut domain::Cow) -> Result<()> {
+        match self {
+            CheckOutcome::Found(found) => {
+                cow.mark_as_found_at(found.redirected_to.clone())?;
+                cow.record_body_hash(found.body_hash.clone());
+                Ok(())
+            }
+            CheckOutcome::DomainNotFound => cow.mark_as_domain_not_found(),
+            CheckOutcome::Missing(Some(reason)) => cow.mark_as_missing_because(*reason),
+            CheckOutcome::Missing(None) => cow.mark_as_missing(),
+        }
+    }
+}
+
+pub(super) fn apply_check_result(cow: &mut domain::Cow, result: Result<Found>) -> Result<()> {
+    CheckOutcome::new(cow.name(), result).apply(cow)
 }
 
 #[async_trait]
-impl<I, D, M> app::UpdateHandler for UpdateHandler<I, D, M>
+impl<I, L, D, M, E> app::UpdateHandler for UpdateHandler<I, L, D, M, E>
 where
     I: Inventory + Send + Sync,
+    L: UpdateLog + Send + Sync,
     D: CowTxtDownloader + Send + Sync,
     M: Metrics + Send + Sync,
+    E: EventSink + Send + Sync,
 {
     async fn handle(&self) -> Result<()> {
+        // a manually triggered update may coincide with the timer, checking the same cows twice
+        // at the same time would only result in conflicting writes
+        let Ok(_running) = self.running.try_lock() else {
+            log::info!("an update is already running, skipping this one");
+            return Ok(());
+        };
         record_application_handler_call!(self.metrics, "update", self.handle_inner().await)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::UpdateHandler as _;
+    use crate::config::StorageBackend;
+    use crate::domain::events::DomainEvent;
+    use crate::domain::time::{DateTime, Duration};
+    use crate::domain::{Character, Cow, VisibleName};
+    use crate::fixtures::{MockCowTxtDownloader, RecordingEventSink};
+
+    fn put_cow_checked_long_ago(database: &impl Inventory, name: &VisibleName) -> Result<()> {
+        let long_ago = DateTime::now() - Duration::new_from_days(1);
+        database.update(name, |_| {
+            Ok(Some(Cow::new_from_history(
+                name.clone(),
+                Character::Brave,
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+            )))
+        })
+    }
+
+    #[tokio::test]
+    async fn publishes_progress_with_shy_cows_censored() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let progress = app::UpdateProgressChannel::new();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+            None,
+        )
+        .with_progress(progress.clone());
+
+        let brave = VisibleName::new("https://brave.example.com/cow.txt")?;
+        let shy = VisibleName::new("https://shy.example.com/cow.txt")?;
+        put_cow_checked_long_ago(&database, &brave)?;
+        database.update(&shy, |_| Ok(Some(Cow::new(shy.clone(), Character::Shy))))?;
+
+        let mut subscription = progress.subscribe();
+        handler.handle().await?;
+
+        let mut published = vec![];
+        while let Ok(progress) = subscription.try_recv() {
+            published.push(progress);
+        }
+        assert_eq!(
+            published
+                .iter()
+                .map(|v| (v.checked(), v.total()))
+                .collect::<Vec<_>>(),
+            vec![(1, 2), (2, 2)]
+        );
+        let mut names: Vec<_> = published.iter().map(|v| v.current_cow().clone()).collect();
+        names.sort_by_key(|name| matches!(name, domain::Name::Censored(_)));
+        assert_eq!(names[0], domain::Name::Visible(brave));
+        assert!(matches!(names[1], domain::Name::Censored(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn publishes_progress_with_the_configured_host_censoring() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let progress = app::UpdateProgressChannel::new();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+            None,
+        )
+        .with_host_censoring(HostCensoring::Collapsed)
+        .with_progress(progress.clone());
+        let shy = VisibleName::new("https://api.eu.example.com/cow.txt")?;
+        database.update(&shy, |_| Ok(Some(Cow::new(shy.clone(), Character::Shy))))?;
+
+        let mut subscription = progress.subscribe();
+        handler.handle().await?;
+
+        let progress = subscription.try_recv().map_err(|err| anyhow!(err))?;
+        assert!(matches!(
+            progress.current_cow(),
+            domain::Name::Censored(name) if name.url() == "https://*******.com/cow.txt"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn emits_cow_content_changed_once_when_the_body_changes() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let events = RecordingEventSink::new();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            adapters::Metrics::new()?,
+            events.clone(),
+            4,
+            None,
+        );
+
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        put_cow_checked_long_ago(&database, &name)?;
+        let check_again = || {
+            database.update(&name, |cow| {
+                let cow = cow.unwrap();
+                let long_ago = DateTime::now() - Duration::new_from_days(1);
+                Ok(Some(
+                    Cow::new_from_history(
+                        name.clone(),
+                        Character::Brave,
+                        cow.first_seen().cloned(),
+                        Some(long_ago.clone()),
+                        Some(long_ago),
+                    )
+                    .with_body_hash(cow.body_hash().cloned()),
+                ))
+            })
+        };
+
+        handler.handle().await?;
+        assert!(database.get(&name)?.unwrap().body_hash().is_some());
+        assert!(events.events().is_empty());
+
+        let edited = format!(
+            "{}\nredrawn\n",
+            include_str!("../ports/http/static/cow.txt")
+        );
+        downloader.set_body(&name, edited);
+        check_again()?;
+        handler.handle().await?;
+        check_again()?;
+        handler.handle().await?;
+
+        assert_eq!(
+            events.events(),
+            vec![DomainEvent::CowContentChanged { name }]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn emits_cow_ran_away_and_cow_returned() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let events = RecordingEventSink::new();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            adapters::Metrics::new()?,
+            events.clone(),
+            4,
+            None,
+        );
+
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let long_ago = DateTime::now() - Duration::new_from_days(1);
+        let a_while_ago = DateTime::now() - Duration::new_from_hours(3);
+        database.update(&name, |_| {
+            Ok(Some(Cow::new_from_history(
+                name.clone(),
+                Character::Brave,
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+            )))
+        })?;
+
+        downloader.set_missing(&name, true);
+        handler.handle().await?;
+        assert_eq!(
+            events.events(),
+            vec![DomainEvent::CowRanAway { name: name.clone() }]
+        );
+
+        database.update(&name, |cow| {
+            let cow = cow.unwrap();
+            Ok(Some(Cow::new_from_history(
+                name.clone(),
+                Character::Brave,
+                cow.first_seen().cloned(),
+                cow.last_seen().cloned(),
+                Some(a_while_ago.clone()),
+            )))
+        })?;
+        downloader.set_missing(&name, false);
+        handler.handle().await?;
+        assert_eq!(
+            events.events(),
+            vec![
+                DomainEvent::CowRanAway { name: name.clone() },
+                DomainEvent::CowReturned { name },
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn alerts_when_too_many_cows_run_away_at_once() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let metrics = adapters::Metrics::new()?;
+        let events = RecordingEventSink::new();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            metrics.clone(),
+            events.clone(),
+            4,
+            None,
+        )
+        .with_down_ratio_alert(Some(DownRatioAlert::new(50)?));
+        let names = (0..4)
+            .map(|i| VisibleName::new(format!("https://cow{i}.example.com/cow.txt")))
+            .collect::<Result<Vec<_>>>()?;
+        let herd_events = || {
+            events
+                .events()
+                .into_iter()
+                .filter(|event| event.name().is_none())
+                .collect::<Vec<_>>()
+        };
+        let herd_degraded = || {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == "moooodotfarm_herd_degraded")
+                .map(|family| family.get_metric()[0].get_gauge().value())
+        };
+
+        for (missing, expected_events, expected_gauge) in [
+            (2, vec![], 0.0),
+            (
+                3,
+                vec![DomainEvent::HerdDegraded {
+                    ran_away: 3,
+                    checked: 4,
+                }],
+                1.0,
+            ),
+            (
+                4,
+                vec![DomainEvent::HerdDegraded {
+                    ran_away: 3,
+                    checked: 4,
+                }],
+                1.0,
+            ),
+            (
+                1,
+                vec![
+                    DomainEvent::HerdDegraded {
+                        ran_away: 3,
+                        checked: 4,
+                    },
+                    DomainEvent::HerdRecovered {
+                        ran_away: 1,
+                        checked: 4,
+                    },
+                ],
+                0.0,
+            ),
+        ] {
+            for (i, name) in names.iter().enumerate() {
+                put_cow_checked_long_ago(&database, name)?;
+                downloader.set_missing(name, i < missing);
+            }
+            handler.handle().await?;
+            assert_eq!(herd_events(), expected_events);
+            assert_eq!(herd_degraded(), Some(expected_gauge));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stops_checking_a_host_once_its_circuit_breaker_opens() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+            None,
+        );
+
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        downloader.set_missing(&name, true);
+        for _ in 0..5 {
+            put_cow_checked_long_ago(&database, &name)?;
+            handler.handle().await?;
+            assert!(database.get(&name)?.unwrap().last_check_succeeded() == Some(false));
+        }
+        assert_eq!(
+            handler.circuit_breaker_states(),
+            vec![CircuitBreakerState::Open]
+        );
+
+        downloader.set_missing(&name, false);
+        put_cow_checked_long_ago(&database, &name)?;
+        handler.handle().await?;
+
+        let cow = database.get(&name)?.unwrap();
+        assert!(&DateTime::now() - cow.last_checked().unwrap() > Duration::new_from_hours(12));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn skips_paused_cows() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+            None,
+        );
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        put_cow_checked_long_ago(&database, &name)?;
+        database.update(&name, |cow| Ok(cow.map(|cow| cow.with_paused(true))))?;
+        let last_checked = database.get(&name)?.unwrap().last_checked().cloned();
+
+        handler.handle().await?;
+
+        assert_eq!(downloader.downloads(), 0);
+        assert_eq!(
+            database.get(&name)?.unwrap().last_checked().cloned(),
+            last_checked
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tracks_inflight_downloads() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        downloader.set_delay(std::time::Duration::from_millis(300));
+        let metrics = adapters::Metrics::new()?;
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            metrics.clone(),
+            RecordingEventSink::new(),
+            2,
+            None,
+        );
+        for i in 0..5 {
+            let name = VisibleName::new(format!("https://{i}.example.com/cow.txt"))?;
+            put_cow_checked_long_ago(&database, &name)?;
+        }
+
+        let inflight_cow_downloads = || {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == "moooodotfarm_inflight_cow_downloads")
+                .map(|family| family.get_metric()[0].get_gauge().value())
+        };
+
+        let update = tokio::spawn(async move { handler.handle().await });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(inflight_cow_downloads(), Some(2.0));
+
+        update.await.map_err(|err| anyhow!(err))??;
+        assert_eq!(inflight_cow_downloads(), Some(0.0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn leaves_slow_cows_unchecked_once_the_deadline_passes() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        downloader.set_delay(std::time::Duration::from_secs(5));
+        let metrics = adapters::Metrics::new()?;
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            metrics.clone(),
+            RecordingEventSink::new(),
+            4,
+            None,
+        )
+        .with_deadline(Some(std::time::Duration::from_millis(100)));
+        let name = VisibleName::new("https://slow.example.com/cow.txt")?;
+        put_cow_checked_long_ago(&database, &name)?;
+        let last_checked = database.get(&name)?.unwrap().last_checked().cloned();
+
+        tokio::time::timeout(std::time::Duration::from_secs(2), handler.handle())
+            .await
+            .map_err(|err| anyhow!(err))??;
+
+        let cow = database.get(&name)?.unwrap();
+        assert_eq!(cow.last_checked().cloned(), last_checked);
+        assert_eq!(cow.failure_streak(), 0);
+        assert_eq!(cow.last_check_succeeded(), Some(true));
+        assert!(cow.should_check(&FlapPenalty::default()));
+        let inflight_cow_downloads = metrics
+            .registry()
+            .gather()
+            .iter()
+            .find(|family| family.name() == "moooodotfarm_inflight_cow_downloads")
+            .map(|family| family.get_metric()[0].get_gauge().value());
+        assert_eq!(inflight_cow_downloads, Some(0.0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_cows_which_keep_missing_their_first_check_as_overdue() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        downloader.set_delay(std::time::Duration::from_secs(5));
+        let metrics = adapters::Metrics::new()?;
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            metrics.clone(),
+            RecordingEventSink::new(),
+            4,
+            None,
+        )
+        .with_deadline(Some(std::time::Duration::from_millis(100)))
+        .with_unchecked_cows(Some(UncheckedCows::new(Duration::new_from_minutes(10))));
+        let name = VisibleName::new("https://slow.example.com/cow.txt")?;
+        database.update(&name, |_| {
+            Ok(Some(
+                Cow::new(name.clone(), Character::Brave)
+                    .with_added_at(DateTime::now() - Duration::new_from_hours(1)),
+            ))
+        })?;
+        let overdue_gauge = || {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == "moooodotfarm_cows_overdue_for_first_check")
+                .map(|family| family.get_metric()[0].get_gauge().value())
+        };
+
+        handler.handle().await?;
+        assert_eq!(overdue_gauge(), Some(1.0));
+
+        downloader.set_delay(std::time::Duration::ZERO);
+        handler.handle().await?;
+        assert_eq!(overdue_gauge(), Some(0.0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn updates_cows_in_every_storage_backend() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "moooodotfarm-update-backend-{}.db",
+            std::process::id()
+        ));
+        for backend in [StorageBackend::Redb, StorageBackend::Memory] {
+            let database = adapters::ConfiguredInventory::open(backend, path.to_string_lossy())?;
+            assert_eq!(
+                matches!(database, adapters::ConfiguredInventory::Memory(_)),
+                backend == StorageBackend::Memory
+            );
+            let downloader = MockCowTxtDownloader::new();
+            let handler = UpdateHandler::new(
+                database.clone(),
+                database.clone(),
+                downloader.clone(),
+                adapters::Metrics::new()?,
+                RecordingEventSink::new(),
+                4,
+                None,
+            );
+            let present = VisibleName::new("https://present.example.com/cow.txt")?;
+            let missing = VisibleName::new("https://missing.example.com/cow.txt")?;
+            put_cow_checked_long_ago(&database, &present)?;
+            put_cow_checked_long_ago(&database, &missing)?;
+            downloader.set_missing(&missing, true);
+
+            handler.handle().await?;
+
+            assert_eq!(
+                database.get(&present)?.unwrap().last_check_succeeded(),
+                Some(true),
+                "Failed for backend: {backend:?}"
+            );
+            assert_eq!(
+                database.get(&missing)?.unwrap().failure_streak(),
+                1,
+                "Failed for backend: {backend:?}"
+            );
+            assert!(database.last_update()?.finished().is_some());
+        }
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    struct FailingUpdateLog;
+
+    impl UpdateLog for FailingUpdateLog {
+        fn record_update_started(&self, _at: &DateTime) -> Result<()> {
+            Err(Error::StorageError(anyhow!("disk full")))
+        }
+
+        fn record_update_finished(&self, _at: &DateTime, _result: app::UpdateResult) -> Result<()> {
+            Err(Error::StorageError(anyhow!("disk full")))
+        }
+
+        fn last_update(&self) -> Result<app::LastUpdate> {
+            Err(Error::StorageError(anyhow!("disk full")))
+        }
+    }
+
+    #[tokio::test]
+    async fn failing_to_record_an_update_keeps_its_result() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            FailingUpdateLog,
+            downloader.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+            None,
+        );
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        put_cow_checked_long_ago(&database, &name)?;
+
+        handler.handle().await?;
+
+        assert_eq!(downloader.downloads(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn skips_an_update_while_another_one_is_running() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let downloader = MockCowTxtDownloader::new();
+        downloader.set_delay(std::time::Duration::from_millis(200));
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            downloader.clone(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+            None,
+        );
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        put_cow_checked_long_ago(&database, &name)?;
+
+        let (first, second) = tokio::join!(handler.handle(), handler.handle());
+        first?;
+        second?;
+
+        assert_eq!(downloader.downloads(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_checks_cows_of_its_own_shard() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let shard = Shard::new(0, 2)?;
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+            None,
+        )
+        .with_shard(Some(shard));
+        let mut names = vec![];
+        for i in 0..10 {
+            let name = VisibleName::new(format!("https://{i}.example.com/cow.txt"))?;
+            database.update(&name, |_| {
+                Ok(Some(Cow::new(name.clone(), Character::Brave)))
+            })?;
+            names.push(name);
+        }
+
+        handler.handle().await?;
+
+        for name in &names {
+            let checked = database.get(name)?.unwrap().last_checked().is_some();
+            assert_eq!(
+                checked,
+                shard.contains(name),
+                "Failed for cow: {}",
+                name.url()
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn defers_cows_exceeding_the_request_budget() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let metrics = adapters::Metrics::new()?;
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            metrics.clone(),
+            RecordingEventSink::new(),
+            4,
+            Some(2),
+        );
+        let mut names = vec![];
+        for i in 0..5 {
+            let name = VisibleName::new(format!("https://{i}.example.com/cow.txt"))?;
+            put_cow_checked_long_ago(&database, &name)?;
+            names.push(name);
+        }
+
+        handler.handle().await?;
+
+        let recently_checked = |name: &VisibleName| -> Result<bool> {
+            let cow = database.get(name)?.unwrap();
+            Ok(&DateTime::now() - cow.last_checked().unwrap() < Duration::new_from_hours(1))
+        };
+        let checked = names
+            .iter()
+            .map(recently_checked)
+            .collect::<Result<Vec<bool>>>()?;
+        assert_eq!(checked.iter().filter(|v| **v).count(), 2);
+
+        let gauge = |name: &str| {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == name)
+                .map(|family| family.get_metric()[0].get_gauge().value())
+        };
+        assert_eq!(gauge("moooodotfarm_update_requests"), Some(2.0));
+        assert_eq!(gauge("moooodotfarm_update_deferred_cows"), Some(3.0));
+
+        handler.handle().await?;
+        handler.handle().await?;
+
+        for name in &names {
+            assert!(recently_checked(name)?);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checks_cows_in_the_current_config_order() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let configured_order = app::ConfiguredOrder::default();
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            4,
+            Some(1),
+        )
+        .with_check_order(CheckOrder::Config, configured_order.clone());
+        let a = VisibleName::new("https://a.example.com/cow.txt")?;
+        let b = VisibleName::new("https://b.example.com/cow.txt")?;
+        let c = VisibleName::new("https://c.example.com/cow.txt")?;
+        for name in [&a, &b, &c] {
+            put_cow_checked_long_ago(&database, name)?;
+        }
+        let recently_checked = |name: &VisibleName| -> Result<bool> {
+            let cow = database.get(name)?.unwrap();
+            Ok(&DateTime::now() - cow.last_checked().unwrap() < Duration::new_from_hours(1))
+        };
+
+        configured_order.replace(vec![c.clone(), a.clone(), b.clone()]);
+        handler.handle().await?;
+        assert!(recently_checked(&c)?);
+        assert!(!recently_checked(&a)?);
+
+        // e.g. the config was reloaded
+        configured_order.replace(vec![b.clone(), a.clone(), c.clone()]);
+        handler.handle().await?;
+        assert!(recently_checked(&b)?);
+        assert!(!recently_checked(&a)?);
+        Ok(())
+    }
+
+    // fails every write which touches the given cow
+    struct FailingInventory {
+        database: Database,
+        failing: VisibleName,
+    }
+
+    impl Inventory for FailingInventory {
+        fn get(&self, name: &VisibleName) -> Result<Option<Cow>> {
+            self.database.get(name)
+        }
+
+        fn list(&self) -> Result<Vec<Cow>> {
+            self.database.list()
+        }
+
+        fn update<F>(&self, name: &VisibleName, f: F) -> Result<()>
+        where
+            F: FnOnce(Option<Cow>) -> Result<Option<Cow>>,
+        {
+            if name == &self.failing {
+                return Err(anyhow!("injected error").into());
+            }
+            self.database.update(name, f)
+        }
+
+        fn update_many<F>(&self, names: &[VisibleName], f: F) -> Result<()>
+        where
+            F: FnMut(&VisibleName, Option<Cow>) -> Result<Option<Cow>>,
+        {
+            if names.contains(&self.failing) {
+                return Err(anyhow!("injected error").into());
+            }
+            self.database.update_many(names, f)
+        }
+
+        fn delete(&self, name: &VisibleName) -> Result<()> {
+            self.database.delete(name)
+        }
+    }
+
+    #[tokio::test]
+    async fn stores_the_other_cows_when_one_of_them_cant_be_stored() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let metrics = adapters::Metrics::new()?;
+        let names = (0..3)
+            .map(|i| VisibleName::new(format!("https://{i}.example.com/cow.txt")))
+            .collect::<Result<Vec<_>>>()?;
+        for name in &names {
+            put_cow_checked_long_ago(&database, name)?;
+        }
+        let handler = UpdateHandler::new(
+            FailingInventory {
+                database: database.clone(),
+                failing: names[1].clone(),
+            },
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            metrics.clone(),
+            RecordingEventSink::new(),
+            4,
+            None,
+        );
+
+        let err = handler
+            .handle()
+            .await
+            .expect_err("the update should have failed");
+        assert!(err.to_string().contains(names[1].url().as_str()), "{err}");
+
+        let update_failures = metrics
+            .registry()
+            .gather()
+            .iter()
+            .find(|family| family.name() == "moooodotfarm_cow_update_failures")
+            .map(|family| family.get_metric()[0].get_counter().value());
+        assert_eq!(update_failures, Some(1.0));
+        let recently = DateTime::now() - Duration::new_from_minutes(1);
+        for (name, expected_checked) in [(&names[0], true), (&names[1], false), (&names[2], true)] {
+            let checked = database
+                .get(name)?
+                .and_then(|cow| cow.last_checked().cloned())
+                .is_some_and(|last_checked| last_checked > recently);
+            assert_eq!(checked, expected_checked, "{}", name.url());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn records_the_last_update() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let metrics = adapters::Metrics::new()?;
+        let handler = UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            metrics.clone(),
+            RecordingEventSink::new(),
+            4,
+            None,
+        );
+        assert_eq!(database.last_update()?, app::LastUpdate::default());
+
+        let before = DateTime::now().truncate_to_seconds()?;
+        handler.handle().await?;
+
+        let last_update = database.last_update()?;
+        let started = last_update.started().unwrap();
+        let finished = last_update.finished().unwrap();
+        assert!(&before <= started);
+        assert!(started <= finished);
+        assert_eq!(last_update.result(), Some(app::UpdateResult::Succeeded));
+
+        let gauge = |name: &str| {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .find(|family| family.name() == name)
+                .map(|family| family.get_metric()[0].get_gauge().value())
+        };
+        assert_eq!(
+            gauge("moooodotfarm_last_update_finished_timestamp_seconds"),
+            Some(finished.unix_timestamp() as f64)
+        );
+        assert_eq!(gauge("moooodotfarm_last_update_succeeded"), Some(1.0));
+        Ok(())
+    }
+}
@@ -1,22 +1,50 @@
+use anyhow::anyhow;
+use axum_server::tls_rustls::RustlsConfig;
 use clap::{Command, arg};
-use env_logger::Env;
-use log::error;
-use moooodotfarm_backend::adapters::{ConfigLoader, database};
+use log::{error, info};
+use moooodotfarm_backend::adapters::runtime::RuntimeFlavor;
+use moooodotfarm_backend::adapters::{ConfigLoader, database, discovery, events, logging};
 use moooodotfarm_backend::app::add_cow::AddCowHandler;
 use moooodotfarm_backend::app::change_cow_character::ChangeCowCharacterHandler;
+use moooodotfarm_backend::app::change_log_level::ChangeLogLevelHandler;
+use moooodotfarm_backend::app::compact_database::CompactDatabaseHandler;
+use moooodotfarm_backend::app::cow_bodies::StoringCowTxtDownloader;
 use moooodotfarm_backend::app::delete_cow::DeleteCowHandler;
-use moooodotfarm_backend::app::get_herd::GetHerdHandler;
+use moooodotfarm_backend::app::discover_cows::DiscoverCowsHandler;
+use moooodotfarm_backend::app::get_cow::GetCowHandler;
+use moooodotfarm_backend::app::get_cow_audit::GetCowAuditHandler;
+use moooodotfarm_backend::app::get_cow_body::GetCowBodyHandler;
+use moooodotfarm_backend::app::get_cow_notes::GetCowNotesHandler;
+use moooodotfarm_backend::app::get_herd::{GetHerdHandler, ShyCows};
+use moooodotfarm_backend::app::get_insights::GetInsightsHandler;
+use moooodotfarm_backend::app::get_status::GetStatusHandler;
+use moooodotfarm_backend::app::list_cow_notes::ListCowNotesHandler;
+use moooodotfarm_backend::app::pause_cow::PauseCowHandler;
+use moooodotfarm_backend::app::refresh_cow::RefreshCowHandler;
+use moooodotfarm_backend::app::reload_config::{ReloadConfigHandler, adopt_configured_cows};
+use moooodotfarm_backend::app::resume_cow::ResumeCowHandler;
+use moooodotfarm_backend::app::set_cow_notes::SetCowNotesHandler;
 use moooodotfarm_backend::app::update::UpdateHandler;
-use moooodotfarm_backend::config::Config;
-use moooodotfarm_backend::errors::Result;
+use moooodotfarm_backend::config::{Config, JsonFieldNaming};
+use moooodotfarm_backend::domain::herd_health::{DownRatioAlert, UncheckedCows};
+use moooodotfarm_backend::domain::host_policy::HostPolicy;
+use moooodotfarm_backend::domain::time::DisplayFormat;
+use moooodotfarm_backend::errors::{Error, Result};
 use moooodotfarm_backend::ports::grpc::generated::moooodotfarm_service_client::MoooodotfarmServiceClient;
 use moooodotfarm_backend::ports::grpc::generated::{
-    AddCowRequest, ChangeCowCharacterRequest, DeleteCowRequest, GetHerdRequest,
+    AddCowRequest, ChangeCowCharacterRequest, CowNotes, DeleteCowRequest, GetHerdRequest, Herd,
+    ListCowNotesRequest, PauseCowRequest, PingRequest, ResumeCowRequest, SetCowNotesRequest,
+    WatchUpdateProgressRequest,
 };
 use moooodotfarm_backend::ports::timers;
 use moooodotfarm_backend::ports::{grpc, http};
-use moooodotfarm_backend::{adapters, app};
-use prometheus::Registry;
+use moooodotfarm_backend::{adapters, app, domain};
+use prometheus::{Registry, TextEncoder};
+use tokio_util::sync::CancellationToken;
+
+static DEFAULT_GRPC_TIMEOUT_SECONDS: u64 = 5;
+static DEFAULT_GRPC_CONNECT_ATTEMPTS: u32 = 3;
+static DEFAULT_GRPC_CONNECT_RETRY_DELAY_MILLISECONDS: u64 = 500;
 
 fn cli() -> Command {
     Command::new("moooodotfarm")
@@ -26,13 +54,56 @@ fn cli() -> Command {
         .subcommand(
             Command::new("run")
                 .about("Runs the program")
+                .arg(arg!(<CONFIG> "Path to the configuration file, - reads it from stdin")),
+        )
+        .subcommand(
+            Command::new("compact")
+                .about("Compacts the database, the program must not be running")
+                .arg(arg!(<CONFIG> "Path to the configuration file")),
+        )
+        .subcommand(
+            Command::new("test_migrate")
+                .about("Migrates a throwaway copy of the database and reports the results")
+                .arg(arg!(<CONFIG> "Path to the configuration file")),
+        )
+        .subcommand(
+            Command::new("metrics")
+                .about(
+                    "Runs a single update and prints the metrics, the program must not be running",
+                )
                 .arg(arg!(<CONFIG> "Path to the configuration file")),
         )
-        .subcommand(Command::new("get_herd").about("Fetches the herd over gRPC"))
+        .subcommand(
+            Command::new("export")
+                .about("Exports the database as JSON lines, the program must not be running")
+                .arg(arg!(<CONFIG> "Path to the configuration file"))
+                .arg(arg!([FILE] "Path to the output file, defaults to stdout")),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Imports JSON lines into the database, the program must not be running")
+                .arg(arg!(<CONFIG> "Path to the configuration file"))
+                .arg(arg!([FILE] "Path to the input file, defaults to stdin")),
+        )
+        .subcommand(
+            Command::new("get_herd")
+                .about(
+                    "Fetches the herd over gRPC, lists the notes too if \
+                     MOOOODOTFARM_OWNER_BEARER_TOKEN is set",
+                )
+                .arg(arg!(--status <STATUS> "Only show cows with this status (e.g. ran-away)"))
+                .arg(arg!(--watch "Keeps refreshing the herd until interrupted"))
+                .arg(
+                    arg!(--interval <SECONDS> "Seconds between refreshes in watch mode")
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .default_value("5"),
+                ),
+        )
+        .subcommand(Command::new("ping").about("Checks if the gRPC server is reachable"))
         .subcommand(
             Command::new("add_cow")
                 .about("Adds a cow over gRPC")
-                .arg(arg!(<NAME> "Name/URL of the cow"))
+                .arg(arg!(<NAME> "Name/URL of the cow, /cow.txt is appended to a bare website URL"))
                 .arg(arg!(<CHARACTER> "Character of the cow (brave/shy)")),
         )
         .subcommand(
@@ -46,20 +117,92 @@ fn cli() -> Command {
                 .about("Deletes a cow over gRPC")
                 .arg(arg!(<NAME> "Name/URL of the cow")),
         )
+        .subcommand(
+            Command::new("pause_cow")
+                .about("Stops checking a cow over gRPC until it is resumed")
+                .arg(arg!(<NAME> "Name/URL of the cow")),
+        )
+        .subcommand(
+            Command::new("resume_cow")
+                .about("Resumes checking a paused cow over gRPC")
+                .arg(arg!(<NAME> "Name/URL of the cow")),
+        )
+        .subcommand(
+            Command::new("set_cow_notes")
+                .about("Sets the notes kept on a cow over gRPC, empty notes remove them")
+                .arg(arg!(<NAME> "Name/URL of the cow"))
+                .arg(arg!(<NOTES> "Notes about the cow")),
+        )
+        .subcommand(
+            Command::new("watch_update_progress")
+                .about("Prints the progress of updates over gRPC as the cows are checked"),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Downloads a cow and checks if it is present")
+                .arg(arg!(<NAME> "Name/URL of the cow"))
+                .arg(arg!(--verbose "Shows how a missing cow differs from the reference cow")),
+        )
+        .subcommand(
+            Command::new("censor")
+                .about("Shows how a cow would be displayed publicly")
+                .arg(arg!(<NAME> "Name/URL of the cow"))
+                .arg(arg!(<CHARACTER> "Character of the cow (brave/shy)"))
+                .arg(
+                    arg!(--censoring <MODE> "How the host is censored (per_label/collapsed/registrable_domain)")
+                        .default_value("per_label"),
+                ),
+        )
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::Builder::from_env(Env::default().filter_or("RUST_LOG", "info")).init();
+fn main() -> Result<()> {
+    RuntimeFlavor::from_env()?.build()?.block_on(async_main())
+}
 
+async fn async_main() -> Result<()> {
     let matches = cli().get_matches();
+    if !matches!(matches.subcommand(), Some(("run", _))) {
+        logging::init_default();
+    }
+
     match matches.subcommand() {
         Some(("run", sub_matches)) => {
             let config_file_path = sub_matches.try_get_one::<String>("CONFIG")?.unwrap();
             run(config_file_path).await?;
         }
-        Some(("get_herd", _sub_matches)) => {
-            get_herd().await?;
+        Some(("compact", sub_matches)) => {
+            let config_file_path = sub_matches.try_get_one::<String>("CONFIG")?.unwrap();
+            compact(config_file_path)?;
+        }
+        Some(("test_migrate", sub_matches)) => {
+            let config_file_path = sub_matches.try_get_one::<String>("CONFIG")?.unwrap();
+            test_migrate(config_file_path)?;
+        }
+        Some(("metrics", sub_matches)) => {
+            let config_file_path = sub_matches.try_get_one::<String>("CONFIG")?.unwrap();
+            print!("{}", scrape_once(config_file_path).await?);
+        }
+        Some(("export", sub_matches)) => {
+            let config_file_path = sub_matches.try_get_one::<String>("CONFIG")?.unwrap();
+            let file_path = sub_matches.try_get_one::<String>("FILE")?;
+            export(config_file_path, file_path)?;
+        }
+        Some(("import", sub_matches)) => {
+            let config_file_path = sub_matches.try_get_one::<String>("CONFIG")?.unwrap();
+            let file_path = sub_matches.try_get_one::<String>("FILE")?;
+            import(config_file_path, file_path)?;
+        }
+        Some(("get_herd", sub_matches)) => {
+            let status = sub_matches.try_get_one::<String>("status")?;
+            if sub_matches.get_flag("watch") {
+                let interval = sub_matches.try_get_one::<u64>("interval")?.unwrap();
+                watch_herd(status, std::time::Duration::from_secs(*interval)).await?;
+            } else {
+                get_herd(status).await?;
+            }
+        }
+        Some(("ping", _)) => {
+            ping().await?;
         }
         Some(("add_cow", sub_matches)) => {
             let name = sub_matches.try_get_one::<String>("NAME")?.unwrap();
@@ -75,6 +218,32 @@ async fn main() -> Result<()> {
             let name = sub_matches.try_get_one::<String>("NAME")?.unwrap();
             delete_cow(name).await?;
         }
+        Some(("pause_cow", sub_matches)) => {
+            let name = sub_matches.try_get_one::<String>("NAME")?.unwrap();
+            pause_cow(name).await?;
+        }
+        Some(("resume_cow", sub_matches)) => {
+            let name = sub_matches.try_get_one::<String>("NAME")?.unwrap();
+            resume_cow(name).await?;
+        }
+        Some(("set_cow_notes", sub_matches)) => {
+            let name = sub_matches.try_get_one::<String>("NAME")?.unwrap();
+            let notes = sub_matches.try_get_one::<String>("NOTES")?.unwrap();
+            set_cow_notes(name, notes).await?;
+        }
+        Some(("watch_update_progress", _)) => {
+            watch_update_progress().await?;
+        }
+        Some(("check", sub_matches)) => {
+            let name = sub_matches.try_get_one::<String>("NAME")?.unwrap();
+            check(name, sub_matches.get_flag("verbose")).await?;
+        }
+        Some(("censor", sub_matches)) => {
+            let name = sub_matches.try_get_one::<String>("NAME")?.unwrap();
+            let character = sub_matches.try_get_one::<String>("CHARACTER")?.unwrap();
+            let censoring = sub_matches.try_get_one::<String>("censoring")?.unwrap();
+            println!("{}", censor(name, character, censoring)?);
+        }
         _ => unreachable!(),
     }
 
@@ -82,35 +251,278 @@ async fn main() -> Result<()> {
 }
 
 async fn run(config_file_path: &str) -> Result<()> {
-    let config = ConfigLoader::new(config_file_path).load()?;
-    let service = Service::new(&config)?;
+    let config_loader = ConfigLoader::new(config_file_path);
+    let config = config_loader.load()?;
+    let log_level = logging::init(&config)?;
+    let tls = match config.tls() {
+        Some(tls) => Some(http::load_tls(tls).await?),
+        None => None,
+    };
+    let cancellation_token = CancellationToken::new();
+    let service = Service::new(
+        &config,
+        config_loader,
+        log_level,
+        tls,
+        cancellation_token.clone(),
+    )?;
 
     tokio::join!(
+        shutdown_signal_loop(&cancellation_token),
         service.update_timer.run(),
-        http_server_loop(&service.http_server),
-        grpc_server_loop(&service.grpc_server)
+        log_level_signal_loop(&service.change_log_level_handler, &cancellation_token),
+        compact_database_timer_loop(service.compact_database_timer.as_ref()),
+        discover_cows_timer_loop(service.discover_cows_timer.as_ref()),
+        http_server_loop(&service.http_server, &cancellation_token),
+        grpc_server_loop(&service.grpc_server, &cancellation_token)
     );
+    info!("stopped");
     Ok(())
 }
 
-async fn get_herd() -> Result<()> {
+// SIGINT and SIGTERM stop the timers and let the servers answer the requests they are handling
+async fn shutdown_signal_loop(cancellation_token: &CancellationToken) {
+    let mut terminate =
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(signals) => signals,
+            Err(err) => {
+                error!("failed to listen for SIGTERM: {}", err);
+                return;
+            }
+        };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("received SIGINT, shutting down"),
+        _ = terminate.recv() => info!("received SIGTERM, shutting down"),
+    }
+    cancellation_token.cancel();
+}
+
+// SIGUSR1 switches between the configured log level and debug
+async fn log_level_signal_loop(
+    handler: &ChangeLogLevelHandlerImpl,
+    cancellation_token: &CancellationToken,
+) {
+    let mut signals =
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1()) {
+            Ok(signals) => signals,
+            Err(err) => {
+                error!("failed to listen for SIGUSR1: {}", err);
+                return;
+            }
+        };
+    loop {
+        tokio::select! {
+            signal = signals.recv() => if signal.is_none() { break },
+            _ = cancellation_token.cancelled() => break,
+        }
+        if let Err(err) =
+            app::ChangeLogLevelHandler::handle(handler, &app::ChangeLogLevel::ToggleDebug).await
+        {
+            error!("failed to change the log level: {}", err);
+        }
+    }
+}
+
+async fn compact_database_timer_loop(timer: Option<&CompactDatabaseTimerImpl>) {
+    if let Some(timer) = timer {
+        timer.run().await;
+    }
+}
+
+async fn discover_cows_timer_loop(timer: Option<&DiscoverCowsTimerImpl>) {
+    if let Some(timer) = timer {
+        timer.run().await;
+    }
+}
+
+fn compact(config_file_path: &str) -> Result<()> {
+    let config = ConfigLoader::new(config_file_path).load()?;
+    let database = database::Database::new(config.database_path())?;
+    let reclaimed_bytes = database.compact()?;
+    println!("Database compacted, reclaimed {} bytes.", reclaimed_bytes);
+    Ok(())
+}
+
+fn test_migrate(config_file_path: &str) -> Result<()> {
+    let config = ConfigLoader::new(config_file_path).load()?;
+    let copy_path = format!("{}.staging", config.database_path());
+    let copy = database::Database::open_copy(config.database_path(), copy_path.as_str())?;
+    let result = copy.migrate();
+    drop(copy);
+    std::fs::remove_file(&copy_path)?;
+    println!("Migration succeeded, {} cows would be changed.", result?);
+    Ok(())
+}
+
+// the herd metrics are only filled in by an update so one is run before the registry is dumped
+async fn scrape_once(config_file_path: &str) -> Result<String> {
+    let config_loader = ConfigLoader::new(config_file_path);
+    let config = config_loader.load()?;
+    // the logger was set up before the config was known so there is nothing to switch
+    let log_level = logging::LogLevel::new(config.log_level());
+    let service = Service::new(
+        &config,
+        config_loader,
+        log_level,
+        None,
+        CancellationToken::new(),
+    )?;
+    app::UpdateHandler::handle(&service.update_handler).await?;
+    TextEncoder::new()
+        .encode_to_string(&service.metrics.registry().gather())
+        .map_err(|err| anyhow!(err).into())
+}
+
+fn export(config_file_path: &str, file_path: Option<&String>) -> Result<()> {
+    let config = ConfigLoader::new(config_file_path).load()?;
+    let database = database::Database::new(config.database_path())?;
+    let exported = match file_path {
+        Some(file_path) => {
+            database.export_jsonl(std::io::BufWriter::new(std::fs::File::create(file_path)?))?
+        }
+        None => database.export_jsonl(std::io::stdout().lock())?,
+    };
+    eprintln!("Exported {} cows.", exported);
+    Ok(())
+}
+
+fn import(config_file_path: &str, file_path: Option<&String>) -> Result<()> {
+    let config = ConfigLoader::new(config_file_path).load()?;
+    let database = database::Database::new(config.database_path())?;
+    let imported = match file_path {
+        Some(file_path) => {
+            database.import_jsonl(std::io::BufReader::new(std::fs::File::open(file_path)?))?
+        }
+        None => database.import_jsonl(std::io::stdin().lock())?,
+    };
+    println!("Imported {} cows.", imported);
+    Ok(())
+}
+
+async fn get_herd(status: Option<&String>) -> Result<()> {
     let mut client = get_client().await?;
-    let response = client.get_herd(GetHerdRequest {}).await?;
+    // tells a server which can't be reached apart from one which fails to return the herd
+    client
+        .ping(PingRequest::default())
+        .await
+        .map_err(|err| anyhow!("the gRPC server is unreachable: {}", err.message()))?;
+    let response = client
+        .get_herd(GetHerdRequest {
+            status_filter: status.cloned(),
+        })
+        .await
+        .map_err(|err| {
+            anyhow!(
+                "the gRPC server failed to return the herd: {}",
+                err.message()
+            )
+        })?;
+
+    let notes = list_cow_notes(&mut client).await?;
 
     if let Some(herd) = response.into_inner().herd {
-        for cow in herd.cows {
-            println!("{}", cow.name);
+        print_herd(&herd, &notes);
+    }
+
+    Ok(())
+}
+
+async fn watch_herd(status: Option<&String>, interval: std::time::Duration) -> Result<()> {
+    let client = get_client().await?;
+    let cancellation_token = CancellationToken::new();
+    tokio::spawn({
+        let cancellation_token = cancellation_token.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancellation_token.cancel();
+            }
+        }
+    });
+
+    timers::repeat_until_cancelled(interval, &cancellation_token, || {
+        let mut client = client.clone();
+        async move {
+            let response = client
+                .get_herd(GetHerdRequest {
+                    status_filter: status.cloned(),
+                })
+                .await?;
+            let notes = list_cow_notes(&mut client).await?;
+            // clears the screen and moves the cursor to the top left corner
+            print!("\x1B[2J\x1B[H");
+            if let Some(herd) = response.into_inner().herd {
+                print_herd(&herd, &notes);
+            }
+            Ok(())
         }
+    })
+    .await
+}
+
+// shy cows are censored in the herd so their notes can't be shown next to them, all notes are
+// listed by the real url of the cow after the herd instead
+fn print_herd(herd: &Herd, notes: &[CowNotes]) {
+    for cow in &herd.cows {
+        println!("{} (added {})", cow.name, cow.added_at);
+        if cow.unexpected_character {
+            println!(
+                "    warning: the config expects a different character than {}",
+                cow.character
+            );
+        }
+    }
+    if !notes.is_empty() {
+        println!();
+        println!("Notes:");
     }
+    for cow in notes {
+        println!("{}", cow.url);
+        for line in cow.notes.lines() {
+            println!("    {}", line);
+        }
+    }
+}
+
+// notes are only returned to the owner, without a token the herd is printed without them
+async fn list_cow_notes(
+    client: &mut MoooodotfarmServiceClient<tonic::transport::Channel>,
+) -> Result<Vec<CowNotes>> {
+    let Ok(token) = std::env::var("MOOOODOTFARM_OWNER_BEARER_TOKEN") else {
+        return Ok(vec![]);
+    };
+    let mut request = tonic::Request::new(ListCowNotesRequest {});
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {}", token)
+            .parse()
+            .map_err(|err| anyhow!("invalid MOOOODOTFARM_OWNER_BEARER_TOKEN: {}", err))?,
+    );
+    let response = client.list_cow_notes(request).await.map_err(|err| {
+        anyhow!(
+            "the gRPC server failed to list the notes: {}",
+            err.message()
+        )
+    })?;
+    Ok(response.into_inner().notes)
+}
 
+async fn ping() -> Result<()> {
+    let mut client = get_client().await?;
+    let response = client.ping(PingRequest::default()).await?.into_inner();
+    println!(
+        "Server version {} is reachable, its time is {}.",
+        response.version,
+        domain::time::DateTime::new_from_unix_timestamp(response.server_time.max(0) as u64)
+    );
     Ok(())
 }
 
 async fn add_cow(name: &str, character: &str) -> Result<()> {
+    let name = domain::VisibleName::from_base(name)?;
     let mut client = get_client().await?;
     client
         .add_cow(AddCowRequest {
-            name: name.to_string(),
+            name: name.url().to_string(),
             character: character.to_string(),
         })
         .await?;
@@ -130,6 +542,35 @@ async fn change_cow_character(name: &str, character: &str) -> Result<()> {
     Ok(())
 }
 
+async fn check(name: &str, verbose: bool) -> Result<()> {
+    let downloader = adapters::CowTxtDownloader::new(Default::default(), encoding_rs::UTF_8)?;
+    let name = domain::VisibleName::new(name)?;
+    match app::CowTxtDownloader::download(&downloader, &name).await {
+        Ok(_) => println!("Cow is present."),
+        Err(Error::CowIsNotPresent(content)) => {
+            println!("Cow is missing.");
+            if verbose {
+                print!("{}", domain::CowTxt::diff_against_reference(&content));
+            }
+        }
+        Err(err) => return Err(err),
+    }
+    Ok(())
+}
+
+fn censor(name: &str, character: &str, censoring: &str) -> Result<String> {
+    let cow = domain::Cow::new(
+        domain::VisibleName::new(name)?,
+        domain::Character::try_from(character.to_string())?,
+    );
+    let name =
+        domain::Name::new_with_host_censoring(&cow, domain::HostCensoring::try_from(censoring)?)?;
+    Ok(match name {
+        domain::Name::Visible(name) => name.url().to_string(),
+        domain::Name::Censored(name) => name.url().to_string(),
+    })
+}
+
 async fn delete_cow(name: &str) -> Result<()> {
     let mut client = get_client().await?;
     client
@@ -141,20 +582,103 @@ async fn delete_cow(name: &str) -> Result<()> {
     Ok(())
 }
 
+async fn pause_cow(name: &str) -> Result<()> {
+    let mut client = get_client().await?;
+    client
+        .pause_cow(PauseCowRequest {
+            name: name.to_string(),
+        })
+        .await?;
+    println!("Cow paused successfully!");
+    Ok(())
+}
+
+async fn resume_cow(name: &str) -> Result<()> {
+    let mut client = get_client().await?;
+    client
+        .resume_cow(ResumeCowRequest {
+            name: name.to_string(),
+        })
+        .await?;
+    println!("Cow resumed successfully!");
+    Ok(())
+}
+
+async fn set_cow_notes(name: &str, notes: &str) -> Result<()> {
+    let mut client = get_client().await?;
+    client
+        .set_cow_notes(SetCowNotesRequest {
+            name: name.to_string(),
+            notes: notes.to_string(),
+        })
+        .await?;
+    println!("Cow notes set successfully!");
+    Ok(())
+}
+
+async fn watch_update_progress() -> Result<()> {
+    let mut client = get_client().await?;
+    let mut stream = client
+        .watch_update_progress(WatchUpdateProgressRequest {})
+        .await?
+        .into_inner();
+    while let Some(progress) = stream.message().await? {
+        println!(
+            "[{}/{}] {}",
+            progress.checked, progress.total, progress.current_cow
+        );
+    }
+    Ok(())
+}
+
 async fn get_client() -> Result<MoooodotfarmServiceClient<tonic::transport::Channel>> {
     let grpc_address = std::env::var("MOOOODOTFARM_GRPC_ADDRESS")?;
-    let endpoint = format!("http://{}", grpc_address);
-
-    let client = MoooodotfarmServiceClient::connect(endpoint).await?;
-    Ok(client)
+    let timeout = env_var_or(
+        "MOOOODOTFARM_GRPC_TIMEOUT_SECONDS",
+        DEFAULT_GRPC_TIMEOUT_SECONDS,
+    )?;
+    let retry = grpc::ConnectRetry::new(
+        env_var_or(
+            "MOOOODOTFARM_GRPC_CONNECT_ATTEMPTS",
+            DEFAULT_GRPC_CONNECT_ATTEMPTS,
+        )?,
+        std::time::Duration::from_millis(env_var_or(
+            "MOOOODOTFARM_GRPC_CONNECT_RETRY_DELAY_MILLISECONDS",
+            DEFAULT_GRPC_CONNECT_RETRY_DELAY_MILLISECONDS,
+        )?),
+    )?;
+    grpc::connect_with_retry(
+        &grpc_address,
+        std::time::Duration::from_secs(timeout),
+        retry,
+    )
+    .await
 }
 
-async fn http_server_loop<'a, D>(server: &http::Server<'a, D>)
+fn env_var_or<T>(name: &str, default: T) -> Result<T>
 where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse::<T>()
+            .map_err(|err| anyhow!("invalid {} '{}': {}", name, value, err).into()),
+        Err(_) => Ok(default),
+    }
+}
+
+async fn http_server_loop<'a, D>(
+    server: &http::Server<'a, D>,
+    cancellation_token: &CancellationToken,
+) where
     D: http::Deps + Sync + Send + Clone + 'static,
 {
-    loop {
-        match server.run().await {
+    while !cancellation_token.is_cancelled() {
+        match server.run(cancellation_token).await {
+            Ok(_) if cancellation_token.is_cancelled() => {
+                info!("the server stopped")
+            }
             Ok(_) => {
                 error!("the server exited without returning any errors")
             }
@@ -165,12 +689,17 @@ where
     }
 }
 
-async fn grpc_server_loop<'a, D>(server: &grpc::GrpcServer<'a, D>)
-where
+async fn grpc_server_loop<'a, D>(
+    server: &grpc::GrpcServer<'a, D>,
+    cancellation_token: &CancellationToken,
+) where
     D: grpc::Deps + Sync + Send + Clone + 'static,
 {
-    loop {
-        match server.run().await {
+    while !cancellation_token.is_cancelled() {
+        match server.run(cancellation_token).await {
+            Ok(_) if cancellation_token.is_cancelled() => {
+                info!("the grpc server stopped")
+            }
             Ok(_) => {
                 error!("the grpc server exited without returning any errors")
             }
@@ -182,68 +711,235 @@ where
 }
 
 #[derive(Clone)]
-struct HttpDeps<GHH> {
+struct HttpDeps<GHH, GCH, GBH, GAH, GNH, GIH, GSH, RFH, RCH, CLH> {
     get_herd_handler: GHH,
+    get_cow_handler: GCH,
+    get_cow_body_handler: GBH,
+    get_cow_audit_handler: GAH,
+    get_cow_notes_handler: GNH,
+    get_insights_handler: GIH,
+    get_status_handler: GSH,
+    refresh_cow_handler: RFH,
+    reload_config_handler: RCH,
+    change_log_level_handler: CLH,
     metrics: adapters::Metrics,
+    display_format: DisplayFormat,
+    metrics_bearer_token: Option<String>,
+    owner_bearer_token: Option<String>,
+    json_field_naming: JsonFieldNaming,
+    site: http::Site,
+    host_policy: HostPolicy,
 }
 
-impl<GHH> HttpDeps<GHH> {
-    pub fn new(get_herd_handler: GHH, metrics: adapters::Metrics) -> Self {
+impl<GHH, GCH, GBH, GAH, GNH, GIH, GSH, RFH, RCH, CLH>
+    HttpDeps<GHH, GCH, GBH, GAH, GNH, GIH, GSH, RFH, RCH, CLH>
+{
+    // every handler of the http port is passed in, grouping them would only move the problem
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        get_herd_handler: GHH,
+        get_cow_handler: GCH,
+        get_cow_body_handler: GBH,
+        get_cow_audit_handler: GAH,
+        get_cow_notes_handler: GNH,
+        get_insights_handler: GIH,
+        get_status_handler: GSH,
+        refresh_cow_handler: RFH,
+        reload_config_handler: RCH,
+        change_log_level_handler: CLH,
+        metrics: adapters::Metrics,
+        display_format: DisplayFormat,
+        metrics_bearer_token: Option<String>,
+        owner_bearer_token: Option<String>,
+        json_field_naming: JsonFieldNaming,
+        site: http::Site,
+        host_policy: HostPolicy,
+    ) -> Self {
         Self {
             get_herd_handler,
+            get_cow_handler,
+            get_cow_body_handler,
+            get_cow_audit_handler,
+            get_cow_notes_handler,
+            get_insights_handler,
+            get_status_handler,
+            refresh_cow_handler,
+            reload_config_handler,
+            change_log_level_handler,
             metrics,
+            display_format,
+            metrics_bearer_token,
+            owner_bearer_token,
+            json_field_naming,
+            site,
+            host_policy,
         }
     }
 }
 
-impl<GHH> http::Deps for HttpDeps<GHH>
+impl<GHH, GCH, GBH, GAH, GNH, GIH, GSH, RFH, RCH, CLH> http::Deps
+    for HttpDeps<GHH, GCH, GBH, GAH, GNH, GIH, GSH, RFH, RCH, CLH>
 where
     GHH: app::GetHerdHandler,
+    GCH: app::GetCowHandler,
+    GBH: app::GetCowBodyHandler,
+    GAH: app::GetCowAuditHandler,
+    GNH: app::GetCowNotesHandler,
+    GIH: app::GetInsightsHandler,
+    GSH: app::GetStatusHandler,
+    RFH: app::RefreshCowHandler,
+    RCH: app::ReloadConfigHandler,
+    CLH: app::ChangeLogLevelHandler,
 {
     fn get_herd_handler(&self) -> &impl app::GetHerdHandler {
         &self.get_herd_handler
     }
 
+    fn get_cow_handler(&self) -> &impl app::GetCowHandler {
+        &self.get_cow_handler
+    }
+
+    fn get_cow_body_handler(&self) -> &impl app::GetCowBodyHandler {
+        &self.get_cow_body_handler
+    }
+
+    fn get_cow_audit_handler(&self) -> &impl app::GetCowAuditHandler {
+        &self.get_cow_audit_handler
+    }
+
+    fn get_cow_notes_handler(&self) -> &impl app::GetCowNotesHandler {
+        &self.get_cow_notes_handler
+    }
+
+    fn get_insights_handler(&self) -> &impl app::GetInsightsHandler {
+        &self.get_insights_handler
+    }
+
+    fn get_status_handler(&self) -> &impl app::GetStatusHandler {
+        &self.get_status_handler
+    }
+
+    fn refresh_cow_handler(&self) -> &impl app::RefreshCowHandler {
+        &self.refresh_cow_handler
+    }
+
+    fn reload_config_handler(&self) -> &impl app::ReloadConfigHandler {
+        &self.reload_config_handler
+    }
+
+    fn change_log_level_handler(&self) -> &impl app::ChangeLogLevelHandler {
+        &self.change_log_level_handler
+    }
+
     fn metrics(&self) -> &Registry {
         self.metrics.registry()
     }
+
+    fn display_format(&self) -> &DisplayFormat {
+        &self.display_format
+    }
+
+    fn metrics_bearer_token(&self) -> Option<&str> {
+        self.metrics_bearer_token.as_deref()
+    }
+
+    fn owner_bearer_token(&self) -> Option<&str> {
+        self.owner_bearer_token.as_deref()
+    }
+
+    fn json_field_naming(&self) -> JsonFieldNaming {
+        self.json_field_naming
+    }
+
+    fn site(&self) -> &http::Site {
+        &self.site
+    }
+
+    fn host_policy(&self) -> &HostPolicy {
+        &self.host_policy
+    }
 }
 
 #[derive(Clone)]
-struct GrpcDeps<GHH, ACH, CCH, DCH> {
+struct GrpcDeps<GHH, GCH, ACH, CCH, DCH, PCH, RSH, SNH, LNH> {
     get_herd_handler: GHH,
+    get_cow_handler: GCH,
     add_cow_handler: ACH,
     change_cow_character_handler: CCH,
     delete_cow_handler: DCH,
+    pause_cow_handler: PCH,
+    resume_cow_handler: RSH,
+    set_cow_notes_handler: SNH,
+    list_cow_notes_handler: LNH,
+    owner_bearer_token: Option<String>,
+    update_progress: app::UpdateProgressChannel,
+    display_format: DisplayFormat,
 }
 
-impl<GHH, ACH, CCH, DCH> GrpcDeps<GHH, ACH, CCH, DCH> {
+impl<GHH, GCH, ACH, CCH, DCH, PCH, RSH, SNH, LNH>
+    GrpcDeps<GHH, GCH, ACH, CCH, DCH, PCH, RSH, SNH, LNH>
+{
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         get_herd_handler: GHH,
+        get_cow_handler: GCH,
         add_cow_handler: ACH,
         change_cow_character_handler: CCH,
         delete_cow_handler: DCH,
+        pause_cow_handler: PCH,
+        resume_cow_handler: RSH,
+        set_cow_notes_handler: SNH,
+        list_cow_notes_handler: LNH,
+        owner_bearer_token: Option<String>,
     ) -> Self {
         Self {
             get_herd_handler,
+            get_cow_handler,
             add_cow_handler,
             change_cow_character_handler,
             delete_cow_handler,
+            pause_cow_handler,
+            resume_cow_handler,
+            set_cow_notes_handler,
+            list_cow_notes_handler,
+            owner_bearer_token,
+            update_progress: app::UpdateProgressChannel::new(),
+            display_format: DisplayFormat::default(),
         }
     }
+
+    pub fn with_update_progress(mut self, update_progress: app::UpdateProgressChannel) -> Self {
+        self.update_progress = update_progress;
+        self
+    }
+
+    pub fn with_display_format(mut self, display_format: DisplayFormat) -> Self {
+        self.display_format = display_format;
+        self
+    }
 }
 
-impl<GHH, ACH, CCH, DCH> grpc::Deps for GrpcDeps<GHH, ACH, CCH, DCH>
+impl<GHH, GCH, ACH, CCH, DCH, PCH, RSH, SNH, LNH> grpc::Deps
+    for GrpcDeps<GHH, GCH, ACH, CCH, DCH, PCH, RSH, SNH, LNH>
 where
     GHH: app::GetHerdHandler,
+    GCH: app::GetCowHandler,
     ACH: app::AddCowHandler,
     CCH: app::ChangeCowCharacterHandler,
     DCH: app::DeleteCowHandler,
+    PCH: app::PauseCowHandler,
+    RSH: app::ResumeCowHandler,
+    SNH: app::SetCowNotesHandler,
+    LNH: app::ListCowNotesHandler,
 {
     fn get_herd_handler(&self) -> &impl app::GetHerdHandler {
         &self.get_herd_handler
     }
 
+    fn get_cow_handler(&self) -> &impl app::GetCowHandler {
+        &self.get_cow_handler
+    }
+
     fn add_cow_handler(&self) -> &impl app::AddCowHandler {
         &self.add_cow_handler
     }
@@ -255,64 +951,475 @@ where
     fn delete_cow_handler(&self) -> &impl app::DeleteCowHandler {
         &self.delete_cow_handler
     }
+
+    fn pause_cow_handler(&self) -> &impl app::PauseCowHandler {
+        &self.pause_cow_handler
+    }
+
+    fn resume_cow_handler(&self) -> &impl app::ResumeCowHandler {
+        &self.resume_cow_handler
+    }
+
+    fn set_cow_notes_handler(&self) -> &impl app::SetCowNotesHandler {
+        &self.set_cow_notes_handler
+    }
+
+    fn list_cow_notes_handler(&self) -> &impl app::ListCowNotesHandler {
+        &self.list_cow_notes_handler
+    }
+
+    fn owner_bearer_token(&self) -> Option<&str> {
+        self.owner_bearer_token.as_deref()
+    }
+
+    fn update_progress(&self) -> &app::UpdateProgressChannel {
+        &self.update_progress
+    }
+
+    fn display_format(&self) -> &DisplayFormat {
+        &self.display_format
+    }
 }
 
-type GetHerdHandlerImpl = GetHerdHandler<database::Database, adapters::Metrics>;
-type UpdateHandlerImpl =
-    UpdateHandler<database::Database, adapters::CowTxtDownloader, adapters::Metrics>;
-type AddCowHandlerImpl =
-    AddCowHandler<database::Database, adapters::CowTxtDownloader, adapters::Metrics>;
-type ChangeCowCharacterHandlerImpl =
-    ChangeCowCharacterHandler<database::Database, adapters::Metrics>;
-type DeleteCowHandlerImpl = DeleteCowHandler<database::Database, adapters::Metrics>;
-type HttpDepsImpl = HttpDeps<GetHerdHandlerImpl>;
+type GetHerdHandlerImpl = GetHerdHandler<adapters::ConfiguredInventory, adapters::Metrics>;
+type GetCowHandlerImpl = GetCowHandler<adapters::ConfiguredInventory, adapters::Metrics>;
+type GetCowBodyHandlerImpl = GetCowBodyHandler<
+    adapters::ConfiguredInventory,
+    adapters::ConfiguredInventory,
+    adapters::Metrics,
+>;
+type GetCowAuditHandlerImpl = GetCowAuditHandler<
+    adapters::ConfiguredInventory,
+    adapters::ConfiguredInventory,
+    adapters::Metrics,
+>;
+type GetCowNotesHandlerImpl = GetCowNotesHandler<adapters::ConfiguredInventory, adapters::Metrics>;
+type GetInsightsHandlerImpl = GetInsightsHandler<adapters::ConfiguredInventory, adapters::Metrics>;
+type CowTxtDownloaderImpl =
+    StoringCowTxtDownloader<adapters::CowTxtDownloader, adapters::ConfiguredInventory>;
+type GetStatusHandlerImpl = GetStatusHandler<adapters::ConfiguredInventory, adapters::Metrics>;
+type UpdateHandlerImpl = UpdateHandler<
+    adapters::ConfiguredInventory,
+    adapters::ConfiguredInventory,
+    CowTxtDownloaderImpl,
+    adapters::Metrics,
+    events::LoggingEventSink,
+>;
+type AddCowHandlerImpl = AddCowHandler<
+    adapters::ConfiguredInventory,
+    CowTxtDownloaderImpl,
+    adapters::Metrics,
+    events::LoggingEventSink,
+>;
+type ChangeCowCharacterHandlerImpl = ChangeCowCharacterHandler<
+    adapters::ConfiguredInventory,
+    adapters::Metrics,
+    events::LoggingEventSink,
+>;
+type DeleteCowHandlerImpl =
+    DeleteCowHandler<adapters::ConfiguredInventory, adapters::Metrics, events::LoggingEventSink>;
+type PauseCowHandlerImpl =
+    PauseCowHandler<adapters::ConfiguredInventory, adapters::Metrics, events::LoggingEventSink>;
+type ResumeCowHandlerImpl =
+    ResumeCowHandler<adapters::ConfiguredInventory, adapters::Metrics, events::LoggingEventSink>;
+type SetCowNotesHandlerImpl = SetCowNotesHandler<adapters::ConfiguredInventory, adapters::Metrics>;
+type ListCowNotesHandlerImpl =
+    ListCowNotesHandler<adapters::ConfiguredInventory, adapters::Metrics>;
+type RefreshCowHandlerImpl = RefreshCowHandler<
+    adapters::ConfiguredInventory,
+    CowTxtDownloaderImpl,
+    adapters::Metrics,
+    events::LoggingEventSink,
+>;
+type ReloadConfigHandlerImpl = ReloadConfigHandler<
+    ConfigLoader,
+    adapters::ConfiguredInventory,
+    adapters::Metrics,
+    events::LoggingEventSink,
+>;
+type ChangeLogLevelHandlerImpl = ChangeLogLevelHandler<logging::LogLevel, adapters::Metrics>;
+type HttpDepsImpl = HttpDeps<
+    GetHerdHandlerImpl,
+    GetCowHandlerImpl,
+    GetCowBodyHandlerImpl,
+    GetCowAuditHandlerImpl,
+    GetCowNotesHandlerImpl,
+    GetInsightsHandlerImpl,
+    GetStatusHandlerImpl,
+    RefreshCowHandlerImpl,
+    ReloadConfigHandlerImpl,
+    ChangeLogLevelHandlerImpl,
+>;
 type HttpServerImpl<'a> = http::Server<'a, HttpDepsImpl>;
 type GrpcDepsImpl = GrpcDeps<
     GetHerdHandlerImpl,
+    GetCowHandlerImpl,
     AddCowHandlerImpl,
     ChangeCowCharacterHandlerImpl,
     DeleteCowHandlerImpl,
+    PauseCowHandlerImpl,
+    ResumeCowHandlerImpl,
+    SetCowNotesHandlerImpl,
+    ListCowNotesHandlerImpl,
 >;
 type GrpcServerImpl<'a> = grpc::GrpcServer<'a, GrpcDepsImpl>;
 type UpdateTimerImpl = timers::UpdateTimer<UpdateHandlerImpl>;
+type CompactDatabaseHandlerImpl =
+    CompactDatabaseHandler<adapters::ConfiguredInventory, adapters::Metrics>;
+type CompactDatabaseTimerImpl = timers::CompactDatabaseTimer<CompactDatabaseHandlerImpl>;
+type DiscoverCowsHandlerImpl = DiscoverCowsHandler<
+    discovery::MdnsDiscovery,
+    adapters::ConfiguredInventory,
+    AddCowHandlerImpl,
+    adapters::Metrics,
+>;
+type DiscoverCowsTimerImpl = timers::DiscoverCowsTimer<DiscoverCowsHandlerImpl>;
 
 struct Service<'a> {
     http_server: HttpServerImpl<'a>,
     grpc_server: GrpcServerImpl<'a>,
     update_timer: UpdateTimerImpl,
+    update_handler: UpdateHandlerImpl,
+    change_log_level_handler: ChangeLogLevelHandlerImpl,
+    metrics: adapters::Metrics,
+    compact_database_timer: Option<CompactDatabaseTimerImpl>,
+    discover_cows_timer: Option<DiscoverCowsTimerImpl>,
 }
 
 impl<'a> Service<'a> {
-    fn new(config: &'a Config) -> Result<Self> {
-        let metrics = adapters::Metrics::new()?;
+    fn new(
+        config: &'a Config,
+        config_loader: ConfigLoader,
+        log_level: logging::LogLevel,
+        tls: Option<RustlsConfig>,
+        // stops the timers and the servers, see shutdown_signal_loop
+        cancellation_token: CancellationToken,
+    ) -> Result<Self> {
+        let metrics = adapters::Metrics::new_with_namespace(config.metrics_namespace())?
+            .with_handler_names(config.metrics_handler_names());
 
-        let database = database::Database::new(config.database_path())?;
-        let downloader = adapters::CowTxtDownloader::new();
+        let inventory =
+            adapters::ConfiguredInventory::open(config.storage_backend(), config.database_path())?;
+        // values filled in for cows persisted by older versions, e.g. the added_at of a cow which
+        // was never seen, would otherwise change every time the cow is read
+        if let adapters::ConfiguredInventory::Redb(database) = &inventory {
+            let migrated = database.migrate()?;
+            if migrated > 0 {
+                info!("Migrated {} cows persisted by an older version.", migrated);
+            }
+        }
+        let downloader = adapters::CowTxtDownloader::new(
+            config.host_policy().clone(),
+            config.fallback_encoding(),
+        )?
+        .with_credentials(
+            config
+                .cows()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|cow| {
+                    cow.credentials()
+                        .map(|credentials| (cow.name().clone(), credentials.clone()))
+                })
+                .collect(),
+        )
+        .with_headers(
+            config
+                .cows()
+                .unwrap_or_default()
+                .iter()
+                .filter(|cow| !cow.headers().is_empty())
+                .map(|cow| (cow.name().clone(), cow.headers().clone()))
+                .collect(),
+        )
+        .with_must_match(
+            config.must_match().cloned(),
+            config
+                .cows()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|cow| {
+                    cow.must_match()
+                        .map(|must_match| (cow.name().clone(), must_match.clone()))
+                })
+                .collect(),
+        )
+        .with_check_via(
+            config
+                .cows()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|cow| {
+                    cow.check_via()
+                        .map(|check_via| (cow.name().clone(), check_via.clone()))
+                })
+                .collect(),
+        )
+        .with_check_cache_ttl(config.check_cache_ttl())
+        .with_redirect_policy(*config.redirect_policy())?;
+        let downloader =
+            StoringCowTxtDownloader::new(downloader, inventory.clone(), config.store_cow_bodies());
+        let event_sink = events::LoggingEventSink::new();
+        adopt_configured_cows(config, &inventory)?;
+        app::update_cow_counts(config, &inventory, &metrics)?;
+        app::Metrics::update_last_update(&metrics, &app::UpdateLog::last_update(&inventory)?);
+        let update_progress = app::UpdateProgressChannel::new();
+        let unchecked_cows = UncheckedCows::new(domain::time::Duration::new_from_std(
+            config.max_unchecked_age(),
+        ))
+        .with_active_hours(config.active_hours().copied());
+        let configured_order = app::ConfiguredOrder::new(
+            config
+                .cows()
+                .unwrap_or_default()
+                .iter()
+                .map(|cow| cow.name().clone())
+                .collect(),
+        );
 
-        let update_handler =
-            UpdateHandler::new(database.clone(), downloader.clone(), metrics.clone());
-        let get_herd_handler = GetHerdHandler::new(database.clone(), metrics.clone());
-        let add_cow_handler =
-            AddCowHandler::new(database.clone(), downloader.clone(), metrics.clone());
+        let update_handler = UpdateHandler::new(
+            inventory.clone(),
+            inventory.clone(),
+            downloader.clone(),
+            metrics.clone(),
+            event_sink.clone(),
+            config.max_concurrent_downloads(),
+            config.max_requests_per_update(),
+        )
+        .with_shard(config.shard().copied())
+        .with_check_order(config.check_order(), configured_order.clone())
+        .with_deadline(Some(
+            timers::UPDATE_EVERY * config.update_deadline_percent() / 100,
+        ))
+        .with_active_hours(config.active_hours().copied())
+        .with_flap_penalty(*config.flap_penalty())
+        .with_down_ratio_alert(
+            config
+                .herd_degraded_percent()
+                .map(DownRatioAlert::new)
+                .transpose()?,
+        )
+        .with_unchecked_cows(Some(unchecked_cows.clone()))
+        .with_host_censoring(config.host_censoring())
+        .with_progress(update_progress.clone());
+        let herd_shuffle = if config.stable_herd_order() {
+            domain::HerdShuffle::new_seeded_per_boot()
+        } else {
+            domain::HerdShuffle::PerRequest
+        };
+        let get_herd_handler = GetHerdHandler::new(
+            inventory.clone(),
+            metrics.clone(),
+            ShyCows::Censored,
+            herd_shuffle,
+        )
+        .with_active_hours(config.active_hours().copied())
+        .with_unchecked_cows(Some(unchecked_cows.clone()))
+        .with_host_censoring(config.host_censoring())
+        .with_flap_penalty(*config.flap_penalty());
+        let public_get_herd_handler = GetHerdHandler::new(
+            inventory.clone(),
+            metrics.clone(),
+            public_shy_cows(config),
+            herd_shuffle,
+        )
+        .with_active_hours(config.active_hours().copied())
+        .with_unchecked_cows(Some(unchecked_cows))
+        .with_host_censoring(config.host_censoring())
+        .with_flap_penalty(*config.flap_penalty());
+        let get_cow_handler =
+            GetCowHandler::new(inventory.clone(), metrics.clone(), ShyCows::Censored)
+                .with_active_hours(config.active_hours().copied())
+                .with_host_censoring(config.host_censoring())
+                .with_flap_penalty(*config.flap_penalty());
+        // a censored cow looked up by its real url would tell that the url is in the herd, shy cows
+        // are only ever shown censored in the herd itself
+        let public_get_cow_handler =
+            GetCowHandler::new(inventory.clone(), metrics.clone(), ShyCows::Hidden)
+                .with_active_hours(config.active_hours().copied())
+                .with_host_censoring(config.host_censoring())
+                .with_flap_penalty(*config.flap_penalty());
+        let public_get_cow_body_handler =
+            GetCowBodyHandler::new(inventory.clone(), inventory.clone(), metrics.clone());
+        let get_cow_audit_handler =
+            GetCowAuditHandler::new(inventory.clone(), inventory.clone(), metrics.clone());
+        let get_cow_notes_handler = GetCowNotesHandler::new(inventory.clone(), metrics.clone());
+        let public_get_insights_handler =
+            GetInsightsHandler::new(inventory.clone(), metrics.clone(), public_shy_cows(config))
+                .with_active_hours(config.active_hours().copied())
+                .with_host_censoring(config.host_censoring())
+                .with_flap_penalty(*config.flap_penalty());
+        let get_status_handler = GetStatusHandler::new(inventory.clone(), metrics.clone());
+        let add_cow_handler = AddCowHandler::new(
+            inventory.clone(),
+            downloader.clone(),
+            metrics.clone(),
+            event_sink.clone(),
+            config.max_concurrent_downloads(),
+        );
         let change_cow_character_handler =
-            ChangeCowCharacterHandler::new(database.clone(), metrics.clone());
-        let delete_cow_handler = DeleteCowHandler::new(database.clone(), metrics.clone());
+            ChangeCowCharacterHandler::new(inventory.clone(), metrics.clone(), event_sink.clone());
+        let delete_cow_handler =
+            DeleteCowHandler::new(inventory.clone(), metrics.clone(), event_sink.clone());
+        let pause_cow_handler =
+            PauseCowHandler::new(inventory.clone(), metrics.clone(), event_sink.clone());
+        let resume_cow_handler =
+            ResumeCowHandler::new(inventory.clone(), metrics.clone(), event_sink.clone());
+        let set_cow_notes_handler = SetCowNotesHandler::new(inventory.clone(), metrics.clone());
+        let list_cow_notes_handler = ListCowNotesHandler::new(inventory.clone(), metrics.clone());
+        let refresh_cow_handler = RefreshCowHandler::new(
+            inventory.clone(),
+            downloader.clone(),
+            metrics.clone(),
+            event_sink.clone(),
+            public_shy_cows(config),
+        )
+        .with_host_censoring(config.host_censoring())
+        .with_active_hours(config.active_hours().copied())
+        .with_flap_penalty(*config.flap_penalty());
+        let reload_config_handler = ReloadConfigHandler::new(
+            config,
+            config_loader,
+            inventory.clone(),
+            metrics.clone(),
+            event_sink.clone(),
+        )
+        .with_configured_order(configured_order);
+        let change_log_level_handler = ChangeLogLevelHandler::new(log_level, metrics.clone());
 
-        let timer = timers::UpdateTimer::new(update_handler.clone());
-        let http_deps = HttpDeps::new(get_herd_handler.clone(), metrics);
+        let timer = timers::UpdateTimer::new(update_handler.clone(), cancellation_token.clone())
+            .with_max_first_update_delay(config.max_first_update_delay())
+            .with_active_hours(config.active_hours().copied());
+        let compact_database_timer = config.database_compaction_interval().map(|interval| {
+            timers::CompactDatabaseTimer::new(
+                CompactDatabaseHandler::new(inventory.clone(), metrics.clone()),
+                interval,
+                cancellation_token.clone(),
+            )
+        });
+        let discover_cows_timer = config.mdns_discovery().then(|| {
+            timers::DiscoverCowsTimer::new(
+                DiscoverCowsHandler::new(
+                    discovery::MdnsDiscovery::new(),
+                    inventory.clone(),
+                    add_cow_handler.clone(),
+                    metrics.clone(),
+                ),
+                cancellation_token.clone(),
+            )
+        });
+        let http_deps = HttpDeps::new(
+            public_get_herd_handler,
+            public_get_cow_handler,
+            public_get_cow_body_handler,
+            get_cow_audit_handler,
+            get_cow_notes_handler,
+            public_get_insights_handler,
+            get_status_handler,
+            refresh_cow_handler,
+            reload_config_handler,
+            change_log_level_handler.clone(),
+            metrics.clone(),
+            config.display_format().clone(),
+            config.metrics_bearer_token().map(|v| v.to_string()),
+            config.owner_bearer_token().map(|v| v.to_string()),
+            config.json_field_naming(),
+            http::Site::new()
+                .with_title(config.site_title().map(|v| v.to_string()))
+                .with_public_url(config.public_url())
+                .with_favicon(
+                    config
+                        .favicon_path()
+                        .map(http::SiteImage::load)
+                        .transpose()?,
+                )
+                .with_logo(config.logo_path().map(http::SiteImage::load).transpose()?),
+            config.host_policy().clone(),
+        );
         let grpc_deps = GrpcDeps::new(
             get_herd_handler.clone(),
+            get_cow_handler,
             add_cow_handler,
             change_cow_character_handler,
             delete_cow_handler,
-        );
-        let http_server = http::Server::new(config, http_deps);
+            pause_cow_handler,
+            resume_cow_handler,
+            set_cow_notes_handler,
+            list_cow_notes_handler,
+            config.owner_bearer_token().map(|v| v.to_string()),
+        )
+        .with_update_progress(update_progress)
+        .with_display_format(config.display_format().clone());
+        let http_server = http::Server::new(config, http_deps).with_tls(tls);
         let grpc_server = grpc::GrpcServer::new(config, grpc_deps);
 
         Ok(Self {
             http_server,
             grpc_server,
             update_timer: timer,
+            update_handler,
+            change_log_level_handler,
+            metrics,
+            compact_database_timer,
+            discover_cows_timer,
         })
     }
 }
+
+fn public_shy_cows(config: &Config) -> ShyCows {
+    if config.hide_shy_from_public() {
+        ShyCows::Hidden
+    } else {
+        ShyCows::Censored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn metrics_print_the_herd_numbers_after_an_update() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("moooodotfarm-metrics-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+http_address = "127.0.0.1:0"
+grpc_address = "127.0.0.1:0"
+environment = "development"
+database_path = "/moooodotfarm.db"
+backend = "memory"
+"#,
+        )?;
+
+        let result = scrape_once(path.to_str().unwrap()).await;
+        std::fs::remove_file(&path)?;
+
+        let metrics = result?;
+        assert!(metrics.contains("# TYPE moooodotfarm_herd_numbers gauge"));
+        assert!(metrics.contains("moooodotfarm_herd_numbers{status=\"happily_grazing\"} 0"));
+        Ok(())
+    }
+
+    #[test]
+    fn censor_masks_every_subdomain_of_a_shy_cow() -> Result<()> {
+        assert_eq!(
+            censor(
+                "https://api.eu.example.com/herd/cow.txt",
+                "shy",
+                "per_label"
+            )?,
+            "https://***.**.*******.com/****/cow.txt"
+        );
+        assert_eq!(
+            censor(
+                "https://api.eu.example.com/herd/cow.txt",
+                "shy",
+                "collapsed"
+            )?,
+            "https://*******.com/****/cow.txt"
+        );
+        Ok(())
+    }
+}
@@ -1,12 +1,103 @@
-use crate::errors::Result;
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::check_order::CheckOrder;
+use crate::domain::flap_penalty::FlapPenalty;
+use crate::domain::herd_health::DownRatioAlert;
+use crate::domain::host_policy::HostPolicy;
+use crate::domain::redirect_policy::RedirectPolicy;
+use crate::domain::shard::Shard;
+use crate::domain::time::DisplayFormat;
+use crate::domain::{Character, HostCensoring, MustMatch, Nickname, Tag, VisibleName};
+use crate::errors::{Error, Result};
 use anyhow::anyhow;
+use std::fmt;
+
+static DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 8;
+static DEFAULT_UPDATE_DEADLINE_PERCENT: u32 = 80;
+pub static DEFAULT_METRICS_NAMESPACE: &str = "moooodotfarm";
+// handler names end up as metric labels, anything not listed is reported as "other" so that a
+// handler which forgot to use a fixed name can't blow up the cardinality
+pub static DEFAULT_METRICS_HANDLER_NAMES: &[&str] = &[
+    "add_cow",
+    "change_cow_character",
+    "change_log_level",
+    "compact_database",
+    "delete_cow",
+    "discover_cows",
+    "get_cow",
+    "get_cow_audit",
+    "get_cow_body",
+    "get_cow_notes",
+    "get_herd",
+    "get_insights",
+    "get_status",
+    "list_cow_notes",
+    "pause_cow",
+    "refresh_cow",
+    "reload_config",
+    "resume_cow",
+    "set_cow_notes",
+    "update",
+];
+static DEFAULT_MAX_FIRST_UPDATE_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+static DEFAULT_GRPC_CONCURRENCY_LIMIT: usize = 32;
+static DEFAULT_GRPC_MAX_CONCURRENT_STREAMS: u32 = 100;
+static DEFAULT_GRPC_TCP_KEEPALIVE: std::time::Duration = std::time::Duration::from_secs(60);
+// timers can't be scheduled arbitrarily far ahead, nobody needs to compact less than once a year
+static MAX_DATABASE_COMPACTION_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(366 * 24 * 60 * 60);
+// twice the update interval
+static DEFAULT_MAX_UNCHECKED_AGE: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+// a cow which wasn't checked for a year isn't waiting for its first check anymore, it was forgotten
+static MAX_MAX_UNCHECKED_AGE: std::time::Duration =
+    std::time::Duration::from_secs(366 * 24 * 60 * 60);
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Config {
     http_address: String,
     grpc_address: String,
+    grpc_limits: GrpcLimits,
+    grpc_web_origins: Vec<String>,
+    tls: Option<TlsConfig>,
     environment: Environment,
     database_path: String,
+    storage_backend: StorageBackend,
+    cows: Option<Vec<CowConfig>>,
+    log_level: log::LevelFilter,
+    log_format: LogFormat,
+    hide_shy_from_public: bool,
+    security_headers: bool,
+    site_title: Option<String>,
+    favicon_path: Option<String>,
+    logo_path: Option<String>,
+    public_url: Option<url::Url>,
+    host_censoring: HostCensoring,
+    stable_herd_order: bool,
+    host_policy: HostPolicy,
+    redirect_policy: RedirectPolicy,
+    check_order: CheckOrder,
+    mdns_discovery: bool,
+    store_cow_bodies: bool,
+    allow_indexing: bool,
+    serve_herd_txt: bool,
+    database_compaction_interval: Option<std::time::Duration>,
+    check_cache_ttl: Option<std::time::Duration>,
+    max_concurrent_downloads: usize,
+    max_requests_per_update: Option<usize>,
+    update_deadline_percent: u32,
+    herd_degraded_percent: Option<u32>,
+    flap_penalty: FlapPenalty,
+    max_first_update_delay: std::time::Duration,
+    max_unchecked_age: std::time::Duration,
+    active_hours: Option<ActiveHours>,
+    display_format: DisplayFormat,
+    json_field_naming: JsonFieldNaming,
+    fallback_encoding: &'static encoding_rs::Encoding,
+    metrics_bearer_token: Option<String>,
+    owner_bearer_token: Option<String>,
+    metrics_namespace: Option<String>,
+    metrics_handler_names: Vec<String>,
+    shard: Option<Shard>,
+    must_match: Option<MustMatch>,
 }
 
 impl Config {
@@ -28,22 +119,406 @@ impl Config {
         if database_path.is_empty() {
             return Err(anyhow!("database_path can't be empty").into());
         }
+        let security_headers = environment == Environment::Production;
         Ok(Self {
             http_address,
             grpc_address,
+            grpc_limits: GrpcLimits::default(),
+            grpc_web_origins: vec![],
+            tls: None,
             environment,
             database_path,
+            storage_backend: StorageBackend::Redb,
+            cows: None,
+            log_level: log::LevelFilter::Info,
+            log_format: LogFormat::Text,
+            hide_shy_from_public: false,
+            security_headers,
+            site_title: None,
+            favicon_path: None,
+            logo_path: None,
+            public_url: None,
+            host_censoring: HostCensoring::default(),
+            stable_herd_order: false,
+            host_policy: HostPolicy::default(),
+            redirect_policy: RedirectPolicy::default(),
+            check_order: CheckOrder::default(),
+            mdns_discovery: false,
+            store_cow_bodies: false,
+            allow_indexing: false,
+            serve_herd_txt: false,
+            database_compaction_interval: None,
+            check_cache_ttl: None,
+            max_concurrent_downloads: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+            max_requests_per_update: None,
+            update_deadline_percent: DEFAULT_UPDATE_DEADLINE_PERCENT,
+            herd_degraded_percent: None,
+            flap_penalty: FlapPenalty::default(),
+            max_first_update_delay: DEFAULT_MAX_FIRST_UPDATE_DELAY,
+            max_unchecked_age: DEFAULT_MAX_UNCHECKED_AGE,
+            active_hours: None,
+            display_format: DisplayFormat::default(),
+            json_field_naming: JsonFieldNaming::default(),
+            fallback_encoding: encoding_rs::UTF_8,
+            metrics_bearer_token: None,
+            owner_bearer_token: None,
+            metrics_namespace: Some(DEFAULT_METRICS_NAMESPACE.to_string()),
+            metrics_handler_names: DEFAULT_METRICS_HANDLER_NAMES
+                .iter()
+                .map(|v| v.to_string())
+                .collect(),
+            shard: None,
+            must_match: None,
         })
     }
 
+    pub fn with_grpc_limits(mut self, grpc_limits: GrpcLimits) -> Self {
+        self.grpc_limits = grpc_limits;
+        self
+    }
+
+    // pages served from these origins may call the grpc service from the browser, e.g.
+    // `https://moooo.farm`, pages served by the grpc address itself always can
+    pub fn with_grpc_web_origins(mut self, grpc_web_origins: Vec<String>) -> Result<Self> {
+        for origin in &grpc_web_origins {
+            let valid = url::Url::parse(origin)
+                .is_ok_and(|url| url.origin().ascii_serialization() == *origin);
+            if !valid {
+                return Err(anyhow!("invalid grpc web origin: {}", origin).into());
+            }
+        }
+        self.grpc_web_origins = grpc_web_origins;
+        Ok(self)
+    }
+
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn with_logging(mut self, log_level: log::LevelFilter, log_format: LogFormat) -> Self {
+        self.log_level = log_level;
+        self.log_format = log_format;
+        self
+    }
+
+    // see domain::HerdShuffle for what is given up by making the order stable
+    pub fn with_stable_herd_order(mut self, stable_herd_order: bool) -> Self {
+        self.stable_herd_order = stable_herd_order;
+        self
+    }
+
+    pub fn with_hide_shy_from_public(mut self, hide_shy_from_public: bool) -> Self {
+        self.hide_shy_from_public = hide_shy_from_public;
+        self
+    }
+
+    // defaults to on in production, development is usually served over plain http from localhost
+    pub fn with_security_headers(mut self, security_headers: bool) -> Self {
+        self.security_headers = security_headers;
+        self
+    }
+
+    // shown next to the title of every page for deployments which aren't moooo.farm
+    pub fn with_site_title(mut self, site_title: impl Into<String>) -> Result<Self> {
+        let site_title = site_title.into();
+        if site_title.trim().is_empty() {
+            return Err(anyhow!("site_title can't be empty").into());
+        }
+        self.site_title = Some(site_title);
+        Ok(self)
+    }
+
+    // served instead of the embedded favicon, the file is read once at startup
+    pub fn with_favicon_path(mut self, favicon_path: impl Into<String>) -> Result<Self> {
+        let favicon_path = favicon_path.into();
+        if favicon_path.is_empty() {
+            return Err(anyhow!("favicon_path can't be empty").into());
+        }
+        self.favicon_path = Some(favicon_path);
+        Ok(self)
+    }
+
+    // shown instead of the cow at the top of the home page, the file is read once at startup
+    pub fn with_logo_path(mut self, logo_path: impl Into<String>) -> Result<Self> {
+        let logo_path = logo_path.into();
+        if logo_path.is_empty() {
+            return Err(anyhow!("logo_path can't be empty").into());
+        }
+        self.logo_path = Some(logo_path);
+        Ok(self)
+    }
+
+    // the address the pages link to when shared, e.g. `https://moooo.farm`
+    pub fn with_public_url(mut self, public_url: url::Url) -> Result<Self> {
+        if !matches!(public_url.scheme(), "http" | "https")
+            || public_url.query().is_some()
+            || public_url.fragment().is_some()
+        {
+            return Err(anyhow!("invalid public_url: {}", public_url).into());
+        }
+        self.public_url = Some(public_url);
+        Ok(self)
+    }
+
+    pub fn with_host_censoring(mut self, host_censoring: HostCensoring) -> Self {
+        self.host_censoring = host_censoring;
+        self
+    }
+
+    pub fn with_host_policy(mut self, host_policy: HostPolicy) -> Self {
+        self.host_policy = host_policy;
+        self
+    }
+
+    pub fn with_redirect_policy(mut self, redirect_policy: RedirectPolicy) -> Self {
+        self.redirect_policy = redirect_policy;
+        self
+    }
+
+    pub fn with_check_order(mut self, check_order: CheckOrder) -> Self {
+        self.check_order = check_order;
+        self
+    }
+
+    // cows found on the local network are added without anyone asking for them
+    pub fn with_mdns_discovery(mut self, mdns_discovery: bool) -> Self {
+        self.mdns_discovery = mdns_discovery;
+        self
+    }
+
+    // search engines are asked to stay away unless indexing is allowed
+    pub fn with_allow_indexing(mut self, allow_indexing: bool) -> Self {
+        self.allow_indexing = allow_indexing;
+        self
+    }
+
+    // the plain text herd is meant for scripts, it is only served to those who ask for it
+    pub fn with_serve_herd_txt(mut self, serve_herd_txt: bool) -> Self {
+        self.serve_herd_txt = serve_herd_txt;
+        self
+    }
+
+    // nothing survives a restart when the herd is kept in memory, the database path is then unused
+    pub fn with_storage_backend(mut self, storage_backend: StorageBackend) -> Self {
+        self.storage_backend = storage_backend;
+        self
+    }
+
+    // bodies take up far more space than everything else known about a cow
+    pub fn with_store_cow_bodies(mut self, store_cow_bodies: bool) -> Self {
+        self.store_cow_bodies = store_cow_bodies;
+        self
+    }
+
+    pub fn with_database_compaction_interval(
+        mut self,
+        interval: std::time::Duration,
+    ) -> Result<Self> {
+        if interval.is_zero() {
+            return Err(anyhow!("database compaction interval can't be zero").into());
+        }
+        if interval > MAX_DATABASE_COMPACTION_INTERVAL {
+            return Err(anyhow!("database compaction interval can't be longer than a year").into());
+        }
+        self.database_compaction_interval = Some(interval);
+        Ok(self)
+    }
+
+    // cows sharing a url are only downloaded once within the ttl
+    pub fn with_check_cache_ttl(mut self, check_cache_ttl: std::time::Duration) -> Result<Self> {
+        if check_cache_ttl.is_zero() {
+            return Err(Error::ValidationError(anyhow!(
+                "check cache ttl can't be zero"
+            )));
+        }
+        self.check_cache_ttl = Some(check_cache_ttl);
+        Ok(self)
+    }
+
+    pub fn with_max_concurrent_downloads(
+        mut self,
+        max_concurrent_downloads: usize,
+    ) -> Result<Self> {
+        if max_concurrent_downloads == 0 {
+            return Err(anyhow!("max_concurrent_downloads can't be zero").into());
+        }
+        self.max_concurrent_downloads = max_concurrent_downloads;
+        Ok(self)
+    }
+
+    pub fn with_max_requests_per_update(mut self, max_requests_per_update: usize) -> Result<Self> {
+        if max_requests_per_update == 0 {
+            return Err(anyhow!("max_requests_per_update can't be zero").into());
+        }
+        self.max_requests_per_update = Some(max_requests_per_update);
+        Ok(self)
+    }
+
+    // relative to the update interval so that the next update is never delayed by the previous one
+    pub fn with_update_deadline_percent(mut self, update_deadline_percent: u32) -> Result<Self> {
+        if !(1..=100).contains(&update_deadline_percent) {
+            return Err(Error::ValidationError(anyhow!(
+                "update_deadline_percent must be between 1 and 100"
+            )));
+        }
+        self.update_deadline_percent = update_deadline_percent;
+        Ok(self)
+    }
+
+    // the herd is degraded when more than this percentage of the checked cows ran away
+    pub fn with_herd_degraded_percent(mut self, herd_degraded_percent: u32) -> Result<Self> {
+        // the alert is only built once the farm starts but it knows which thresholds are valid
+        DownRatioAlert::new(herd_degraded_percent)?;
+        self.herd_degraded_percent = Some(herd_degraded_percent);
+        Ok(self)
+    }
+
+    // zero runs the first update right away
+    pub fn with_max_first_update_delay(
+        mut self,
+        max_first_update_delay: std::time::Duration,
+    ) -> Self {
+        self.max_first_update_delay = max_first_update_delay;
+        self
+    }
+
+    // a cow which is still waiting for its first check after this long points at updates which
+    // don't run rather than at a problem with the cow
+    pub fn with_max_unchecked_age(
+        mut self,
+        max_unchecked_age: std::time::Duration,
+    ) -> Result<Self> {
+        if max_unchecked_age.is_zero() {
+            return Err(Error::ValidationError(anyhow!(
+                "max_unchecked_age must be positive"
+            )));
+        }
+        if max_unchecked_age > MAX_MAX_UNCHECKED_AGE {
+            return Err(Error::ValidationError(anyhow!(
+                "max_unchecked_age can't be longer than a year"
+            )));
+        }
+        self.max_unchecked_age = max_unchecked_age;
+        Ok(self)
+    }
+
+    pub fn with_flap_penalty(mut self, flap_penalty: FlapPenalty) -> Self {
+        self.flap_penalty = flap_penalty;
+        self
+    }
+
+    pub fn with_active_hours(mut self, active_hours: ActiveHours) -> Self {
+        self.active_hours = Some(active_hours);
+        self
+    }
+
+    pub fn with_shard(mut self, shard: Shard) -> Self {
+        self.shard = Some(shard);
+        self
+    }
+
+    pub fn with_display_format(mut self, display_format: DisplayFormat) -> Self {
+        self.display_format = display_format;
+        self
+    }
+
+    pub fn with_json_field_naming(mut self, json_field_naming: JsonFieldNaming) -> Self {
+        self.json_field_naming = json_field_naming;
+        self
+    }
+
+    pub fn with_fallback_encoding(
+        mut self,
+        fallback_encoding: &'static encoding_rs::Encoding,
+    ) -> Self {
+        self.fallback_encoding = fallback_encoding;
+        self
+    }
+
+    pub fn with_metrics_bearer_token(mut self, token: impl Into<String>) -> Result<Self> {
+        let token = token.into();
+        if token.is_empty() {
+            return Err(anyhow!("metrics bearer token can't be empty").into());
+        }
+        self.metrics_bearer_token = Some(token);
+        Ok(self)
+    }
+
+    // endpoints meant only for the owner of the farm refuse everyone while no token is configured
+    pub fn with_owner_bearer_token(mut self, token: impl Into<String>) -> Result<Self> {
+        let token = token.into();
+        if token.is_empty() {
+            return Err(Error::ValidationError(anyhow!(
+                "owner bearer token can't be empty"
+            )));
+        }
+        self.owner_bearer_token = Some(token);
+        Ok(self)
+    }
+
+    // none leaves metric names without a prefix
+    pub fn with_metrics_namespace(mut self, namespace: Option<String>) -> Result<Self> {
+        if let Some(namespace) = &namespace {
+            let mut chars = namespace.chars();
+            let valid = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(Error::ValidationError(anyhow!(
+                    "invalid metrics namespace: {}",
+                    namespace
+                )));
+            }
+        }
+        self.metrics_namespace = namespace;
+        Ok(self)
+    }
+
+    // replaces the default handler names, calls of any other handler are reported as "other"
+    pub fn with_metrics_handler_names(mut self, handler_names: Vec<String>) -> Result<Self> {
+        if let Some(handler_name) = handler_names.iter().find(|v| v.trim().is_empty()) {
+            return Err(Error::ValidationError(anyhow!(
+                "invalid metrics handler name: {:?}",
+                handler_name
+            )));
+        }
+        self.metrics_handler_names = handler_names;
+        Ok(self)
+    }
+
+    // applies to every cow which doesn't have its own pattern
+    pub fn with_must_match(mut self, must_match: MustMatch) -> Self {
+        self.must_match = Some(must_match);
+        self
+    }
+
+    pub fn with_cows(mut self, cows: Vec<CowConfig>) -> Self {
+        self.cows = Some(cows);
+        self
+    }
+
     pub fn http_address(&self) -> &str {
         &self.http_address
     }
 
+    pub fn tls(&self) -> Option<&TlsConfig> {
+        self.tls.as_ref()
+    }
+
     pub fn grpc_address(&self) -> &str {
         &self.grpc_address
     }
 
+    pub fn grpc_limits(&self) -> &GrpcLimits {
+        &self.grpc_limits
+    }
+
+    pub fn grpc_web_origins(&self) -> &[String] {
+        &self.grpc_web_origins
+    }
+
     pub fn environment(&self) -> &Environment {
         &self.environment
     }
@@ -51,6 +526,446 @@ impl Config {
     pub fn database_path(&self) -> &str {
         &self.database_path
     }
+
+    pub fn storage_backend(&self) -> StorageBackend {
+        self.storage_backend
+    }
+
+    pub fn cows(&self) -> Option<&[CowConfig]> {
+        self.cows.as_deref()
+    }
+
+    pub fn log_level(&self) -> log::LevelFilter {
+        self.log_level
+    }
+
+    pub fn log_format(&self) -> &LogFormat {
+        &self.log_format
+    }
+
+    pub fn hide_shy_from_public(&self) -> bool {
+        self.hide_shy_from_public
+    }
+
+    pub fn security_headers(&self) -> bool {
+        self.security_headers
+    }
+
+    pub fn site_title(&self) -> Option<&str> {
+        self.site_title.as_deref()
+    }
+
+    pub fn favicon_path(&self) -> Option<&str> {
+        self.favicon_path.as_deref()
+    }
+
+    pub fn logo_path(&self) -> Option<&str> {
+        self.logo_path.as_deref()
+    }
+
+    pub fn public_url(&self) -> Option<&url::Url> {
+        self.public_url.as_ref()
+    }
+
+    pub fn host_censoring(&self) -> HostCensoring {
+        self.host_censoring
+    }
+
+    pub fn stable_herd_order(&self) -> bool {
+        self.stable_herd_order
+    }
+
+    pub fn host_policy(&self) -> &HostPolicy {
+        &self.host_policy
+    }
+
+    pub fn redirect_policy(&self) -> &RedirectPolicy {
+        &self.redirect_policy
+    }
+
+    pub fn check_order(&self) -> CheckOrder {
+        self.check_order
+    }
+
+    pub fn mdns_discovery(&self) -> bool {
+        self.mdns_discovery
+    }
+
+    pub fn allow_indexing(&self) -> bool {
+        self.allow_indexing
+    }
+
+    pub fn serve_herd_txt(&self) -> bool {
+        self.serve_herd_txt
+    }
+
+    pub fn store_cow_bodies(&self) -> bool {
+        self.store_cow_bodies
+    }
+
+    pub fn database_compaction_interval(&self) -> Option<std::time::Duration> {
+        self.database_compaction_interval
+    }
+
+    pub fn check_cache_ttl(&self) -> Option<std::time::Duration> {
+        self.check_cache_ttl
+    }
+
+    pub fn max_concurrent_downloads(&self) -> usize {
+        self.max_concurrent_downloads
+    }
+
+    pub fn max_requests_per_update(&self) -> Option<usize> {
+        self.max_requests_per_update
+    }
+
+    pub fn update_deadline_percent(&self) -> u32 {
+        self.update_deadline_percent
+    }
+
+    pub fn herd_degraded_percent(&self) -> Option<u32> {
+        self.herd_degraded_percent
+    }
+
+    pub fn max_first_update_delay(&self) -> std::time::Duration {
+        self.max_first_update_delay
+    }
+
+    pub fn max_unchecked_age(&self) -> std::time::Duration {
+        self.max_unchecked_age
+    }
+
+    pub fn flap_penalty(&self) -> &FlapPenalty {
+        &self.flap_penalty
+    }
+
+    pub fn active_hours(&self) -> Option<&ActiveHours> {
+        self.active_hours.as_ref()
+    }
+
+    pub fn shard(&self) -> Option<&Shard> {
+        self.shard.as_ref()
+    }
+
+    pub fn display_format(&self) -> &DisplayFormat {
+        &self.display_format
+    }
+
+    pub fn json_field_naming(&self) -> JsonFieldNaming {
+        self.json_field_naming
+    }
+
+    pub fn fallback_encoding(&self) -> &'static encoding_rs::Encoding {
+        self.fallback_encoding
+    }
+
+    pub fn metrics_bearer_token(&self) -> Option<&str> {
+        self.metrics_bearer_token.as_deref()
+    }
+
+    pub fn owner_bearer_token(&self) -> Option<&str> {
+        self.owner_bearer_token.as_deref()
+    }
+
+    pub fn metrics_namespace(&self) -> Option<&str> {
+        self.metrics_namespace.as_deref()
+    }
+
+    pub fn metrics_handler_names(&self) -> &[String] {
+        &self.metrics_handler_names
+    }
+
+    pub fn must_match(&self) -> Option<&MustMatch> {
+        self.must_match.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CowConfig {
+    name: VisibleName,
+    character: Character,
+    expected_character: Option<Character>,
+    nickname: Option<Nickname>,
+    tags: Vec<Tag>,
+    credentials: Option<CowCredentials>,
+    headers: http::HeaderMap,
+    must_match: Option<MustMatch>,
+    check_via: Option<url::Url>,
+}
+
+impl CowConfig {
+    pub fn new(name: VisibleName, character: Character) -> Self {
+        Self {
+            name,
+            character,
+            expected_character: None,
+            nickname: None,
+            tags: vec![],
+            credentials: None,
+            headers: http::HeaderMap::new(),
+            must_match: None,
+            check_via: None,
+        }
+    }
+
+    // a cow whose character ends up different, e.g. after it was changed by hand, is flagged
+    // instead of being changed back
+    pub fn with_expected_character(mut self, expected_character: Character) -> Self {
+        self.expected_character = Some(expected_character);
+        self
+    }
+
+    pub fn with_nickname(mut self, nickname: Nickname) -> Self {
+        self.nickname = Some(nickname);
+        self
+    }
+
+    pub fn with_tags(mut self, mut tags: Vec<Tag>) -> Self {
+        // kept in the same order as on a cow so that reloads can compare them directly
+        tags.sort();
+        tags.dedup();
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: CowCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self> {
+        let (name, value) = sensitive_header(name, value)?;
+        self.headers.append(name, value);
+        Ok(self)
+    }
+
+    pub fn with_must_match(mut self, must_match: MustMatch) -> Self {
+        self.must_match = Some(must_match);
+        self
+    }
+
+    // checks are sent to this url instead while the cow is still known and shown under its name
+    pub fn with_check_via(mut self, check_via: url::Url) -> Result<Self> {
+        if !matches!(check_via.scheme(), "http" | "https") {
+            return Err(Error::ValidationError(anyhow!(
+                "check_via must be an http or https url: {}",
+                check_via
+            )));
+        }
+        self.check_via = Some(check_via);
+        Ok(self)
+    }
+
+    pub fn name(&self) -> &VisibleName {
+        &self.name
+    }
+
+    pub fn character(&self) -> &Character {
+        &self.character
+    }
+
+    pub fn expected_character(&self) -> Option<&Character> {
+        self.expected_character.as_ref()
+    }
+
+    pub fn nickname(&self) -> Option<&Nickname> {
+        self.nickname.as_ref()
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn credentials(&self) -> Option<&CowCredentials> {
+        self.credentials.as_ref()
+    }
+
+    pub fn headers(&self) -> &http::HeaderMap {
+        &self.headers
+    }
+
+    pub fn check_via(&self) -> Option<&url::Url> {
+        self.check_via.as_ref()
+    }
+
+    pub fn must_match(&self) -> Option<&MustMatch> {
+        self.must_match.as_ref()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum CowCredentials {
+    Basic {
+        username: String,
+        password: String,
+    },
+    Header {
+        name: http::HeaderName,
+        value: http::HeaderValue,
+    },
+}
+
+impl CowCredentials {
+    pub fn new_basic(username: impl Into<String>, password: impl Into<String>) -> Result<Self> {
+        let username = username.into();
+        if username.is_empty() {
+            return Err(Error::ValidationError(anyhow!("username can't be empty")));
+        }
+        if username.contains(':') {
+            return Err(Error::ValidationError(anyhow!(
+                "username can't contain ':'"
+            )));
+        }
+        Ok(Self::Basic {
+            username,
+            password: password.into(),
+        })
+    }
+
+    pub fn new_header(name: &str, value: &str) -> Result<Self> {
+        let (name, value) = sensitive_header(name, value)?;
+        Ok(Self::Header { name, value })
+    }
+}
+
+// custom headers and header credentials both often carry api keys so all of them are treated as
+// sensitive
+fn sensitive_header(name: &str, value: &str) -> Result<(http::HeaderName, http::HeaderValue)> {
+    let name = http::HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| Error::ValidationError(anyhow!("invalid header name: {}", name)))?;
+    let mut value = http::HeaderValue::from_str(value)
+        .map_err(|_| Error::ValidationError(anyhow!("invalid value of the header: {}", name)))?;
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+// anything printing the config with {:?} must not leak the secrets
+impl fmt::Debug for CowCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CowCredentials::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            CowCredentials::Header { name, .. } => f
+                .debug_struct("Header")
+                .field("name", name)
+                .field("value", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+// tonic doesn't limit anything by default, a single client could otherwise tie up the whole server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcLimits {
+    concurrency_limit: usize,
+    max_concurrent_streams: u32,
+    tcp_keepalive: Option<std::time::Duration>,
+}
+
+impl GrpcLimits {
+    // the limit is shared by all connections, requests beyond it are rejected instead of waiting
+    // for one of the running requests
+    pub fn with_concurrency_limit(mut self, concurrency_limit: usize) -> Result<Self> {
+        if concurrency_limit == 0 {
+            return Err(Error::ValidationError(anyhow!(
+                "grpc concurrency_limit can't be zero"
+            )));
+        }
+        self.concurrency_limit = concurrency_limit;
+        Ok(self)
+    }
+
+    pub fn with_max_concurrent_streams(mut self, max_concurrent_streams: u32) -> Result<Self> {
+        if max_concurrent_streams == 0 {
+            return Err(Error::ValidationError(anyhow!(
+                "grpc max_concurrent_streams can't be zero"
+            )));
+        }
+        self.max_concurrent_streams = max_concurrent_streams;
+        Ok(self)
+    }
+
+    // none turns tcp keepalive off
+    pub fn with_tcp_keepalive(mut self, tcp_keepalive: Option<std::time::Duration>) -> Self {
+        self.tcp_keepalive = tcp_keepalive;
+        self
+    }
+
+    pub fn concurrency_limit(&self) -> usize {
+        self.concurrency_limit
+    }
+
+    pub fn max_concurrent_streams(&self) -> u32 {
+        self.max_concurrent_streams
+    }
+
+    pub fn tcp_keepalive(&self) -> Option<std::time::Duration> {
+        self.tcp_keepalive
+    }
+}
+
+impl Default for GrpcLimits {
+    fn default() -> Self {
+        Self {
+            concurrency_limit: DEFAULT_GRPC_CONCURRENCY_LIMIT,
+            max_concurrent_streams: DEFAULT_GRPC_MAX_CONCURRENT_STREAMS,
+            tcp_keepalive: Some(DEFAULT_GRPC_TCP_KEEPALIVE),
+        }
+    }
+}
+
+// the files are only read when the http server starts, replacing the certificate requires a restart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    cert_path: String,
+    key_path: String,
+    redirect_http_address: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn new(cert_path: impl Into<String>, key_path: impl Into<String>) -> Result<Self> {
+        let cert_path = cert_path.into();
+        if cert_path.is_empty() {
+            return Err(anyhow!("tls cert_path can't be empty").into());
+        }
+        let key_path = key_path.into();
+        if key_path.is_empty() {
+            return Err(anyhow!("tls key_path can't be empty").into());
+        }
+        Ok(Self {
+            cert_path,
+            key_path,
+            redirect_http_address: None,
+        })
+    }
+
+    // plain http requests arriving at this address are redirected to https
+    pub fn with_redirect_http_address(
+        mut self,
+        redirect_http_address: impl Into<String>,
+    ) -> Result<Self> {
+        let redirect_http_address = redirect_http_address.into();
+        if redirect_http_address.is_empty() {
+            return Err(anyhow!("tls redirect_http_address can't be empty").into());
+        }
+        self.redirect_http_address = Some(redirect_http_address);
+        Ok(self)
+    }
+
+    pub fn cert_path(&self) -> &str {
+        &self.cert_path
+    }
+
+    pub fn key_path(&self) -> &str {
+        &self.key_path
+    }
+
+    pub fn redirect_http_address(&self) -> Option<&str> {
+        self.redirect_http_address.as_deref()
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -58,3 +973,24 @@ pub enum Environment {
     Production,
     Development,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Redb,
+    Memory,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+    Compact,
+}
+
+// the casing of the fields in the responses of the HTTP API, snake_case unless configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonFieldNaming {
+    #[default]
+    SnakeCase,
+    CamelCase,
+}
@@ -0,0 +1,183 @@
+use crate::domain::time::{DateTime, Duration, TimeZone};
+use crate::errors::{Error, Result};
+use anyhow::anyhow;
+
+static SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+// updates only run within these hours, e.g. to keep the pager quiet at night. a window which ends
+// before it starts wraps around midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveHours {
+    start: u32,
+    end: u32,
+    offset_east_in_seconds: i32,
+}
+
+impl ActiveHours {
+    // the window is written as `08:00-20:00`, the offset as `+02:00` or `UTC`
+    pub fn new(window: &str, offset: Option<&str>) -> Result<Self> {
+        let invalid = || Error::ValidationError(anyhow!("invalid active hours: {}", window));
+
+        let (start, end) = window.split_once('-').ok_or_else(invalid)?;
+        let start = parse_time_of_day(start.trim()).ok_or_else(invalid)?;
+        let end = parse_time_of_day(end.trim()).ok_or_else(invalid)?;
+        if start == end {
+            return Err(Error::ValidationError(anyhow!(
+                "active hours can't start and end at the same time: {}",
+                window
+            )));
+        }
+        let offset_east_in_seconds = match offset {
+            None | Some("UTC") => 0,
+            Some(offset) => offset
+                .parse::<chrono::FixedOffset>()
+                .map_err(|_| {
+                    Error::ValidationError(anyhow!("invalid active hours timezone: {}", offset))
+                })?
+                .local_minus_utc(),
+        };
+        Ok(Self {
+            start,
+            end,
+            offset_east_in_seconds,
+        })
+    }
+
+    pub fn contains(&self, at: &DateTime) -> bool {
+        let timezone = if self.offset_east_in_seconds >= 0 {
+            TimeZone::OffsetEastInSeconds(self.offset_east_in_seconds as u64)
+        } else {
+            TimeZone::OffsetWestInSeconds(self.offset_east_in_seconds.unsigned_abs() as u64)
+        };
+        let at = at.in_timezone(timezone);
+        let minute = at.hour() * 60 + at.minute();
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+
+    // how long the cows were checked on between the two moments, the nights in between don't count
+    pub fn active_between(&self, from: &DateTime, to: &DateTime) -> Duration {
+        let active = self.active_since_epoch(to) - self.active_since_epoch(from);
+        Duration::new_from_seconds(active.max(0) as u64)
+    }
+
+    // counted in local days so that the window doesn't move with the offset
+    fn active_since_epoch(&self, at: &DateTime) -> i64 {
+        let local = at.unix_timestamp() + i64::from(self.offset_east_in_seconds);
+        local.div_euclid(SECONDS_PER_DAY) * self.active_within_day_until(SECONDS_PER_DAY)
+            + self.active_within_day_until(local.rem_euclid(SECONDS_PER_DAY))
+    }
+
+    fn active_within_day_until(&self, second_of_day: i64) -> i64 {
+        let start = i64::from(self.start) * 60;
+        let end = i64::from(self.end) * 60;
+        if start < end {
+            second_of_day.clamp(start, end) - start
+        } else {
+            second_of_day.min(end) + (second_of_day - start).max(0)
+        }
+    }
+}
+
+fn parse_time_of_day(s: &str) -> Option<u32> {
+    let (hour, minute) = s.split_once(':')?;
+    if hour.len() != 2 || minute.len() != 2 {
+        return None;
+    }
+    let hour: u32 = hour.parse().ok()?;
+    let minute: u32 = minute.parse().ok()?;
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> Result<DateTime> {
+        DateTime::new_from_str(&format!("2026-02-10 {time} +0000"), "%Y-%m-%d %H:%M:%S %z")
+    }
+
+    #[test]
+    fn contains_times_within_the_window() -> Result<()> {
+        let active_hours = ActiveHours::new("08:00-20:00", None)?;
+
+        assert!(active_hours.contains(&at("08:00:00")?));
+        assert!(active_hours.contains(&at("12:30:00")?));
+        assert!(!active_hours.contains(&at("20:00:00")?));
+        assert!(!active_hours.contains(&at("03:00:00")?));
+        Ok(())
+    }
+
+    #[test]
+    fn windows_can_wrap_around_midnight() -> Result<()> {
+        let active_hours = ActiveHours::new("22:00-06:00", None)?;
+
+        assert!(active_hours.contains(&at("23:00:00")?));
+        assert!(active_hours.contains(&at("05:59:59")?));
+        assert!(!active_hours.contains(&at("12:00:00")?));
+        Ok(())
+    }
+
+    #[test]
+    fn windows_are_checked_in_the_configured_timezone() -> Result<()> {
+        let active_hours = ActiveHours::new("08:00-20:00", Some("+02:00"))?;
+
+        assert!(active_hours.contains(&at("06:30:00")?));
+        assert!(!active_hours.contains(&at("18:30:00")?));
+
+        let active_hours = ActiveHours::new("08:00-20:00", Some("-05:00"))?;
+        assert!(!active_hours.contains(&at("12:00:00")?));
+        assert!(active_hours.contains(&at("13:00:00")?));
+        Ok(())
+    }
+
+    #[test]
+    fn only_active_time_is_counted_across_several_days() -> Result<()> {
+        let on = |day: u32, time: &str| {
+            DateTime::new_from_str(
+                &format!("2026-02-{day} {time} +0000"),
+                "%Y-%m-%d %H:%M:%S %z",
+            )
+        };
+
+        let active_hours = ActiveHours::new("08:00-20:00", None)?;
+        assert_eq!(
+            active_hours.active_between(&on(10, "19:00:00")?, &on(13, "09:00:00")?),
+            Duration::new_from_hours(1 + 12 + 12 + 1)
+        );
+        assert_eq!(
+            active_hours.active_between(&on(10, "21:00:00")?, &on(11, "07:00:00")?),
+            Duration::new_from_seconds(0)
+        );
+
+        let active_hours = ActiveHours::new("22:00-06:00", None)?;
+        assert_eq!(
+            active_hours.active_between(&on(10, "05:00:00")?, &on(12, "23:00:00")?),
+            Duration::new_from_hours(1 + 2 + 6 + 2 + 6 + 1)
+        );
+
+        let active_hours = ActiveHours::new("08:00-20:00", Some("+02:00"))?;
+        assert_eq!(
+            active_hours.active_between(&on(10, "05:00:00")?, &on(10, "07:00:00")?),
+            Duration::new_from_hours(1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_active_hours_are_rejected() {
+        for window in [
+            "08:00",
+            "8:00-20:00",
+            "08:00-24:00",
+            "08:60-20:00",
+            "08:00-08:00",
+        ] {
+            assert!(ActiveHours::new(window, None).is_err(), "{window}");
+        }
+        assert!(ActiveHours::new("08:00-20:00", Some("Europe/Warsaw")).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::domain::{Cow, VisibleName};
+use crate::errors::{Error, Result};
+use anyhow::anyhow;
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+
+// decides which cows are checked first, which matters once the request budget or the deadline
+// cuts an update short
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CheckOrder {
+    #[default]
+    Config,
+    Random,
+    LeastRecentlyChecked,
+}
+
+impl CheckOrder {
+    // cows which aren't in the config, e.g. discovered ones, are checked after the configured ones
+    pub fn sort(&self, cows: &mut [Cow], configured: &[VisibleName]) {
+        match self {
+            CheckOrder::Config => {
+                let positions: HashMap<&VisibleName, usize> = configured
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| (name, i))
+                    .collect();
+                cows.sort_by_key(|cow| positions.get(cow.name()).copied().unwrap_or(usize::MAX));
+            }
+            CheckOrder::Random => cows.shuffle(&mut rand::thread_rng()),
+            // cows which were never checked come first
+            CheckOrder::LeastRecentlyChecked => cows.sort_by_key(|cow| cow.last_checked().cloned()),
+        }
+    }
+}
+
+impl TryFrom<&str> for CheckOrder {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "config" => Ok(CheckOrder::Config),
+            "random" => Ok(CheckOrder::Random),
+            "lru" => Ok(CheckOrder::LeastRecentlyChecked),
+            other => Err(Error::ValidationError(anyhow!(
+                "invalid check order: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Character;
+    use crate::domain::time::{DateTime, Duration};
+
+    fn names(cows: &[Cow]) -> Vec<&str> {
+        cows.iter().map(|cow| cow.name().url().as_str()).collect()
+    }
+
+    fn cow(name: &str, last_checked: Option<DateTime>) -> Result<Cow> {
+        Ok(Cow::new_from_history(
+            VisibleName::new(name)?,
+            Character::Brave,
+            None,
+            None,
+            last_checked,
+        ))
+    }
+
+    #[test]
+    fn config_order_follows_the_config() -> Result<()> {
+        let mut cows = vec![
+            cow("https://a.example.com/cow.txt", None)?,
+            cow("https://discovered.example.com/cow.txt", None)?,
+            cow("https://b.example.com/cow.txt", None)?,
+            cow("https://c.example.com/cow.txt", None)?,
+        ];
+        let configured = [
+            VisibleName::new("https://c.example.com/cow.txt")?,
+            VisibleName::new("https://a.example.com/cow.txt")?,
+            VisibleName::new("https://b.example.com/cow.txt")?,
+        ];
+
+        CheckOrder::Config.sort(&mut cows, &configured);
+
+        assert_eq!(
+            names(&cows),
+            vec![
+                "https://c.example.com/cow.txt",
+                "https://a.example.com/cow.txt",
+                "https://b.example.com/cow.txt",
+                "https://discovered.example.com/cow.txt",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn least_recently_checked_cows_come_first() -> Result<()> {
+        let now = DateTime::now();
+        let mut cows = vec![
+            cow("https://recent.example.com/cow.txt", Some(now.clone()))?,
+            cow("https://never.example.com/cow.txt", None)?,
+            cow(
+                "https://old.example.com/cow.txt",
+                Some(&now - Duration::new_from_days(2)),
+            )?,
+            cow(
+                "https://older.example.com/cow.txt",
+                Some(&now - Duration::new_from_days(3)),
+            )?,
+        ];
+
+        CheckOrder::LeastRecentlyChecked.sort(&mut cows, &[]);
+
+        assert_eq!(
+            names(&cows),
+            vec![
+                "https://never.example.com/cow.txt",
+                "https://older.example.com/cow.txt",
+                "https://old.example.com/cow.txt",
+                "https://recent.example.com/cow.txt",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn random_order_keeps_every_cow() -> Result<()> {
+        let mut cows = (0..20)
+            .map(|i| cow(&format!("https://{i}.example.com/cow.txt"), None))
+            .collect::<Result<Vec<_>>>()?;
+        let mut orders = std::collections::HashSet::new();
+
+        for _ in 0..10 {
+            CheckOrder::Random.sort(&mut cows, &[]);
+            let mut sorted = names(&cows);
+            orders.insert(sorted.join(","));
+            sorted.sort();
+            sorted.dedup();
+            assert_eq!(sorted.len(), 20);
+        }
+        assert!(orders.len() > 1, "cows were never shuffled");
+        Ok(())
+    }
+
+    #[test]
+    fn check_order_is_parsed() -> Result<()> {
+        assert_eq!(
+            CheckOrder::try_from("lru")?,
+            CheckOrder::LeastRecentlyChecked
+        );
+        assert!(CheckOrder::try_from("alphabetical").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use crate::domain::time::{DateTime, Duration};
+
+static OPEN_AFTER_CONSECUTIVE_FAILURES: u32 = 5;
+static HALF_OPEN_AFTER_HOURS: u64 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitBreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitBreakerState {
+    pub fn all_variants() -> &'static [CircuitBreakerState] {
+        &[
+            CircuitBreakerState::Closed,
+            CircuitBreakerState::Open,
+            CircuitBreakerState::HalfOpen,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBreaker {
+    consecutive_failures: u32,
+    opened_at: Option<DateTime>,
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self, now: &DateTime) -> CircuitBreakerState {
+        match &self.opened_at {
+            None => CircuitBreakerState::Closed,
+            Some(opened_at) => {
+                if now - opened_at > Duration::new_from_hours(HALF_OPEN_AFTER_HOURS) {
+                    CircuitBreakerState::HalfOpen
+                } else {
+                    CircuitBreakerState::Open
+                }
+            }
+        }
+    }
+
+    pub fn allows_check(&self, now: &DateTime) -> bool {
+        self.state(now) != CircuitBreakerState::Open
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+    }
+
+    pub fn record_failure(&mut self, now: &DateTime) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        let probe_failed = self.state(now) == CircuitBreakerState::HalfOpen;
+        if probe_failed || self.consecutive_failures >= OPEN_AFTER_CONSECUTIVE_FAILURES {
+            self.opened_at = Some(now.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_opens_after_repeated_failures_and_recovers() {
+        let start = DateTime::now();
+        let mut breaker = CircuitBreaker::new();
+
+        for _ in 0..OPEN_AFTER_CONSECUTIVE_FAILURES - 1 {
+            breaker.record_failure(&start);
+            assert_eq!(breaker.state(&start), CircuitBreakerState::Closed);
+        }
+        breaker.record_failure(&start);
+        assert_eq!(breaker.state(&start), CircuitBreakerState::Open);
+        assert!(!breaker.allows_check(&start));
+
+        let later = &start + Duration::new_from_hours(HALF_OPEN_AFTER_HOURS + 1);
+        assert_eq!(breaker.state(&later), CircuitBreakerState::HalfOpen);
+        assert!(breaker.allows_check(&later));
+
+        breaker.record_failure(&later);
+        assert_eq!(breaker.state(&later), CircuitBreakerState::Open);
+
+        let even_later = &later + Duration::new_from_hours(HALF_OPEN_AFTER_HOURS + 1);
+        assert_eq!(breaker.state(&even_later), CircuitBreakerState::HalfOpen);
+
+        breaker.record_success();
+        assert_eq!(breaker.state(&even_later), CircuitBreakerState::Closed);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::domain::{Character, VisibleName};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DomainEvent {
+    CowAdded {
+        name: VisibleName,
+        character: Character,
+    },
+    CowRemoved {
+        name: VisibleName,
+    },
+    CharacterChanged {
+        name: VisibleName,
+        old_character: Character,
+        new_character: Character,
+    },
+    CowRanAway {
+        name: VisibleName,
+    },
+    CowReturned {
+        name: VisibleName,
+    },
+    CowPaused {
+        name: VisibleName,
+    },
+    CowResumed {
+        name: VisibleName,
+    },
+    CowContentChanged {
+        name: VisibleName,
+    },
+    HerdDegraded {
+        ran_away: usize,
+        checked: usize,
+    },
+    HerdRecovered {
+        ran_away: usize,
+        checked: usize,
+    },
+}
+
+impl DomainEvent {
+    // events which concern the whole herd don't have a name
+    pub fn name(&self) -> Option<&VisibleName> {
+        match self {
+            DomainEvent::CowAdded { name, .. } => Some(name),
+            DomainEvent::CowRemoved { name } => Some(name),
+            DomainEvent::CharacterChanged { name, .. } => Some(name),
+            DomainEvent::CowRanAway { name } => Some(name),
+            DomainEvent::CowReturned { name } => Some(name),
+            DomainEvent::CowPaused { name } => Some(name),
+            DomainEvent::CowResumed { name } => Some(name),
+            DomainEvent::CowContentChanged { name } => Some(name),
+            DomainEvent::HerdDegraded { .. } | DomainEvent::HerdRecovered { .. } => None,
+        }
+    }
+}
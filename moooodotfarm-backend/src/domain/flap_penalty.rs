@@ -0,0 +1,79 @@
+use crate::domain::{CHECK_COW_IF_NOT_CHECKED_FOR_HOURS, FLAPS_ARE_FORGOTTEN_AFTER_HOURS};
+use crate::errors::Result;
+use anyhow::anyhow;
+
+static DEFAULT_MAX_MULTIPLIER: u32 = 8;
+// a cow which ran away once and came back flapped twice, that alone isn't penalized
+static FLAPS_WITHOUT_PENALTY: u32 = 2;
+
+// a cow which keeps running away and coming back wastes checks and makes noise, every further flap
+// doubles its check interval until the multiplier reaches the cap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlapPenalty {
+    max_multiplier: u32,
+}
+
+impl FlapPenalty {
+    // a multiplier of 1 turns the penalty off
+    pub fn new(max_multiplier: u32) -> Result<Self> {
+        let max_max_multiplier = max_reachable_multiplier();
+        if !(1..=max_max_multiplier).contains(&max_multiplier) {
+            return Err(anyhow!(
+                "the maximum flap penalty multiplier must be between 1 and {} as flaps are forgotten before a larger one is reached",
+                max_max_multiplier
+            )
+            .into());
+        }
+        Ok(Self { max_multiplier })
+    }
+
+    pub fn max_multiplier(&self) -> u32 {
+        self.max_multiplier
+    }
+
+    pub fn multiplier(&self, recent_flaps: u32) -> u32 {
+        let doublings = recent_flaps.saturating_sub(FLAPS_WITHOUT_PENALTY);
+        2u32.checked_pow(doublings)
+            .unwrap_or(u32::MAX)
+            .min(self.max_multiplier)
+    }
+}
+
+// flaps are forgotten a while after the last one so the multiplier only doubles again while the
+// penalized check interval is shorter than that
+fn max_reachable_multiplier() -> u32 {
+    let mut multiplier: u32 = 1;
+    while CHECK_COW_IF_NOT_CHECKED_FOR_HOURS * u64::from(multiplier)
+        < FLAPS_ARE_FORGOTTEN_AFTER_HOURS
+    {
+        multiplier *= 2;
+    }
+    multiplier
+}
+
+impl Default for FlapPenalty {
+    fn default() -> Self {
+        Self {
+            max_multiplier: DEFAULT_MAX_MULTIPLIER,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_flap_doubles_the_multiplier_up_to_the_cap() -> Result<()> {
+        let penalty = FlapPenalty::new(8)?;
+        let multipliers: Vec<u32> = (0..8).map(|flaps| penalty.multiplier(flaps)).collect();
+        assert_eq!(multipliers, vec![1, 1, 1, 2, 4, 8, 8, 8]);
+        assert_eq!(penalty.multiplier(u32::MAX), 8);
+
+        assert_eq!(FlapPenalty::new(1)?.multiplier(10), 1);
+        assert!(FlapPenalty::new(0).is_err());
+        assert!(FlapPenalty::new(16).is_ok());
+        assert!(FlapPenalty::new(17).is_err());
+        Ok(())
+    }
+}
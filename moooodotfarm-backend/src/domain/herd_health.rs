@@ -0,0 +1,168 @@
+use crate::domain::Cow;
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::events::DomainEvent;
+use crate::domain::time::{DateTime, Duration};
+use crate::errors::{Error, Result};
+use anyhow::anyhow;
+
+// many cows running away at once usually means that something shared by them is down, e.g. a cdn,
+// rather than that all of them are gone
+#[derive(Debug, Clone)]
+pub struct DownRatioAlert {
+    threshold_percent: u32,
+    degraded: bool,
+}
+
+impl DownRatioAlert {
+    pub fn new(threshold_percent: u32) -> Result<Self> {
+        if !(1..=100).contains(&threshold_percent) {
+            return Err(Error::ValidationError(anyhow!(
+                "the herd degraded threshold must be between 1 and 100 percent"
+            )));
+        }
+        Ok(Self {
+            threshold_percent,
+            degraded: false,
+        })
+    }
+
+    pub fn degraded(&self) -> bool {
+        self.degraded
+    }
+
+    // only entering and leaving the degraded state produce an event so that a long outage doesn't
+    // produce one after every update
+    pub fn observe(&mut self, ran_away: usize, checked: usize) -> Option<DomainEvent> {
+        let degraded = checked > 0 && ran_away * 100 > checked * self.threshold_percent as usize;
+        if degraded == self.degraded {
+            return None;
+        }
+        self.degraded = degraded;
+        Some(if degraded {
+            DomainEvent::HerdDegraded { ran_away, checked }
+        } else {
+            DomainEvent::HerdRecovered { ran_away, checked }
+        })
+    }
+}
+
+// a cow which keeps waiting for its first check points at updates which don't run, e.g. a stalled
+// timer, rather than at a problem with the cow. the waiting starts when the cow is added and only
+// the active hours count as nobody checks on the cows outside of them
+#[derive(Debug, Clone)]
+pub struct UncheckedCows {
+    max_age: Duration,
+    active_hours: Option<ActiveHours>,
+}
+
+impl UncheckedCows {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            active_hours: None,
+        }
+    }
+
+    pub fn with_active_hours(mut self, active_hours: Option<ActiveHours>) -> Self {
+        self.active_hours = active_hours;
+        self
+    }
+
+    // paused cows aren't supposed to be checked so they are never overdue
+    pub fn is_overdue(&self, cow: &Cow, now: &DateTime) -> bool {
+        if cow.last_checked().is_some() || cow.paused() {
+            return false;
+        }
+        let waiting_for = match &self.active_hours {
+            Some(active_hours) => active_hours.active_between(cow.added_at(), now),
+            None => now - cow.added_at(),
+        };
+        waiting_for > self.max_age
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Character, VisibleName};
+
+    #[test]
+    fn alert_fires_when_entering_and_leaving_the_degraded_state() -> Result<()> {
+        let mut alert = DownRatioAlert::new(50)?;
+
+        assert_eq!(alert.observe(5, 10), None);
+        assert!(!alert.degraded());
+        assert_eq!(
+            alert.observe(6, 10),
+            Some(DomainEvent::HerdDegraded {
+                ran_away: 6,
+                checked: 10
+            })
+        );
+        assert_eq!(alert.observe(9, 10), None);
+        assert!(alert.degraded());
+        assert_eq!(
+            alert.observe(2, 10),
+            Some(DomainEvent::HerdRecovered {
+                ran_away: 2,
+                checked: 10
+            })
+        );
+        assert_eq!(alert.observe(0, 0), None);
+        assert!(!alert.degraded());
+        Ok(())
+    }
+
+    #[test]
+    fn cows_waiting_for_their_first_check_for_too_long_are_overdue() -> Result<()> {
+        let unchecked_cows = UncheckedCows::new(Duration::new_from_minutes(10));
+        let name = VisibleName::new("https://brave.example.com/cow.txt")?;
+        let added_at = DateTime::now() - Duration::new_from_minutes(11);
+        let unchecked = Cow::new(name.clone(), Character::Brave).with_added_at(added_at.clone());
+        let checked = Cow::new_from_history(
+            name.clone(),
+            Character::Brave,
+            None,
+            None,
+            Some(DateTime::now()),
+        )
+        .with_added_at(added_at.clone());
+
+        assert!(
+            !unchecked_cows.is_overdue(&unchecked, &(&added_at + Duration::new_from_minutes(9)))
+        );
+        assert!(unchecked_cows.is_overdue(&unchecked, &DateTime::now()));
+        assert!(!unchecked_cows.is_overdue(&unchecked.clone().with_paused(true), &DateTime::now()));
+        assert!(!unchecked_cows.is_overdue(&checked, &DateTime::now()));
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_active_hours_count_towards_being_overdue() -> Result<()> {
+        let on = |day: u32, time: &str| {
+            DateTime::new_from_str(
+                &format!("2026-02-{day} {time} +0000"),
+                "%Y-%m-%d %H:%M:%S %z",
+            )
+        };
+        let unchecked_cows = UncheckedCows::new(Duration::new_from_minutes(10))
+            .with_active_hours(Some(ActiveHours::new("08:00-20:00", None)?));
+        let cow = Cow::new(
+            VisibleName::new("https://brave.example.com/cow.txt")?,
+            Character::Brave,
+        )
+        .with_added_at(on(10, "19:55:00")?);
+
+        // the night in between doesn't count
+        assert!(!unchecked_cows.is_overdue(&cow, &on(11, "08:04:00")?));
+        assert!(unchecked_cows.is_overdue(&cow, &on(11, "08:06:00")?));
+        Ok(())
+    }
+
+    #[test]
+    fn threshold_must_be_a_percentage() {
+        assert!(DownRatioAlert::new(0).is_err());
+        assert!(DownRatioAlert::new(101).is_err());
+        assert!(DownRatioAlert::new(100).is_ok());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::errors::{Error, Result};
+use anyhow::anyhow;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix_length: u8,
+}
+
+impl IpNetwork {
+    pub fn new(address: IpAddr, prefix_length: u8) -> Result<Self> {
+        let max_prefix_length = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_length > max_prefix_length {
+            return Err(Error::Unknown(anyhow!(
+                "prefix length {} is too long for {}",
+                prefix_length,
+                address
+            )));
+        }
+        Ok(Self {
+            address,
+            prefix_length,
+        })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_length))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_length))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (address, prefix_length) = match s.split_once('/') {
+            Some((address, prefix_length)) => (
+                address,
+                Some(
+                    prefix_length
+                        .parse::<u8>()
+                        .map_err(|_| anyhow!("invalid prefix length in network: {}", s))?,
+                ),
+            ),
+            None => (s, None),
+        };
+        let address = address
+            .parse::<IpAddr>()
+            .map_err(|_| anyhow!("invalid address in network: {}", s))?;
+        let prefix_length = prefix_length.unwrap_or(match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        });
+        IpNetwork::new(address, prefix_length)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostPolicy {
+    allowed: Vec<IpNetwork>,
+    denied: Vec<IpNetwork>,
+}
+
+impl HostPolicy {
+    pub fn new(allowed: Vec<IpNetwork>, denied: Vec<IpNetwork>) -> Self {
+        Self { allowed, denied }
+    }
+
+    pub fn check(&self, ip: &IpAddr) -> Result<()> {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(*v6)),
+            IpAddr::V4(_) => *ip,
+        };
+        if self.allowed.iter().any(|network| network.contains(&ip)) {
+            return Ok(());
+        }
+        if self.denied.iter().any(|network| network.contains(&ip)) || !is_public(&ip) {
+            return Err(Error::Unknown(anyhow!(
+                "cow host address is not allowed: {}",
+                ip
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_v4(v4),
+        IpAddr::V6(v6) => is_public_v6(v6),
+    }
+}
+
+fn is_public_v4(ip: &Ipv4Addr) -> bool {
+    let shared_address_space = IpNetwork {
+        address: IpAddr::V4(Ipv4Addr::new(100, 64, 0, 0)),
+        prefix_length: 10,
+    };
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || shared_address_space.contains(&IpAddr::V4(*ip)))
+}
+
+fn is_public_v6(ip: &Ipv6Addr) -> bool {
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_policy_works() -> Result<()> {
+        struct HostPolicyTestCase {
+            name: &'static str,
+            policy: HostPolicy,
+            ip: &'static str,
+            expected_ok: bool,
+        }
+
+        let test_cases = vec![
+            HostPolicyTestCase {
+                name: "cloud metadata address",
+                policy: HostPolicy::default(),
+                ip: "169.254.169.254",
+                expected_ok: false,
+            },
+            HostPolicyTestCase {
+                name: "loopback",
+                policy: HostPolicy::default(),
+                ip: "127.0.0.1",
+                expected_ok: false,
+            },
+            HostPolicyTestCase {
+                name: "private",
+                policy: HostPolicy::default(),
+                ip: "10.1.2.3",
+                expected_ok: false,
+            },
+            HostPolicyTestCase {
+                name: "ipv6 loopback",
+                policy: HostPolicy::default(),
+                ip: "::1",
+                expected_ok: false,
+            },
+            HostPolicyTestCase {
+                name: "ipv4 mapped metadata address",
+                policy: HostPolicy::default(),
+                ip: "::ffff:169.254.169.254",
+                expected_ok: false,
+            },
+            HostPolicyTestCase {
+                name: "public",
+                policy: HostPolicy::default(),
+                ip: "93.184.216.34",
+                expected_ok: true,
+            },
+            HostPolicyTestCase {
+                name: "public ipv6",
+                policy: HostPolicy::default(),
+                ip: "2606:2800:220:1:248:1893:25c8:1946",
+                expected_ok: true,
+            },
+            HostPolicyTestCase {
+                name: "explicitly allowed private network",
+                policy: HostPolicy::new(vec!["10.0.0.0/8".parse()?], vec![]),
+                ip: "10.1.2.3",
+                expected_ok: true,
+            },
+            HostPolicyTestCase {
+                name: "explicitly denied public address",
+                policy: HostPolicy::new(vec![], vec!["93.184.216.0/24".parse()?]),
+                ip: "93.184.216.34",
+                expected_ok: false,
+            },
+        ];
+
+        for test_case in test_cases {
+            let ip: IpAddr = test_case.ip.parse().unwrap();
+            assert_eq!(
+                test_case.policy.check(&ip).is_ok(),
+                test_case.expected_ok,
+                "Failed for test case: {}",
+                test_case.name
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_networks_are_rejected() {
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("not an address/8".parse::<IpNetwork>().is_err());
+        assert!("10.0.0.0/x".parse::<IpNetwork>().is_err());
+    }
+}
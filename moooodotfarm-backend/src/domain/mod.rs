@@ -1,27 +1,75 @@
+pub mod active_hours;
+pub mod check_order;
+pub mod circuit_breaker;
+pub mod events;
+pub mod flap_penalty;
+pub mod herd_health;
+pub mod host_policy;
+pub mod redirect_policy;
+pub mod shard;
 pub mod time;
 
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::events::DomainEvent;
+use crate::domain::flap_penalty::FlapPenalty;
+use crate::domain::host_policy::HostPolicy;
 use crate::domain::time::{DateTime, Duration};
 use crate::errors::Error;
 use crate::errors::Result;
 use anyhow::anyhow;
+use publicsuffix::Psl;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
 const COW_BODY: &str = include_str!("../ports/http/static/cow.txt");
+// a copy of https://publicsuffix.org/list/public_suffix_list.dat
+const PUBLIC_SUFFIX_LIST_DAT: &str = include_str!("public_suffix_list.dat");
 
 const COW_SUFFIX: &str = "/cow.txt";
+const COW_SCHEMES: [&str; 2] = ["http", "https"];
 
 static CHECK_COW_IF_NOT_CHECKED_FOR_HOURS: u64 = 2;
 static CHECK_COW_WHICH_WAS_NEVER_SEEN_IF_NOT_CHECKED_FOR_MINUTES: u64 = 15;
+static REFRESH_COW_ON_DEMAND_IF_NOT_CHECKED_FOR_MINUTES: u64 = 1;
+static MAX_NICKNAME_LENGTH: usize = 64;
+static MAX_NOTES_LENGTH: usize = 1000;
+static MAX_TAG_LENGTH: usize = 32;
+static CONSISTENTLY_MISSING_AFTER_FAILED_CHECKS: u32 = 3;
+static FLAPS_ARE_FORGOTTEN_AFTER_HOURS: u64 = 24;
+
+lazy_static::lazy_static! {
+    static ref PUBLIC_SUFFIX_LIST: publicsuffix::List = PUBLIC_SUFFIX_LIST_DAT
+        .parse()
+        .expect("the public suffix list is valid");
+}
 
 #[derive(Debug, Clone)]
 pub struct Cow {
     name: VisibleName,
     character: Character,
+    expected_character: Option<Character>,
+    nickname: Option<Nickname>,
+    tags: Vec<Tag>,
+    notes: String,
+    added_at: DateTime,
     first_seen: Option<DateTime>,
     last_seen: Option<DateTime>,
+    body_hash: Option<BodyHash>,
     last_checked: Option<DateTime>,
+    failure_streak: u32,
+    flaps: u32,
+    last_flapped_at: Option<DateTime>,
+    redirected_to: Option<VisibleName>,
+    missing_reason: Option<MissingReason>,
+    paused: bool,
+    // only cows which come from the config are removed once they are no longer configured, cows
+    // added over grpc or discovered are left alone by reloads
+    managed_by_config: bool,
 }
 
 impl Cow {
@@ -29,9 +77,22 @@ impl Cow {
         Self {
             name,
             character,
+            expected_character: None,
+            nickname: None,
+            tags: vec![],
+            notes: String::new(),
+            added_at: DateTime::now(),
             first_seen: None,
             last_seen: None,
+            body_hash: None,
             last_checked: None,
+            failure_streak: 0,
+            flaps: 0,
+            last_flapped_at: None,
+            redirected_to: None,
+            missing_reason: None,
+            paused: false,
+            managed_by_config: false,
         }
     }
 
@@ -45,53 +106,314 @@ impl Cow {
         Self {
             name,
             character,
+            expected_character: None,
+            nickname: None,
+            tags: vec![],
+            notes: String::new(),
+            // the cow had to be added before it could be seen, see with_added_at
+            added_at: first_seen.clone().unwrap_or_else(DateTime::now),
             first_seen,
             last_seen,
+            body_hash: None,
             last_checked,
+            failure_streak: 0,
+            flaps: 0,
+            last_flapped_at: None,
+            redirected_to: None,
+            missing_reason: None,
+            paused: false,
+            managed_by_config: false,
         }
     }
 
-    pub fn should_check(&self) -> bool {
+    pub fn with_expected_character(mut self, expected_character: Option<Character>) -> Self {
+        self.expected_character = expected_character;
+        self
+    }
+
+    pub fn with_nickname(mut self, nickname: Option<Nickname>) -> Self {
+        self.nickname = nickname;
+        self
+    }
+
+    pub fn with_tags(mut self, tags: Vec<Tag>) -> Self {
+        self.tags = normalize_tags(tags);
+        self
+    }
+
+    pub fn with_added_at(mut self, added_at: DateTime) -> Self {
+        self.added_at = added_at;
+        self
+    }
+
+    // restores notes which were validated when they were set, see change_notes
+    pub fn with_notes(mut self, notes: impl Into<String>) -> Self {
+        self.notes = notes.into();
+        self
+    }
+
+    pub fn with_body_hash(mut self, body_hash: Option<BodyHash>) -> Self {
+        self.body_hash = body_hash;
+        self
+    }
+
+    pub fn with_failure_streak(mut self, failure_streak: u32) -> Self {
+        self.failure_streak = failure_streak;
+        self
+    }
+
+    // for cows recorded before failed checks were counted, all that is known is whether the last
+    // check failed. if it did the cow may well have been gone for days so it is assumed to be
+    // consistently missing rather than having just failed its first check
+    pub fn with_unrecorded_failure_streak(mut self) -> Self {
+        let last_check_failed = self.last_checked.is_some() && self.last_seen != self.last_checked;
+        self.failure_streak = if last_check_failed {
+            CONSISTENTLY_MISSING_AFTER_FAILED_CHECKS
+        } else {
+            0
+        };
+        self
+    }
+
+    pub fn with_flaps(mut self, flaps: u32, last_flapped_at: Option<DateTime>) -> Self {
+        self.flaps = flaps;
+        self.last_flapped_at = last_flapped_at;
+        self
+    }
+
+    pub fn with_redirected_to(mut self, redirected_to: Option<VisibleName>) -> Self {
+        self.redirected_to = redirected_to;
+        self
+    }
+
+    pub fn with_missing_reason(mut self, missing_reason: Option<MissingReason>) -> Self {
+        self.missing_reason = missing_reason;
+        self
+    }
+
+    pub fn with_paused(mut self, paused: bool) -> Self {
+        self.paused = paused;
+        self
+    }
+
+    pub fn with_managed_by_config(mut self, managed_by_config: bool) -> Self {
+        self.managed_by_config = managed_by_config;
+        self
+    }
+
+    // a paused cow is left alone until it is resumed, e.g. while its server is under maintenance
+    pub fn should_check(&self, flap_penalty: &FlapPenalty) -> bool {
+        if self.paused {
+            return false;
+        }
         if let Some(last_checked) = &self.last_checked {
-            let duration = if self.first_seen.is_none() {
-                Duration::new_from_minutes(
-                    CHECK_COW_WHICH_WAS_NEVER_SEEN_IF_NOT_CHECKED_FOR_MINUTES,
-                )
-            } else {
-                Duration::new_from_hours(CHECK_COW_IF_NOT_CHECKED_FOR_HOURS)
-            };
-            return &DateTime::now() - last_checked > duration;
+            return &DateTime::now() - last_checked > self.check_interval(flap_penalty);
         }
         true
     }
 
-    pub fn mark_as_ok(&mut self) {
-        let now = DateTime::now();
+    // a cow which was never checked is due right away, an overdue cow returns a time in the past
+    // and a paused cow isn't due at all
+    pub fn next_check_at(&self, flap_penalty: &FlapPenalty) -> Option<DateTime> {
+        if self.paused {
+            return None;
+        }
+        Some(match &self.last_checked {
+            Some(last_checked) => last_checked + self.check_interval(flap_penalty),
+            None => DateTime::now(),
+        })
+    }
+
+    pub fn check_interval(&self, flap_penalty: &FlapPenalty) -> Duration {
+        self.base_check_interval() * flap_penalty.multiplier(self.recent_flaps(&DateTime::now()))
+    }
 
+    fn base_check_interval(&self) -> Duration {
         if self.first_seen.is_none() {
-            self.first_seen = Some(now.clone());
+            Duration::new_from_minutes(CHECK_COW_WHICH_WAS_NEVER_SEEN_IF_NOT_CHECKED_FOR_MINUTES)
+        } else {
+            Duration::new_from_hours(CHECK_COW_IF_NOT_CHECKED_FOR_HOURS)
         }
+    }
 
-        self.last_seen = Some(now.clone());
-        self.last_checked = Some(now.clone());
+    // a cow which stayed either present or missing for a while is stable again
+    pub fn recent_flaps(&self, now: &DateTime) -> u32 {
+        match &self.last_flapped_at {
+            Some(last_flapped_at)
+                if now - last_flapped_at
+                    < Duration::new_from_hours(FLAPS_ARE_FORGOTTEN_AFTER_HOURS) =>
+            {
+                self.flaps
+            }
+            _ => 0,
+        }
     }
 
-    pub fn mark_as_missing(&mut self) {
-        let now = DateTime::now();
-        self.last_checked = Some(now.clone());
+    // a flap is a check whose result differs from the one before it
+    fn record_check_result(&mut self, succeeded: bool, at: &DateTime) {
+        if self.last_check_succeeded() == Some(!succeeded) {
+            self.flaps = self.recent_flaps(at).saturating_add(1);
+            self.last_flapped_at = Some(at.clone());
+        }
     }
 
-    pub fn change_character(&mut self, new_character: Character) -> Result<()> {
-        if self.character == new_character {
-            return Err(Error::Unknown(anyhow!(
-                "cow already has the character: {:?}",
-                new_character
-            )));
+    pub fn can_refresh_on_demand(&self) -> bool {
+        match &self.last_checked {
+            Some(last_checked) => {
+                &DateTime::now() - last_checked
+                    > Duration::new_from_minutes(REFRESH_COW_ON_DEMAND_IF_NOT_CHECKED_FOR_MINUTES)
+            }
+            None => true,
+        }
+    }
+
+    pub fn apply(&mut self, transition: CowTransition) -> Result<()> {
+        match transition {
+            CowTransition::CheckSucceeded(at) => {
+                self.record_check_result(true, &at);
+                if self.first_seen.is_none() {
+                    self.first_seen = Some(at.clone());
+                }
+                self.last_seen = Some(at.clone());
+                self.last_checked = Some(at);
+                self.failure_streak = 0;
+                self.missing_reason = None;
+            }
+            CowTransition::CheckFailed(at) => {
+                self.record_check_result(false, &at);
+                self.last_checked = Some(at);
+                self.failure_streak = self.failure_streak.saturating_add(1);
+                self.missing_reason = None;
+            }
+            CowTransition::CharacterChanged(new_character) => {
+                if self.character == new_character {
+                    return Err(Error::Unknown(anyhow!(
+                        "cow already has the character: {:?}",
+                        new_character
+                    )));
+                }
+                self.character = new_character;
+            }
+            CowTransition::NicknameChanged(new_nickname) => {
+                if self.nickname == new_nickname {
+                    return Err(Error::Unknown(anyhow!(
+                        "cow already has the nickname: {:?}",
+                        new_nickname
+                    )));
+                }
+                self.nickname = new_nickname;
+            }
+            CowTransition::NotesChanged(new_notes) => {
+                let new_notes = validate_notes(new_notes)?;
+                if self.notes == new_notes {
+                    return Err(Error::Unknown(anyhow!("cow already has these notes")));
+                }
+                self.notes = new_notes;
+            }
+            CowTransition::TagsChanged(new_tags) => {
+                let new_tags = normalize_tags(new_tags);
+                if self.tags == new_tags {
+                    return Err(Error::Unknown(anyhow!(
+                        "cow already has the tags: {:?}",
+                        new_tags
+                    )));
+                }
+                self.tags = new_tags;
+            }
+            CowTransition::Paused => {
+                if self.paused {
+                    return Err(Error::Unknown(anyhow!("cow is already paused")));
+                }
+                self.paused = true;
+            }
+            CowTransition::Resumed => {
+                if !self.paused {
+                    return Err(Error::Unknown(anyhow!("cow isn't paused")));
+                }
+                self.paused = false;
+            }
         }
-        self.character = new_character;
         Ok(())
     }
 
+    pub fn mark_as_ok(&mut self) -> Result<()> {
+        self.apply(CowTransition::CheckSucceeded(DateTime::now()))
+    }
+
+    // only set when the cow is identified by the url it was finally found at, a cow which stops
+    // redirecting goes back to being shown under its own name
+    pub fn mark_as_found_at(&mut self, redirected_to: Option<VisibleName>) -> Result<()> {
+        self.mark_as_ok()?;
+        self.redirected_to = redirected_to.filter(|url| url != &self.name);
+        Ok(())
+    }
+
+    // the hash outlives failed checks so that a cow which comes back with a different body is
+    // noticed as well
+    pub fn record_body_hash(&mut self, body_hash: BodyHash) {
+        self.body_hash = Some(body_hash);
+    }
+
+    pub fn mark_as_missing(&mut self) -> Result<()> {
+        self.apply(CowTransition::CheckFailed(DateTime::now()))
+    }
+
+    // a domain which no longer resolves usually means that the cow is gone for good rather than
+    // that its server is down for a while
+    pub fn mark_as_domain_not_found(&mut self) -> Result<()> {
+        self.mark_as_missing_because(MissingReason::DnsError)
+    }
+
+    pub fn mark_as_missing_because(&mut self, reason: MissingReason) -> Result<()> {
+        self.apply(CowTransition::CheckFailed(DateTime::now()))?;
+        self.missing_reason = Some(reason);
+        Ok(())
+    }
+
+    pub fn change_character(&mut self, new_character: Character) -> Result<()> {
+        self.apply(CowTransition::CharacterChanged(new_character))
+    }
+
+    // the expectation only produces a warning, the character itself is left alone
+    pub fn set_expected_character(&mut self, expected_character: Option<Character>) {
+        self.expected_character = expected_character;
+    }
+
+    pub fn change_nickname(&mut self, new_nickname: Option<Nickname>) -> Result<()> {
+        self.apply(CowTransition::NicknameChanged(new_nickname))
+    }
+
+    pub fn change_tags(&mut self, new_tags: Vec<Tag>) -> Result<()> {
+        self.apply(CowTransition::TagsChanged(new_tags))
+    }
+
+    // empty notes remove them
+    pub fn change_notes(&mut self, new_notes: impl Into<String>) -> Result<()> {
+        self.apply(CowTransition::NotesChanged(new_notes.into()))
+    }
+
+    pub fn pause(&mut self) -> Result<()> {
+        self.apply(CowTransition::Paused)
+    }
+
+    pub fn resume(&mut self) -> Result<()> {
+        self.apply(CowTransition::Resumed)
+    }
+
+    pub fn last_check_succeeded(&self) -> Option<bool> {
+        self.last_checked
+            .as_ref()
+            .map(|last_checked| self.last_seen.as_ref() == Some(last_checked))
+    }
+
+    pub fn check_status(&self) -> CheckStatus {
+        match self.last_check_succeeded() {
+            None => CheckStatus::NotCheckedYet,
+            Some(true) => CheckStatus::Present,
+            Some(false) => CheckStatus::Missing,
+        }
+    }
+
     pub fn name(&self) -> &VisibleName {
         &self.name
     }
@@ -100,6 +422,36 @@ impl Cow {
         &self.character
     }
 
+    pub fn expected_character(&self) -> Option<&Character> {
+        self.expected_character.as_ref()
+    }
+
+    pub fn has_unexpected_character(&self) -> bool {
+        self.expected_character
+            .as_ref()
+            .is_some_and(|expected| expected != &self.character)
+    }
+
+    pub fn nickname(&self) -> Option<&Nickname> {
+        self.nickname.as_ref()
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    pub fn body_hash(&self) -> Option<&BodyHash> {
+        self.body_hash.as_ref()
+    }
+
+    pub fn added_at(&self) -> &DateTime {
+        &self.added_at
+    }
+
     pub fn first_seen(&self) -> Option<&DateTime> {
         self.first_seen.as_ref()
     }
@@ -111,6 +463,82 @@ impl Cow {
     pub fn last_checked(&self) -> Option<&DateTime> {
         self.last_checked.as_ref()
     }
+
+    pub fn failure_streak(&self) -> u32 {
+        self.failure_streak
+    }
+
+    pub fn flaps(&self) -> u32 {
+        self.flaps
+    }
+
+    pub fn last_flapped_at(&self) -> Option<&DateTime> {
+        self.last_flapped_at.as_ref()
+    }
+
+    pub fn redirected_to(&self) -> Option<&VisibleName> {
+        self.redirected_to.as_ref()
+    }
+
+    pub fn domain_not_found(&self) -> bool {
+        self.missing_reason == Some(MissingReason::DnsError)
+    }
+
+    // only known for cows whose last check failed
+    pub fn missing_reason(&self) -> Option<MissingReason> {
+        self.missing_reason
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn managed_by_config(&self) -> bool {
+        self.managed_by_config
+    }
+
+    // the name under which the cow is shown and censored
+    pub fn shown_name(&self) -> &VisibleName {
+        self.redirected_to.as_ref().unwrap_or(&self.name)
+    }
+}
+
+// why the last check of a cow failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingReason {
+    HttpStatus(u16),
+    Timeout,
+    DnsError,
+    ValidationFailed,
+    ConnectionRefused,
+}
+
+impl MissingReason {
+    // other failures, e.g. a refused address, don't tell the owner of the cow anything useful
+    pub fn from_error(err: &Error) -> Option<Self> {
+        match err {
+            Error::UnexpectedHttpStatus(status) => Some(MissingReason::HttpStatus(*status)),
+            Error::Timeout(_) => Some(MissingReason::Timeout),
+            Error::DomainNotFound(_) => Some(MissingReason::DnsError),
+            Error::CowIsNotPresent(_) | Error::CowDoesNotMatch(_) => {
+                Some(MissingReason::ValidationFailed)
+            }
+            Error::ConnectionRefused(_) => Some(MissingReason::ConnectionRefused),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CowTransition {
+    CheckSucceeded(DateTime),
+    CheckFailed(DateTime),
+    CharacterChanged(Character),
+    NicknameChanged(Option<Nickname>),
+    TagsChanged(Vec<Tag>),
+    NotesChanged(String),
+    Paused,
+    Resumed,
 }
 
 impl fmt::Display for Cow {
@@ -119,7 +547,7 @@ impl fmt::Display for Cow {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct VisibleName {
     url: url::Url,
 }
@@ -128,7 +556,7 @@ impl VisibleName {
     pub fn new(s: impl Into<String>) -> Result<Self> {
         let url = url::Url::parse(&s.into())?;
         if !url.path().ends_with(COW_SUFFIX) {
-            return Err(Error::Unknown(anyhow!(
+            return Err(Error::ValidationError(anyhow!(
                 "cow must have a tail and end with '{}'",
                 COW_SUFFIX
             )));
@@ -136,11 +564,225 @@ impl VisibleName {
         Ok(Self { url })
     }
 
+    // people tend to paste the address of their website rather than of their cow, the cow is
+    // then expected to live at its root. a path pointing at some other file is still rejected
+    pub fn from_base(s: &str) -> Result<Self> {
+        let mut url = url::Url::parse(s.trim())?;
+        let points_at_file = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .is_some_and(|segment| segment.contains('.'));
+        if !points_at_file {
+            let path = format!("{}{}", url.path().trim_end_matches('/'), COW_SUFFIX);
+            url.set_path(&path);
+        }
+        Self::new(url.as_str())
+    }
+
     pub fn url(&self) -> &url::Url {
         &self.url
     }
 }
 
+// checks everything that can be checked without fetching the cow, hosts given by name are
+// only checked against the host policy once they resolve
+pub fn validate_cow_url(s: &str, host_policy: &HostPolicy) -> Result<VisibleName> {
+    validate_cow_name(VisibleName::new(s)?, host_policy)
+}
+
+// same as validate_cow_url but accepts urls missing the cow.txt, see VisibleName::from_base
+pub fn validate_cow_base_url(s: &str, host_policy: &HostPolicy) -> Result<VisibleName> {
+    validate_cow_name(VisibleName::from_base(s)?, host_policy)
+}
+
+fn validate_cow_name(name: VisibleName, host_policy: &HostPolicy) -> Result<VisibleName> {
+    let url = name.url();
+    if !COW_SCHEMES.contains(&url.scheme()) {
+        return Err(Error::ValidationError(anyhow!(
+            "cow must be served over {}, not {}",
+            COW_SCHEMES.join(" or "),
+            url.scheme()
+        )));
+    }
+    let ip = match url.host() {
+        Some(url::Host::Ipv4(ip)) => Some(std::net::IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => Some(std::net::IpAddr::V6(ip)),
+        Some(url::Host::Domain(_)) => None,
+        None => return Err(Error::ValidationError(anyhow!("cow must have a host"))),
+    };
+    if let Some(ip) = ip {
+        host_policy
+            .check(&ip)
+            .map_err(|err| Error::ValidationError(anyhow!(err)))?;
+    }
+    Ok(name)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nickname {
+    nickname: String,
+}
+
+impl Nickname {
+    pub fn new(s: impl Into<String>) -> Result<Self> {
+        let nickname = s.into().trim().to_string();
+        if nickname.is_empty() {
+            return Err(Error::ValidationError(anyhow!("nickname can't be empty")));
+        }
+        if nickname.chars().count() > MAX_NICKNAME_LENGTH {
+            return Err(Error::ValidationError(anyhow!(
+                "nickname can't be longer than {} characters",
+                MAX_NICKNAME_LENGTH
+            )));
+        }
+        if nickname.chars().any(char::is_control) {
+            return Err(Error::ValidationError(anyhow!(
+                "nickname can't contain control characters"
+            )));
+        }
+        Ok(Self { nickname })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.nickname
+    }
+}
+
+impl Display for Nickname {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.nickname)
+    }
+}
+
+// tags end up as metric labels so they are kept short and simple to keep the cardinality sane
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct Tag {
+    tag: String,
+}
+
+impl Tag {
+    pub fn new(s: impl Into<String>) -> Result<Self> {
+        let tag = s.into().trim().to_string();
+        if tag.is_empty() {
+            return Err(Error::ValidationError(anyhow!("tag can't be empty")));
+        }
+        if tag.len() > MAX_TAG_LENGTH {
+            return Err(Error::ValidationError(anyhow!(
+                "tag can't be longer than {} characters",
+                MAX_TAG_LENGTH
+            )));
+        }
+        if !tag
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(Error::ValidationError(anyhow!(
+                "tag can only contain lowercase letters, digits, '-' and '_': {}",
+                tag
+            )));
+        }
+        Ok(Self { tag })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.tag
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tag)
+    }
+}
+
+// only the hash of a body is kept, it is enough to tell that someone edited the cow
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BodyHash {
+    hash: String,
+}
+
+impl BodyHash {
+    pub fn new(s: impl Into<String>) -> Result<Self> {
+        let hash = s.into();
+        if hash.len() != 64 || !hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+            return Err(Error::ValidationError(anyhow!(
+                "body hash must be a lowercase hex encoded sha256 hash: {}",
+                hash
+            )));
+        }
+        Ok(Self { hash })
+    }
+
+    pub fn of(content: &str) -> Self {
+        Self {
+            hash: format!("{:x}", Sha256::digest(content.as_bytes())),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.hash
+    }
+}
+
+impl Display for BodyHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.hash)
+    }
+}
+
+// notes span multiple lines but are otherwise held to the same rules as nicknames
+fn validate_notes(notes: String) -> Result<String> {
+    let notes = notes.trim().to_string();
+    if notes.chars().count() > MAX_NOTES_LENGTH {
+        return Err(Error::ValidationError(anyhow!(
+            "notes can't be longer than {} characters",
+            MAX_NOTES_LENGTH
+        )));
+    }
+    if notes
+        .chars()
+        .any(|c| c.is_control() && c != '\n' && c != '\t')
+    {
+        return Err(Error::ValidationError(anyhow!(
+            "notes can't contain control characters"
+        )));
+    }
+    Ok(notes)
+}
+
+fn normalize_tags(mut tags: Vec<Tag>) -> Vec<Tag> {
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+// masking every label of a shy cow's host separately still gives away how many subdomains it has
+// and how long they are, collapsing them hides that as well. keeping the registrable domain only
+// hides the subdomains so a shy cow living directly on its registrable domain isn't hidden at all
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HostCensoring {
+    #[default]
+    PerLabel,
+    Collapsed,
+    KeepRegistrableDomain,
+}
+
+impl TryFrom<&str> for HostCensoring {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "per_label" => Ok(HostCensoring::PerLabel),
+            "collapsed" => Ok(HostCensoring::Collapsed),
+            "registrable_domain" => Ok(HostCensoring::KeepRegistrableDomain),
+            other => Err(Error::ValidationError(anyhow!(
+                "invalid host censoring: {}",
+                other
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CensoredName {
     url: String,
@@ -149,40 +791,77 @@ pub struct CensoredName {
 const CENSORSHIP_CHARACTER: char = '*';
 const PATH_SEPARATOR: char = '/';
 const DOMAIN_SEPARATOR: char = '.';
+const COLLAPSED_HOST_MASK_LENGTH: usize = 7;
 
 impl CensoredName {
     pub fn new(cow: &Cow) -> Result<Self> {
+        Self::new_with_host_censoring(cow, HostCensoring::default())
+    }
+
+    pub fn new_with_host_censoring(cow: &Cow, host_censoring: HostCensoring) -> Result<Self> {
         if cow.character == Character::Brave {
             return Ok(Self {
-                url: cow.name().url().to_string(),
+                url: cow.shown_name().url().to_string(),
             });
         }
 
-        let url = cow.name().url();
+        let url = cow.shown_name().url();
         let port_with_colon = url.port().map(|p| format!(":{}", p)).unwrap_or_default();
 
         let censored_url = format!(
             "{}://{}{}{}",
             url.scheme(),
-            Self::censor_host(url)?,
+            Self::censor_host(url, host_censoring)?,
             port_with_colon,
             Self::censor_path(url)?,
         );
         Ok(Self { url: censored_url })
     }
 
-    fn censor_host(url: &url::Url) -> Result<String> {
+    fn censor_host(url: &url::Url, host_censoring: HostCensoring) -> Result<String> {
         let host = url
             .host_str()
             .ok_or_else(|| Error::Unknown(anyhow!("no host in url")))?;
 
+        if host_censoring == HostCensoring::KeepRegistrableDomain {
+            // hosts without a registrable domain, e.g. ones on the local network, are censored as
+            // usual
+            if let Some(censored_host) = Self::censor_subdomains(host) {
+                return Ok(censored_host);
+            }
+        }
+
         let last_dot_pos = host
             .rfind(DOMAIN_SEPARATOR)
             .ok_or_else(|| Error::Unknown(anyhow!("no TLD found in host")))?;
 
         let (before_tld, tld_with_dot) = host.split_at(last_dot_pos);
 
-        let censored_before: String = before_tld
+        let censored_before: String = match host_censoring {
+            HostCensoring::PerLabel | HostCensoring::KeepRegistrableDomain => {
+                Self::censor_labels(before_tld)
+            }
+            HostCensoring::Collapsed => {
+                std::iter::repeat_n(CENSORSHIP_CHARACTER, COLLAPSED_HOST_MASK_LENGTH).collect()
+            }
+        };
+
+        Ok(format!("{}{}", censored_before, tld_with_dot))
+    }
+
+    fn censor_subdomains(host: &str) -> Option<String> {
+        let registrable_domain = PUBLIC_SUFFIX_LIST.domain(host.as_bytes())?;
+        let registrable_domain = std::str::from_utf8(registrable_domain.as_bytes()).ok()?;
+        let subdomains = host.strip_suffix(registrable_domain)?;
+        Some(format!(
+            "{}{}",
+            Self::censor_labels(subdomains),
+            registrable_domain
+        ))
+    }
+
+    fn censor_labels(labels: &str) -> String {
+        labels
             .chars()
             .map(|c| {
                 if c == DOMAIN_SEPARATOR {
@@ -191,9 +870,7 @@ impl CensoredName {
                     CENSORSHIP_CHARACTER
                 }
             })
-            .collect();
-
-        Ok(format!("{}{}", censored_before, tld_with_dot))
+            .collect()
     }
 
     fn censor_path(url: &url::Url) -> Result<String> {
@@ -230,9 +907,30 @@ pub enum Name {
 
 impl Name {
     pub fn new(cow: &Cow) -> Result<Self> {
+        Self::new_with_host_censoring(cow, HostCensoring::default())
+    }
+
+    pub fn new_with_host_censoring(cow: &Cow, host_censoring: HostCensoring) -> Result<Self> {
+        match CensorReason::new(cow) {
+            CensorReason::Visible => Ok(Name::Visible(cow.shown_name().clone())),
+            CensorReason::CensoredByCharacter => Ok(Name::Censored(
+                CensoredName::new_with_host_censoring(cow, host_censoring)?,
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CensorReason {
+    Visible,
+    CensoredByCharacter,
+}
+
+impl CensorReason {
+    pub fn new(cow: &Cow) -> Self {
         match cow.character() {
-            Character::Brave => Ok(Name::Visible(cow.name().clone())),
-            Character::Shy => Ok(Name::Censored(CensoredName::new(cow)?)),
+            Character::Brave => CensorReason::Visible,
+            Character::Shy => CensorReason::CensoredByCharacter,
         }
     }
 }
@@ -246,42 +944,169 @@ pub enum Character {
 #[derive(Clone)]
 pub struct CensoredCow {
     name: Name,
+    censor_reason: CensorReason,
     character: Character,
+    unexpected_character: bool,
+    nickname: Option<Nickname>,
+    tags: Vec<Tag>,
+    notes: String,
+    added_at: DateTime,
     first_seen: Option<DateTime>,
     last_seen: Option<DateTime>,
     last_checked: Option<DateTime>,
+    next_check_at: Option<DateTime>,
+    check_interval: Duration,
+    base_check_interval: Duration,
+    recent_flaps: u32,
+    failure_streak: u32,
+    missing_reason: Option<MissingReason>,
+    paused: bool,
+    active_hours: Option<ActiveHours>,
+    first_check_overdue: bool,
 }
 
 impl CensoredCow {
     pub fn new(cow: &Cow) -> Result<Self> {
+        Self::new_with_host_censoring(cow, HostCensoring::default())
+    }
+
+    pub fn new_with_host_censoring(cow: &Cow, host_censoring: HostCensoring) -> Result<Self> {
         Ok(Self {
-            name: Name::new(cow)?,
+            name: Name::new_with_host_censoring(cow, host_censoring)?,
+            censor_reason: CensorReason::new(cow),
             character: cow.character().clone(),
+            unexpected_character: cow.has_unexpected_character(),
+            // a nickname could easily give away who is behind a shy cow
+            nickname: match CensorReason::new(cow) {
+                CensorReason::Visible => cow.nickname.clone(),
+                CensorReason::CensoredByCharacter => None,
+            },
+            // tags group cows by team or environment which narrows down who is behind a shy cow
+            tags: match CensorReason::new(cow) {
+                CensorReason::Visible => cow.tags.clone(),
+                CensorReason::CensoredByCharacter => vec![],
+            },
+            // notes are written by whoever runs the farm but tend to say who owns the cow
+            notes: match CensorReason::new(cow) {
+                CensorReason::Visible => cow.notes.clone(),
+                CensorReason::CensoredByCharacter => String::new(),
+            },
+            added_at: cow.added_at.clone(),
             first_seen: cow.first_seen.clone(),
             last_seen: cow.last_seen.clone(),
             last_checked: cow.last_checked.clone(),
+            next_check_at: cow.next_check_at(&FlapPenalty::default()),
+            check_interval: cow.check_interval(&FlapPenalty::default()),
+            base_check_interval: cow.base_check_interval(),
+            recent_flaps: cow.recent_flaps(&DateTime::now()),
+            failure_streak: cow.failure_streak,
+            missing_reason: cow.missing_reason,
+            paused: cow.paused,
+            active_hours: None,
+            first_check_overdue: false,
         })
     }
 
+    // nobody checks on the cow outside of the active hours so that time doesn't count against it
+    pub fn with_active_hours(mut self, active_hours: Option<&ActiveHours>) -> Self {
+        self.active_hours = active_hours.copied();
+        self
+    }
+
+    // the penalty is configured by whoever runs the farm, see Cow::check_interval
+    pub fn with_flap_penalty(mut self, flap_penalty: &FlapPenalty) -> Self {
+        self.check_interval =
+            self.base_check_interval.clone() * flap_penalty.multiplier(self.recent_flaps);
+        if let (Some(_), Some(last_checked)) = (&self.next_check_at, &self.last_checked) {
+            self.next_check_at = Some(last_checked + &self.check_interval);
+        }
+        self
+    }
+
+    // only whoever schedules the checks knows whether the cow should have been checked by now
+    pub fn with_first_check_overdue(mut self, first_check_overdue: bool) -> Self {
+        self.first_check_overdue = first_check_overdue;
+        self
+    }
+
     pub fn name(&self) -> &Name {
         &self.name
     }
 
+    pub fn censor_reason(&self) -> CensorReason {
+        self.censor_reason
+    }
+
     pub fn character(&self) -> &Character {
         &self.character
     }
 
-    pub fn first_seen(&self) -> Option<&DateTime> {
-        self.first_seen.as_ref()
+    pub fn unexpected_character(&self) -> bool {
+        self.unexpected_character
     }
 
-    pub fn last_seen(&self) -> Option<&DateTime> {
+    pub fn nickname(&self) -> Option<&Nickname> {
+        self.nickname.as_ref()
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    pub fn added_at(&self) -> &DateTime {
+        &self.added_at
+    }
+
+    pub fn first_seen(&self) -> Option<&DateTime> {
+        self.first_seen.as_ref()
+    }
+
+    pub fn last_seen(&self) -> Option<&DateTime> {
         self.last_seen.as_ref()
     }
 
     pub fn last_checked(&self) -> Option<&DateTime> {
         self.last_checked.as_ref()
     }
+
+    // none while the cow is paused
+    pub fn next_check_at(&self) -> Option<&DateTime> {
+        self.next_check_at.as_ref()
+    }
+
+    // includes the penalty for flapping
+    pub fn check_interval(&self) -> &Duration {
+        &self.check_interval
+    }
+
+    pub fn domain_not_found(&self) -> bool {
+        self.missing_reason == Some(MissingReason::DnsError)
+    }
+
+    pub fn missing_reason(&self) -> Option<MissingReason> {
+        self.missing_reason
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn active_hours(&self) -> Option<&ActiveHours> {
+        self.active_hours.as_ref()
+    }
+
+    pub fn first_check_overdue(&self) -> bool {
+        self.first_check_overdue
+    }
+
+    // a single failed check is usually a network blip rather than a cow which ran away
+    pub fn is_consistently_missing(&self) -> bool {
+        self.failure_streak >= CONSISTENTLY_MISSING_AFTER_FAILED_CHECKS
+    }
 }
 
 impl TryFrom<&Cow> for CensoredCow {
@@ -291,22 +1116,136 @@ impl TryFrom<&Cow> for CensoredCow {
         Self::new(value)
     }
 }
+
+// shuffling on every request means that the position of a censored cow never says anything about
+// it. a seed picked once per boot keeps the order stable for the lifetime of the process, which is
+// less confusing on a small page, but lets someone who keeps watching the page follow a censored
+// cow by its position, e.g. to learn that it is the same cow which keeps running away. the seed
+// is never persisted so the order still can't be predicted across deployments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HerdShuffle {
+    PerRequest,
+    Seeded(u64),
+}
+
+impl HerdShuffle {
+    pub fn new_seeded_per_boot() -> Self {
+        HerdShuffle::Seeded(rand::random())
+    }
+}
+
+// a name is only ever present once, adding a cow which is already in the herd replaces it
+#[derive(Debug, Clone, Default)]
+pub struct Herd {
+    cows: BTreeMap<VisibleName, Cow>,
+}
+
+impl Herd {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, cow: Cow) -> Option<Cow> {
+        self.cows.insert(cow.name().clone(), cow)
+    }
+
+    pub fn remove(&mut self, name: &VisibleName) -> Option<Cow> {
+        self.cows.remove(name)
+    }
+
+    pub fn contains(&self, name: &VisibleName) -> bool {
+        self.cows.contains_key(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cow> {
+        self.cows.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cows.is_empty()
+    }
+
+    // ties go to the cow which comes first by name so that the answer doesn't change between calls
+    pub fn stats(&self) -> HerdStats<'_> {
+        let longest_lived = self
+            .iter()
+            .filter(|cow| cow.first_seen().is_some())
+            .min_by_key(|cow| cow.first_seen());
+        let newest = self.cows.values().rev().max_by_key(|cow| cow.added_at());
+        let flakiest = self
+            .cows
+            .values()
+            .rev()
+            .filter(|cow| cow.failure_streak() > 0)
+            .max_by_key(|cow| cow.failure_streak());
+        HerdStats {
+            longest_lived,
+            newest,
+            flakiest,
+        }
+    }
+}
+
+// a stat is missing when no cow qualifies for it, e.g. no cow was ever seen or none is failing
+pub struct HerdStats<'a> {
+    longest_lived: Option<&'a Cow>,
+    newest: Option<&'a Cow>,
+    flakiest: Option<&'a Cow>,
+}
+
+impl<'a> HerdStats<'a> {
+    // the cow which was first seen the longest time ago
+    pub fn longest_lived(&self) -> Option<&'a Cow> {
+        self.longest_lived
+    }
+
+    // the cow which was added most recently
+    pub fn newest(&self) -> Option<&'a Cow> {
+        self.newest
+    }
+
+    // the cow with the most consecutive failed checks
+    pub fn flakiest(&self) -> Option<&'a Cow> {
+        self.flakiest
+    }
+}
+
+impl FromIterator<Cow> for Herd {
+    fn from_iter<T: IntoIterator<Item = Cow>>(iter: T) -> Self {
+        let mut herd = Herd::new();
+        for cow in iter {
+            herd.add(cow);
+        }
+        herd
+    }
+}
+
 pub struct CensoredHerd {
     cows: Vec<CensoredCow>,
 }
 
 impl CensoredHerd {
-    pub fn new(mut cows: Vec<CensoredCow>) -> Self {
-        CensoredHerd::guard_against_side_channel_attacks(&mut cows);
+    pub fn new(cows: Vec<CensoredCow>) -> Self {
+        Self::new_with_shuffle(cows, HerdShuffle::PerRequest)
+    }
+
+    pub fn new_with_shuffle(mut cows: Vec<CensoredCow>, shuffle: HerdShuffle) -> Self {
+        CensoredHerd::guard_against_side_channel_attacks(&mut cows, shuffle);
         Self { cows }
     }
 
-    fn guard_against_side_channel_attacks(cows: &mut [CensoredCow]) {
+    fn guard_against_side_channel_attacks(cows: &mut [CensoredCow], shuffle: HerdShuffle) {
         // we could rely on implementing Ord for VisibleName, but we want to be explicit here.
         // this type is supposed guard against sidechannel attacks and if someone ever changes
         // Ord for VisibleName this could lead to accidentally removing this safeguard
-        let mut rng = rand::thread_rng();
-        cows.shuffle(&mut rng);
+        match shuffle {
+            HerdShuffle::PerRequest => cows.shuffle(&mut rand::thread_rng()),
+            HerdShuffle::Seeded(seed) => cows.shuffle(&mut StdRng::seed_from_u64(seed)),
+        }
         cows.sort_by(|a, b| match (a.name(), b.name()) {
             (Name::Censored(_), Name::Censored(_)) => std::cmp::Ordering::Equal,
             (Name::Censored(_), Name::Visible(_)) => std::cmp::Ordering::Greater,
@@ -318,8 +1257,206 @@ impl CensoredHerd {
         &self.cows
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    NotCheckedYet,
+    Present,
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusChange {
+    name: VisibleName,
+    old_status: CheckStatus,
+    new_status: CheckStatus,
+}
+
+impl StatusChange {
+    pub fn name(&self) -> &VisibleName {
+        &self.name
+    }
+
+    pub fn old_status(&self) -> CheckStatus {
+        self.old_status
+    }
+
+    pub fn new_status(&self) -> CheckStatus {
+        self.new_status
+    }
+}
+
+// a record of the character of a cow being changed, kept so that it is possible to tell later why
+// a cow suddenly became censored. the storage records one whenever an update changes the
+// character so that it is stored together with the cow no matter what changed it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharacterChange {
+    old_character: Character,
+    new_character: Character,
+    changed_at: DateTime,
+}
+
+impl CharacterChange {
+    pub fn new(old_character: Character, new_character: Character, changed_at: DateTime) -> Self {
+        Self {
+            old_character,
+            new_character,
+            changed_at,
+        }
+    }
+
+    pub fn new_from_update(before: Option<&Cow>, after: &Cow) -> Option<Self> {
+        let before = before?;
+        if before.character() == after.character() {
+            return None;
+        }
+        Some(Self::new(
+            before.character().clone(),
+            after.character().clone(),
+            DateTime::now(),
+        ))
+    }
+
+    pub fn old_character(&self) -> &Character {
+        &self.old_character
+    }
+
+    pub fn new_character(&self) -> &Character {
+        &self.new_character
+    }
+
+    pub fn changed_at(&self) -> &DateTime {
+        &self.changed_at
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HerdDiff {
+    added: Vec<VisibleName>,
+    removed: Vec<VisibleName>,
+    status_changed: Vec<StatusChange>,
+    content_changed: Vec<VisibleName>,
+}
+
+impl HerdDiff {
+    pub fn added(&self) -> &[VisibleName] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[VisibleName] {
+        &self.removed
+    }
+
+    pub fn status_changed(&self) -> &[StatusChange] {
+        &self.status_changed
+    }
+
+    // only cows which were found with a body before and after count, the first body says nothing
+    pub fn content_changed(&self) -> &[VisibleName] {
+        &self.content_changed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.status_changed.is_empty()
+            && self.content_changed.is_empty()
+    }
+
+    // a cow which was never checked before didn't run away or return, it simply showed up
+    pub fn status_events(&self) -> Vec<DomainEvent> {
+        self.status_changed
+            .iter()
+            .filter_map(|change| match (change.old_status, change.new_status) {
+                (CheckStatus::Present, CheckStatus::Missing) => Some(DomainEvent::CowRanAway {
+                    name: change.name.clone(),
+                }),
+                (CheckStatus::Missing, CheckStatus::Present) => Some(DomainEvent::CowReturned {
+                    name: change.name.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn events(&self) -> Vec<DomainEvent> {
+        let mut events = self.status_events();
+        events.extend(
+            self.content_changed
+                .iter()
+                .map(|name| DomainEvent::CowContentChanged { name: name.clone() }),
+        );
+        events
+    }
+}
+
+pub fn diff_herds(old: &[Cow], new: &[Cow]) -> HerdDiff {
+    let old_by_name: BTreeMap<&VisibleName, &Cow> =
+        old.iter().map(|cow| (cow.name(), cow)).collect();
+    let new_by_name: BTreeMap<&VisibleName, &Cow> =
+        new.iter().map(|cow| (cow.name(), cow)).collect();
+
+    let mut diff = HerdDiff::default();
+    for (name, new_cow) in &new_by_name {
+        match old_by_name.get(name) {
+            None => diff.added.push((*name).clone()),
+            Some(old_cow) => {
+                if old_cow.check_status() != new_cow.check_status() {
+                    diff.status_changed.push(StatusChange {
+                        name: (*name).clone(),
+                        old_status: old_cow.check_status(),
+                        new_status: new_cow.check_status(),
+                    });
+                }
+                let content_changed = match (old_cow.body_hash(), new_cow.body_hash()) {
+                    (Some(old_hash), Some(new_hash)) => old_hash != new_hash,
+                    _ => false,
+                };
+                if content_changed {
+                    diff.content_changed.push((*name).clone());
+                }
+            }
+        }
+    }
+    for name in old_by_name.keys() {
+        if !new_by_name.contains_key(name) {
+            diff.removed.push((*name).clone());
+        }
+    }
+    diff
+}
+// compiled once when the config is loaded, a cow which looks like the reference cow but doesn't
+// match it is treated as missing
+#[derive(Debug, Clone)]
+pub struct MustMatch {
+    regex: regex::Regex,
+}
+
+impl MustMatch {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = regex::Regex::new(pattern).map_err(|err| {
+            Error::ValidationError(anyhow!("invalid must_match pattern: {}", err))
+        })?;
+        Ok(Self { regex })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+}
+
+impl PartialEq for MustMatch {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for MustMatch {}
+
+#[derive(Clone)]
 pub struct CowTxt<'a> {
     content: std::borrow::Cow<'a, str>,
+    redirected_to: Option<VisibleName>,
 }
 
 impl<'a> CowTxt<'a> {
@@ -329,7 +1466,15 @@ impl<'a> CowTxt<'a> {
             return Err(Error::CowIsNotPresent(content.into_owned()));
         }
 
-        Ok(Self { content })
+        Ok(Self {
+            content,
+            redirected_to: None,
+        })
+    }
+
+    pub fn with_redirected_to(mut self, redirected_to: Option<VisibleName>) -> Self {
+        self.redirected_to = redirected_to;
+        self
     }
 
     fn cow_is_present(s: &str) -> bool {
@@ -350,6 +1495,37 @@ impl<'a> CowTxt<'a> {
     pub fn content(&self) -> &str {
         &self.content
     }
+
+    pub fn body_hash(&self) -> BodyHash {
+        BodyHash::of(&self.content)
+    }
+
+    pub fn redirected_to(&self) -> Option<&VisibleName> {
+        self.redirected_to.as_ref()
+    }
+
+    pub fn ensure_matches(&self, must_match: &MustMatch) -> Result<()> {
+        if !must_match.regex.is_match(&self.content) {
+            return Err(Error::CowDoesNotMatch(must_match.as_str().to_string()));
+        }
+        Ok(())
+    }
+
+    // diffing is far more expensive than validating so it is only done when someone asks
+    pub fn diff_against_reference(content: &str) -> String {
+        // unlike during validation the indentation of the first line has to be kept, otherwise
+        // it shows up as a difference as soon as any line is prepended
+        let normalize = |s: &str| {
+            Self::trim_trailing_whitespace_from_each_line(s.trim_end().trim_start_matches('\n'))
+                + "\n"
+        };
+        let reference = normalize(COW_BODY);
+        let content = normalize(content);
+        similar::TextDiff::from_lines(&reference, &content)
+            .unified_diff()
+            .header("reference", "cow.txt")
+            .to_string()
+    }
 }
 
 impl<'a> Display for CowTxt<'a> {
@@ -364,6 +1540,360 @@ mod tests {
     use crate::fixtures;
     use std::fs::read_to_string;
 
+    #[test]
+    fn cow_txt_is_appended_to_base_urls() -> Result<()> {
+        for (base, expected) in [
+            ("https://example.com", "https://example.com/cow.txt"),
+            ("https://example.com/", "https://example.com/cow.txt"),
+            (
+                "https://example.com/herd/",
+                "https://example.com/herd/cow.txt",
+            ),
+            (
+                "https://example.com/herd",
+                "https://example.com/herd/cow.txt",
+            ),
+            ("https://example.com/cow.txt", "https://example.com/cow.txt"),
+            (
+                " https://example.com/herd/cow.txt ",
+                "https://example.com/herd/cow.txt",
+            ),
+        ] {
+            assert_eq!(
+                VisibleName::from_base(base)?.url().as_str(),
+                expected,
+                "{base}"
+            );
+        }
+        assert!(VisibleName::from_base("example.com").is_err());
+        assert!(VisibleName::from_base("https://example.com/cow.html").is_err());
+        assert!(VisibleName::new("https://example.com/").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cow_urls_are_validated_without_creating_a_cow() {
+        for (url, expected_ok) in [
+            ("https://example.com/cow.txt", true),
+            ("http://93.184.215.14/cow.txt", true),
+            ("https://example.com/cow.html", false),
+            ("https://example.com/", false),
+            ("ftp://example.com/cow.txt", false),
+            ("file:///etc/cow.txt", false),
+            ("http://127.0.0.1/cow.txt", false),
+            ("http://169.254.169.254/cow.txt", false),
+            ("http://[::1]/cow.txt", false),
+            ("not a url", false),
+        ] {
+            let result = validate_cow_url(url, &HostPolicy::default());
+            assert_eq!(result.is_ok(), expected_ok, "{url}");
+            if let Err(err) = result {
+                assert!(matches!(err, Error::ValidationError(_)), "{url}");
+            }
+        }
+    }
+
+    #[test]
+    fn cow_urls_are_validated_against_the_given_host_policy() -> Result<()> {
+        let host_policy = HostPolicy::new(vec!["10.0.0.0/8".parse()?], vec![]);
+
+        assert!(validate_cow_url("http://10.0.0.1/cow.txt", &host_policy).is_ok());
+        assert!(validate_cow_url("http://10.0.0.1/cow.txt", &HostPolicy::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn herd_keeps_a_single_cow_per_name() -> Result<()> {
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let mut herd = Herd::new();
+
+        assert!(herd.add(Cow::new(name.clone(), Character::Brave)).is_none());
+        let replaced = herd.add(Cow::new(name.clone(), Character::Shy));
+
+        assert_eq!(
+            replaced.map(|cow| cow.character().clone()),
+            Some(Character::Brave)
+        );
+        assert_eq!(herd.len(), 1);
+        assert_eq!(
+            herd.iter()
+                .map(|cow| cow.character().clone())
+                .collect::<Vec<_>>(),
+            vec![Character::Shy]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn herd_stats_pick_the_cows_which_stand_out() -> Result<()> {
+        let old = VisibleName::new("https://old.example.com/cow.txt")?;
+        let new = VisibleName::new("https://new.example.com/cow.txt")?;
+        let flaky = VisibleName::new("https://flaky.example.com/cow.txt")?;
+        let unseen = VisibleName::new("https://unseen.example.com/cow.txt")?;
+        let herd: Herd = [
+            Cow::new_from_history(
+                old.clone(),
+                Character::Brave,
+                Some(DateTime::new_from_ymdhms(2020, 1, 1, 0, 0, 0)),
+                None,
+                None,
+            )
+            .with_failure_streak(1),
+            Cow::new_from_history(
+                flaky.clone(),
+                Character::Shy,
+                Some(DateTime::new_from_ymdhms(2022, 1, 1, 0, 0, 0)),
+                None,
+                None,
+            )
+            .with_failure_streak(5),
+            Cow::new_from_history(unseen.clone(), Character::Brave, None, None, None)
+                .with_added_at(DateTime::new_from_ymdhms(2023, 1, 1, 0, 0, 0)),
+            Cow::new_from_history(new.clone(), Character::Brave, None, None, None)
+                .with_added_at(DateTime::new_from_ymdhms(2024, 1, 1, 0, 0, 0)),
+        ]
+        .into_iter()
+        .collect();
+
+        let stats = herd.stats();
+
+        assert_eq!(stats.longest_lived().map(|cow| cow.name()), Some(&old));
+        assert_eq!(stats.newest().map(|cow| cow.name()), Some(&new));
+        assert_eq!(stats.flakiest().map(|cow| cow.name()), Some(&flaky));
+        Ok(())
+    }
+
+    #[test]
+    fn herd_stats_are_missing_when_no_cow_qualifies() -> Result<()> {
+        let herd: Herd = [Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+        )]
+        .into_iter()
+        .collect();
+
+        let stats = herd.stats();
+
+        assert!(stats.longest_lived().is_none());
+        assert!(stats.newest().is_some());
+        assert!(stats.flakiest().is_none());
+        assert!(Herd::new().stats().newest().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn removing_a_cow_from_the_herd() -> Result<()> {
+        let a = VisibleName::new("https://a.example.com/cow.txt")?;
+        let b = VisibleName::new("https://b.example.com/cow.txt")?;
+        let mut herd: Herd = [
+            Cow::new(a.clone(), Character::Brave),
+            Cow::new(b.clone(), Character::Brave),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            herd.remove(&a).map(|cow| cow.name().clone()),
+            Some(a.clone())
+        );
+        assert!(herd.remove(&a).is_none());
+        assert!(!herd.contains(&a));
+        assert!(herd.contains(&b));
+        assert_eq!(herd.len(), 1);
+
+        herd.remove(&b);
+        assert!(herd.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn failed_checks_are_mapped_to_missing_reasons() {
+        let test_cases = [
+            (
+                Error::UnexpectedHttpStatus(404),
+                Some(MissingReason::HttpStatus(404)),
+            ),
+            (
+                Error::Timeout("https://example.com/cow.txt".to_string()),
+                Some(MissingReason::Timeout),
+            ),
+            (
+                Error::DomainNotFound("example.com".to_string()),
+                Some(MissingReason::DnsError),
+            ),
+            (
+                Error::CowIsNotPresent("not a cow".to_string()),
+                Some(MissingReason::ValidationFailed),
+            ),
+            (
+                Error::CowDoesNotMatch("moo".to_string()),
+                Some(MissingReason::ValidationFailed),
+            ),
+            (
+                Error::ConnectionRefused("example.com".to_string()),
+                Some(MissingReason::ConnectionRefused),
+            ),
+            (Error::NetworkError(anyhow!("not allowed")), None),
+        ];
+
+        for (err, expected) in test_cases {
+            assert_eq!(MissingReason::from_error(&err), expected, "{err}");
+        }
+    }
+
+    #[test]
+    fn missing_reason_is_cleared_by_the_next_check() -> Result<()> {
+        let mut cow = Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+        );
+
+        cow.mark_as_missing_because(MissingReason::HttpStatus(503))?;
+        assert_eq!(cow.missing_reason(), Some(MissingReason::HttpStatus(503)));
+
+        cow.mark_as_missing()?;
+        assert_eq!(cow.missing_reason(), None);
+
+        cow.mark_as_domain_not_found()?;
+        assert_eq!(cow.missing_reason(), Some(MissingReason::DnsError));
+        assert!(cow.domain_not_found());
+
+        cow.mark_as_ok()?;
+        assert_eq!(cow.missing_reason(), None);
+        assert!(!cow.domain_not_found());
+        Ok(())
+    }
+
+    #[test]
+    fn paused_cow_is_not_checked_until_resumed() -> Result<()> {
+        let mut cow = Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+        );
+
+        cow.pause()?;
+        assert!(!cow.should_check(&FlapPenalty::default()));
+        assert!(cow.pause().is_err());
+
+        cow.resume()?;
+        assert!(cow.should_check(&FlapPenalty::default()));
+        assert!(cow.resume().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn never_checked_cow_is_due_now() -> Result<()> {
+        let before = DateTime::now();
+        let cow = Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+        );
+
+        let next_check_at = cow.next_check_at(&FlapPenalty::default()).unwrap();
+
+        assert!(before <= next_check_at && next_check_at <= DateTime::now());
+        Ok(())
+    }
+
+    #[test]
+    fn recently_checked_cow_is_due_after_the_interval() -> Result<()> {
+        let last_checked = DateTime::now() - Duration::new_from_minutes(10);
+        let cow = Cow::new_from_history(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+            Some(last_checked.clone()),
+            Some(last_checked.clone()),
+            Some(last_checked.clone()),
+        );
+
+        assert_eq!(
+            cow.next_check_at(&FlapPenalty::default()),
+            Some(&last_checked + Duration::new_from_hours(CHECK_COW_IF_NOT_CHECKED_FOR_HOURS))
+        );
+        assert!(cow.next_check_at(&FlapPenalty::default()).unwrap() > DateTime::now());
+        assert!(!cow.should_check(&FlapPenalty::default()));
+        Ok(())
+    }
+
+    #[test]
+    fn overdue_cow_was_due_in_the_past() -> Result<()> {
+        let last_checked = DateTime::now() - Duration::new_from_days(2);
+        let cow = Cow::new_from_history(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+            Some(last_checked.clone()),
+            Some(last_checked.clone()),
+            Some(last_checked),
+        );
+
+        assert!(cow.next_check_at(&FlapPenalty::default()).unwrap() < DateTime::now());
+        assert!(cow.should_check(&FlapPenalty::default()));
+        Ok(())
+    }
+
+    #[test]
+    fn paused_cow_is_never_due() -> Result<()> {
+        let last_checked = DateTime::now() - Duration::new_from_days(2);
+        let cow = Cow::new_from_history(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+            Some(last_checked.clone()),
+            Some(last_checked.clone()),
+            Some(last_checked),
+        )
+        .with_paused(true);
+
+        assert_eq!(cow.next_check_at(&FlapPenalty::default()), None);
+        let censored = CensoredCow::new(&cow)?.with_flap_penalty(&FlapPenalty::new(8)?);
+        assert_eq!(censored.next_check_at(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn flapping_cows_are_checked_less_often_until_they_are_stable() -> Result<()> {
+        let flap_penalty = FlapPenalty::new(8)?;
+        let base_interval = Duration::new_from_hours(CHECK_COW_IF_NOT_CHECKED_FOR_HOURS);
+        let mut cow = Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+        );
+        cow.mark_as_ok()?;
+        assert_eq!(cow.check_interval(&flap_penalty), base_interval);
+
+        let mut intervals = vec![];
+        for _ in 0..4 {
+            cow.mark_as_missing()?;
+            intervals.push(cow.check_interval(&flap_penalty));
+            cow.mark_as_ok()?;
+            intervals.push(cow.check_interval(&flap_penalty));
+        }
+        assert_eq!(cow.flaps(), 8);
+        assert_eq!(
+            intervals,
+            [1, 1, 2, 4, 8, 8, 8, 8]
+                .into_iter()
+                .map(|multiplier| base_interval.clone() * multiplier)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            cow.check_interval(&FlapPenalty::new(1)?),
+            base_interval.clone()
+        );
+
+        // the cow stayed present ever since it last came back
+        let long_ago =
+            DateTime::now() - Duration::new_from_hours(FLAPS_ARE_FORGOTTEN_AFTER_HOURS + 1);
+        let flaps = cow.flaps();
+        cow = cow.with_flaps(flaps, Some(long_ago));
+        assert_eq!(cow.recent_flaps(&DateTime::now()), 0);
+        assert_eq!(cow.check_interval(&flap_penalty), base_interval);
+
+        // and the next flap starts counting from scratch
+        cow.mark_as_missing()?;
+        assert_eq!(cow.flaps(), 1);
+        Ok(())
+    }
+
     #[test]
     fn cow_validation_works() -> Result<()> {
         struct CowValidationTestCase {
@@ -416,6 +1946,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn must_match_is_checked_on_top_of_the_reference_cow() -> Result<()> {
+        let must_match = MustMatch::new(r"moo from \w+")?;
+        let signed_cow = format!("{COW_BODY}\nmoo from bessie\n");
+
+        CowTxt::new(signed_cow.as_str())?.ensure_matches(&must_match)?;
+        assert!(matches!(
+            CowTxt::new(COW_BODY)?.ensure_matches(&must_match),
+            Err(Error::CowDoesNotMatch(_))
+        ));
+        assert!(CowTxt::new("moo from bessie").is_err());
+        assert!(MustMatch::new("moo (").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_censored_name() {
         struct CensoredNameTestCase {
@@ -503,4 +2048,461 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn collapsed_host_censoring_hides_the_subdomains() -> Result<()> {
+        let cow = Cow::new(
+            VisibleName::new("https://api.eu.example.com/herd/cow.txt")?,
+            Character::Shy,
+        );
+        let censored_url = |host_censoring| -> Result<String> {
+            match Name::new_with_host_censoring(&cow, host_censoring)? {
+                Name::Censored(c) => Ok(c.url().to_string()),
+                Name::Visible(v) => Ok(v.url().to_string()),
+            }
+        };
+
+        assert_eq!(
+            censored_url(HostCensoring::PerLabel)?,
+            "https://***.**.*******.com/****/cow.txt"
+        );
+        assert_eq!(
+            censored_url(HostCensoring::Collapsed)?,
+            "https://*******.com/****/cow.txt"
+        );
+        let shallow_cow = Cow::new(VisibleName::new("https://a.com/cow.txt")?, Character::Shy);
+        assert_eq!(
+            CensoredCow::new_with_host_censoring(&shallow_cow, HostCensoring::Collapsed)?.name(),
+            &Name::Censored(CensoredName {
+                url: "https://*******.com/cow.txt".to_string()
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn registrable_domain_host_censoring_only_hides_the_subdomains() -> Result<()> {
+        let censored_url = |url: &str| -> Result<String> {
+            let cow = Cow::new(VisibleName::new(url)?, Character::Shy);
+            match Name::new_with_host_censoring(&cow, HostCensoring::KeepRegistrableDomain)? {
+                Name::Censored(c) => Ok(c.url().to_string()),
+                Name::Visible(v) => Ok(v.url().to_string()),
+            }
+        };
+
+        assert_eq!(
+            censored_url("https://a.example.co.uk/cow.txt")?,
+            "https://*.example.co.uk/cow.txt"
+        );
+        assert_eq!(
+            censored_url("https://api.eu.example.com/herd/cow.txt")?,
+            "https://***.**.example.com/****/cow.txt"
+        );
+        assert_eq!(
+            censored_url("https://example.co.uk/cow.txt")?,
+            "https://example.co.uk/cow.txt"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn censored_cow_explains_why_it_is_censored() -> Result<()> {
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+
+        let brave = CensoredCow::new(&Cow::new(name.clone(), Character::Brave))?;
+        assert_eq!(brave.censor_reason(), CensorReason::Visible);
+        assert!(matches!(brave.name(), Name::Visible(_)));
+
+        let shy = CensoredCow::new(&Cow::new(name, Character::Shy))?;
+        assert_eq!(shy.censor_reason(), CensorReason::CensoredByCharacter);
+        assert!(
+            matches!(shy.name(), Name::Censored(c) if c.url() == "https://*******.com/cow.txt")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn redirected_cows_are_shown_and_censored_under_the_final_url() -> Result<()> {
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let final_url = VisibleName::new("https://cdn.example.net/cow.txt")?;
+
+        let mut shy = Cow::new(name.clone(), Character::Shy);
+        shy.mark_as_found_at(Some(final_url.clone()))?;
+        assert_eq!(shy.name(), &name);
+        assert!(
+            matches!(CensoredCow::new(&shy)?.name(), Name::Censored(c) if c.url() == "https://***.*******.net/cow.txt")
+        );
+
+        shy.mark_as_found_at(None)?;
+        assert_eq!(shy.shown_name(), &name);
+        Ok(())
+    }
+
+    #[test]
+    fn nicknames_of_shy_cows_are_censored() -> Result<()> {
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let nickname = Some(Nickname::new("Bessie")?);
+
+        let brave = Cow::new(name.clone(), Character::Brave).with_nickname(nickname.clone());
+        assert_eq!(CensoredCow::new(&brave)?.nickname(), nickname.as_ref());
+
+        let shy = Cow::new(name, Character::Shy).with_nickname(nickname);
+        assert_eq!(CensoredCow::new(&shy)?.nickname(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn tags_of_shy_cows_are_censored() -> Result<()> {
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+        let tags = vec![Tag::new("dairy")?];
+
+        let brave = Cow::new(name.clone(), Character::Brave).with_tags(tags.clone());
+        assert_eq!(CensoredCow::new(&brave)?.tags(), tags.as_slice());
+
+        let shy = Cow::new(name, Character::Shy).with_tags(tags);
+        assert!(CensoredCow::new(&shy)?.tags().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn notes_of_shy_cows_are_censored() -> Result<()> {
+        let name = VisibleName::new("https://example.com/cow.txt")?;
+
+        let brave = Cow::new(name.clone(), Character::Brave).with_notes("ask bob");
+        assert_eq!(CensoredCow::new(&brave)?.notes(), "ask bob");
+
+        let shy = Cow::new(name, Character::Shy).with_notes("ask bob");
+        assert_eq!(CensoredCow::new(&shy)?.notes(), "");
+        Ok(())
+    }
+
+    #[test]
+    fn cows_whose_character_differs_from_the_expected_one_are_flagged() -> Result<()> {
+        let mut cow = Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Shy,
+        );
+        assert!(!cow.has_unexpected_character());
+
+        cow.set_expected_character(Some(Character::Shy));
+        assert!(!cow.has_unexpected_character());
+
+        cow.set_expected_character(Some(Character::Brave));
+        assert!(cow.has_unexpected_character());
+        assert!(CensoredCow::new(&cow)?.unexpected_character());
+        assert_eq!(cow.character(), &Character::Shy);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_notes_are_rejected() -> Result<()> {
+        let mut cow = Cow::new(
+            VisibleName::new("https://example.com/cow.txt")?,
+            Character::Brave,
+        );
+
+        assert!(cow.change_notes("a".repeat(MAX_NOTES_LENGTH + 1)).is_err());
+        assert!(cow.change_notes("ask\u{7}bob").is_err());
+        assert!(cow.change_notes("  ").is_err());
+
+        cow.change_notes(" ask bob\n\tor alice ")?;
+        assert_eq!(cow.notes(), "ask bob\n\tor alice");
+
+        cow.change_notes("")?;
+        assert_eq!(cow.notes(), "");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_nicknames_are_rejected() {
+        assert!(Nickname::new("  ").is_err());
+        assert!(Nickname::new("a".repeat(MAX_NICKNAME_LENGTH + 1)).is_err());
+        assert!(Nickname::new("Bes\nsie").is_err());
+        assert_eq!(Nickname::new(" Bessie ").unwrap().as_str(), "Bessie");
+    }
+
+    #[test]
+    fn invalid_tags_are_rejected() {
+        assert!(Tag::new("  ").is_err());
+        assert!(Tag::new("a".repeat(MAX_TAG_LENGTH + 1)).is_err());
+        assert!(Tag::new("Dairy").is_err());
+        assert!(Tag::new("dairy farm").is_err());
+        assert_eq!(Tag::new(" dairy-farm_2 ").unwrap().as_str(), "dairy-farm_2");
+    }
+
+    #[test]
+    fn random_transitions_preserve_cow_invariants() -> Result<()> {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0x000c0ffee);
+        for _ in 0..200 {
+            let mut cow = Cow::new(
+                VisibleName::new("https://example.com/cow.txt")?,
+                Character::Brave,
+            );
+            let mut now = DateTime::now() - Duration::new_from_days(365);
+
+            for _ in 0..rng.gen_range(0..50) {
+                now = &now + Duration::new_from_minutes(rng.gen_range(1..600));
+                let first_seen_before = cow.first_seen().cloned();
+                let character_before = cow.character().clone();
+
+                let nickname_before = cow.nickname().cloned();
+                let tags_before = cow.tags().to_vec();
+                let notes_before = cow.notes().to_string();
+                let failure_streak_before = cow.failure_streak();
+                let paused_before = cow.paused();
+
+                let transition = match rng.gen_range(0..8) {
+                    0 => CowTransition::CheckSucceeded(now.clone()),
+                    1 => CowTransition::CheckFailed(now.clone()),
+                    2 => CowTransition::NicknameChanged(if rng.gen_bool(0.5) {
+                        Some(Nickname::new("Bessie")?)
+                    } else {
+                        None
+                    }),
+                    3 => CowTransition::TagsChanged(if rng.gen_bool(0.5) {
+                        vec![Tag::new("dairy")?, Tag::new("dairy")?]
+                    } else {
+                        vec![]
+                    }),
+                    4 => CowTransition::Paused,
+                    5 => CowTransition::Resumed,
+                    6 => CowTransition::NotesChanged(if rng.gen_bool(0.5) {
+                        " owned by the dairy team ".to_string()
+                    } else {
+                        String::new()
+                    }),
+                    _ => CowTransition::CharacterChanged(if rng.gen_bool(0.5) {
+                        Character::Brave
+                    } else {
+                        Character::Shy
+                    }),
+                };
+                let result = cow.apply(transition.clone());
+
+                if first_seen_before.is_some() {
+                    assert_eq!(cow.first_seen(), first_seen_before.as_ref());
+                }
+                if let (Some(first_seen), Some(last_seen)) = (cow.first_seen(), cow.last_seen()) {
+                    assert!(first_seen <= last_seen);
+                }
+                if let (Some(last_seen), Some(last_checked)) = (cow.last_seen(), cow.last_checked())
+                {
+                    assert!(last_seen <= last_checked);
+                }
+                assert_eq!(cow.first_seen().is_some(), cow.last_seen().is_some());
+
+                match transition {
+                    CowTransition::CheckSucceeded(_) => {
+                        assert_eq!(cow.last_check_succeeded(), Some(true));
+                        assert_eq!(cow.failure_streak(), 0);
+                    }
+                    CowTransition::CheckFailed(_) => {
+                        assert_eq!(cow.last_check_succeeded(), Some(false));
+                        assert_eq!(cow.failure_streak(), failure_streak_before + 1);
+                    }
+                    CowTransition::CharacterChanged(new_character) => {
+                        if new_character == character_before {
+                            assert!(result.is_err());
+                        } else {
+                            assert!(result.is_ok());
+                        }
+                        assert_eq!(cow.character(), &new_character);
+                    }
+                    CowTransition::NicknameChanged(new_nickname) => {
+                        assert_eq!(result.is_ok(), new_nickname != nickname_before);
+                        assert_eq!(cow.nickname(), new_nickname.as_ref());
+                    }
+                    CowTransition::TagsChanged(new_tags) => {
+                        let new_tags = normalize_tags(new_tags);
+                        assert_eq!(result.is_ok(), new_tags != tags_before);
+                        assert_eq!(cow.tags(), new_tags.as_slice());
+                    }
+                    CowTransition::NotesChanged(new_notes) => {
+                        let new_notes = new_notes.trim();
+                        assert_eq!(result.is_ok(), new_notes != notes_before);
+                        assert_eq!(cow.notes(), new_notes);
+                    }
+                    CowTransition::Paused => {
+                        assert_eq!(result.is_ok(), !paused_before);
+                        assert!(cow.paused());
+                    }
+                    CowTransition::Resumed => {
+                        assert_eq!(result.is_ok(), paused_before);
+                        assert!(!cow.paused());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn diff_against_reference_shows_an_injected_line() -> Result<()> {
+        let reference = read_to_string(fixtures::test_file_path("src/ports/http/static/cow.txt"))?;
+        let content = format!("Welcome to my website!\n{reference}");
+
+        let diff = CowTxt::diff_against_reference(&content);
+
+        assert!(diff.starts_with("--- reference\n+++ cow.txt\n"), "{diff}");
+        assert!(diff.contains("\n+Welcome to my website!\n"), "{diff}");
+        assert!(
+            !diff
+                .lines()
+                .any(|line| line.starts_with('-') && !line.starts_with("---"))
+        );
+        assert!(CowTxt::diff_against_reference(&reference).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn seeded_shuffle_keeps_the_herd_order_stable() -> Result<()> {
+        let cows = (1..=10)
+            .map(|i| {
+                let name =
+                    VisibleName::new(format!("https://{}.example.com/cow.txt", "a".repeat(i)))?;
+                CensoredCow::new(&Cow::new(name, Character::Shy))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let order = |shuffle: HerdShuffle| -> Vec<Name> {
+            CensoredHerd::new_with_shuffle(cows.clone(), shuffle)
+                .cows()
+                .iter()
+                .map(|cow| cow.name().clone())
+                .collect()
+        };
+
+        let shuffle = HerdShuffle::new_seeded_per_boot();
+        let first_order = order(shuffle);
+        for _ in 0..10 {
+            assert_eq!(order(shuffle), first_order);
+        }
+        Ok(())
+    }
+
+    fn cow_with_status(url: &str, status: CheckStatus) -> Result<Cow> {
+        let long_ago = DateTime::now() - Duration::new_from_days(1);
+        let recently = DateTime::now() - Duration::new_from_hours(1);
+        let (last_seen, last_checked) = match status {
+            CheckStatus::NotCheckedYet => (None, None),
+            CheckStatus::Present => (Some(recently.clone()), Some(recently)),
+            CheckStatus::Missing => (Some(long_ago.clone()), Some(recently)),
+        };
+        Ok(Cow::new_from_history(
+            VisibleName::new(url)?,
+            Character::Brave,
+            last_seen.as_ref().map(|_| long_ago.clone()),
+            last_seen,
+            last_checked,
+        ))
+    }
+
+    #[test]
+    fn diffing_herds_reports_added_and_removed_cows() -> Result<()> {
+        let a = cow_with_status("https://a.example.com/cow.txt", CheckStatus::Present)?;
+        let b = cow_with_status("https://b.example.com/cow.txt", CheckStatus::Present)?;
+        let c = cow_with_status("https://c.example.com/cow.txt", CheckStatus::NotCheckedYet)?;
+
+        let diff = diff_herds(&[a.clone(), b.clone()], &[b.clone(), c.clone()]);
+
+        assert_eq!(diff.added(), &[c.name().clone()]);
+        assert_eq!(diff.removed(), &[a.name().clone()]);
+        assert!(diff.status_changed().is_empty());
+        assert!(diff_herds(&[a.clone(), b.clone()], &[b, a]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn diffing_herds_reports_status_transitions() -> Result<()> {
+        let url_a = "https://a.example.com/cow.txt";
+        let url_b = "https://b.example.com/cow.txt";
+        let url_c = "https://c.example.com/cow.txt";
+        let old = [
+            cow_with_status(url_a, CheckStatus::Present)?,
+            cow_with_status(url_b, CheckStatus::Missing)?,
+            cow_with_status(url_c, CheckStatus::NotCheckedYet)?,
+        ];
+        let new = [
+            cow_with_status(url_a, CheckStatus::Missing)?,
+            cow_with_status(url_b, CheckStatus::Present)?,
+            cow_with_status(url_c, CheckStatus::Missing)?,
+        ];
+
+        let diff = diff_herds(&old, &new);
+
+        assert!(diff.added().is_empty());
+        assert!(diff.removed().is_empty());
+        let transitions: Vec<_> = diff
+            .status_changed()
+            .iter()
+            .map(|change| {
+                (
+                    change.name().url().as_str(),
+                    change.old_status(),
+                    change.new_status(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            transitions,
+            vec![
+                (url_a, CheckStatus::Present, CheckStatus::Missing),
+                (url_b, CheckStatus::Missing, CheckStatus::Present),
+                (url_c, CheckStatus::NotCheckedYet, CheckStatus::Missing),
+            ]
+        );
+        assert_eq!(
+            diff.status_events(),
+            vec![
+                DomainEvent::CowRanAway {
+                    name: VisibleName::new(url_a)?
+                },
+                DomainEvent::CowReturned {
+                    name: VisibleName::new(url_b)?
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn diffing_herds_reports_changed_bodies_once_there_is_one_to_compare_to() -> Result<()> {
+        let cow = |url: &str, body: Option<&str>| -> Result<Cow> {
+            Ok(Cow::new(VisibleName::new(url)?, Character::Brave)
+                .with_body_hash(body.map(BodyHash::of)))
+        };
+        let url_a = "https://a.example.com/cow.txt";
+        let url_b = "https://b.example.com/cow.txt";
+        let url_c = "https://c.example.com/cow.txt";
+        let old = [
+            cow(url_a, Some("moo"))?,
+            cow(url_b, None)?,
+            cow(url_c, Some("moo"))?,
+        ];
+        let new = [
+            cow(url_a, Some("MOO"))?,
+            cow(url_b, Some("moo"))?,
+            cow(url_c, Some("moo"))?,
+        ];
+
+        let diff = diff_herds(&old, &new);
+
+        assert_eq!(diff.content_changed(), &[VisibleName::new(url_a)?]);
+        assert_eq!(
+            diff.events(),
+            vec![DomainEvent::CowContentChanged {
+                name: VisibleName::new(url_a)?
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn body_hashes_must_be_sha256_hashes() {
+        assert!(BodyHash::new(BodyHash::of("moo").as_str()).is_ok());
+        assert!(BodyHash::new("moo").is_err());
+        assert!(BodyHash::new("A".repeat(64)).is_err());
+    }
 }
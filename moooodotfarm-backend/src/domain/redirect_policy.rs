@@ -0,0 +1,123 @@
+use crate::errors::{Error, Result};
+use anyhow::anyhow;
+
+// a cow which redirects to a different host is effectively hosted by someone else, for example a
+// cdn, so following such redirects is opt-in. the scheme and port don't matter as long as the host
+// stays the same so that a cow can move to https, moving back to plain http is never followed
+// without the opt-in though as it would send whatever the request carries unencrypted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    allow_cross_origin: bool,
+    identity: RedirectIdentity,
+}
+
+// the configured url always remains the key under which the cow is tracked, the identity only
+// decides which url the herd is shown and censored under
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedirectIdentity {
+    #[default]
+    ConfiguredUrl,
+    FinalUrl,
+}
+
+impl RedirectPolicy {
+    pub fn new(allow_cross_origin: bool, identity: RedirectIdentity) -> Self {
+        Self {
+            allow_cross_origin,
+            identity,
+        }
+    }
+
+    pub fn allows(&self, from: &url::Url, to: &url::Url) -> bool {
+        let downgraded = from.scheme() == "https" && to.scheme() != "https";
+        self.allow_cross_origin || (from.host() == to.host() && !downgraded)
+    }
+
+    pub fn allow_cross_origin(&self) -> bool {
+        self.allow_cross_origin
+    }
+
+    pub fn identity(&self) -> RedirectIdentity {
+        self.identity
+    }
+}
+
+impl TryFrom<&str> for RedirectIdentity {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "configured_url" => Ok(RedirectIdentity::ConfiguredUrl),
+            "final_url" => Ok(RedirectIdentity::FinalUrl),
+            other => Err(Error::ValidationError(anyhow!(
+                "invalid redirect identity: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_same_host_redirects_are_allowed_by_default() -> Result<()> {
+        let policy = RedirectPolicy::default();
+
+        for (from, to, expected) in [
+            (
+                "https://example.com/cow.txt",
+                "https://example.com/moved/cow.txt",
+                true,
+            ),
+            (
+                "https://example.com/cow.txt",
+                "https://example.com:8443/cow.txt",
+                true,
+            ),
+            (
+                "http://example.com/cow.txt",
+                "https://example.com/cow.txt",
+                true,
+            ),
+            (
+                "https://example.com/cow.txt",
+                "http://example.com/cow.txt",
+                false,
+            ),
+            (
+                "https://example.com/cow.txt",
+                "https://cdn.example.net/cow.txt",
+                false,
+            ),
+            (
+                "http://example.com/cow.txt",
+                "https://www.example.com/cow.txt",
+                false,
+            ),
+        ] {
+            assert_eq!(
+                policy.allows(&url::Url::parse(from)?, &url::Url::parse(to)?),
+                expected,
+                "Failed for redirect from {from} to {to}"
+            );
+        }
+
+        let from = url::Url::parse("https://example.com/cow.txt")?;
+
+        let policy = RedirectPolicy::new(true, RedirectIdentity::ConfiguredUrl);
+        assert!(policy.allows(&from, &url::Url::parse("https://cdn.example.net/cow.txt")?));
+        Ok(())
+    }
+
+    #[test]
+    fn redirect_identity_is_parsed() -> Result<()> {
+        assert_eq!(
+            RedirectIdentity::try_from("final_url")?,
+            RedirectIdentity::FinalUrl
+        );
+        assert!(RedirectIdentity::try_from("final").is_err());
+        Ok(())
+    }
+}
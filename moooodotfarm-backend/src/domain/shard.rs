@@ -0,0 +1,90 @@
+use crate::domain::VisibleName;
+use crate::errors::{Error, Result};
+use anyhow::anyhow;
+
+// several instances can split a large herd between them without talking to each other, every
+// instance checks the cows which hash into its own shard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    index: u32,
+    total: u32,
+}
+
+impl Shard {
+    pub fn new(index: u32, total: u32) -> Result<Self> {
+        if total == 0 {
+            return Err(Error::ValidationError(anyhow!(
+                "total number of shards can't be zero"
+            )));
+        }
+        if index >= total {
+            return Err(Error::ValidationError(anyhow!(
+                "shard index {} is out of range for {} shards",
+                index,
+                total
+            )));
+        }
+        Ok(Self { index, total })
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn contains(&self, name: &VisibleName) -> bool {
+        jump_consistent_hash(fnv1a(name.url().as_str().as_bytes()), self.total) == self.index
+    }
+}
+
+// the hash has to be the same on every instance and across releases so std's hasher won't do
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+// changing the number of shards only moves the cows which have to move, see "A Fast, Minimal
+// Memory, Consistent Hash Algorithm" by Lamping and Veach
+fn jump_consistent_hash(mut key: u64, buckets: u32) -> u32 {
+    let mut b: i64 = -1;
+    let mut j: i64 = 0;
+    while j < i64::from(buckets) {
+        b = j;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shards_partition_the_herd() -> Result<()> {
+        let shards = [Shard::new(0, 2)?, Shard::new(1, 2)?];
+        let names = (0..200)
+            .map(|i| VisibleName::new(format!("https://{i}.example.com/cow.txt")))
+            .collect::<Result<Vec<_>>>()?;
+
+        for name in &names {
+            let owners = shards.iter().filter(|shard| shard.contains(name)).count();
+            assert_eq!(owners, 1, "Failed for cow: {}", name.url());
+        }
+        for shard in &shards {
+            assert!(names.iter().any(|name| shard.contains(name)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_shards_are_rejected() {
+        assert!(Shard::new(0, 0).is_err());
+        assert!(Shard::new(2, 2).is_err());
+        assert!(Shard::new(1, 2).is_ok());
+    }
+}
@@ -1,9 +1,15 @@
 use crate::errors::Result;
 use anyhow::anyhow;
 use chrono::TimeZone as _;
-use chrono::{Datelike as _, DurationRound};
+use chrono::{Datelike as _, DurationRound, Timelike as _};
 use std::fmt::Display;
-use std::ops::{Add, AddAssign, Sub};
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+// timestamps are persisted using this format, changing it would make existing databases unreadable
+// which is why it is deliberately separate from the display format and never configurable
+const STORAGE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+const DEFAULT_DISPLAY_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
 
 pub enum TimeZone {
     UTC,
@@ -110,6 +116,12 @@ impl DateTime {
         }
     }
 
+    // in utc
+    pub fn new_from_ymdhms(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> Self {
+        NaiveDateTime::new_from_ymdhms(year, month, day, hour, min, sec)
+            .attach_timezone(TimeZone::UTC)
+    }
+
     pub fn new_from_unix_timestamp(unix_timestamp: u64) -> Self {
         let dt = chrono::DateTime::from_timestamp(unix_timestamp as i64, 0).unwrap();
         Self {
@@ -158,10 +170,30 @@ impl DateTime {
         self.dt.year()
     }
 
+    pub fn hour(&self) -> u32 {
+        self.dt.hour()
+    }
+
+    pub fn minute(&self) -> u32 {
+        self.dt.minute()
+    }
+
     pub fn format(&self, format: &str) -> String {
         self.dt.format(format).to_string()
     }
 
+    pub fn unix_timestamp(&self) -> i64 {
+        self.dt.timestamp()
+    }
+
+    pub fn new_from_storage_str(s: &str) -> Result<Self> {
+        Self::new_from_str(s, STORAGE_FORMAT)
+    }
+
+    pub fn to_storage_string(&self) -> String {
+        self.format(STORAGE_FORMAT)
+    }
+
     pub fn ago(&self) -> String {
         let now = DateTime::now();
         let duration = now - self;
@@ -255,7 +287,7 @@ impl Sub<&DateTime> for &DateTime {
 
 impl Display for DateTime {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.dt.format("%Y-%m-%d %H:%M:%S %z"))
+        write!(f, "{}", self.dt.format(DEFAULT_DISPLAY_FORMAT))
     }
 }
 
@@ -303,3 +335,95 @@ impl Duration {
         self.d.as_seconds_f64()
     }
 }
+
+impl Mul<u32> for Duration {
+    type Output = Duration;
+
+    // saturates instead of wrapping around so that a large multiplier can't make a duration short
+    fn mul(self, rhs: u32) -> Self::Output {
+        Duration::new(
+            i32::try_from(rhs)
+                .ok()
+                .and_then(|rhs| self.d.checked_mul(rhs))
+                .unwrap_or(if self.d < chrono::Duration::zero() {
+                    chrono::Duration::MIN
+                } else {
+                    chrono::Duration::MAX
+                }),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayFormat {
+    format: String,
+}
+
+impl DisplayFormat {
+    pub fn new(format: impl Into<String>) -> Result<Self> {
+        let format = format.into();
+        if format.is_empty() {
+            return Err(anyhow!("datetime display format can't be empty").into());
+        }
+        if chrono::format::StrftimeItems::new(&format)
+            .any(|item| matches!(item, chrono::format::Item::Error))
+        {
+            return Err(anyhow!("invalid datetime display format: {}", format).into());
+        }
+        Ok(Self { format })
+    }
+
+    pub fn format(&self, dt: &DateTime) -> String {
+        dt.format(&self.format)
+    }
+}
+
+impl Default for DisplayFormat {
+    fn default() -> Self {
+        Self {
+            format: DEFAULT_DISPLAY_FORMAT.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiplying_durations_saturates() {
+        assert_eq!(Duration::new_from_hours(2) * 3, Duration::new_from_hours(6));
+        assert_eq!(
+            Duration::new_from_hours(2) * u32::MAX,
+            Duration::new(chrono::Duration::MAX)
+        );
+    }
+
+    #[test]
+    fn stored_timestamps_round_trip() -> Result<()> {
+        let dt = DateTime::new_from_str("2026-02-10 23:09:43 +0100", STORAGE_FORMAT)?;
+
+        let stored = dt.to_storage_string();
+
+        assert_eq!(stored, "2026-02-10 23:09:43 +0100");
+        assert_eq!(DateTime::new_from_storage_str(&stored)?, dt);
+        Ok(())
+    }
+
+    #[test]
+    fn display_format_is_configurable() -> Result<()> {
+        let dt = DateTime::new_from_str("2026-02-10 23:09:43 +0000", STORAGE_FORMAT)?;
+
+        assert_eq!(
+            DisplayFormat::default().format(&dt),
+            "2026-02-10 23:09:43 +0000"
+        );
+        assert_eq!(
+            DisplayFormat::new("%d/%m/%Y %H:%M")?.format(&dt),
+            "10/02/2026 23:09"
+        );
+        assert!(DisplayFormat::new("%Q").is_err());
+        assert!(DisplayFormat::new("").is_err());
+        Ok(())
+    }
+}
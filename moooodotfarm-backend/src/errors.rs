@@ -7,6 +7,45 @@ pub enum Error {
     #[error("cow is not present in `{0}`")]
     CowIsNotPresent(String),
 
+    #[error("cow doesn't match `{0}`")]
+    CowDoesNotMatch(String),
+
+    #[error("domain `{0}` not found")]
+    DomainNotFound(String),
+
+    #[error("connection to `{0}` refused")]
+    ConnectionRefused(String),
+
+    #[error("request to `{0}` timed out")]
+    Timeout(String),
+
+    #[error("unexpected http status {0}")]
+    UnexpectedHttpStatus(u16),
+
+    #[error("changing `{0}` requires a restart")]
+    ConfigChangeRequiresRestart(String),
+
+    #[error(transparent)]
+    NetworkError(anyhow::Error),
+
+    #[error(transparent)]
+    StorageError(anyhow::Error),
+
+    #[error(transparent)]
+    ValidationError(anyhow::Error),
+
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error(transparent)]
+    Template(anyhow::Error),
+
     #[error(transparent)]
     Unknown(#[from] anyhow::Error),
 }
@@ -19,7 +58,7 @@ impl From<std::io::Error> for Error {
 
 impl From<toml::de::Error> for Error {
     fn from(value: toml::de::Error) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::ValidationError(anyhow!(value))
     }
 }
 
@@ -31,7 +70,7 @@ impl From<prometheus::Error> for Error {
 
 impl From<chrono::ParseError> for Error {
     fn from(value: chrono::ParseError) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::ValidationError(anyhow!(value))
     }
 }
 
@@ -43,7 +82,7 @@ impl From<chrono::RoundingError> for Error {
 
 impl From<url::ParseError> for Error {
     fn from(value: url::ParseError) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::ValidationError(anyhow!(value))
     }
 }
 
@@ -55,49 +94,50 @@ impl From<MatchesError> for Error {
 
 impl From<reqwest::Error> for Error {
     fn from(value: reqwest::Error) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::NetworkError(anyhow!(value))
     }
 }
 
 impl From<redb::DatabaseError> for Error {
     fn from(value: redb::DatabaseError) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::StorageError(anyhow!(value))
     }
 }
 
 impl From<redb::CommitError> for Error {
     fn from(value: redb::CommitError) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::StorageError(anyhow!(value))
     }
 }
 
 impl From<redb::StorageError> for Error {
     fn from(value: redb::StorageError) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::StorageError(anyhow!(value))
     }
 }
 
 impl From<redb::TableError> for Error {
     fn from(value: redb::TableError) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::StorageError(anyhow!(value))
     }
 }
 
 impl From<redb::TransactionError> for Error {
     fn from(value: redb::TransactionError) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::StorageError(anyhow!(value))
     }
 }
 
 impl From<serde_json::Error> for Error {
     fn from(value: serde_json::Error) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::StorageError(anyhow!(value))
     }
 }
 
+// only ever returned by requests which reached the server, failing to connect is a network error
 impl From<tonic::Status> for Error {
     fn from(value: tonic::Status) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::Unknown(anyhow!("the gRPC request failed: {}", value))
     }
 }
 
@@ -107,10 +147,38 @@ impl From<std::env::VarError> for Error {
     }
 }
 
+impl From<askama::Error> for Error {
+    fn from(value: askama::Error) -> Self {
+        Error::Template(anyhow!(value))
+    }
+}
+
 impl From<tonic::transport::Error> for Error {
     fn from(value: tonic::transport::Error) -> Self {
-        Error::Unknown(anyhow!(value))
+        Error::NetworkError(anyhow!(value))
     }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reqwest_errors_are_network_errors() {
+        let err = reqwest::get("http://").await.unwrap_err();
+
+        assert!(matches!(Error::from(err), Error::NetworkError(_)));
+    }
+
+    #[test]
+    fn redb_errors_are_storage_errors() {
+        let path = std::env::temp_dir()
+            .join("moooodotfarm-directory-which-does-not-exist")
+            .join("moooodotfarm.db");
+        let err = redb::Database::create(path).unwrap_err();
+
+        assert!(matches!(Error::from(err), Error::StorageError(_)));
+    }
+}
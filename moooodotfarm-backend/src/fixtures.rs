@@ -1,7 +1,150 @@
+use crate::app;
+use crate::domain::events::DomainEvent;
+use crate::domain::{CowTxt, VisibleName};
+use crate::errors::{Error, Result};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
+
+const COW_BODY: &str = include_str!("ports/http/static/cow.txt");
 
 pub fn test_file_path(relative_path: &str) -> Box<Path> {
     let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     d.push(relative_path);
     d.into_boxed_path()
 }
+
+#[derive(Clone, Default)]
+pub struct MockCowTxtDownloader {
+    missing: Arc<Mutex<HashSet<VisibleName>>>,
+    bodies: Arc<Mutex<HashMap<VisibleName, String>>>,
+    delay: Arc<Mutex<Option<Duration>>>,
+    held: Arc<tokio::sync::RwLock<()>>,
+    downloads: Arc<tokio::sync::watch::Sender<usize>>,
+}
+
+impl MockCowTxtDownloader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_missing(&self, name: &VisibleName, missing: bool) {
+        let mut missing_cows = self.missing.lock().unwrap();
+        if missing {
+            missing_cows.insert(name.clone());
+        } else {
+            missing_cows.remove(name);
+        }
+    }
+
+    // the body still has to look like the reference cow to be accepted
+    pub fn set_body(&self, name: &VisibleName, body: impl Into<String>) {
+        self.bodies
+            .lock()
+            .unwrap()
+            .insert(name.clone(), body.into());
+    }
+
+    pub fn set_delay(&self, delay: Duration) {
+        *self.delay.lock().unwrap() = Some(delay);
+    }
+
+    // downloads which start while the guard is held wait until it is dropped
+    pub async fn hold_downloads(&self) -> tokio::sync::OwnedRwLockWriteGuard<()> {
+        self.held.clone().write_owned().await
+    }
+
+    pub fn downloads(&self) -> usize {
+        *self.downloads.borrow()
+    }
+
+    pub async fn wait_for_downloads(&self, downloads: usize) {
+        let mut receiver = self.downloads.subscribe();
+        receiver
+            .wait_for(|started| *started >= downloads)
+            .await
+            .expect("the sender is kept by the downloader");
+    }
+}
+
+#[async_trait]
+impl app::CowTxtDownloader for MockCowTxtDownloader {
+    async fn download(&self, name: &VisibleName) -> Result<CowTxt<'_>> {
+        self.downloads.send_modify(|downloads| *downloads += 1);
+        let _held = self.held.read().await;
+        let delay = *self.delay.lock().unwrap();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        if self.missing.lock().unwrap().contains(name) {
+            return Err(Error::Unknown(anyhow!("cow is missing")));
+        }
+        match self.bodies.lock().unwrap().get(name) {
+            Some(body) => CowTxt::new(body.clone()),
+            None => CowTxt::new(COW_BODY),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct RecordingEventSink {
+    events: Arc<Mutex<Vec<DomainEvent>>>,
+}
+
+impl RecordingEventSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<DomainEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl app::EventSink for RecordingEventSink {
+    fn emit(&self, event: DomainEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+// only one logger can ever be installed so log records of all tests end up in the same place
+static RECORDING_LOGGER: RecordingLogger = RecordingLogger {
+    records: Mutex::new(Vec::new()),
+};
+
+pub struct RecordingLogger {
+    records: Mutex<Vec<String>>,
+}
+
+impl RecordingLogger {
+    pub fn install() -> &'static RecordingLogger {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&RECORDING_LOGGER).expect("another logger is already installed");
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        &RECORDING_LOGGER
+    }
+
+    pub fn records(&self) -> Vec<String> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl log::Log for RecordingLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.records
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", record.level(), record.args()));
+    }
+
+    fn flush(&self) {}
+}
@@ -1,31 +1,62 @@
-use crate::app::{AddCowHandler, ChangeCowCharacterHandler, DeleteCowHandler, GetHerdHandler};
+use crate::app::{
+    AddCowHandler, ChangeCowCharacterHandler, DeleteCowHandler, GetCowHandler, GetHerdHandler,
+    ListCowNotesHandler, PauseCowHandler, ResumeCowHandler, SetCowNotesHandler,
+};
 use crate::config;
+use crate::config::GrpcLimits;
 use crate::errors::{Error, Result};
 use crate::{app, domain};
 use anyhow::anyhow;
-use tonic::transport::Server;
+use futures_util::Stream;
+use futures_util::stream;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use subtle::ConstantTimeEq;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Channel, Endpoint, Server};
 use tonic::{Request, Response, Status};
+use tonic_web::GrpcWebLayer;
+use tower::layer::util::{Identity, Stack};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
 pub mod generated {
     tonic::include_proto!("moooodotfarm.grpc");
 }
 
 use crate::domain::Character;
+use crate::domain::time::{DateTime, DisplayFormat};
+use generated::moooodotfarm_service_client::MoooodotfarmServiceClient;
 use generated::moooodotfarm_service_server::{MoooodotfarmService, MoooodotfarmServiceServer};
 use generated::{
     AddCowRequest, AddCowResponse, ChangeCowCharacterRequest, ChangeCowCharacterResponse, Cow,
-    DeleteCowRequest, DeleteCowResponse, GetHerdRequest, GetHerdResponse, Herd,
+    CowNotes, DeleteCowRequest, DeleteCowResponse, GetCowRequest, GetCowResponse, GetHerdRequest,
+    GetHerdResponse, Herd, ListCowNotesRequest, ListCowNotesResponse, PauseCowRequest,
+    PauseCowResponse, PingRequest, PingResponse, ResumeCowRequest, ResumeCowResponse,
+    SetCowNotesRequest, SetCowNotesResponse, UpdateProgress, WatchUpdateProgressRequest,
 };
 
-const DT_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
-
 pub trait Deps {
     fn get_herd_handler(&self) -> &impl GetHerdHandler;
+    fn get_cow_handler(&self) -> &impl GetCowHandler;
     fn add_cow_handler(&self) -> &impl AddCowHandler;
     fn change_cow_character_handler(&self) -> &impl ChangeCowCharacterHandler;
     fn delete_cow_handler(&self) -> &impl DeleteCowHandler;
+    fn pause_cow_handler(&self) -> &impl PauseCowHandler;
+    fn resume_cow_handler(&self) -> &impl ResumeCowHandler;
+    fn set_cow_notes_handler(&self) -> &impl SetCowNotesHandler;
+    fn list_cow_notes_handler(&self) -> &impl ListCowNotesHandler;
+    fn owner_bearer_token(&self) -> Option<&str>;
+    fn update_progress(&self) -> &app::UpdateProgressChannel;
+    fn display_format(&self) -> &DisplayFormat;
 }
 
+static GRACEFUL_SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+static MAX_CONNECT_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
 pub struct GrpcServer<'a, D> {
     config: &'a config::Config,
     deps: D,
@@ -39,20 +70,231 @@ where
         Self { config, deps }
     }
 
-    pub async fn run(&self) -> Result<()> {
-        let address = self
-            .config
-            .grpc_address()
-            .parse::<std::net::SocketAddr>()
-            .map_err(|err| Error::Unknown(anyhow!(err)))?;
-        let service = HerdServiceImpl::new(self.deps.clone());
+    // returns once the cancellation token is cancelled and the calls in flight were answered
+    pub async fn run(&self, cancellation_token: &CancellationToken) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(self.config.grpc_address()).await?;
+        serve(
+            listener,
+            self.config.grpc_web_origins(),
+            self.config.grpc_limits(),
+            HerdServiceImpl::new(self.deps.clone()),
+            cancellation_token.clone(),
+        )
+        .await
+    }
+}
 
-        Server::builder()
-            .add_service(MoooodotfarmServiceServer::new(service))
-            .serve(address)
-            .await
-            .map_err(|err| Error::Unknown(anyhow!(err)))?;
-        Ok(())
+async fn serve<D>(
+    listener: tokio::net::TcpListener,
+    web_origins: &[String],
+    limits: &GrpcLimits,
+    service: HerdServiceImpl<D>,
+    cancellation_token: CancellationToken,
+) -> Result<()>
+where
+    D: Deps + Send + Sync + 'static,
+{
+    // keepalive is set on the incoming connections as the builder only applies it to listeners it
+    // binds itself
+    let incoming = TcpIncoming::from_listener(listener, true, limits.tcp_keepalive())
+        .map_err(|err| Error::Unknown(anyhow!(err)))?;
+    let server = server_builder(web_origins, limits)?
+        .add_service(MoooodotfarmServiceServer::new(service))
+        .serve_with_incoming_shutdown(incoming, cancellation_token.clone().cancelled_owned());
+    // watching the update progress never ends on its own so the streams are cut off eventually
+    tokio::select! {
+        result = server => result.map_err(|err| Error::Unknown(anyhow!(err)))?,
+        _ = async {
+            cancellation_token.cancelled().await;
+            tokio::time::sleep(GRACEFUL_SHUTDOWN_TIMEOUT).await;
+        } => log::warn!("closing the grpc connections which are still open"),
+    }
+    Ok(())
+}
+
+type ServerLayers = Stack<ConcurrencyLimitLayer, Stack<GrpcWebLayer, Stack<CorsLayer, Identity>>>;
+
+// browsers speak gRPC-Web over http1 while native clients keep using the same listener, cross
+// origin calls are allowed only from the configured origins. the services are layered once and
+// then shared by all connections so the concurrency limit applies to the server as a whole
+fn server_builder(web_origins: &[String], limits: &GrpcLimits) -> Result<Server<ServerLayers>> {
+    let web_origins = web_origins
+        .iter()
+        .map(|origin| http::HeaderValue::from_str(origin))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|err| Error::Unknown(anyhow!(err)))?;
+    let cors = match web_origins.is_empty() {
+        true => CorsLayer::new(),
+        false => CorsLayer::new()
+            .allow_origin(AllowOrigin::list(web_origins))
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers(Any),
+    };
+    Ok(Server::builder()
+        .max_concurrent_streams(Some(limits.max_concurrent_streams()))
+        .accept_http1(true)
+        .layer(cors)
+        .layer(GrpcWebLayer::new())
+        .layer(ConcurrencyLimitLayer::new(limits.concurrency_limit())))
+}
+
+// requests beyond the limit are answered right away instead of waiting for one of the running
+// requests to finish. the answer is a regular status so that gRPC-Web clients understand it too
+#[derive(Clone)]
+struct ConcurrencyLimitLayer {
+    permits: Arc<tokio::sync::Semaphore>,
+}
+
+impl ConcurrencyLimitLayer {
+    fn new(limit: usize) -> Self {
+        Self {
+            permits: Arc::new(tokio::sync::Semaphore::new(limit)),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for ConcurrencyLimitLayer {
+    type Service = ConcurrencyLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConcurrencyLimit {
+            inner,
+            permits: self.permits.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ConcurrencyLimit<S> {
+    inner: S,
+    permits: Arc<tokio::sync::Semaphore>,
+}
+
+impl<S, B> tower::Service<http::Request<B>> for ConcurrencyLimit<S>
+where
+    S: tower::Service<http::Request<B>, Response = http::Response<tonic::body::BoxBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let Ok(permit) = self.permits.clone().try_acquire_owned() else {
+            return Box::pin(std::future::ready(Ok(Status::resource_exhausted(
+                "too many concurrent requests",
+            )
+            .into_http())));
+        };
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            drop(permit);
+            response
+        })
+    }
+}
+
+// the timeout applies both to establishing the connection and to every request made afterwards
+pub async fn connect(
+    address: &str,
+    timeout: std::time::Duration,
+) -> Result<MoooodotfarmServiceClient<Channel>> {
+    let endpoint = Endpoint::from_shared(format!("http://{}", address))?
+        .connect_timeout(timeout)
+        .timeout(timeout);
+    let channel = tokio::time::timeout(timeout, endpoint.connect())
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "timed out after {:?} connecting to the gRPC server at {}",
+                timeout,
+                address
+            )
+        })?
+        .map_err(|err| {
+            anyhow!(
+                "couldn't connect to the gRPC server at {}: {}",
+                address,
+                err
+            )
+        })?;
+    Ok(MoooodotfarmServiceClient::new(channel))
+}
+
+// the server may be briefly unavailable e.g. during a deploy, the delay doubles after every
+// failed attempt up to a limit so that many attempts don't end up waiting for hours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectRetry {
+    attempts: u32,
+    delay: std::time::Duration,
+}
+
+impl ConnectRetry {
+    pub fn new(attempts: u32, delay: std::time::Duration) -> Result<Self> {
+        if attempts == 0 {
+            return Err(Error::ValidationError(anyhow!(
+                "at least one connection attempt is needed"
+            )));
+        }
+        Ok(Self { attempts, delay })
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn delay(&self) -> std::time::Duration {
+        self.delay
+    }
+
+    // a configured delay which is already longer than the limit is used as it is
+    fn delay_after(&self, failed_attempts: u32) -> std::time::Duration {
+        let factor = 2u32.saturating_pow(failed_attempts.saturating_sub(1));
+        self.delay
+            .saturating_mul(factor)
+            .min(MAX_CONNECT_RETRY_DELAY.max(self.delay))
+    }
+}
+
+// only establishing the connection is retried, a request which fails once connected fails as
+// usual
+pub async fn connect_with_retry(
+    address: &str,
+    timeout: std::time::Duration,
+    retry: ConnectRetry,
+) -> Result<MoooodotfarmServiceClient<Channel>> {
+    let mut attempt = 1;
+    loop {
+        match connect(address, timeout).await {
+            Ok(client) => return Ok(client),
+            Err(err) if attempt < retry.attempts => {
+                let delay = retry.delay_after(attempt);
+                log::debug!(
+                    "connection attempt {} of {} failed, retrying in {:?}: {}",
+                    attempt,
+                    retry.attempts,
+                    delay,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(err) => {
+                return Err(Error::NetworkError(anyhow!(
+                    "giving up after {} connection attempts: {}",
+                    attempt,
+                    err
+                )));
+            }
+        }
     }
 }
 
@@ -72,18 +314,60 @@ impl<D> MoooodotfarmService for HerdServiceImpl<D>
 where
     D: Deps + Send + Sync + 'static,
 {
+    type WatchUpdateProgressStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<UpdateProgress, Status>> + Send>>;
+
     async fn get_herd(
         &self,
-        _request: Request<GetHerdRequest>,
+        request: Request<GetHerdRequest>,
     ) -> std::result::Result<Response<GetHerdResponse>, Status> {
+        let status_filter = request
+            .into_inner()
+            .status_filter
+            .map(|v| parse_status(&v))
+            .transpose()
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
         let herd = self
             .deps
             .get_herd_handler()
             .handle()
             .await
-            .map_err(|err| Status::internal(err.to_string()))?;
+            .map_err(status_from_error)?;
+        let cows = herd
+            .cows()
+            .iter()
+            .filter(|cow| {
+                status_filter
+                    .as_ref()
+                    .is_none_or(|status| cow.status() == status)
+            })
+            .map(|cow| Cow::new(cow, self.deps.display_format()))
+            .collect();
         let response = GetHerdResponse {
-            herd: Some(Herd::from(&herd)),
+            herd: Some(Herd { cows }),
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn get_cow(
+        &self,
+        request: Request<GetCowRequest>,
+    ) -> std::result::Result<Response<GetCowResponse>, Status> {
+        let payload = request.into_inner();
+        let name = domain::VisibleName::new(payload.url)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let query = app::GetCow::new(name);
+
+        let cow = self
+            .deps
+            .get_cow_handler()
+            .handle(&query)
+            .await
+            .map_err(status_from_error)?
+            .ok_or_else(|| Status::not_found("cow not found"))?;
+        let response = GetCowResponse {
+            cow: Some(Cow::new(&cow, self.deps.display_format())),
         };
 
         Ok(Response::new(response))
@@ -104,7 +388,7 @@ where
             .add_cow_handler()
             .handle(&command)
             .await
-            .map_err(|err| Status::internal(err.to_string()))?;
+            .map_err(status_from_error)?;
 
         Ok(Response::new(AddCowResponse {}))
     }
@@ -124,7 +408,7 @@ where
             .change_cow_character_handler()
             .handle(&command)
             .await
-            .map_err(|err| Status::internal(err.to_string()))?;
+            .map_err(status_from_error)?;
 
         Ok(Response::new(ChangeCowCharacterResponse {}))
     }
@@ -142,22 +426,125 @@ where
             .delete_cow_handler()
             .handle(&command)
             .await
-            .map_err(|err| Status::internal(err.to_string()))?;
+            .map_err(status_from_error)?;
 
         Ok(Response::new(DeleteCowResponse {}))
     }
-}
 
-impl From<&app::Herd> for Herd {
-    fn from(value: &app::Herd) -> Self {
-        Self {
-            cows: value.cows().iter().map(Cow::from).collect(),
-        }
+    async fn pause_cow(
+        &self,
+        request: Request<PauseCowRequest>,
+    ) -> std::result::Result<Response<PauseCowResponse>, Status> {
+        let payload = request.into_inner();
+        let name = domain::VisibleName::new(payload.name)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let command = app::PauseCow::new(name);
+
+        self.deps
+            .pause_cow_handler()
+            .handle(&command)
+            .await
+            .map_err(status_from_error)?;
+
+        Ok(Response::new(PauseCowResponse {}))
+    }
+
+    async fn resume_cow(
+        &self,
+        request: Request<ResumeCowRequest>,
+    ) -> std::result::Result<Response<ResumeCowResponse>, Status> {
+        let payload = request.into_inner();
+        let name = domain::VisibleName::new(payload.name)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let command = app::ResumeCow::new(name);
+
+        self.deps
+            .resume_cow_handler()
+            .handle(&command)
+            .await
+            .map_err(status_from_error)?;
+
+        Ok(Response::new(ResumeCowResponse {}))
+    }
+
+    async fn set_cow_notes(
+        &self,
+        request: Request<SetCowNotesRequest>,
+    ) -> std::result::Result<Response<SetCowNotesResponse>, Status> {
+        let payload = request.into_inner();
+        let name = domain::VisibleName::new(payload.name)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let command = app::SetCowNotes::new(name, payload.notes);
+
+        self.deps
+            .set_cow_notes_handler()
+            .handle(&command)
+            .await
+            .map_err(status_from_error)?;
+
+        Ok(Response::new(SetCowNotesResponse {}))
+    }
+
+    async fn list_cow_notes(
+        &self,
+        request: Request<ListCowNotesRequest>,
+    ) -> std::result::Result<Response<ListCowNotesResponse>, Status> {
+        authorize_owner(self.deps.owner_bearer_token(), &request)?;
+
+        let notes = self
+            .deps
+            .list_cow_notes_handler()
+            .handle()
+            .await
+            .map_err(status_from_error)?
+            .iter()
+            .map(|v| CowNotes {
+                url: v.name().url().to_string(),
+                notes: v.notes().to_string(),
+            })
+            .collect();
+
+        Ok(Response::new(ListCowNotesResponse { notes }))
+    }
+
+    // only the updates which run while the stream is open are reported, the stream never ends on
+    // its own
+    async fn watch_update_progress(
+        &self,
+        _request: Request<WatchUpdateProgressRequest>,
+    ) -> std::result::Result<Response<Self::WatchUpdateProgressStream>, Status> {
+        let receiver = self.deps.update_progress().subscribe();
+        let progress = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(progress) => return Some((Ok(UpdateProgress::new(&progress)), receiver)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::debug!("update progress subscriber skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(progress)))
+    }
+
+    // never touches the herd so that it only says whether the server can be reached at all
+    async fn ping(
+        &self,
+        request: Request<PingRequest>,
+    ) -> std::result::Result<Response<PingResponse>, Status> {
+        let payload = request.into_inner().payload;
+
+        Ok(Response::new(PingResponse {
+            payload,
+            server_time: DateTime::now().unix_timestamp(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }))
     }
 }
 
-impl From<&app::Cow> for Cow {
-    fn from(value: &app::Cow) -> Self {
+impl Cow {
+    fn new(value: &app::Cow, display_format: &DisplayFormat) -> Self {
         let name_str = match value.name() {
             domain::Name::Visible(v) => v.url().to_string(),
             domain::Name::Censored(c) => c.url().to_string(),
@@ -168,27 +555,826 @@ impl From<&app::Cow> for Cow {
         };
         let last_seen = value
             .last_seen()
-            .map(|dt| dt.format(DT_FORMAT))
+            .map(|dt| display_format.format(dt))
             .unwrap_or_default();
-        let status_str = match value.status() {
-            app::CowStatus::HappilyGrazing => "happily-grazing",
-            app::CowStatus::RanAway => "ran-away",
-            app::CowStatus::HaveNotCheckedYet => "have-not-checked-yet",
-        };
 
         Self {
             name: name_str,
             character: character_str.to_string(),
             last_seen,
-            status: status_str.to_string(),
+            status: format_status(value.status()).to_string(),
+            added_at: display_format.format(value.added_at()),
+            unexpected_character: value.unexpected_character(),
+        }
+    }
+}
+
+impl UpdateProgress {
+    fn new(value: &app::UpdateProgress) -> Self {
+        Self {
+            checked: value.checked() as u64,
+            total: value.total() as u64,
+            current_cow: match value.current_cow() {
+                domain::Name::Visible(v) => v.url().to_string(),
+                domain::Name::Censored(c) => c.url().to_string(),
+            },
         }
     }
 }
 
+// the same bearer token as the owner only parts of the http api, see http::authorize_owner
+#[allow(clippy::result_large_err)]
+fn authorize_owner<T>(
+    expected_token: Option<&str>,
+    request: &Request<T>,
+) -> std::result::Result<(), Status> {
+    let Some(expected_token) = expected_token else {
+        return Err(Status::permission_denied(
+            "no owner bearer token is configured",
+        ));
+    };
+    let token = request
+        .metadata()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| Status::unauthenticated("missing bearer token"))?;
+    if !bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())) {
+        return Err(Status::unauthenticated("invalid bearer token"));
+    }
+    Ok(())
+}
+
+fn status_from_error(err: Error) -> Status {
+    match err {
+        Error::NotFound(message) => Status::not_found(message),
+        Error::Conflict(message) => Status::already_exists(message),
+        Error::Forbidden(message) => Status::permission_denied(message),
+        Error::ValidationError(err) => Status::invalid_argument(err.to_string()),
+        other => Status::internal(other.to_string()),
+    }
+}
+
+fn format_status(value: &app::CowStatus) -> &'static str {
+    match value {
+        app::CowStatus::HappilyGrazing => "happily-grazing",
+        app::CowStatus::RanAway => "ran-away",
+        app::CowStatus::HaveNotCheckedYet => "have-not-checked-yet",
+        app::CowStatus::Paused => "paused",
+    }
+}
+
+fn parse_status(value: &str) -> Result<app::CowStatus> {
+    app::CowStatus::all_variants()
+        .iter()
+        .find(|status| format_status(status) == value)
+        .copied()
+        .ok_or_else(|| Error::ValidationError(anyhow!("invalid status: {value}")))
+}
+
 fn parse_character(value: &str) -> Result<Character> {
     match value {
         "brave" => Ok(Character::Brave),
         "shy" => Ok(Character::Shy),
-        other => Err(Error::Unknown(anyhow!("invalid character: {other}"))),
+        other => Err(Error::ValidationError(anyhow!(
+            "invalid character: {other}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters;
+    use crate::adapters::database::Database;
+    use crate::app::Inventory;
+    use crate::app::add_cow;
+    use crate::app::change_cow_character;
+    use crate::app::delete_cow;
+    use crate::app::get_cow;
+    use crate::app::get_herd::{self, ShyCows};
+    use crate::app::list_cow_notes;
+    use crate::app::pause_cow;
+    use crate::app::resume_cow;
+    use crate::app::set_cow_notes;
+    use crate::app::update;
+    use crate::domain::flap_penalty::FlapPenalty;
+    use crate::domain::time::Duration;
+    use crate::fixtures::{MockCowTxtDownloader, RecordingEventSink};
+
+    struct TestDeps {
+        get_herd_handler: get_herd::GetHerdHandler<Database, adapters::Metrics>,
+        get_cow_handler: get_cow::GetCowHandler<Database, adapters::Metrics>,
+        add_cow_handler: add_cow::AddCowHandler<
+            Database,
+            MockCowTxtDownloader,
+            adapters::Metrics,
+            RecordingEventSink,
+        >,
+        change_cow_character_handler: change_cow_character::ChangeCowCharacterHandler<
+            Database,
+            adapters::Metrics,
+            RecordingEventSink,
+        >,
+        delete_cow_handler:
+            delete_cow::DeleteCowHandler<Database, adapters::Metrics, RecordingEventSink>,
+        pause_cow_handler:
+            pause_cow::PauseCowHandler<Database, adapters::Metrics, RecordingEventSink>,
+        resume_cow_handler:
+            resume_cow::ResumeCowHandler<Database, adapters::Metrics, RecordingEventSink>,
+        set_cow_notes_handler: set_cow_notes::SetCowNotesHandler<Database, adapters::Metrics>,
+        list_cow_notes_handler: list_cow_notes::ListCowNotesHandler<Database, adapters::Metrics>,
+        owner_bearer_token: Option<String>,
+        update_progress: app::UpdateProgressChannel,
+        display_format: DisplayFormat,
+    }
+
+    impl Deps for TestDeps {
+        fn get_herd_handler(&self) -> &impl GetHerdHandler {
+            &self.get_herd_handler
+        }
+
+        fn get_cow_handler(&self) -> &impl GetCowHandler {
+            &self.get_cow_handler
+        }
+
+        fn add_cow_handler(&self) -> &impl AddCowHandler {
+            &self.add_cow_handler
+        }
+
+        fn change_cow_character_handler(&self) -> &impl ChangeCowCharacterHandler {
+            &self.change_cow_character_handler
+        }
+
+        fn delete_cow_handler(&self) -> &impl DeleteCowHandler {
+            &self.delete_cow_handler
+        }
+
+        fn pause_cow_handler(&self) -> &impl PauseCowHandler {
+            &self.pause_cow_handler
+        }
+
+        fn resume_cow_handler(&self) -> &impl ResumeCowHandler {
+            &self.resume_cow_handler
+        }
+
+        fn set_cow_notes_handler(&self) -> &impl SetCowNotesHandler {
+            &self.set_cow_notes_handler
+        }
+
+        fn list_cow_notes_handler(&self) -> &impl ListCowNotesHandler {
+            &self.list_cow_notes_handler
+        }
+
+        fn owner_bearer_token(&self) -> Option<&str> {
+            self.owner_bearer_token.as_deref()
+        }
+
+        fn update_progress(&self) -> &app::UpdateProgressChannel {
+            &self.update_progress
+        }
+
+        fn display_format(&self) -> &DisplayFormat {
+            &self.display_format
+        }
+    }
+
+    const OWNER_BEARER_TOKEN: &str = "owner-token";
+
+    fn new_service(database: &Database) -> Result<HerdServiceImpl<TestDeps>> {
+        new_service_with_downloader(database, MockCowTxtDownloader::new())
+    }
+
+    fn new_service_with_downloader(
+        database: &Database,
+        downloader: MockCowTxtDownloader,
+    ) -> Result<HerdServiceImpl<TestDeps>> {
+        let metrics = adapters::Metrics::new()?;
+        let events = RecordingEventSink::new();
+        Ok(HerdServiceImpl::new(TestDeps {
+            get_herd_handler: get_herd::GetHerdHandler::new(
+                database.clone(),
+                metrics.clone(),
+                ShyCows::Censored,
+                domain::HerdShuffle::PerRequest,
+            ),
+            get_cow_handler: get_cow::GetCowHandler::new(
+                database.clone(),
+                metrics.clone(),
+                ShyCows::Censored,
+            ),
+            add_cow_handler: add_cow::AddCowHandler::new(
+                database.clone(),
+                downloader,
+                metrics.clone(),
+                events.clone(),
+                4,
+            ),
+            change_cow_character_handler: change_cow_character::ChangeCowCharacterHandler::new(
+                database.clone(),
+                metrics.clone(),
+                events.clone(),
+            ),
+            delete_cow_handler: delete_cow::DeleteCowHandler::new(
+                database.clone(),
+                metrics.clone(),
+                events.clone(),
+            ),
+            pause_cow_handler: pause_cow::PauseCowHandler::new(
+                database.clone(),
+                metrics.clone(),
+                events.clone(),
+            ),
+            resume_cow_handler: resume_cow::ResumeCowHandler::new(
+                database.clone(),
+                metrics.clone(),
+                events,
+            ),
+            set_cow_notes_handler: set_cow_notes::SetCowNotesHandler::new(
+                database.clone(),
+                metrics.clone(),
+            ),
+            list_cow_notes_handler: list_cow_notes::ListCowNotesHandler::new(
+                database.clone(),
+                metrics,
+            ),
+            owner_bearer_token: Some(OWNER_BEARER_TOKEN.to_string()),
+            update_progress: app::UpdateProgressChannel::new(),
+            display_format: DisplayFormat::default(),
+        }))
+    }
+
+    #[tokio::test]
+    async fn get_cow_returns_a_censored_cow() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let service = new_service(&database)?;
+
+        let url = "https://example.com/cow.txt";
+        let name = domain::VisibleName::new(url)?;
+        database.update(&name, |_| {
+            Ok(Some(domain::Cow::new(name.clone(), Character::Shy)))
+        })?;
+
+        let response = service
+            .get_cow(Request::new(GetCowRequest {
+                url: url.to_string(),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+
+        let cow = response.into_inner().cow.unwrap();
+        assert_ne!(cow.name, url);
+        assert_eq!(cow.character, "shy");
+        assert_eq!(cow.status, "have-not-checked-yet");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn paused_cow_is_not_checked_until_resumed() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let service = new_service(&database)?;
+
+        let url = "https://example.com/cow.txt";
+        let name = domain::VisibleName::new(url)?;
+        database.update(&name, |_| {
+            Ok(Some(domain::Cow::new_from_history(
+                name.clone(),
+                Character::Brave,
+                Some(DateTime::now() - Duration::new_from_days(2)),
+                Some(DateTime::now() - Duration::new_from_days(2)),
+                Some(DateTime::now() - Duration::new_from_days(1)),
+            )))
+        })?;
+
+        service
+            .pause_cow(Request::new(PauseCowRequest {
+                name: url.to_string(),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+
+        let cow = database.get(&name)?.unwrap();
+        assert!(cow.paused());
+        assert!(!cow.should_check(&FlapPenalty::default()));
+
+        service
+            .resume_cow(Request::new(ResumeCowRequest {
+                name: url.to_string(),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+
+        let cow = database.get(&name)?.unwrap();
+        assert!(!cow.paused());
+        assert!(cow.should_check(&FlapPenalty::default()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn notes_are_only_listed_for_the_owner() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let service = new_service(&database)?;
+
+        for (url, character) in [
+            ("https://brave.example.com/cow.txt", Character::Brave),
+            ("https://shy.example.com/cow.txt", Character::Shy),
+        ] {
+            let name = domain::VisibleName::new(url)?;
+            database.update(&name, |_| {
+                Ok(Some(domain::Cow::new(name.clone(), character.clone())))
+            })?;
+            service
+                .set_cow_notes(Request::new(SetCowNotesRequest {
+                    name: url.to_string(),
+                    notes: "ask bob".to_string(),
+                }))
+                .await
+                .map_err(|err| anyhow!(err))?;
+        }
+
+        let list_cow_notes = |token: Option<&str>| {
+            let mut request = Request::new(ListCowNotesRequest {});
+            if let Some(token) = token {
+                request
+                    .metadata_mut()
+                    .insert("authorization", format!("Bearer {token}").parse().unwrap());
+            }
+            service.list_cow_notes(request)
+        };
+        let status = list_cow_notes(None).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        let status = list_cow_notes(Some("not-the-owner")).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let notes: Vec<(String, String)> = list_cow_notes(Some(OWNER_BEARER_TOKEN))
+            .await
+            .map_err(|err| anyhow!(err))?
+            .into_inner()
+            .notes
+            .into_iter()
+            .map(|v| (v.url, v.notes))
+            .collect();
+        assert_eq!(
+            notes,
+            vec![
+                (
+                    "https://brave.example.com/cow.txt".to_string(),
+                    "ask bob".to_string()
+                ),
+                (
+                    "https://shy.example.com/cow.txt".to_string(),
+                    "ask bob".to_string()
+                ),
+            ]
+        );
+
+        let status = service
+            .set_cow_notes(Request::new(SetCowNotesRequest {
+                name: "https://brave.example.com/cow.txt".to_string(),
+                notes: "ask\u{7}bob".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn notes_can_not_be_listed_without_a_configured_owner_token() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let mut service = new_service(&database)?;
+        service.deps.owner_bearer_token = None;
+
+        let mut request = Request::new(ListCowNotesRequest {});
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer ".parse().unwrap());
+        let status = service.list_cow_notes(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn paused_cow_is_shown_as_paused() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let service = new_service(&database)?;
+
+        let url = "https://example.com/cow.txt";
+        let name = domain::VisibleName::new(url)?;
+        database.update(&name, |_| {
+            Ok(Some(domain::Cow::new_from_history(
+                name.clone(),
+                Character::Brave,
+                Some(DateTime::now() - Duration::new_from_days(2)),
+                Some(DateTime::now() - Duration::new_from_days(2)),
+                Some(DateTime::now()),
+            )))
+        })?;
+
+        service
+            .pause_cow(Request::new(PauseCowRequest {
+                name: url.to_string(),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+
+        let response = service
+            .get_cow(Request::new(GetCowRequest {
+                url: url.to_string(),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+        assert_eq!(response.into_inner().cow.unwrap().status, "paused");
+
+        service
+            .resume_cow(Request::new(ResumeCowRequest {
+                name: url.to_string(),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+
+        let response = service
+            .get_cow(Request::new(GetCowRequest {
+                url: url.to_string(),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+        assert_eq!(response.into_inner().cow.unwrap().status, "ran-away");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_herd_filters_cows_by_status() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let service = new_service(&database)?;
+
+        let long_ago = DateTime::now() - Duration::new_from_days(2);
+        for (url, last_seen) in [
+            ("https://grazing.example.com/cow.txt", DateTime::now()),
+            ("https://ran-away.example.com/cow.txt", long_ago.clone()),
+        ] {
+            let name = domain::VisibleName::new(url)?;
+            database.update(&name, |_| {
+                Ok(Some(domain::Cow::new_from_history(
+                    name.clone(),
+                    Character::Brave,
+                    Some(long_ago.clone()),
+                    Some(last_seen.clone()),
+                    Some(DateTime::now()),
+                )))
+            })?;
+        }
+
+        let response = service
+            .get_herd(Request::new(GetHerdRequest {
+                status_filter: Some("ran-away".to_string()),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+
+        let cows = response.into_inner().herd.unwrap().cows;
+        assert_eq!(cows.len(), 1);
+        assert_eq!(cows[0].name, "https://ran-away.example.com/cow.txt");
+        assert_eq!(cows[0].status, "ran-away");
+
+        let name = domain::VisibleName::new("https://ran-away.example.com/cow.txt")?;
+        database.update(&name, |cow| Ok(cow.map(|cow| cow.with_paused(true))))?;
+        let response = service
+            .get_herd(Request::new(GetHerdRequest {
+                status_filter: Some("paused".to_string()),
+            }))
+            .await
+            .map_err(|err| anyhow!(err))?;
+
+        let cows = response.into_inner().herd.unwrap().cows;
+        assert_eq!(cows.len(), 1);
+        assert_eq!(cows[0].name, "https://ran-away.example.com/cow.txt");
+        assert_eq!(cows[0].status, "paused");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_cow_returns_not_found_for_an_absent_cow() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        let service = new_service(&database)?;
+
+        let status = service
+            .get_cow(Request::new(GetCowRequest {
+                url: "https://example.com/cow.txt".to_string(),
+            }))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        Ok(())
+    }
+
+    async fn start_server(
+        service: HerdServiceImpl<TestDeps>,
+        limits: GrpcLimits,
+    ) -> Result<String> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        tokio::spawn(async move {
+            serve(
+                listener,
+                &["https://moooo.farm".to_string()],
+                &limits,
+                service,
+                CancellationToken::new(),
+            )
+            .await
+        });
+        Ok(address)
+    }
+
+    #[tokio::test]
+    async fn ping_round_trips_through_a_running_server() -> Result<()> {
+        let address = start_server(
+            new_service(&Database::new_in_memory()?)?,
+            GrpcLimits::default(),
+        )
+        .await?;
+        let started_at = DateTime::now();
+
+        let mut client = connect(&address, std::time::Duration::from_secs(5)).await?;
+        let response = client
+            .ping(PingRequest {
+                payload: "moo".to_string(),
+            })
+            .await?
+            .into_inner();
+
+        assert_eq!(response.payload, "moo");
+        assert_eq!(response.version, env!("CARGO_PKG_VERSION"));
+        assert!(response.server_time >= started_at.unix_timestamp());
+        assert!(response.server_time <= DateTime::now().unix_timestamp());
+        Ok(())
+    }
+
+    // a frame is a flag byte followed by the big endian length of the message, the trailers
+    // follow the last message
+    async fn call_over_grpc_web<T>(
+        address: &str,
+        method: &str,
+        request: impl prost::Message,
+    ) -> Result<T>
+    where
+        T: prost::Message + Default,
+    {
+        let message = request.encode_to_vec();
+        let mut body = vec![0u8];
+        body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        body.extend_from_slice(&message);
+        let response = reqwest::Client::new()
+            .post(format!(
+                "http://{address}/moooodotfarm.grpc.MoooodotfarmService/{method}"
+            ))
+            .header(http::header::CONTENT_TYPE, "application/grpc-web+proto")
+            .body(body)
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            "application/grpc-web+proto"
+        );
+        let body = response.bytes().await?;
+        assert_eq!(body[0], 0);
+        let length = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+        let trailers = String::from_utf8_lossy(&body[5 + length..]);
+        assert!(trailers.contains("grpc-status:0"), "{trailers}");
+        T::decode(&body[5..5 + length]).map_err(|err| anyhow!(err).into())
+    }
+
+    #[tokio::test]
+    async fn browsers_can_call_the_service_over_grpc_web() -> Result<()> {
+        let address = start_server(
+            new_service(&Database::new_in_memory()?)?,
+            GrpcLimits::default(),
+        )
+        .await?;
+
+        let ping: PingResponse = call_over_grpc_web(
+            &address,
+            "Ping",
+            PingRequest {
+                payload: "moo".to_string(),
+            },
+        )
+        .await?;
+        assert_eq!(ping.payload, "moo");
+        assert_eq!(ping.version, env!("CARGO_PKG_VERSION"));
+
+        let _: AddCowResponse = call_over_grpc_web(
+            &address,
+            "AddCow",
+            AddCowRequest {
+                name: "https://example.com/cow.txt".to_string(),
+                character: "brave".to_string(),
+            },
+        )
+        .await?;
+        let herd: GetHerdResponse =
+            call_over_grpc_web(&address, "GetHerd", GetHerdRequest::default()).await?;
+        let cows = herd.herd.unwrap().cows;
+        assert_eq!(cows.len(), 1);
+        assert_eq!(cows[0].name, "https://example.com/cow.txt");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_the_configured_origins_may_call_over_grpc_web() -> Result<()> {
+        let address = start_server(
+            new_service(&Database::new_in_memory()?)?,
+            GrpcLimits::default(),
+        )
+        .await?;
+
+        for (origin, expected_allowed) in [
+            ("https://moooo.farm", true),
+            ("https://evil.example.com", false),
+        ] {
+            let response = reqwest::Client::new()
+                .request(
+                    reqwest::Method::OPTIONS,
+                    format!("http://{address}/moooodotfarm.grpc.MoooodotfarmService/GetHerd"),
+                )
+                .header(http::header::ORIGIN, origin)
+                .header(http::header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .send()
+                .await?;
+            assert_eq!(
+                response
+                    .headers()
+                    .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                    .is_some(),
+                expected_allowed,
+                "{origin}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn requests_beyond_the_concurrency_limit_are_rejected() -> Result<()> {
+        let downloader = MockCowTxtDownloader::new();
+        let held_downloads = downloader.hold_downloads().await;
+        let service = new_service_with_downloader(&Database::new_in_memory()?, downloader.clone())?;
+        let address =
+            start_server(service, GrpcLimits::default().with_concurrency_limit(1)?).await?;
+        let mut client = connect(&address, std::time::Duration::from_secs(5)).await?;
+
+        let mut slow_client = client.clone();
+        let slow_request = tokio::spawn(async move {
+            slow_client
+                .add_cow(AddCowRequest {
+                    name: "https://example.com/cow.txt".to_string(),
+                    character: "brave".to_string(),
+                })
+                .await
+        });
+        downloader.wait_for_downloads(1).await;
+
+        let rejected = client.ping(PingRequest::default()).await.unwrap_err();
+        assert_eq!(rejected.code(), tonic::Code::ResourceExhausted);
+
+        drop(held_downloads);
+        slow_request.await.map_err(|err| anyhow!(err))??;
+        client.ping(PingRequest::default()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_progress_is_streamed_to_subscribers() -> Result<()> {
+        let database = Database::new_in_memory()?;
+        for (url, character) in [
+            ("https://brave.example.com/cow.txt", Character::Brave),
+            ("https://shy.example.com/cow.txt", Character::Shy),
+        ] {
+            let name = domain::VisibleName::new(url)?;
+            database.update(&name, |_| {
+                Ok(Some(domain::Cow::new(name.clone(), character.clone())))
+            })?;
+        }
+        let service = new_service(&database)?;
+        let update_handler = update::UpdateHandler::new(
+            database.clone(),
+            database.clone(),
+            MockCowTxtDownloader::new(),
+            adapters::Metrics::new()?,
+            RecordingEventSink::new(),
+            1,
+            None,
+        )
+        .with_progress(service.deps.update_progress.clone());
+        let address = start_server(service, GrpcLimits::default()).await?;
+
+        let mut client = connect(&address, std::time::Duration::from_secs(5)).await?;
+        let mut stream = client
+            .watch_update_progress(WatchUpdateProgressRequest {})
+            .await?
+            .into_inner();
+        app::UpdateHandler::handle(&update_handler).await?;
+
+        let mut received = vec![];
+        for _ in 0..2 {
+            let progress =
+                tokio::time::timeout(std::time::Duration::from_secs(5), stream.message())
+                    .await
+                    .map_err(|err| anyhow!(err))??
+                    .unwrap();
+            received.push(progress);
+        }
+
+        assert_eq!(
+            received
+                .iter()
+                .map(|v| (v.checked, v.total))
+                .collect::<Vec<_>>(),
+            vec![(1, 2), (2, 2)]
+        );
+        let names: Vec<&str> = received.iter().map(|v| v.current_cow.as_str()).collect();
+        assert!(names.contains(&"https://brave.example.com/cow.txt"));
+        assert!(!names.contains(&"https://shy.example.com/cow.txt"));
+        Ok(())
+    }
+
+    #[test]
+    fn connect_retry_delay_doubles_up_to_a_limit() -> Result<()> {
+        let retry = ConnectRetry::new(100, std::time::Duration::from_millis(500))?;
+        let delays: Vec<_> = [1, 2, 3, 7, 8, 99]
+            .into_iter()
+            .map(|failed_attempts| retry.delay_after(failed_attempts).as_millis())
+            .collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 30000, 30000, 30000]);
+
+        let retry = ConnectRetry::new(3, std::time::Duration::from_secs(60))?;
+        assert_eq!(retry.delay_after(2), std::time::Duration::from_secs(60));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn client_retries_until_the_server_becomes_available() -> Result<()> {
+        // the port is only reserved to find one which is free, the server binds it again later
+        let address = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await?
+            .local_addr()?
+            .to_string();
+        let service = new_service(&Database::new_in_memory()?)?;
+        let server_address = address.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            let listener = tokio::net::TcpListener::bind(&server_address).await?;
+            serve(
+                listener,
+                &[],
+                &GrpcLimits::default(),
+                service,
+                CancellationToken::new(),
+            )
+            .await
+        });
+
+        let result = connect_with_retry(
+            &address,
+            std::time::Duration::from_secs(1),
+            ConnectRetry::new(1, std::time::Duration::from_millis(100))?,
+        )
+        .await;
+        assert!(matches!(result, Err(Error::NetworkError(_))));
+
+        let mut client = connect_with_retry(
+            &address,
+            std::time::Duration::from_secs(1),
+            ConnectRetry::new(5, std::time::Duration::from_millis(100))?,
+        )
+        .await?;
+        client.ping(PingRequest::default()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn client_fails_promptly_for_an_unresponsive_server() -> Result<()> {
+        // connections to this listener are never accepted so nothing is ever said back
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?.to_string();
+        let started_at = std::time::Instant::now();
+
+        let result = async {
+            let mut client = connect(&address, std::time::Duration::from_secs(1)).await?;
+            client
+                .get_herd(GetHerdRequest {
+                    status_filter: None,
+                })
+                .await?;
+            Ok::<(), Error>(())
+        }
+        .await;
+
+        assert!(result.is_err());
+        assert!(started_at.elapsed() < std::time::Duration::from_secs(5));
+        Ok(())
     }
 }
@@ -1,37 +1,177 @@
-use crate::app::GetHerdHandler;
-use crate::config::Environment;
-use crate::domain::time::DateTime;
+use crate::app::{
+    ChangeLogLevelHandler, GetCowAuditHandler, GetCowBodyHandler, GetCowHandler,
+    GetCowNotesHandler, GetHerdHandler, GetInsightsHandler, GetStatusHandler, RefreshCowHandler,
+    ReloadConfigHandler,
+};
+use crate::config::{Environment, JsonFieldNaming};
+use crate::domain::MissingReason;
+use crate::domain::host_policy::HostPolicy;
+use crate::domain::time::{DateTime, DisplayFormat};
 use crate::errors::{Error, Result};
-use crate::{app, config};
+use crate::{app, config, domain};
 use askama::Template;
 use axum::extract::Request;
+use axum::http::HeaderMap;
+use axum::http::Uri;
 use axum::middleware::Next;
-use axum::response::Html;
-use axum::{Router, ServiceExt, routing::get};
+use axum::response::{Html, Redirect};
+use axum::{
+    Router, ServiceExt,
+    routing::{get, post},
+};
 use axum::{
+    extract::ConnectInfo,
     extract::Json,
+    extract::Path,
+    extract::Query,
     extract::State,
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use http::header;
+use axum_server::tls_rustls::RustlsConfig;
+use http::{HeaderValue, header};
 use include_dir::{Dir, include_dir};
 use prometheus::TextEncoder;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::fmt::Display;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tokio_util::sync::CancellationToken;
 use tower::{Layer, ServiceBuilder};
 use tower_http::compression::CompressionLayer;
-use tower_http::normalize_path::NormalizePathLayer;
+use tower_http::normalize_path::{NormalizePath, NormalizePathLayer};
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::TraceLayer,
 };
 
+mod open_metrics;
+
 static STATIC_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/ports/http/static");
 
+mod static_integrity {
+    include!(concat!(env!("OUT_DIR"), "/static_integrity.rs"));
+}
+
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+const REFRESH_ON_DEMAND_PER_CLIENT_EVERY_SECONDS: u64 = 10;
+// connections which are still open this long after shutting down was requested are closed
+const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// loaded once at startup so that a broken certificate stops the farm from starting instead of
+// leaving it with a server which keeps failing to start
+pub async fn load_tls(tls: &config::TlsConfig) -> Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(tls.cert_path(), tls.key_path())
+        .await
+        .map_err(|err| {
+            Error::Unknown(anyhow::anyhow!(
+                "failed to load the tls certificate {} and key {}: {}",
+                tls.cert_path(),
+                tls.key_path(),
+                err
+            ))
+        })
+}
+
+static DEFAULT_PUBLIC_URL: &str = "https://moooo.farm";
+
+// replaces one of the embedded images, read once at startup so that a missing file stops the farm
+// from starting
+#[derive(Clone)]
+pub struct SiteImage {
+    content_type: &'static str,
+    contents: axum::body::Bytes,
+}
+
+impl SiteImage {
+    pub fn load(path: &str) -> Result<Self> {
+        let content_type = get_mime_type(path)
+            .ok()
+            .filter(|content_type| matches!(*content_type, "image/png" | "image/x-icon"))
+            .ok_or_else(|| {
+                Error::Unknown(anyhow::anyhow!(
+                    "the image must be an .ico or .png file: {}",
+                    path
+                ))
+            })?;
+        let contents = std::fs::read(path).map_err(|err| {
+            Error::Unknown(anyhow::anyhow!(
+                "failed to read the image {}: {}",
+                path,
+                err
+            ))
+        })?;
+        Ok(Self {
+            content_type,
+            contents: contents.into(),
+        })
+    }
+}
+
+impl IntoResponse for SiteImage {
+    fn into_response(self) -> Response {
+        ([(header::CONTENT_TYPE, self.content_type)], self.contents).into_response()
+    }
+}
+
+// the branding shown on every page, deployments which aren't moooo.farm replace it with their own
+#[derive(Clone)]
+pub struct Site {
+    title: Option<String>,
+    public_url: String,
+    favicon: Option<SiteImage>,
+    logo: Option<SiteImage>,
+}
+
+impl Site {
+    pub fn new() -> Self {
+        Self {
+            title: None,
+            public_url: DEFAULT_PUBLIC_URL.to_string(),
+            favicon: None,
+            logo: None,
+        }
+    }
+
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        self.title = title;
+        self
+    }
+
+    // the pages append their own paths to it
+    pub fn with_public_url(mut self, public_url: Option<&url::Url>) -> Self {
+        self.public_url = public_url
+            .map(|v| v.as_str().trim_end_matches('/').to_string())
+            .unwrap_or_else(|| DEFAULT_PUBLIC_URL.to_string());
+        self
+    }
+
+    pub fn with_favicon(mut self, favicon: Option<SiteImage>) -> Self {
+        self.favicon = favicon;
+        self
+    }
+
+    pub fn with_logo(mut self, logo: Option<SiteImage>) -> Self {
+        self.logo = logo;
+        self
+    }
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct Server<'a, D> {
     config: &'a config::Config,
     deps: D,
+    tls: Option<RustlsConfig>,
+    refresh_limiter: RefreshLimiter,
 }
 
 impl<'a, D> Server<'a, D>
@@ -39,10 +179,48 @@ where
     D: Deps + Sync + Send + Clone + 'static,
 {
     pub fn new(config: &'a config::Config, deps: D) -> Self {
-        Self { config, deps }
+        Self {
+            config,
+            deps,
+            tls: None,
+            refresh_limiter: RefreshLimiter::new(Duration::from_secs(
+                REFRESH_ON_DEMAND_PER_CLIENT_EVERY_SECONDS,
+            )),
+        }
+    }
+
+    // https is served instead of http, see load_tls
+    pub fn with_tls(mut self, tls: Option<RustlsConfig>) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    // returns once the cancellation token is cancelled and the requests in flight were answered
+    pub async fn run(&self, cancellation_token: &CancellationToken) -> Result<()> {
+        let app = self.app();
+
+        let listener = tokio::net::TcpListener::bind(self.config.http_address()).await?;
+        let redirect_http_address = self
+            .tls
+            .as_ref()
+            .and(self.config.tls())
+            .and_then(|tls| tls.redirect_http_address());
+        let https_port = listener.local_addr()?.port();
+        tokio::try_join!(
+            serve(listener, self.tls.clone(), app, cancellation_token.clone()),
+            async {
+                match redirect_http_address {
+                    Some(address) => {
+                        serve_https_redirect(address, https_port, cancellation_token.clone()).await
+                    }
+                    None => Ok(()),
+                }
+            }
+        )?;
+        Ok(())
     }
 
-    pub async fn run(&self) -> Result<()> {
+    fn app(&self) -> NormalizePath<Router> {
         let trace = TraceLayer::new_for_http();
         let cors = match self.config.environment() {
             Environment::Production => CorsLayer::new(),
@@ -53,36 +231,149 @@ where
         };
 
         let compression = CompressionLayer::new();
+        let strict_security_headers = self.config.security_headers();
+        let allow_indexing = self.config.allow_indexing();
+        let refresh_limiter = self.refresh_limiter.clone();
 
         let router = Router::new()
             .route("/", get(handle_get_index::<D>))
-            .route("/rfc", get(handle_get_rfc))
-            .route("/new", get(handle_get_new))
-            .route("/cves", get(handle_get_cves))
+            .route("/rfc", get(handle_get_rfc::<D>))
+            .route("/new", get(handle_get_new::<D>))
+            .route("/cves", get(handle_get_cves::<D>))
             .route("/metrics", get(handle_get_metrics::<D>))
-            .route("/api", get(handle_get_redoc))
+            .route("/partials/herd", get(handle_get_herd_partial::<D>))
+            .route(
+                "/robots.txt",
+                get(move || async move { robots_txt(allow_indexing) }),
+            )
+            .route("/api", get(handle_get_redoc::<D>))
             .route("/api/herd", get(handle_get_herd::<D>))
+            .route("/api/herd/{url}", get(handle_get_cow::<D>))
+            .route("/api/herd/{url}/body", get(handle_get_cow_body::<D>))
+            .route("/api/herd/{url}/audit", get(handle_get_cow_audit::<D>))
+            .route("/api/herd/{url}/notes", get(handle_get_cow_notes::<D>))
+            .route("/api/insights", get(handle_get_insights::<D>))
+            .route("/api/status", get(handle_get_status::<D>))
+            .route("/api/probe", get(handle_get_probe::<D>))
+            .route("/api/validate-url", get(handle_get_validate_url::<D>))
+            .route(
+                "/api/refresh",
+                post(handle_post_refresh::<D>).layer(axum::middleware::from_fn(
+                    move |req, next| limit_refreshes(refresh_limiter.clone(), req, next),
+                )),
+            )
+            .route("/admin/reload", post(handle_post_admin_reload::<D>))
+            .route("/admin/loglevel", post(handle_post_admin_loglevel::<D>));
+        let router = if self.config.serve_herd_txt() {
+            router.route("/herd.txt", get(handle_get_herd_txt::<D>))
+        } else {
+            router
+        };
+        // the overrides take precedence over the embedded images served by handle_static
+        let site = self.deps.site().clone();
+        let router = match site.favicon.clone() {
+            Some(favicon) => router.route(
+                "/favicon.ico",
+                get(move || async move { favicon.into_response() }),
+            ),
+            None => router,
+        };
+        let router = match site.logo.clone() {
+            Some(logo) => router.route("/logo", get(move || async move { logo.into_response() })),
+            None => router,
+        };
+        let router = router
             .fallback(handle_static)
             .layer(
                 ServiceBuilder::new()
                     .layer(trace.clone())
                     .layer(compression.clone())
                     .layer(cors.clone())
-                    .layer(axum::middleware::from_fn(you_won)),
+                    .layer(axum::middleware::from_fn(move |req, next| {
+                        security_headers(strict_security_headers, req, next)
+                    }))
+                    .layer(axum::middleware::from_fn({
+                        let site = site.clone();
+                        move |req, next| you_won(site.clone(), req, next)
+                    }))
+                    .layer(axum::middleware::from_fn(move |req, next| {
+                        html_error_pages(site.clone(), req, next)
+                    })),
             )
             .with_state(self.deps.clone());
 
-        let normalize_path = NormalizePathLayer::trim_trailing_slash();
-        let app = normalize_path.layer(router);
-        let service = ServiceExt::<Request>::into_make_service(app);
+        NormalizePathLayer::trim_trailing_slash().layer(router)
+    }
+}
 
-        let listener = tokio::net::TcpListener::bind(self.config.http_address()).await?;
-        axum::serve(listener, service).await?;
-        Ok(())
+async fn serve(
+    listener: tokio::net::TcpListener,
+    tls: Option<RustlsConfig>,
+    app: NormalizePath<Router>,
+    cancellation_token: CancellationToken,
+) -> Result<()> {
+    let service = ServiceExt::<Request>::into_make_service_with_connect_info::<SocketAddr>(app);
+    match tls {
+        Some(tls) => {
+            let handle = axum_server::Handle::new();
+            tokio::spawn({
+                let handle = handle.clone();
+                async move {
+                    cancellation_token.cancelled().await;
+                    handle.graceful_shutdown(Some(GRACEFUL_SHUTDOWN_TIMEOUT));
+                }
+            });
+            axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                .handle(handle)
+                .serve(service)
+                .await?
+        }
+        None => {
+            let server = axum::serve(listener, service)
+                .with_graceful_shutdown(cancellation_token.clone().cancelled_owned());
+            tokio::select! {
+                result = server => result?,
+                _ = async {
+                    cancellation_token.cancelled().await;
+                    tokio::time::sleep(GRACEFUL_SHUTDOWN_TIMEOUT).await;
+                } => log::warn!("closing the connections which are still open"),
+            }
+        }
     }
+    Ok(())
+}
+
+async fn serve_https_redirect(
+    address: &str,
+    https_port: u16,
+    cancellation_token: CancellationToken,
+) -> Result<()> {
+    let router = Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+        match https_redirect_location(host, &uri, https_port) {
+            Some(location) => Redirect::permanent(&location).into_response(),
+            None => (StatusCode::BAD_REQUEST, "Missing host").into_response(),
+        }
+    });
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    axum::serve(listener, router)
+        .with_graceful_shutdown(cancellation_token.cancelled_owned())
+        .await?;
+    Ok(())
+}
+
+// the port of the plain http request is replaced with the one https is served on, it is left out
+// when it is the default one
+fn https_redirect_location(host: Option<&str>, uri: &Uri, https_port: u16) -> Option<String> {
+    let authority: http::uri::Authority = host?.parse().ok()?;
+    let path_and_query = uri.path_and_query().map(|v| v.as_str()).unwrap_or("/");
+    Some(match https_port {
+        443 => format!("https://{}{}", authority.host(), path_and_query),
+        port => format!("https://{}:{}{}", authority.host(), port, path_and_query),
+    })
 }
 
-async fn you_won(req: Request, next: Next) -> Response {
+async fn you_won(site: Site, req: Request, next: Next) -> Response {
     if let Some(referer) = req
         .headers()
         .get(header::REFERER)
@@ -101,7 +392,10 @@ async fn you_won(req: Request, next: Next) -> Response {
                 .path_and_query()
                 .map(|pq| pq.to_string())
                 .unwrap_or_else(|| "/".to_string());
-            let template = YouWonTemplate { original_path };
+            let template = YouWonTemplate {
+                site,
+                original_path,
+            };
             return match template.render() {
                 Ok(html) => Html(html).into_response(),
                 Err(_) => (
@@ -116,29 +410,231 @@ async fn you_won(req: Request, next: Next) -> Response {
     next.run(req).await
 }
 
+// the redoc page and htmx come from their cdns, the inline scripts and styles of the templates are
+// still allowed as long as they exist
+const CONTENT_SECURITY_POLICY: &str = "default-src 'self'; \
+    script-src 'self' 'unsafe-inline' https://unpkg.com https://cdn.redoc.ly; \
+    style-src 'self' 'unsafe-inline' https://fonts.googleapis.com; \
+    font-src 'self' https://fonts.gstatic.com; \
+    img-src 'self' data: https://cdn.redoc.ly; \
+    worker-src 'self' blob:; \
+    object-src 'none'; \
+    base-uri 'self'; \
+    frame-ancestors 'none'";
+const STRICT_TRANSPORT_SECURITY: &str = "max-age=31536000; includeSubDomains";
+
+// an on demand refresh downloads a cow.txt there and then so a client may only ask for one every so
+// often, whichever cow it asks about
+#[derive(Clone)]
+struct RefreshLimiter {
+    interval: Duration,
+    last_refreshed_at: Arc<Mutex<HashMap<IpAddr, Instant>>>,
+}
+
+impl RefreshLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_refreshed_at: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn try_refresh(&self, client: IpAddr, now: Instant) -> bool {
+        let mut last_refreshed_at = self.last_refreshed_at.lock().unwrap();
+        // clients which may refresh again are forgotten so that the map doesn't keep growing
+        last_refreshed_at.retain(|_, at| now.duration_since(*at) < self.interval);
+        match last_refreshed_at.entry(client) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+}
+
+async fn limit_refreshes(limiter: RefreshLimiter, req: Request, next: Next) -> Response {
+    let client = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|connect_info| connect_info.0.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    if !limiter.try_refresh(client, Instant::now()) {
+        return AppError::TooManyRequests.into_response();
+    }
+    next.run(req).await
+}
+
+// sniffing and leaking full urls is never wanted, hsts and the csp are only strict when configured
+// as they would get in the way of serving plain http from localhost during development
+async fn security_headers(strict: bool, req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::REFERRER_POLICY,
+        HeaderValue::from_static("strict-origin-when-cross-origin"),
+    );
+    if strict {
+        headers.insert(
+            header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_static(STRICT_TRANSPORT_SECURITY),
+        );
+        headers.insert(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static(CONTENT_SECURITY_POLICY),
+        );
+    }
+    response
+}
+
+// errors are json so that api clients can parse them, browsers get a page which fits the website
+async fn html_error_pages(site: Site, req: Request, next: Next) -> Response {
+    let accepts_html = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    let response = next.run(req).await;
+    if !accepts_html || !is_json(&response) {
+        return response;
+    }
+
+    let rendered = match response.status() {
+        StatusCode::NOT_FOUND => NotFoundTemplate { site }.render(),
+        StatusCode::INTERNAL_SERVER_ERROR => InternalErrorTemplate { site }.render(),
+        _ => return response,
+    };
+    match rendered {
+        Ok(html) => (response.status(), Html(html)).into_response(),
+        Err(err) => {
+            log::error!("failed to render an error page: {:#}", err);
+            response
+        }
+    }
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"))
+}
+
 async fn handle_get_index<D>(State(deps): State<D>) -> std::result::Result<Html<String>, AppError>
 where
     D: Deps,
 {
     let herd = deps.get_herd_handler().handle().await?;
+    Ok(Html(render_index(&herd, deps.site())?))
+}
+
+async fn handle_get_herd_partial<D>(
+    State(deps): State<D>,
+) -> std::result::Result<Html<String>, AppError>
+where
+    D: Deps,
+{
+    let herd = deps.get_herd_handler().handle().await?;
+    Ok(Html(render_herd_partial(&herd)?))
+}
+
+fn render_index(herd: &app::Herd, site: &Site) -> askama::Result<String> {
     let template = IndexTemplate {
+        site: site.clone(),
         cows: herd.cows().iter().map(|v| v.into()).collect(),
     };
-    Ok(Html(template.render()?))
+    template.render()
+}
+
+fn render_herd_partial(herd: &app::Herd) -> askama::Result<String> {
+    let template = HerdPartialTemplate {
+        cows: herd.cows().iter().map(|v| v.into()).collect(),
+    };
+    template.render()
+}
+
+async fn handle_get_herd_txt<D>(
+    State(deps): State<D>,
+) -> std::result::Result<impl IntoResponse, AppError>
+where
+    D: Deps,
+{
+    let herd = deps.get_herd_handler().handle().await?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        render_herd_txt(&herd),
+    ))
 }
 
-async fn handle_get_rfc() -> std::result::Result<Html<String>, AppError> {
-    let template = RfcTemplate {};
+// the same rows as the html herd so that curl users see exactly what visitors see
+fn render_herd_txt(herd: &app::Herd) -> String {
+    let cows: Vec<TemplateCow> = herd.cows().iter().map(|v| v.into()).collect();
+    let header = ("NAME", "STATUS", "LAST SEEN");
+    let name_width = cows
+        .iter()
+        .map(|cow| cow.name_with_kind.name.chars().count())
+        .chain([header.0.len()])
+        .max()
+        .unwrap_or_default();
+    let status_width = CowStatus::HaveNotCheckedYet.to_string().len();
+
+    let mut txt = format!(
+        "{:<name_width$}  {:<status_width$}  {}\n",
+        header.0, header.1, header.2
+    );
+    for cow in &cows {
+        txt.push_str(&format!(
+            "{:<name_width$}  {:<status_width$}  {}\n",
+            cow.name_with_kind.name,
+            cow.status.to_string(),
+            cow.last_seen
+        ));
+    }
+    txt
+}
+
+// crawlers are asked to stay away from the whole site unless indexing is allowed
+fn robots_txt(allow_indexing: bool) -> Response {
+    let disallow = if allow_indexing { "" } else { "/" };
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        format!("User-agent: *\nDisallow: {}\n", disallow),
+    )
+        .into_response()
+}
+
+async fn handle_get_rfc<D>(State(deps): State<D>) -> std::result::Result<Html<String>, AppError>
+where
+    D: Deps,
+{
+    let template = RfcTemplate {
+        site: deps.site().clone(),
+    };
     Ok(Html(template.render()?))
 }
 
-async fn handle_get_new() -> std::result::Result<Html<String>, AppError> {
-    let template = NewTemplate {};
+async fn handle_get_new<D>(State(deps): State<D>) -> std::result::Result<Html<String>, AppError>
+where
+    D: Deps,
+{
+    let template = NewTemplate {
+        site: deps.site().clone(),
+    };
     Ok(Html(template.render()?))
 }
 
-async fn handle_get_cves() -> std::result::Result<Html<String>, AppError> {
-    let template = CvesTemplate {};
+async fn handle_get_cves<D>(State(deps): State<D>) -> std::result::Result<Html<String>, AppError>
+where
+    D: Deps,
+{
+    let template = CvesTemplate {
+        site: deps.site().clone(),
+    };
     Ok(Html(template.render()?))
 }
 
@@ -147,19 +643,42 @@ async fn handle_static(uri: axum::http::Uri) -> impl IntoResponse {
 
     match STATIC_DIR.get_file(path) {
         Some(file) => match get_mime_type(path) {
-            Ok(mime) => ([(header::CONTENT_TYPE, mime)], file.contents()).into_response(),
+            Ok(mime) => {
+                let mut response =
+                    ([(header::CONTENT_TYPE, mime)], file.contents()).into_response();
+                if is_versioned_static_request(path, uri.query()) {
+                    response.headers_mut().insert(
+                        header::CACHE_CONTROL,
+                        header::HeaderValue::from_static(IMMUTABLE_CACHE_CONTROL),
+                    );
+                }
+                response
+            }
             Err(_) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported file type").into_response(),
         },
-        None => {
-            let template = NotFoundTemplate {};
-            match template.render() {
-                Ok(html) => (StatusCode::NOT_FOUND, Html(html)).into_response(),
-                Err(_) => (StatusCode::NOT_FOUND, "File not found").into_response(),
-            }
-        }
+        None => AppError::NotFound("file not found".to_string()).into_response(),
     }
 }
 
+struct StaticAssetIntegrity {
+    integrity: &'static str,
+    version: &'static str,
+}
+
+fn static_asset_integrity(path: &str) -> Option<StaticAssetIntegrity> {
+    static_integrity::STATIC_INTEGRITY
+        .iter()
+        .find(|(asset_path, _, _)| *asset_path == path)
+        .map(|(_, integrity, version)| StaticAssetIntegrity { integrity, version })
+}
+
+fn is_versioned_static_request(path: &str, query: Option<&str>) -> bool {
+    let (Some(asset), Some(query)) = (static_asset_integrity(path), query) else {
+        return false;
+    };
+    url::form_urlencoded::parse(query.as_bytes()).any(|(k, v)| k == "v" && v == asset.version)
+}
+
 fn get_mime_type(path: &str) -> std::result::Result<&'static str, ()> {
     if path.ends_with(".png") {
         Ok("image/png")
@@ -169,30 +688,316 @@ fn get_mime_type(path: &str) -> std::result::Result<&'static str, ()> {
         Ok("text/plain; charset=utf-8")
     } else if path.ends_with(".yaml") {
         Ok("text/yaml")
+    } else if path.ends_with(".js") {
+        Ok("text/javascript; charset=utf-8")
     } else {
         Err(())
     }
 }
 
-async fn handle_get_metrics<D>(State(deps): State<D>) -> std::result::Result<String, AppError>
+async fn handle_get_metrics<D>(
+    State(deps): State<D>,
+    headers: HeaderMap,
+) -> std::result::Result<Response, AppError>
 where
     D: Deps,
 {
+    authorize_metrics_scrape(deps.metrics_bearer_token(), &headers)?;
+    encode_metrics(&deps.metrics().gather(), &headers)
+}
+
+// scrapers which don't ask for openmetrics keep getting the prometheus text format
+fn encode_metrics(
+    families: &[prometheus::proto::MetricFamily],
+    headers: &HeaderMap,
+) -> std::result::Result<Response, AppError> {
+    let wants_open_metrics = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/openmetrics-text"));
+    if wants_open_metrics {
+        return Ok((
+            [(header::CONTENT_TYPE, open_metrics::CONTENT_TYPE)],
+            open_metrics::encode(families),
+        )
+            .into_response());
+    }
     let encoder = TextEncoder::new();
-    let families = deps.metrics().gather();
-    Ok(encoder.encode_to_string(&families)?)
+    Ok(encoder.encode_to_string(families)?.into_response())
+}
+
+// metrics stay public unless a token is configured so that local development doesn't need one
+fn authorize_metrics_scrape(
+    expected_token: Option<&str>,
+    headers: &HeaderMap,
+) -> std::result::Result<(), AppError> {
+    let Some(expected_token) = expected_token else {
+        return Ok(());
+    };
+    authorize_bearer(expected_token, headers)
+}
+
+// unlike metrics, what only the owner may see stays closed until a token is configured
+fn authorize_owner(
+    expected_token: Option<&str>,
+    headers: &HeaderMap,
+) -> std::result::Result<(), AppError> {
+    let Some(expected_token) = expected_token else {
+        return Err(AppError::Forbidden(
+            "no owner bearer token is configured".to_string(),
+        ));
+    };
+    authorize_bearer(expected_token, headers)
 }
 
-async fn handle_get_herd<D>(State(deps): State<D>) -> std::result::Result<Json<APIHerd>, AppError>
+fn authorize_bearer(
+    expected_token: &str,
+    headers: &HeaderMap,
+) -> std::result::Result<(), AppError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(AppError::Unauthorized)?;
+    if !bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())) {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(())
+}
+
+async fn handle_get_herd<D>(
+    State(deps): State<D>,
+    Query(query): Query<APIHerdQuery>,
+) -> std::result::Result<APIJson<APIHerd>, AppError>
+where
+    D: Deps,
+{
+    let tag = query.tag.map(domain::Tag::new).transpose()?;
+    let herd = deps.get_herd_handler().handle().await?;
+    Ok(APIJson::new(
+        APIHerd::new(&herd, tag.as_ref(), deps.display_format()),
+        deps.json_field_naming(),
+    ))
+}
+
+async fn handle_get_probe<D>(
+    State(deps): State<D>,
+    Query(query): Query<APIHerdQuery>,
+) -> std::result::Result<(StatusCode, APIJson<APIProbe>), AppError>
 where
     D: Deps,
 {
+    let tag = query.tag.map(domain::Tag::new).transpose()?;
     let herd = deps.get_herd_handler().handle().await?;
-    Ok(Json(APIHerd::from(&herd)))
+    let probe = APIProbe::new(&herd, tag.as_ref());
+    Ok((
+        probe.status(),
+        APIJson::new(probe, deps.json_field_naming()),
+    ))
+}
+
+async fn handle_get_cow<D>(
+    State(deps): State<D>,
+    Path(url): Path<String>,
+) -> std::result::Result<APIJson<APICow>, AppError>
+where
+    D: Deps,
+{
+    Ok(APIJson::new(
+        get_api_cow(deps.get_cow_handler(), url, deps.display_format()).await?,
+        deps.json_field_naming(),
+    ))
+}
+
+async fn handle_get_validate_url<D>(
+    State(deps): State<D>,
+    Query(query): Query<APIValidateUrlQuery>,
+) -> std::result::Result<APIJson<APIValidatedUrl>, AppError>
+where
+    D: Deps,
+{
+    let name = domain::validate_cow_base_url(&query.url, deps.host_policy())?;
+    Ok(APIJson::new(
+        APIValidatedUrl {
+            url: name.url().to_string(),
+        },
+        deps.json_field_naming(),
+    ))
+}
+
+// the url arrives already percent-decoded
+async fn get_api_cow(
+    handler: &impl GetCowHandler,
+    url: String,
+    display_format: &DisplayFormat,
+) -> std::result::Result<APICow, AppError> {
+    let name = domain::VisibleName::new(url)?;
+    let cow = handler
+        .handle(&app::GetCow::new(name))
+        .await?
+        .ok_or_else(|| AppError::NotFound("cow does not exist".to_string()))?;
+    Ok(APICow::new(&cow, display_format))
+}
+
+async fn handle_get_cow_body<D>(
+    State(deps): State<D>,
+    Path(url): Path<String>,
+) -> std::result::Result<impl IntoResponse, AppError>
+where
+    D: Deps,
+{
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        get_cow_body(deps.get_cow_body_handler(), url).await?,
+    ))
+}
+
+async fn get_cow_body(
+    handler: &impl GetCowBodyHandler,
+    url: String,
+) -> std::result::Result<String, AppError> {
+    let name = domain::VisibleName::new(url)?;
+    handler
+        .handle(&app::GetCowBody::new(name))
+        .await?
+        .ok_or_else(|| AppError::NotFound("no body was stored for this cow".to_string()))
+}
+
+async fn handle_get_cow_audit<D>(
+    State(deps): State<D>,
+    Path(url): Path<String>,
+    headers: HeaderMap,
+) -> std::result::Result<APIJson<APICowAudit>, AppError>
+where
+    D: Deps,
+{
+    authorize_owner(deps.owner_bearer_token(), &headers)?;
+    let name = domain::VisibleName::new(url)?;
+    let changes = deps
+        .get_cow_audit_handler()
+        .handle(&app::GetCowAudit::new(name))
+        .await?
+        .ok_or_else(|| AppError::NotFound("cow does not exist".to_string()))?;
+    Ok(APIJson::new(
+        APICowAudit::new(&changes, deps.display_format()),
+        deps.json_field_naming(),
+    ))
+}
+
+async fn handle_get_cow_notes<D>(
+    State(deps): State<D>,
+    Path(url): Path<String>,
+    headers: HeaderMap,
+) -> std::result::Result<APIJson<APICowNotes>, AppError>
+where
+    D: Deps,
+{
+    authorize_owner(deps.owner_bearer_token(), &headers)?;
+    let name = domain::VisibleName::new(url)?;
+    let notes = deps
+        .get_cow_notes_handler()
+        .handle(&app::GetCowNotes::new(name))
+        .await?
+        .ok_or_else(|| AppError::NotFound("cow does not exist".to_string()))?;
+    Ok(APIJson::new(
+        APICowNotes { notes },
+        deps.json_field_naming(),
+    ))
+}
+
+async fn handle_get_insights<D>(
+    State(deps): State<D>,
+) -> std::result::Result<APIJson<APIInsights>, AppError>
+where
+    D: Deps,
+{
+    let insights = deps.get_insights_handler().handle().await?;
+    Ok(APIJson::new(
+        APIInsights::new(&insights, deps.display_format()),
+        deps.json_field_naming(),
+    ))
+}
+
+async fn handle_get_status<D>(
+    State(deps): State<D>,
+) -> std::result::Result<APIJson<APIStatus>, AppError>
+where
+    D: Deps,
+{
+    let last_update = deps.get_status_handler().handle().await?;
+    Ok(APIJson::new(
+        APIStatus::new(&last_update, deps.display_format()),
+        deps.json_field_naming(),
+    ))
+}
+
+async fn handle_post_refresh<D>(
+    State(deps): State<D>,
+    Json(payload): Json<APIRefreshRequest>,
+) -> std::result::Result<APIJson<APICow>, AppError>
+where
+    D: Deps,
+{
+    let name = domain::VisibleName::new(payload.name)?;
+    let cow = deps
+        .refresh_cow_handler()
+        .handle(&app::RefreshCow::new(name))
+        .await?;
+    Ok(APIJson::new(
+        APICow::new(&cow, deps.display_format()),
+        deps.json_field_naming(),
+    ))
+}
+
+async fn handle_post_admin_reload<D>(
+    State(deps): State<D>,
+    headers: HeaderMap,
+) -> std::result::Result<APIJson<APIReloadSummary>, AppError>
+where
+    D: Deps,
+{
+    authorize_owner(deps.owner_bearer_token(), &headers)?;
+    let summary = deps.reload_config_handler().handle().await?;
+    Ok(APIJson::new(
+        APIReloadSummary::from(&summary),
+        deps.json_field_naming(),
+    ))
+}
+
+// without a level the configured one is restored
+async fn handle_post_admin_loglevel<D>(
+    State(deps): State<D>,
+    headers: HeaderMap,
+    Query(query): Query<APILogLevelQuery>,
+) -> std::result::Result<APIJson<APILogLevel>, AppError>
+where
+    D: Deps,
+{
+    authorize_owner(deps.owner_bearer_token(), &headers)?;
+    let command = match query.level {
+        Some(level) => app::ChangeLogLevel::Set(
+            level
+                .parse()
+                .map_err(|_| AppError::InvalidInput(format!("invalid log level: {level}")))?,
+        ),
+        None => app::ChangeLogLevel::Revert,
+    };
+    let level = deps.change_log_level_handler().handle(&command).await?;
+    Ok(APIJson::new(
+        APILogLevel {
+            level: level.to_string().to_lowercase(),
+        },
+        deps.json_field_naming(),
+    ))
 }
 
-async fn handle_get_redoc() -> std::result::Result<Html<String>, AppError> {
-    let t = RedocTemplate {};
+async fn handle_get_redoc<D>(State(deps): State<D>) -> std::result::Result<Html<String>, AppError>
+where
+    D: Deps,
+{
+    let t = RedocTemplate {
+        site: deps.site().clone(),
+    };
     Ok(Html(t.render()?))
 }
 
@@ -201,73 +1006,304 @@ struct APIHerd {
     cows: Vec<APICow>,
 }
 
-impl From<&app::Herd> for APIHerd {
-    fn from(value: &app::Herd) -> Self {
+impl APIHerd {
+    fn new(value: &app::Herd, tag: Option<&domain::Tag>, display_format: &DisplayFormat) -> Self {
         Self {
-            cows: value.cows().iter().map(|v| v.into()).collect(),
+            cows: value
+                .cows()
+                .iter()
+                .filter(|v| tag.is_none_or(|tag| v.tags().contains(tag)))
+                .map(|v| APICow::new(v, display_format))
+                .collect(),
         }
     }
 }
 
+// a cow which wasn't checked yet isn't known to be down, otherwise every restart would page someone
 #[derive(Serialize)]
-struct APICow {
-    name: String,
-    character: String,
-    last_seen: Option<String>,
+struct APIProbe {
+    down: Vec<String>,
 }
 
-const DT_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
-
-impl From<&app::Cow> for APICow {
-    fn from(value: &app::Cow) -> Self {
-        let name_str = match value.name() {
-            crate::domain::Name::Visible(v) => v.url().to_string(),
-            crate::domain::Name::Censored(c) => c.url().to_string(),
-        };
-        let character_str = match value.character() {
-            crate::domain::Character::Brave => "brave".to_string(),
-            crate::domain::Character::Shy => "shy".to_string(),
-        };
+impl APIProbe {
+    fn new(value: &app::Herd, tag: Option<&domain::Tag>) -> Self {
         Self {
-            name: name_str,
-            character: character_str,
-            last_seen: value.last_seen().map(|dt| dt.format(DT_FORMAT)),
+            down: value
+                .cows()
+                .iter()
+                .filter(|v| tag.is_none_or(|tag| v.tags().contains(tag)))
+                .filter(|v| matches!(v.status(), app::CowStatus::RanAway))
+                .map(|v| TemplateCowName::from(v.name()).name)
+                .collect(),
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        if self.down.is_empty() {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
         }
     }
 }
 
-#[derive(Template)]
-#[template(path = "index.html")]
-struct IndexTemplate {
-    cows: Vec<TemplateCow>,
+#[derive(Deserialize)]
+struct APIHerdQuery {
+    tag: Option<String>,
 }
 
-#[derive(Template)]
-#[template(path = "rfc.html")]
-struct RfcTemplate {}
+#[derive(Serialize)]
+struct APIInsights {
+    longest_lived: Option<APICow>,
+    newest: Option<APICow>,
+    flakiest: Option<APICow>,
+}
 
-#[derive(Template)]
-#[template(path = "new.html")]
-struct NewTemplate {}
+impl APIInsights {
+    fn new(value: &app::Insights, display_format: &DisplayFormat) -> Self {
+        Self {
+            longest_lived: value
+                .longest_lived()
+                .map(|v| APICow::new(v, display_format)),
+            newest: value.newest().map(|v| APICow::new(v, display_format)),
+            flakiest: value.flakiest().map(|v| APICow::new(v, display_format)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct APIStatus {
+    last_update_started: Option<String>,
+    last_update_finished: Option<String>,
+    last_update_result: Option<String>,
+}
+
+impl APIStatus {
+    fn new(value: &app::LastUpdate, display_format: &DisplayFormat) -> Self {
+        Self {
+            last_update_started: value.started().map(|dt| display_format.format(dt)),
+            last_update_finished: value.finished().map(|dt| display_format.format(dt)),
+            last_update_result: value.result().map(|v| match v {
+                app::UpdateResult::Succeeded => "succeeded".to_string(),
+                app::UpdateResult::Failed => "failed".to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct APIRefreshRequest {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct APIValidateUrlQuery {
+    url: String,
+}
+
+#[derive(Serialize)]
+struct APIValidatedUrl {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct APILogLevelQuery {
+    level: Option<String>,
+}
+
+#[derive(Serialize)]
+struct APILogLevel {
+    level: String,
+}
+
+#[derive(Serialize)]
+struct APIReloadSummary {
+    added: Vec<String>,
+    removed: Vec<String>,
+    character_changed: Vec<String>,
+    nickname_changed: Vec<String>,
+    tags_changed: Vec<String>,
+}
+
+impl From<&app::ReloadSummary> for APIReloadSummary {
+    fn from(value: &app::ReloadSummary) -> Self {
+        let urls = |names: &[crate::domain::VisibleName]| {
+            names.iter().map(|v| v.url().to_string()).collect()
+        };
+        Self {
+            added: urls(value.added()),
+            removed: urls(value.removed()),
+            character_changed: urls(value.character_changed()),
+            nickname_changed: urls(value.nickname_changed()),
+            tags_changed: urls(value.tags_changed()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct APICow {
+    name: String,
+    nickname: Option<String>,
+    tags: Vec<String>,
+    character: String,
+    status: String,
+    added_at: String,
+    last_seen: Option<String>,
+    next_check_at: Option<String>,
+    check_interval_seconds: u64,
+    missing_reason: Option<String>,
+    unexpected_character: bool,
+}
+
+impl APICow {
+    fn new(value: &app::Cow, display_format: &DisplayFormat) -> Self {
+        let name_str = match value.name() {
+            crate::domain::Name::Visible(v) => v.url().to_string(),
+            crate::domain::Name::Censored(c) => c.url().to_string(),
+        };
+        Self {
+            name: name_str,
+            nickname: value.nickname().map(|v| v.to_string()),
+            tags: value.tags().iter().map(|v| v.to_string()).collect(),
+            character: api_character(value.character()),
+            status: CowStatus::from(value.status()).to_string(),
+            added_at: display_format.format(value.added_at()),
+            last_seen: value.last_seen().map(|dt| display_format.format(dt)),
+            next_check_at: value.next_check_at().map(|dt| display_format.format(dt)),
+            check_interval_seconds: value.check_interval().as_seconds() as u64,
+            missing_reason: value.missing_reason().map(|reason| match reason {
+                MissingReason::HttpStatus(status) => format!("http-status-{status}"),
+                MissingReason::Timeout => "timeout".to_string(),
+                MissingReason::DnsError => "dns-error".to_string(),
+                MissingReason::ValidationFailed => "validation-failed".to_string(),
+                MissingReason::ConnectionRefused => "connection-refused".to_string(),
+            }),
+            unexpected_character: value.unexpected_character(),
+        }
+    }
+}
+
+fn api_character(character: &domain::Character) -> String {
+    match character {
+        domain::Character::Brave => "brave".to_string(),
+        domain::Character::Shy => "shy".to_string(),
+    }
+}
+
+#[derive(Serialize)]
+struct APICowNotes {
+    notes: String,
+}
+
+#[derive(Serialize)]
+struct APICowAudit {
+    character_changes: Vec<APICharacterChange>,
+}
+
+impl APICowAudit {
+    fn new(changes: &[domain::CharacterChange], display_format: &DisplayFormat) -> Self {
+        Self {
+            character_changes: changes
+                .iter()
+                .map(|change| APICharacterChange {
+                    old_character: api_character(change.old_character()),
+                    new_character: api_character(change.new_character()),
+                    changed_at: display_format.format(change.changed_at()),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct APICharacterChange {
+    old_character: String,
+    new_character: String,
+    changed_at: String,
+}
+
+#[derive(Template)]
+#[template(path = "index.html")]
+struct IndexTemplate {
+    site: Site,
+    cows: Vec<TemplateCow>,
+}
+
+#[derive(Template)]
+#[template(path = "herd.html")]
+struct HerdPartialTemplate {
+    cows: Vec<TemplateCow>,
+}
+
+#[derive(Template)]
+#[template(path = "rfc.html")]
+struct RfcTemplate {
+    site: Site,
+}
+
+#[derive(Template)]
+#[template(path = "new.html")]
+struct NewTemplate {
+    site: Site,
+}
 
 #[derive(Template)]
 #[template(path = "cves.html")]
-struct CvesTemplate {}
+struct CvesTemplate {
+    site: Site,
+}
 
 #[derive(Template)]
 #[template(path = "redoc.html")]
-struct RedocTemplate {}
+struct RedocTemplate {
+    site: Site,
+}
 
 #[derive(Template)]
 #[template(path = "not_found.html")]
-struct NotFoundTemplate {}
+struct NotFoundTemplate {
+    site: Site,
+}
+
+#[derive(Template)]
+#[template(path = "internal_error.html")]
+struct InternalErrorTemplate {
+    site: Site,
+}
 
 #[derive(Template)]
 #[template(path = "you_won.html")]
 struct YouWonTemplate {
+    site: Site,
     original_path: String,
 }
 
+mod filters {
+    use askama::filters::Safe;
+    use std::fmt::Display;
+
+    fn lookup(path: impl Display) -> askama::Result<super::StaticAssetIntegrity> {
+        let path = path.to_string();
+        super::static_asset_integrity(&path)
+            .ok_or_else(|| askama::Error::custom(format!("unknown static asset: {path}")))
+    }
+
+    #[askama::filter_fn]
+    pub fn integrity(
+        path: impl Display,
+        _: &dyn askama::Values,
+    ) -> askama::Result<Safe<&'static str>> {
+        Ok(Safe(lookup(path)?.integrity))
+    }
+
+    #[askama::filter_fn]
+    pub fn versioned(path: impl Display, _: &dyn askama::Values) -> askama::Result<String> {
+        let path = path.to_string();
+        let asset = lookup(&path)?;
+        Ok(format!("/{}?v={}", path, asset.version))
+    }
+}
+
 struct TemplateCowName {
     name: String,
     kind: TemplateCowNameKind,
@@ -296,9 +1332,15 @@ pub enum TemplateCowNameKind {
 
 struct TemplateCow {
     name_with_kind: TemplateCowName,
+    nickname: Option<String>,
     last_seen: String,
+    next_check: Option<String>,
     status: CowStatus,
     is_new: bool,
+    domain_not_found: bool,
+    first_check_overdue: bool,
+    unexpected_character: bool,
+    missing_reason: Option<String>,
 }
 
 lazy_static::lazy_static! {
@@ -323,6 +1365,17 @@ impl From<&app::Cow> for TemplateCow {
                 }
             })
             .unwrap_or_else(|| "never".to_string());
+        // overdue cows are picked up by the next update
+        let next_check_str = value.next_check_at().map(|next_check_at| {
+            let until_next_check = (next_check_at - &now).as_seconds();
+            if until_next_check < 60.0 {
+                "now".to_string()
+            } else if until_next_check < 60.0 * 60.0 {
+                format!("in {}m", (until_next_check / 60.0) as u64)
+            } else {
+                format!("in {}h", (until_next_check / (60.0 * 60.0)) as u64)
+            }
+        });
         let is_new = value
             .first_seen()
             .map(|v| &now - v < *NEW_THRESHOLD)
@@ -330,9 +1383,21 @@ impl From<&app::Cow> for TemplateCow {
 
         Self {
             name_with_kind: value.name().into(),
+            nickname: value.nickname().map(|v| v.to_string()),
             last_seen: last_seen_str,
+            next_check: next_check_str,
             status: value.status().into(),
             is_new,
+            domain_not_found: value.domain_not_found(),
+            first_check_overdue: value.first_check_overdue(),
+            unexpected_character: value.unexpected_character(),
+            missing_reason: value.missing_reason().map(|reason| match reason {
+                MissingReason::HttpStatus(status) => format!("the server answered with {status}"),
+                MissingReason::Timeout => "the server took too long to answer".to_string(),
+                MissingReason::DnsError => "the domain doesn't resolve".to_string(),
+                MissingReason::ValidationFailed => "the server didn't serve a cow".to_string(),
+                MissingReason::ConnectionRefused => "the server refused to connect".to_string(),
+            }),
         }
     }
 }
@@ -341,6 +1406,7 @@ pub enum CowStatus {
     HappilyGrazing,
     RanAway,
     HaveNotCheckedYet,
+    Paused,
 }
 
 impl From<&app::CowStatus> for CowStatus {
@@ -349,6 +1415,7 @@ impl From<&app::CowStatus> for CowStatus {
             app::CowStatus::HappilyGrazing => CowStatus::HappilyGrazing,
             app::CowStatus::RanAway => CowStatus::RanAway,
             app::CowStatus::HaveNotCheckedYet => CowStatus::HaveNotCheckedYet,
+            app::CowStatus::Paused => CowStatus::Paused,
         }
     }
 }
@@ -359,22 +1426,67 @@ impl Display for CowStatus {
             CowStatus::HappilyGrazing => write!(f, "happily-grazing"),
             CowStatus::RanAway => write!(f, "ran-away"),
             CowStatus::HaveNotCheckedYet => write!(f, "have-not-checked-yet"),
+            CowStatus::Paused => write!(f, "paused"),
         }
     }
 }
 
 pub trait Deps {
     fn get_herd_handler(&self) -> &impl GetHerdHandler;
+    fn get_cow_handler(&self) -> &impl GetCowHandler;
+    fn get_cow_body_handler(&self) -> &impl GetCowBodyHandler;
+    fn get_cow_audit_handler(&self) -> &impl GetCowAuditHandler;
+    fn get_cow_notes_handler(&self) -> &impl GetCowNotesHandler;
+    fn get_insights_handler(&self) -> &impl GetInsightsHandler;
+    fn get_status_handler(&self) -> &impl GetStatusHandler;
+    fn refresh_cow_handler(&self) -> &impl RefreshCowHandler;
+    fn reload_config_handler(&self) -> &impl ReloadConfigHandler;
+    fn change_log_level_handler(&self) -> &impl ChangeLogLevelHandler;
     fn metrics(&self) -> &prometheus::Registry;
+    fn display_format(&self) -> &DisplayFormat;
+    fn metrics_bearer_token(&self) -> Option<&str>;
+    fn owner_bearer_token(&self) -> Option<&str>;
+    fn json_field_naming(&self) -> JsonFieldNaming;
+    fn site(&self) -> &Site;
+    fn host_policy(&self) -> &HostPolicy;
 }
 
 enum AppError {
+    RestartRequired(String),
+    Unauthorized,
+    TooManyRequests,
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    InvalidInput(String),
     UnknownError,
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
+            AppError::RestartRequired(field) => (
+                StatusCode::CONFLICT,
+                format!("changing {field} requires a restart, reload can't apply it"),
+            ),
+            AppError::Forbidden(message) => (StatusCode::FORBIDDEN, message),
+            AppError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            AppError::Conflict(message) => (StatusCode::CONFLICT, message),
+            AppError::InvalidInput(message) => (StatusCode::BAD_REQUEST, message),
+            AppError::Unauthorized => {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer")],
+                    Json(TransportError {
+                        message: "Unauthorized".into(),
+                    }),
+                )
+                    .into_response();
+            }
+            AppError::TooManyRequests => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, try again later".into(),
+            ),
             AppError::UnknownError => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal server error".into(),
@@ -388,19 +1500,1399 @@ impl<E> From<E> for AppError
 where
     E: Into<Error>,
 {
-    fn from(_err: E) -> Self {
-        Self::UnknownError
-    }
-}
-
-impl From<askama::Error> for AppError {
-    fn from(_err: askama::Error) -> Self {
-        Self::UnknownError
+    fn from(err: E) -> Self {
+        match err.into() {
+            Error::ConfigChangeRequiresRestart(field) => Self::RestartRequired(field),
+            Error::NotFound(message) => Self::NotFound(message),
+            Error::Conflict(message) => Self::Conflict(message),
+            Error::Forbidden(message) => Self::Forbidden(message),
+            Error::ValidationError(err) => Self::InvalidInput(err.to_string()),
+            // the details would only confuse visitors but are needed to fix the template
+            Error::Template(err) => {
+                log::error!("failed to render a template: {:#}", err);
+                Self::UnknownError
+            }
+            _ => Self::UnknownError,
+        }
     }
 }
 
+// the fields of errors are single words so they read the same in every naming convention
 #[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 struct TransportError {
     message: String,
 }
+
+// the API types are declared in snake_case, every response goes through this so that the
+// configured naming convention is applied to all of them in the same way
+struct APIJson<T> {
+    value: T,
+    naming: JsonFieldNaming,
+}
+
+impl<T> APIJson<T> {
+    fn new(value: T, naming: JsonFieldNaming) -> Self {
+        Self { value, naming }
+    }
+}
+
+impl<T: Serialize> IntoResponse for APIJson<T> {
+    fn into_response(self) -> Response {
+        match serde_json::to_value(&self.value) {
+            Ok(value) => Json(rename_fields(value, self.naming)).into_response(),
+            Err(err) => {
+                log::error!("failed to serialize an API response: {}", err);
+                AppError::UnknownError.into_response()
+            }
+        }
+    }
+}
+
+fn rename_fields(value: serde_json::Value, naming: JsonFieldNaming) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(fields) => serde_json::Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (rename_field(&key, naming), rename_fields(value, naming)))
+                .collect(),
+        ),
+        serde_json::Value::Array(values) => serde_json::Value::Array(
+            values
+                .into_iter()
+                .map(|value| rename_fields(value, naming))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn rename_field(key: &str, naming: JsonFieldNaming) -> String {
+    match naming {
+        JsonFieldNaming::SnakeCase => key.to_string(),
+        JsonFieldNaming::CamelCase => key
+            .split('_')
+            .enumerate()
+            .map(|(i, word)| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) if i > 0 => first.to_uppercase().chain(chars).collect(),
+                    _ => word.to_string(),
+                }
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_integrity_manifest_contains_cow_txt() {
+        let asset =
+            static_asset_integrity("cow.txt").expect("cow.txt is missing from the manifest");
+        assert!(asset.integrity.starts_with("sha384-"));
+        assert!(is_versioned_static_request(
+            "cow.txt",
+            Some(&format!("v={}", asset.version))
+        ));
+        assert!(!is_versioned_static_request("cow.txt", Some("v=stale")));
+        assert!(!is_versioned_static_request("cow.txt", None));
+    }
+
+    #[test]
+    fn scripts_are_loaded_with_integrity_unlike_the_favicon() -> Result<()> {
+        let herd: app::Herd = domain::CensoredHerd::new(vec![]).try_into()?;
+        let html = render_index(&herd, &Site::new()).unwrap();
+
+        let grass = static_asset_integrity("grass.js").expect("grass.js is missing");
+        assert!(html.contains(&format!(
+            r#"<script src="/grass.js?v={}" integrity="{}"></script>"#,
+            grass.version, grass.integrity
+        )));
+        assert!(html.contains(
+            r#"<script src="https://unpkg.com/htmx.org@2.0.4/dist/htmx.min.js" integrity="sha384-"#
+        ));
+        let favicon = static_asset_integrity("favicon.ico").expect("favicon.ico is missing");
+        assert!(html.contains(&format!(
+            r#"<link rel="icon" href="/favicon.ico?v={}">"#,
+            favicon.version
+        )));
+        assert_eq!(
+            get_mime_type("grass.js"),
+            Ok("text/javascript; charset=utf-8")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn herd_partial_contains_censored_cow_rows() -> Result<()> {
+        let cows = [
+            domain::Cow::new(
+                domain::VisibleName::new("https://brave.example.com/cow.txt")?,
+                domain::Character::Brave,
+            ),
+            domain::Cow::new(
+                domain::VisibleName::new("https://shy.example.com/cow.txt")?,
+                domain::Character::Shy,
+            ),
+        ];
+        let herd: app::Herd = domain::CensoredHerd::new(
+            cows.iter()
+                .map(domain::CensoredCow::new)
+                .collect::<Result<Vec<_>>>()?,
+        )
+        .try_into()?;
+
+        let html = render_herd_partial(&herd).unwrap();
+
+        assert!(html.trim_start().starts_with("<ul class=\"cow-list\""));
+        assert!(!html.contains("<html"));
+        assert_eq!(html.matches("<li class=\"cow-item").count(), 2);
+        assert!(html.contains("https://brave.example.com/cow.txt"));
+        assert!(html.contains("https://***.*******.com/cow.txt"));
+        assert!(!html.contains("shy.example.com"));
+        assert!(!html.contains("domain not found"));
+        Ok(())
+    }
+
+    #[test]
+    fn herd_partial_hints_at_domains_which_are_gone() -> Result<()> {
+        let mut cow = domain::Cow::new(
+            domain::VisibleName::new("https://gone.example.com/cow.txt")?,
+            domain::Character::Brave,
+        );
+        cow.mark_as_domain_not_found()?;
+        let herd: app::Herd =
+            domain::CensoredHerd::new(vec![domain::CensoredCow::new(&cow)?]).try_into()?;
+
+        let html = render_herd_partial(&herd).unwrap();
+
+        assert!(html.contains("domain not found"));
+        Ok(())
+    }
+
+    #[test]
+    fn herd_flags_cows_with_an_unexpected_character() -> Result<()> {
+        let cows = [
+            domain::Cow::new(
+                domain::VisibleName::new("https://expected.example.com/cow.txt")?,
+                domain::Character::Brave,
+            )
+            .with_expected_character(Some(domain::Character::Brave)),
+            domain::Cow::new(
+                domain::VisibleName::new("https://unexpected.example.com/cow.txt")?,
+                domain::Character::Brave,
+            )
+            .with_expected_character(Some(domain::Character::Shy)),
+        ];
+        let herd: app::Herd = domain::CensoredHerd::new(
+            cows.iter()
+                .map(domain::CensoredCow::new)
+                .collect::<Result<Vec<_>>>()?,
+        )
+        .try_into()?;
+
+        let html = render_herd_partial(&herd).unwrap();
+        assert_eq!(
+            html.matches("character differs from the expected one")
+                .count(),
+            1
+        );
+
+        let api_herd = APIHerd::new(&herd, None, &DisplayFormat::default());
+        let flags: Vec<_> = api_herd
+            .cows
+            .iter()
+            .map(|cow| (cow.name.as_str(), cow.unexpected_character))
+            .collect();
+        assert_eq!(
+            flags,
+            vec![
+                ("https://expected.example.com/cow.txt", false),
+                ("https://unexpected.example.com/cow.txt", true)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn herd_partial_shows_nicknames_of_brave_cows_only() -> Result<()> {
+        let cows = [
+            domain::Cow::new(
+                domain::VisibleName::new("https://brave.example.com/cow.txt")?,
+                domain::Character::Brave,
+            )
+            .with_nickname(Some(domain::Nickname::new("Bessie")?)),
+            domain::Cow::new(
+                domain::VisibleName::new("https://anonymous.example.com/cow.txt")?,
+                domain::Character::Brave,
+            ),
+            domain::Cow::new(
+                domain::VisibleName::new("https://shy.example.com/cow.txt")?,
+                domain::Character::Shy,
+            )
+            .with_nickname(Some(domain::Nickname::new("Daisy")?)),
+        ];
+        let herd: app::Herd = domain::CensoredHerd::new(
+            cows.iter()
+                .map(domain::CensoredCow::new)
+                .collect::<Result<Vec<_>>>()?,
+        )
+        .try_into()?;
+
+        let html = render_herd_partial(&herd).unwrap();
+
+        assert_eq!(html.matches("<div class=\"cow-nickname\">").count(), 1);
+        assert!(html.contains("Bessie"));
+        assert!(html.contains("https://anonymous.example.com/cow.txt"));
+        assert!(!html.contains("Daisy"));
+        assert!(html.contains("https://***.*******.com/cow.txt"));
+
+        let api_herd = APIHerd::new(&herd, None, &DisplayFormat::default());
+        let nicknames: Vec<_> = api_herd
+            .cows
+            .iter()
+            .map(|cow| cow.nickname.as_deref())
+            .collect();
+        assert_eq!(nicknames, vec![None, Some("Bessie"), None]);
+        Ok(())
+    }
+
+    #[test]
+    fn herd_txt_is_a_table_of_censored_cows() -> Result<()> {
+        let cows = [
+            domain::Cow::new(
+                domain::VisibleName::new("https://brave.example.com/cow.txt")?,
+                domain::Character::Brave,
+            ),
+            domain::Cow::new_from_history(
+                domain::VisibleName::new("https://shy.example.com/cow.txt")?,
+                domain::Character::Shy,
+                Some(DateTime::now()),
+                Some(DateTime::now()),
+                Some(DateTime::now()),
+            ),
+        ];
+        let herd: app::Herd = domain::CensoredHerd::new(
+            cows.iter()
+                .map(domain::CensoredCow::new)
+                .collect::<Result<Vec<_>>>()?,
+        )
+        .try_into()?;
+
+        let txt = render_herd_txt(&herd);
+
+        assert_eq!(
+            txt,
+            [
+                "NAME                               STATUS                LAST SEEN\n",
+                "https://brave.example.com/cow.txt  have-not-checked-yet  never\n",
+                "https://***.*******.com/cow.txt    happily-grazing       very recently\n",
+            ]
+            .concat()
+        );
+        assert!(!txt.contains("shy.example.com"));
+        Ok(())
+    }
+
+    #[test]
+    fn probe_fails_when_a_critical_cow_is_down() -> Result<()> {
+        let long_ago = DateTime::now() - crate::domain::time::Duration::new_from_days(2);
+        let critical = vec![domain::Tag::new("critical")?];
+        let cows = [
+            domain::Cow::new_from_history(
+                domain::VisibleName::new("https://grazing.example.com/cow.txt")?,
+                domain::Character::Brave,
+                Some(DateTime::now()),
+                Some(DateTime::now()),
+                Some(DateTime::now()),
+            )
+            .with_tags(critical.clone()),
+            domain::Cow::new_from_history(
+                domain::VisibleName::new("https://down.example.com/cow.txt")?,
+                domain::Character::Brave,
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+                Some(DateTime::now()),
+            )
+            .with_tags(critical.clone()),
+            // shy cows don't reveal their tags so they are only considered without one
+            domain::Cow::new_from_history(
+                domain::VisibleName::new("https://shy.example.com/cow.txt")?,
+                domain::Character::Shy,
+                Some(long_ago.clone()),
+                Some(long_ago.clone()),
+                Some(DateTime::now()),
+            )
+            .with_tags(critical),
+            domain::Cow::new_from_history(
+                domain::VisibleName::new("https://other.example.com/cow.txt")?,
+                domain::Character::Brave,
+                Some(long_ago.clone()),
+                Some(long_ago),
+                Some(DateTime::now()),
+            ),
+        ];
+        let probe = |cows: &[domain::Cow], tag: Option<&str>| -> Result<APIProbe> {
+            let herd: app::Herd = domain::CensoredHerd::new(
+                cows.iter()
+                    .map(domain::CensoredCow::new)
+                    .collect::<Result<Vec<_>>>()?,
+            )
+            .try_into()?;
+            let tag = tag.map(domain::Tag::new).transpose()?;
+            Ok(APIProbe::new(&herd, tag.as_ref()))
+        };
+
+        assert_eq!(
+            probe(&cows[..1], Some("critical"))?.status(),
+            StatusCode::OK
+        );
+        assert_eq!(probe(&cows[..1], None)?.status(), StatusCode::OK);
+
+        let critical = probe(&cows, Some("critical"))?;
+        assert_eq!(critical.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(critical.down, vec!["https://down.example.com/cow.txt"]);
+        let all = probe(&cows, None)?;
+        assert_eq!(all.down.len(), 3);
+        assert!(
+            all.down
+                .contains(&"https://***.*******.com/cow.txt".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn paused_cows_are_shown_as_paused_and_never_fail_the_probe() -> Result<()> {
+        let long_ago = DateTime::now() - crate::domain::time::Duration::new_from_days(2);
+        let cow = domain::Cow::new_from_history(
+            domain::VisibleName::new("https://paused.example.com/cow.txt")?,
+            domain::Character::Brave,
+            Some(long_ago.clone()),
+            Some(long_ago),
+            Some(DateTime::now()),
+        )
+        .with_paused(true);
+        let herd: app::Herd =
+            domain::CensoredHerd::new(vec![domain::CensoredCow::new(&cow)?]).try_into()?;
+
+        let api_cow = APICow::new(&herd.cows()[0], &DisplayFormat::default());
+
+        assert_eq!(api_cow.status, "paused");
+        assert_eq!(APIProbe::new(&herd, None).status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[test]
+    fn herd_can_be_filtered_by_tag() -> Result<()> {
+        let cows = [
+            domain::Cow::new(
+                domain::VisibleName::new("https://dairy.example.com/cow.txt")?,
+                domain::Character::Brave,
+            )
+            .with_tags(vec![domain::Tag::new("dairy")?]),
+            domain::Cow::new(
+                domain::VisibleName::new("https://beef.example.com/cow.txt")?,
+                domain::Character::Brave,
+            )
+            .with_tags(vec![domain::Tag::new("beef")?]),
+            domain::Cow::new(
+                domain::VisibleName::new("https://untagged.example.com/cow.txt")?,
+                domain::Character::Brave,
+            ),
+        ];
+        let herd: app::Herd = domain::CensoredHerd::new(
+            cows.iter()
+                .map(domain::CensoredCow::new)
+                .collect::<Result<Vec<_>>>()?,
+        )
+        .try_into()?;
+        let names = |tag: Option<&domain::Tag>| -> Vec<String> {
+            APIHerd::new(&herd, tag, &DisplayFormat::default())
+                .cows
+                .into_iter()
+                .map(|cow| cow.name)
+                .collect()
+        };
+
+        assert_eq!(names(None).len(), 3);
+        assert_eq!(
+            names(Some(&domain::Tag::new("dairy")?)),
+            vec!["https://dairy.example.com/cow.txt"]
+        );
+        assert!(names(Some(&domain::Tag::new("veal")?)).is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn robots_txt_follows_the_indexing_policy() -> Result<()> {
+        for (allow_indexing, expected) in [
+            (false, "User-agent: *\nDisallow: /\n"),
+            (true, "User-agent: *\nDisallow: \n"),
+        ] {
+            let response = robots_txt(allow_indexing);
+            assert_eq!(
+                response.headers()[header::CONTENT_TYPE],
+                "text/plain; charset=utf-8"
+            );
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map_err(|err| anyhow::anyhow!(err))?;
+            assert_eq!(String::from_utf8_lossy(&bytes), expected);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn metrics_are_served_as_openmetrics_when_asked_for() -> Result<()> {
+        let metrics = crate::adapters::Metrics::new()?;
+        app::Metrics::record_application_handler_call(
+            &metrics,
+            "get_herd",
+            app::ApplicationHandlerCallResult::Ok,
+            crate::domain::time::Duration::new_from_seconds(1),
+        );
+        let families = metrics.registry().gather();
+        let body = |response: Response| async move {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map_err(|err| anyhow::anyhow!(err))?;
+            Ok::<_, Error>(String::from_utf8_lossy(&bytes).to_string())
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT,
+            "application/openmetrics-text; version=1.0.0"
+                .parse()
+                .unwrap(),
+        );
+        let response = encode_metrics(&families, &headers).ok().unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            open_metrics::CONTENT_TYPE
+        );
+        let open_metrics = body(response).await?;
+        assert!(open_metrics.ends_with("# EOF\n"), "{open_metrics}");
+        assert!(
+            open_metrics
+                .contains("# TYPE moooodotfarm_application_handler_calls_counter counter\n")
+        );
+        assert!(open_metrics.contains(
+            "moooodotfarm_application_handler_calls_counter_total{handler_name=\"get_herd\",result=\"ok\"} 1.0\n"
+        ));
+        assert!(open_metrics.contains("le=\"+Inf\""));
+
+        let response = encode_metrics(&families, &HeaderMap::new()).ok().unwrap();
+        assert!(!body(response).await?.contains("# EOF"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn errors_are_html_pages_for_browsers_and_json_for_api_clients() -> Result<()> {
+        use tower::ServiceExt;
+
+        let router = Router::new()
+            .route(
+                "/broken",
+                get(|| async { Err::<String, _>(AppError::UnknownError) }),
+            )
+            .fallback(handle_static)
+            .layer(axum::middleware::from_fn(|req, next| {
+                html_error_pages(Site::new(), req, next)
+            }));
+        let request = |path: &str, accept: &str| {
+            Request::builder()
+                .uri(path)
+                .header(header::ACCEPT, accept)
+                .body(axum::body::Body::empty())
+                .map_err(|err| anyhow::anyhow!(err))
+        };
+        let content_type = |response: &Response| {
+            response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        for (path, expected_status) in [
+            ("/missing.png", StatusCode::NOT_FOUND),
+            ("/broken", StatusCode::INTERNAL_SERVER_ERROR),
+        ] {
+            let response = router
+                .clone()
+                .oneshot(request(path, "text/html,application/xhtml+xml,*/*;q=0.8")?)
+                .await
+                .map_err(|err| anyhow::anyhow!(err))?;
+            assert_eq!(response.status(), expected_status, "{path}");
+            assert!(content_type(&response).starts_with("text/html"), "{path}");
+
+            let response = router
+                .clone()
+                .oneshot(request(path, "application/json")?)
+                .await
+                .map_err(|err| anyhow::anyhow!(err))?;
+            assert_eq!(response.status(), expected_status, "{path}");
+            assert_eq!(content_type(&response), "application/json", "{path}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn security_headers_are_only_strict_when_configured() -> Result<()> {
+        use tower::ServiceExt;
+
+        for (environment, expected_strict) in [
+            (Environment::Production, true),
+            (Environment::Development, false),
+        ] {
+            let config = config::Config::new(
+                "0.0.0.0:8080",
+                "0.0.0.0:9090",
+                environment,
+                "/moooodotfarm.db",
+            )?;
+            let strict = config.security_headers();
+            assert_eq!(strict, expected_strict);
+            let router = Router::new()
+                .route("/api/status", get(|| async { Json("ok") }))
+                .fallback(handle_static)
+                .layer(axum::middleware::from_fn(move |req, next| {
+                    security_headers(strict, req, next)
+                }));
+
+            for path in ["/api/status", "/cow.txt"] {
+                let response = router
+                    .clone()
+                    .oneshot(
+                        Request::builder()
+                            .uri(path)
+                            .body(axum::body::Body::empty())
+                            .map_err(|err| anyhow::anyhow!(err))?,
+                    )
+                    .await
+                    .map_err(|err| anyhow::anyhow!(err))?;
+                let headers = response.headers();
+                assert_eq!(
+                    headers.get(header::X_CONTENT_TYPE_OPTIONS),
+                    Some(&HeaderValue::from_static("nosniff")),
+                    "{path}"
+                );
+                assert!(headers.contains_key(header::REFERRER_POLICY), "{path}");
+                assert_eq!(
+                    headers.contains_key(header::STRICT_TRANSPORT_SECURITY),
+                    strict,
+                    "{path}"
+                );
+                assert_eq!(
+                    headers.contains_key(header::CONTENT_SECURITY_POLICY),
+                    strict,
+                    "{path}"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn branded_site() -> Result<Site> {
+        let logo = SiteImage::load(
+            &crate::fixtures::test_file_path("src/ports/http/static/cow.png").to_string_lossy(),
+        )?;
+        Ok(Site::new()
+            .with_title(Some("The Dairy Co. herd".to_string()))
+            .with_public_url(Some(&"https://cows.example.com/".parse()?))
+            .with_favicon(Some(logo.clone()))
+            .with_logo(Some(logo)))
+    }
+
+    #[test]
+    fn configured_branding_is_shown_on_the_index() -> Result<()> {
+        let herd = app::Herd::try_from(domain::CensoredHerd::new(vec![]))?;
+
+        let html = render_index(&herd, &branded_site()?)?;
+        for expected in [
+            "<title>moooooonitoring the cow.txt herd | The Dairy Co. herd</title>",
+            r#"<meta property="og:title" content="moooooonitoring the cow.txt herd | The Dairy Co. herd">"#,
+            r#"<meta property="og:image" content="https://cows.example.com/og-image.png">"#,
+            r#"<meta property="og:url" content="https://cows.example.com/">"#,
+            r#"<link rel="icon" href="/favicon.ico">"#,
+            r#"<img src="/logo" alt="The Dairy Co. herd""#,
+        ] {
+            assert!(html.contains(expected), "{expected}");
+        }
+
+        let html = render_index(&herd, &Site::new())?;
+        for expected in [
+            "<title>moooooonitoring the cow.txt herd</title>",
+            r#"<meta property="og:url" content="https://moooo.farm/">"#,
+            r#"<img src="/cow.png" alt="a very round cow""#,
+        ] {
+            assert!(html.contains(expected), "{expected}");
+        }
+
+        let html = render_index(&herd, &branded_site()?.with_title(None))?;
+        assert!(html.contains(r#"<img src="/logo" alt="logo""#));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn configured_branding_is_shown_on_every_page() -> Result<()> {
+        let config = test_config()?;
+        let deps = TestDeps::new().with_site(branded_site()?);
+
+        let response = send(&config, deps.clone(), Request::builder().uri("/rfc")).await?;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|err| anyhow::anyhow!(err))?;
+        let html = String::from_utf8_lossy(&body);
+        assert!(html.contains("<title>request for cows (rfc) | The Dairy Co. herd</title>"));
+        assert!(
+            html.contains(r#"<meta property="og:url" content="https://cows.example.com/rfc">"#)
+        );
+
+        let response = send(&config, deps, Request::builder().uri("/logo")).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE),
+            Some(&http::HeaderValue::from_static("image/png"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn site_image_overrides_must_be_images() {
+        assert!(
+            SiteImage::load(
+                &crate::fixtures::test_file_path("src/ports/http/static/cow.png").to_string_lossy()
+            )
+            .is_ok()
+        );
+        for path in [
+            "src/ports/http/static/cow.txt",
+            "src/ports/http/static/missing.ico",
+        ] {
+            assert!(
+                SiteImage::load(&crate::fixtures::test_file_path(path).to_string_lossy()).is_err(),
+                "{path}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn single_cow_can_be_fetched_by_its_url() -> Result<()> {
+        let database = crate::adapters::database::Database::new_in_memory()?;
+        let handler = crate::app::get_cow::GetCowHandler::new(
+            database.clone(),
+            crate::adapters::Metrics::new()?,
+            crate::app::get_herd::ShyCows::Hidden,
+        );
+        for (url, character) in [
+            (
+                "https://brave.example.com/cow.txt",
+                domain::Character::Brave,
+            ),
+            ("https://shy.example.com/cow.txt", domain::Character::Shy),
+        ] {
+            let name = domain::VisibleName::new(url)?;
+            app::Inventory::update(&database, &name, |_| {
+                Ok(Some(domain::Cow::new(name.clone(), character)))
+            })?;
+        }
+        let display_format = DisplayFormat::default();
+        let get = |url: &str| get_api_cow(&handler, url.to_string(), &display_format);
+
+        let cow = get("https://brave.example.com/cow.txt").await.ok().unwrap();
+        assert_eq!(cow.name, "https://brave.example.com/cow.txt");
+        assert_eq!(cow.status, "have-not-checked-yet");
+
+        for (url, expected_status) in [
+            ("https://absent.example.com/cow.txt", StatusCode::NOT_FOUND),
+            ("not a url", StatusCode::BAD_REQUEST),
+            ("https://example.com/no-tail", StatusCode::BAD_REQUEST),
+        ] {
+            let response = get(url).await.err().unwrap().into_response();
+            assert_eq!(response.status(), expected_status, "Failed for url: {url}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shy_cows_cant_be_told_apart_from_absent_cows() -> Result<()> {
+        let database = crate::adapters::database::Database::new_in_memory()?;
+        let handler = crate::app::get_cow::GetCowHandler::new(
+            database.clone(),
+            crate::adapters::Metrics::new()?,
+            crate::app::get_herd::ShyCows::Hidden,
+        );
+        let name = domain::VisibleName::new("https://shy.example.com/cow.txt")?;
+        app::Inventory::update(&database, &name, |_| {
+            Ok(Some(domain::Cow::new(name.clone(), domain::Character::Shy)))
+        })?;
+        let display_format = DisplayFormat::default();
+
+        let mut responses = vec![];
+        for url in [
+            "https://shy.example.com/cow.txt",
+            "https://absent.example.com/cow.txt",
+        ] {
+            let response = get_api_cow(&handler, url.to_string(), &display_format)
+                .await
+                .err()
+                .unwrap()
+                .into_response();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map_err(|err| anyhow::anyhow!(err))?;
+            responses.push((status, body));
+        }
+        assert_eq!(responses[0], responses[1]);
+        assert_eq!(responses[0].0, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bodies_are_served_for_brave_cows_only() -> Result<()> {
+        let database = crate::adapters::database::Database::new_in_memory()?;
+        let handler = crate::app::get_cow_body::GetCowBodyHandler::new(
+            database.clone(),
+            database.clone(),
+            crate::adapters::Metrics::new()?,
+        );
+        for (url, character) in [
+            (
+                "https://brave.example.com/cow.txt",
+                domain::Character::Brave,
+            ),
+            ("https://shy.example.com/cow.txt", domain::Character::Shy),
+        ] {
+            let name = domain::VisibleName::new(url)?;
+            app::Inventory::update(&database, &name, |_| {
+                Ok(Some(domain::Cow::new(name.clone(), character)))
+            })?;
+            app::CowBodies::put(&database, &name, "moo")?;
+        }
+
+        let body = get_cow_body(&handler, "https://brave.example.com/cow.txt".to_string())
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(body, "moo");
+
+        for (url, expected_status) in [
+            ("https://shy.example.com/cow.txt", StatusCode::NOT_FOUND),
+            ("https://absent.example.com/cow.txt", StatusCode::NOT_FOUND),
+        ] {
+            let response = get_cow_body(&handler, url.to_string())
+                .await
+                .err()
+                .unwrap()
+                .into_response();
+            assert_eq!(response.status(), expected_status, "Failed for url: {url}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn serves_https_when_tls_is_configured_until_cancelled() -> Result<()> {
+        let tls = load_tls(&config::TlsConfig::new(
+            crate::fixtures::test_file_path("src/ports/http/testdata/localhost.crt")
+                .to_string_lossy(),
+            crate::fixtures::test_file_path("src/ports/http/testdata/localhost.key")
+                .to_string_lossy(),
+        )?)
+        .await?;
+        let router = Router::new().route("/moo", get(|| async { "moo" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let cancellation_token = CancellationToken::new();
+        let server = tokio::spawn(serve(
+            listener,
+            Some(tls),
+            NormalizePathLayer::trim_trailing_slash().layer(router),
+            cancellation_token.clone(),
+        ));
+
+        let ca = std::fs::read(crate::fixtures::test_file_path(
+            "src/ports/http/testdata/ca.crt",
+        ))?;
+        let client = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(&ca)?)
+            .build()?;
+        let response = client
+            .get(format!("https://localhost:{port}/moo/"))
+            .send()
+            .await?;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await?, "moo");
+        assert!(
+            client
+                .get(format!("http://localhost:{port}/moo"))
+                .send()
+                .await
+                .is_err()
+        );
+
+        cancellation_token.cancel();
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .map_err(|_| anyhow::anyhow!("server didn't stop"))?
+            .map_err(|err| anyhow::anyhow!(err))??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn broken_tls_files_are_reported_at_startup() -> Result<()> {
+        let key_path = crate::fixtures::test_file_path("src/ports/http/testdata/localhost.key");
+        let tls = config::TlsConfig::new(key_path.to_string_lossy(), key_path.to_string_lossy())?;
+        assert!(load_tls(&tls).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn plain_http_is_redirected_to_the_https_port() {
+        let uri: Uri = "/api/herd?tag=dairy".parse().unwrap();
+        for (host, https_port, expected) in [
+            (
+                Some("moooo.farm"),
+                443,
+                Some("https://moooo.farm/api/herd?tag=dairy"),
+            ),
+            (
+                Some("moooo.farm:80"),
+                8443,
+                Some("https://moooo.farm:8443/api/herd?tag=dairy"),
+            ),
+            (
+                Some("[::1]:8080"),
+                443,
+                Some("https://[::1]/api/herd?tag=dairy"),
+            ),
+            (None, 443, None),
+        ] {
+            assert_eq!(
+                https_redirect_location(host, &uri, https_port).as_deref(),
+                expected,
+                "Failed for host: {host:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn metrics_are_public_without_a_token() {
+        assert!(authorize_metrics_scrape(None, &HeaderMap::new()).is_ok());
+    }
+
+    #[test]
+    fn metrics_require_the_configured_token() {
+        let mut authorized = HeaderMap::new();
+        authorized.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_static("Bearer secret"),
+        );
+        assert!(authorize_metrics_scrape(Some("secret"), &authorized).is_ok());
+
+        let mut wrong_token = HeaderMap::new();
+        wrong_token.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_static("Bearer not-secret"),
+        );
+        let mut basic_auth = HeaderMap::new();
+        basic_auth.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_static("Basic c2VjcmV0"),
+        );
+        for headers in [HeaderMap::new(), wrong_token, basic_auth] {
+            let response = authorize_metrics_scrape(Some("secret"), &headers)
+                .expect_err("scrape should have been refused")
+                .into_response();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        }
+    }
+
+    #[test]
+    fn the_audit_is_closed_to_everyone_without_an_owner_token() {
+        let mut authorized = HeaderMap::new();
+        authorized.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_static("Bearer secret"),
+        );
+        let response = authorize_owner(None, &authorized)
+            .expect_err("request should have been refused")
+            .into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        assert!(authorize_owner(Some("secret"), &authorized).is_ok());
+        let response = authorize_owner(Some("secret"), &HeaderMap::new())
+            .expect_err("request should have been refused")
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn urls_can_be_validated_before_suggesting_a_cow() -> Result<()> {
+        let config = test_config()?;
+        let validate = |deps: TestDeps, url: &str| {
+            let uri = format!(
+                "/api/validate-url?url={}",
+                url::form_urlencoded::byte_serialize(url.as_bytes()).collect::<String>()
+            );
+            send(&config, deps, Request::builder().uri(uri))
+        };
+
+        for (url, expected_status) in [
+            ("https://example.com/cow.txt", StatusCode::OK),
+            ("https://example.com/", StatusCode::OK),
+            ("https://example.com/cow.html", StatusCode::BAD_REQUEST),
+            ("gopher://example.com/cow.txt", StatusCode::BAD_REQUEST),
+            ("http://10.0.0.1/cow.txt", StatusCode::BAD_REQUEST),
+            ("example.com", StatusCode::BAD_REQUEST),
+        ] {
+            let response = validate(TestDeps::new(), url).await?;
+            assert_eq!(response.status(), expected_status, "{url}");
+        }
+
+        let deps =
+            TestDeps::new().with_host_policy(HostPolicy::new(vec!["10.0.0.0/8".parse()?], vec![]));
+        let response = validate(deps, "http://10.0.0.1/").await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|err| anyhow::anyhow!(err))?;
+        assert!(String::from_utf8_lossy(&bytes).contains("http://10.0.0.1/cow.txt"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn api_fields_follow_the_configured_naming_convention() -> Result<()> {
+        let cow = domain::Cow::new_from_history(
+            domain::VisibleName::new("https://brave.example.com/cow.txt")?,
+            domain::Character::Brave,
+            Some(DateTime::now()),
+            Some(DateTime::now()),
+            Some(DateTime::now()),
+        );
+        let herd: app::Herd =
+            domain::CensoredHerd::new(vec![domain::CensoredCow::new(&cow)?]).try_into()?;
+        let fields = |naming: JsonFieldNaming| {
+            let herd = APIHerd::new(&herd, None, &DisplayFormat::default());
+            async move {
+                let response = APIJson::new(herd, naming).into_response();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .map_err(|err| anyhow::anyhow!(err))?;
+                let value: serde_json::Value =
+                    serde_json::from_slice(&bytes).map_err(|err| anyhow::anyhow!(err))?;
+                let mut fields: Vec<String> = value["cows"][0]
+                    .as_object()
+                    .map(|cow| cow.keys().cloned().collect())
+                    .unwrap_or_default();
+                fields.sort();
+                Ok::<_, Error>(fields)
+            }
+        };
+
+        assert_eq!(
+            fields(JsonFieldNaming::default()).await?,
+            vec![
+                "added_at",
+                "character",
+                "check_interval_seconds",
+                "last_seen",
+                "missing_reason",
+                "name",
+                "next_check_at",
+                "nickname",
+                "status",
+                "tags",
+                "unexpected_character"
+            ]
+        );
+        assert_eq!(
+            fields(JsonFieldNaming::CamelCase).await?,
+            vec![
+                "addedAt",
+                "character",
+                "checkIntervalSeconds",
+                "lastSeen",
+                "missingReason",
+                "name",
+                "nextCheckAt",
+                "nickname",
+                "status",
+                "tags",
+                "unexpectedCharacter"
+            ]
+        );
+        Ok(())
+    }
+
+    #[derive(Template)]
+    #[template(source = r#"{{ "missing.css"|versioned }}"#, ext = "html")]
+    struct BrokenTemplate {}
+
+    #[test]
+    fn template_errors_are_logged_but_not_shown() {
+        let logger = crate::fixtures::RecordingLogger::install();
+
+        let err = BrokenTemplate {}
+            .render()
+            .expect_err("rendering should have failed");
+        let response = AppError::from(err).into_response();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(logger.records().iter().any(|record| {
+            record.starts_with("ERROR failed to render a template")
+                && record.contains("unknown static asset: missing.css")
+        }));
+    }
+
+    // the handlers behind every route, the tests only care about what happens around them
+    #[derive(Clone)]
+    struct StubHandler;
+
+    #[async_trait::async_trait]
+    impl GetHerdHandler for StubHandler {
+        async fn handle(&self) -> Result<app::Herd> {
+            domain::CensoredHerd::new(vec![]).try_into()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl GetCowHandler for StubHandler {
+        async fn handle(&self, _: &app::GetCow) -> Result<Option<app::Cow>> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl GetCowBodyHandler for StubHandler {
+        async fn handle(&self, _: &app::GetCowBody) -> Result<Option<String>> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl GetCowAuditHandler for StubHandler {
+        async fn handle(
+            &self,
+            _: &app::GetCowAudit,
+        ) -> Result<Option<Vec<domain::CharacterChange>>> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl GetCowNotesHandler for StubHandler {
+        async fn handle(&self, _: &app::GetCowNotes) -> Result<Option<String>> {
+            Ok(Some("ask bob".to_string()))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl GetInsightsHandler for StubHandler {
+        async fn handle(&self) -> Result<app::Insights> {
+            Err(Error::Unknown(anyhow::anyhow!("not stubbed")))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl GetStatusHandler for StubHandler {
+        async fn handle(&self) -> Result<app::LastUpdate> {
+            Err(Error::Unknown(anyhow::anyhow!("not stubbed")))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl RefreshCowHandler for StubHandler {
+        async fn handle(&self, _: &app::RefreshCow) -> Result<app::Cow> {
+            Err(Error::NotFound("cow does not exist".to_string()))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ReloadConfigHandler for StubHandler {
+        async fn handle(&self) -> Result<app::ReloadSummary> {
+            Ok(app::ReloadSummary::default())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ChangeLogLevelHandler for StubHandler {
+        async fn handle(&self, _: &app::ChangeLogLevel) -> Result<log::LevelFilter> {
+            Ok(log::LevelFilter::Info)
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestDeps {
+        metrics: prometheus::Registry,
+        display_format: DisplayFormat,
+        owner_bearer_token: Option<String>,
+        host_policy: HostPolicy,
+        site: Site,
+    }
+
+    impl TestDeps {
+        fn new() -> Self {
+            Self {
+                metrics: prometheus::Registry::new(),
+                display_format: DisplayFormat::default(),
+                owner_bearer_token: None,
+                host_policy: HostPolicy::default(),
+                site: Site::new(),
+            }
+        }
+
+        fn with_site(mut self, site: Site) -> Self {
+            self.site = site;
+            self
+        }
+
+        fn with_host_policy(mut self, host_policy: HostPolicy) -> Self {
+            self.host_policy = host_policy;
+            self
+        }
+
+        fn with_owner_bearer_token(mut self, token: &str) -> Self {
+            self.owner_bearer_token = Some(token.to_string());
+            self
+        }
+    }
+
+    impl Deps for TestDeps {
+        fn get_herd_handler(&self) -> &impl GetHerdHandler {
+            &StubHandler
+        }
+
+        fn get_cow_handler(&self) -> &impl GetCowHandler {
+            &StubHandler
+        }
+
+        fn get_cow_body_handler(&self) -> &impl GetCowBodyHandler {
+            &StubHandler
+        }
+
+        fn get_cow_audit_handler(&self) -> &impl GetCowAuditHandler {
+            &StubHandler
+        }
+
+        fn get_cow_notes_handler(&self) -> &impl GetCowNotesHandler {
+            &StubHandler
+        }
+
+        fn get_insights_handler(&self) -> &impl GetInsightsHandler {
+            &StubHandler
+        }
+
+        fn get_status_handler(&self) -> &impl GetStatusHandler {
+            &StubHandler
+        }
+
+        fn refresh_cow_handler(&self) -> &impl RefreshCowHandler {
+            &StubHandler
+        }
+
+        fn reload_config_handler(&self) -> &impl ReloadConfigHandler {
+            &StubHandler
+        }
+
+        fn change_log_level_handler(&self) -> &impl ChangeLogLevelHandler {
+            &StubHandler
+        }
+
+        fn metrics(&self) -> &prometheus::Registry {
+            &self.metrics
+        }
+
+        fn display_format(&self) -> &DisplayFormat {
+            &self.display_format
+        }
+
+        fn metrics_bearer_token(&self) -> Option<&str> {
+            None
+        }
+
+        fn owner_bearer_token(&self) -> Option<&str> {
+            self.owner_bearer_token.as_deref()
+        }
+
+        fn json_field_naming(&self) -> JsonFieldNaming {
+            JsonFieldNaming::default()
+        }
+
+        fn site(&self) -> &Site {
+            &self.site
+        }
+
+        fn host_policy(&self) -> &HostPolicy {
+            &self.host_policy
+        }
+    }
+
+    fn test_config() -> Result<config::Config> {
+        config::Config::new(
+            "0.0.0.0:8080",
+            "0.0.0.0:9090",
+            Environment::Production,
+            "/moooodotfarm.db",
+        )
+    }
+
+    async fn send(
+        config: &config::Config,
+        deps: TestDeps,
+        request: http::request::Builder,
+    ) -> Result<Response> {
+        use tower::ServiceExt;
+
+        let request = request
+            .body(axum::body::Body::empty())
+            .map_err(|err| anyhow::anyhow!(err))?;
+        let response = Server::new(config, deps)
+            .app()
+            .oneshot(request)
+            .await
+            .map_err(|err| anyhow::anyhow!(err))?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_may_reload_the_config() -> Result<()> {
+        let config = test_config()?;
+        let reload = |token: Option<&str>| {
+            let request = Request::builder().method("POST").uri("/admin/reload");
+            match token {
+                Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {token}")),
+                None => request,
+            }
+        };
+
+        let response = send(&config, TestDeps::new(), reload(Some("secret"))).await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let deps = TestDeps::new().with_owner_bearer_token("secret");
+        for (token, expected_status) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some("wrong"), StatusCode::UNAUTHORIZED),
+            (Some("secret"), StatusCode::OK),
+        ] {
+            let response = send(&config, deps.clone(), reload(token)).await?;
+            assert_eq!(response.status(), expected_status, "{token:?}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_may_read_the_notes() -> Result<()> {
+        let config = test_config()?;
+        let get_notes = |token: Option<&str>| {
+            let request =
+                Request::builder().uri("/api/herd/https%3A%2F%2Fshy.example.com%2Fcow.txt/notes");
+            match token {
+                Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {token}")),
+                None => request,
+            }
+        };
+
+        let response = send(&config, TestDeps::new(), get_notes(Some("secret"))).await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let deps = TestDeps::new().with_owner_bearer_token("secret");
+        for token in [None, Some("wrong")] {
+            let response = send(&config, deps.clone(), get_notes(token)).await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{token:?}");
+        }
+
+        let response = send(&config, deps, get_notes(Some("secret"))).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(|err| anyhow::anyhow!(err))?;
+        assert_eq!(String::from_utf8_lossy(&body), r#"{"notes":"ask bob"}"#);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_may_change_the_log_level() -> Result<()> {
+        let config = test_config()?;
+        let change_log_level = |token: Option<&str>| {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/admin/loglevel?level=debug");
+            match token {
+                Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {token}")),
+                None => request,
+            }
+        };
+
+        let response = send(&config, TestDeps::new(), change_log_level(Some("secret"))).await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let deps = TestDeps::new().with_owner_bearer_token("secret");
+        for (token, expected_status) in [
+            (None, StatusCode::UNAUTHORIZED),
+            (Some("wrong"), StatusCode::UNAUTHORIZED),
+            (Some("secret"), StatusCode::OK),
+        ] {
+            let response = send(&config, deps.clone(), change_log_level(token)).await?;
+            assert_eq!(response.status(), expected_status, "{token:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn refreshes_are_limited_per_client() {
+        let limiter = RefreshLimiter::new(Duration::from_secs(10));
+        let client = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let other_client = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+        let now = Instant::now();
+
+        assert!(limiter.try_refresh(client, now));
+        assert!(!limiter.try_refresh(client, now + Duration::from_secs(9)));
+        assert!(limiter.try_refresh(other_client, now + Duration::from_secs(9)));
+        assert!(limiter.try_refresh(client, now + Duration::from_secs(10)));
+    }
+
+    #[tokio::test]
+    async fn too_many_refreshes_are_refused() -> Result<()> {
+        use tower::ServiceExt;
+
+        let config = test_config()?;
+        let app = Server::new(&config, TestDeps::new()).app();
+        let refresh = || {
+            Request::builder()
+                .method("POST")
+                .uri("/api/refresh")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(
+                    r#"{"name":"https://example.com/cow.txt"}"#,
+                ))
+                .map_err(|err| anyhow::anyhow!(err))
+        };
+
+        let response = app.clone().oneshot(refresh()?).await.unwrap();
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = app.oneshot(refresh()?).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn herd_txt_is_only_served_when_enabled() -> Result<()> {
+        let herd_txt = || Request::builder().uri("/herd.txt");
+
+        let response = send(&test_config()?, TestDeps::new(), herd_txt()).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let config = test_config()?.with_serve_herd_txt(true);
+        let response = send(&config, TestDeps::new(), herd_txt()).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+use std::fmt::Write;
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+// openmetrics requires counter samples to end with `_total` while the metadata uses the name
+// without it, counters which weren't named that way get the suffix added to their samples
+pub fn encode(families: &[MetricFamily]) -> String {
+    let mut out = String::new();
+    for family in families {
+        let (name, kind) = match family.get_field_type() {
+            MetricType::COUNTER => (
+                family
+                    .name()
+                    .strip_suffix("_total")
+                    .unwrap_or(family.name()),
+                "counter",
+            ),
+            MetricType::GAUGE => (family.name(), "gauge"),
+            MetricType::HISTOGRAM => (family.name(), "histogram"),
+            MetricType::SUMMARY => (family.name(), "summary"),
+            MetricType::UNTYPED => (family.name(), "unknown"),
+        };
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        let _ = writeln!(out, "# HELP {name} {}", escape(family.help()));
+
+        for metric in family.get_metric() {
+            match family.get_field_type() {
+                MetricType::COUNTER => {
+                    let value = metric.get_counter().value();
+                    write_sample(&mut out, &format!("{name}_total"), metric, None, value);
+                }
+                MetricType::GAUGE => {
+                    let value = metric.get_gauge().value();
+                    write_sample(&mut out, name, metric, None, value);
+                }
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let bucket_name = format!("{name}_bucket");
+                    let mut inf_seen = false;
+                    for bucket in histogram.get_bucket() {
+                        let upper_bound = bucket.upper_bound();
+                        inf_seen |= upper_bound == f64::INFINITY;
+                        write_sample(
+                            &mut out,
+                            &bucket_name,
+                            metric,
+                            Some(("le", &format_value(upper_bound))),
+                            bucket.cumulative_count() as f64,
+                        );
+                    }
+                    if !inf_seen {
+                        write_sample(
+                            &mut out,
+                            &bucket_name,
+                            metric,
+                            Some(("le", "+Inf")),
+                            histogram.get_sample_count() as f64,
+                        );
+                    }
+                    let count = histogram.get_sample_count() as f64;
+                    write_sample(&mut out, &format!("{name}_count"), metric, None, count);
+                    let sum = histogram.get_sample_sum();
+                    write_sample(&mut out, &format!("{name}_sum"), metric, None, sum);
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        write_sample(
+                            &mut out,
+                            name,
+                            metric,
+                            Some(("quantile", &format_value(quantile.quantile()))),
+                            quantile.value(),
+                        );
+                    }
+                    let count = summary.sample_count() as f64;
+                    write_sample(&mut out, &format!("{name}_count"), metric, None, count);
+                    let sum = summary.sample_sum();
+                    write_sample(&mut out, &format!("{name}_sum"), metric, None, sum);
+                }
+                MetricType::UNTYPED => {
+                    let value = metric.untyped.value();
+                    write_sample(&mut out, name, metric, None, value);
+                }
+            }
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+fn write_sample(
+    out: &mut String,
+    name: &str,
+    metric: &Metric,
+    extra_label: Option<(&str, &str)>,
+    value: f64,
+) {
+    let labels: Vec<String> = metric
+        .get_label()
+        .iter()
+        .map(|label| (label.name(), label.value()))
+        .chain(extra_label)
+        .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+        .collect();
+    out.push_str(name);
+    if !labels.is_empty() {
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {}", format_value(value));
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        format!("{value:?}")
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
@@ -0,0 +1,77 @@
+const grassSvg = `<svg viewBox="0 0 40 50" fill="none">
+    <path d="M8 50 Q10 30, 5 10" stroke="var(--grass)" stroke-width="3" stroke-linecap="round"/>
+    <path d="M15 50 Q17 25, 20 5" stroke="var(--grass-dark)" stroke-width="3" stroke-linecap="round"/>
+    <path d="M22 50 Q20 35, 28 15" stroke="var(--grass-light)" stroke-width="3" stroke-linecap="round"/>
+</svg>`;
+
+const baseWidth = 40;
+const widthVariance = 10;
+
+const baseHeight = 50;
+const heightVariance = 10;
+
+const baseRotate = -5;
+const rotationVariance = 20;
+
+const topVariancePct = 0.10;
+
+const baseLeftRight = 10;
+const leftRightVariance = 5;
+
+const viewHeight = window.innerHeight || 1;
+const pageHeight = Math.max(
+    document.body.scrollHeight,
+    document.documentElement.scrollHeight,
+    viewHeight
+);
+const rows = Math.max(1, Math.ceil(pageHeight / viewHeight));
+const grassPositions = [];
+
+const randomize = (middle, variance) => {
+    const min = middle - variance;
+    const max = middle + variance;
+    return min + Math.random() * (max - min);
+};
+
+for (let row = 0; row < rows; row += 1) {
+    const rowTop = row * viewHeight;
+
+    for (let i = 0; i < 3; i += 1) {
+        const baseTopPx = rowTop + [0.15, 0.50, 0.85][i] * viewHeight;
+
+        const leftTopPx = randomize(baseTopPx, topVariancePct * viewHeight);
+        const leftHeight = Math.round(randomize(baseHeight, heightVariance));
+        if (leftTopPx + leftHeight <= pageHeight) {
+            grassPositions.push({
+                left: `${randomize(baseLeftRight, leftRightVariance)}%`,
+                width: Math.round(randomize(baseWidth, widthVariance)),
+                height: leftHeight,
+                rotate: randomize(baseRotate, rotationVariance),
+                top: `${leftTopPx}px`
+            });
+        }
+
+        const rightTopPx = randomize(baseTopPx, topVariancePct * viewHeight);
+        const rightHeight = Math.round(randomize(baseHeight, heightVariance));
+        if (rightTopPx + rightHeight <= pageHeight) {
+            grassPositions.push({
+                right: `${randomize(baseLeftRight, leftRightVariance)}%`,
+                width: Math.round(randomize(baseWidth, widthVariance)),
+                height: rightHeight,
+                rotate: randomize(baseRotate, rotationVariance),
+                top: `${rightTopPx}px`
+            });
+        }
+    }
+}
+
+const container = document.getElementById('grassContainer');
+grassPositions.forEach(pos => {
+    const tuft = document.createElement('div');
+    tuft.className = 'grass-tuft';
+    tuft.style.cssText = `width: ${pos.width}px; height: ${pos.height}px; transform: rotate(${pos.rotate}deg);` +
+        (pos.left ? `left: ${pos.left};` : `right: ${pos.right};`) +
+        `top: ${pos.top};`;
+    tuft.innerHTML = grassSvg;
+    container.appendChild(tuft);
+});
@@ -1,33 +1,379 @@
-use crate::app::UpdateHandler;
-use log::{debug, error};
+use crate::app::{CompactDatabaseHandler, DiscoverCowsHandler, UpdateHandler};
+use crate::domain::active_hours::ActiveHours;
+use crate::domain::time::DateTime;
+use crate::errors::Result;
+use log::{debug, error, info};
+use rand::Rng;
 use std::time::Duration;
 use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
 
-static UPDATE_EVERY: Duration = Duration::from_secs(60 * 5);
+pub static UPDATE_EVERY: Duration = Duration::from_secs(60 * 5);
+static DISCOVER_COWS_EVERY: Duration = Duration::from_secs(60 * 15);
 
 pub struct UpdateTimer<H: UpdateHandler> {
     handler: H,
+    cancellation_token: CancellationToken,
+    max_first_update_delay: Duration,
+    active_hours: Option<ActiveHours>,
 }
 
 impl<H> UpdateTimer<H>
 where
     H: UpdateHandler,
 {
-    pub fn new(handler: H) -> Self {
-        Self { handler }
+    pub fn new(handler: H, cancellation_token: CancellationToken) -> Self {
+        Self {
+            handler,
+            cancellation_token,
+            max_first_update_delay: Duration::ZERO,
+            active_hours: None,
+        }
+    }
+
+    // spreads the first updates of instances which were started together
+    pub fn with_max_first_update_delay(mut self, max_first_update_delay: Duration) -> Self {
+        self.max_first_update_delay = max_first_update_delay;
+        self
+    }
+
+    // the server keeps serving the herd outside of the active hours, only the updates are skipped
+    pub fn with_active_hours(mut self, active_hours: Option<ActiveHours>) -> Self {
+        self.active_hours = active_hours;
+        self
     }
 
     pub async fn run(&self) {
+        let first_update_delay =
+            rand::thread_rng().gen_range(Duration::ZERO..=self.max_first_update_delay);
+        info!("delaying the first update by {:?}", first_update_delay);
+        tokio::select! {
+            _ = sleep(first_update_delay) => {},
+            _ = self.cancellation_token.cancelled() => {
+                debug!("update timer stopped");
+                return;
+            },
+        }
         loop {
+            if self
+                .active_hours
+                .is_some_and(|active_hours| !active_hours.contains(&DateTime::now()))
+            {
+                debug!("outside of the active hours, skipping the update");
+                tokio::select! {
+                    _ = sleep(UPDATE_EVERY) => continue,
+                    _ = self.cancellation_token.cancelled() => break,
+                }
+            }
+            // an in-flight update is dropped when cancelled, cows which weren't checked yet will be
+            // checked during the next run
+            tokio::select! {
+                result = self.handler.handle() => match result {
+                    Ok(_) => {
+                        debug!("executed update timer");
+                    }
+                    Err(err) => {
+                        error!("error executing update timer: {}", err);
+                    }
+                },
+                _ = self.cancellation_token.cancelled() => break,
+            }
+            tokio::select! {
+                _ = sleep(UPDATE_EVERY) => {},
+                _ = self.cancellation_token.cancelled() => break,
+            }
+        }
+        debug!("update timer stopped");
+    }
+}
+
+// calls f right away and then after every interval until cancelled or until f fails
+pub async fn repeat_until_cancelled<F, Fut>(
+    interval: Duration,
+    cancellation_token: &CancellationToken,
+    mut f: F,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    loop {
+        tokio::select! {
+            result = f() => result?,
+            _ = cancellation_token.cancelled() => return Ok(()),
+        }
+        tokio::select! {
+            _ = sleep(interval) => {},
+            _ = cancellation_token.cancelled() => return Ok(()),
+        }
+    }
+}
+
+pub struct CompactDatabaseTimer<H: CompactDatabaseHandler> {
+    handler: H,
+    interval: Duration,
+    cancellation_token: CancellationToken,
+}
+
+impl<H> CompactDatabaseTimer<H>
+where
+    H: CompactDatabaseHandler,
+{
+    pub fn new(handler: H, interval: Duration, cancellation_token: CancellationToken) -> Self {
+        Self {
+            handler,
+            interval,
+            cancellation_token,
+        }
+    }
+
+    pub async fn run(&self) {
+        loop {
+            tokio::select! {
+                _ = sleep(self.interval) => {},
+                _ = self.cancellation_token.cancelled() => break,
+            }
+            // a compaction which already started is finished as it can't be interrupted anyway
             match self.handler.handle().await {
                 Ok(_) => {
-                    debug!("executed update timer");
+                    debug!("executed compact database timer");
                 }
                 Err(err) => {
-                    error!("error executing update timer: {}", err);
+                    error!("error executing compact database timer: {}", err);
                 }
             }
-            sleep(UPDATE_EVERY).await;
         }
+        debug!("compact database timer stopped");
+    }
+}
+
+pub struct DiscoverCowsTimer<H: DiscoverCowsHandler> {
+    handler: H,
+    cancellation_token: CancellationToken,
+}
+
+impl<H> DiscoverCowsTimer<H>
+where
+    H: DiscoverCowsHandler,
+{
+    pub fn new(handler: H, cancellation_token: CancellationToken) -> Self {
+        Self {
+            handler,
+            cancellation_token,
+        }
+    }
+
+    pub async fn run(&self) {
+        loop {
+            tokio::select! {
+                result = self.handler.handle() => match result {
+                    Ok(added) => {
+                        debug!("executed discover cows timer, added {} cows", added);
+                    }
+                    Err(err) => {
+                        error!("error executing discover cows timer: {}", err);
+                    }
+                },
+                _ = self.cancellation_token.cancelled() => break,
+            }
+            tokio::select! {
+                _ = sleep(DISCOVER_COWS_EVERY) => {},
+                _ = self.cancellation_token.cancelled() => break,
+            }
+        }
+        debug!("discover cows timer stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct NeverEndingUpdateHandler;
+
+    #[async_trait]
+    impl UpdateHandler for NeverEndingUpdateHandler {
+        async fn handle(&self) -> Result<()> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn update_timer_stops_when_cancelled() -> Result<()> {
+        let cancellation_token = CancellationToken::new();
+        let timer = UpdateTimer::new(NeverEndingUpdateHandler, cancellation_token.clone());
+
+        let run = tokio::spawn(async move { timer.run().await });
+        cancellation_token.cancel();
+
+        tokio::time::timeout(Duration::from_secs(5), run)
+            .await
+            .map_err(|_| anyhow::anyhow!("timer didn't stop"))?
+            .map_err(|err| anyhow::anyhow!(err))?;
+        Ok(())
+    }
+
+    struct RecordingUpdateHandler {
+        called_at: Arc<std::sync::Mutex<Option<tokio::time::Instant>>>,
+        cancellation_token: CancellationToken,
+    }
+
+    #[async_trait]
+    impl UpdateHandler for RecordingUpdateHandler {
+        async fn handle(&self) -> Result<()> {
+            self.called_at
+                .lock()
+                .unwrap()
+                .get_or_insert_with(tokio::time::Instant::now);
+            self.cancellation_token.cancel();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn update_timer_delays_the_first_update_within_the_bound() -> Result<()> {
+        let max_first_update_delay = Duration::from_millis(200);
+        let cancellation_token = CancellationToken::new();
+        let called_at = Arc::new(std::sync::Mutex::new(None));
+        let timer = UpdateTimer::new(
+            RecordingUpdateHandler {
+                called_at: called_at.clone(),
+                cancellation_token: cancellation_token.clone(),
+            },
+            cancellation_token,
+        )
+        .with_max_first_update_delay(max_first_update_delay);
+
+        let started_at = tokio::time::Instant::now();
+        tokio::time::timeout(Duration::from_secs(5), timer.run())
+            .await
+            .map_err(|_| anyhow::anyhow!("timer didn't stop"))?;
+
+        let called_at = called_at.lock().unwrap().expect("update wasn't run");
+        // some slack for scheduling
+        assert!(called_at - started_at < max_first_update_delay + Duration::from_millis(100));
+        Ok(())
+    }
+
+    // a window of a few hours around the current hour so that the tests don't depend on the time
+    fn active_hours_starting_in(hours: u32) -> Result<ActiveHours> {
+        let start = (DateTime::now().hour() + 24 - 1 + hours) % 24;
+        ActiveHours::new(&format!("{:02}:00-{:02}:00", start, (start + 3) % 24), None)
+    }
+
+    #[tokio::test]
+    async fn update_timer_runs_updates_within_the_active_hours() -> Result<()> {
+        let cancellation_token = CancellationToken::new();
+        let called_at = Arc::new(std::sync::Mutex::new(None));
+        let timer = UpdateTimer::new(
+            RecordingUpdateHandler {
+                called_at: called_at.clone(),
+                cancellation_token: cancellation_token.clone(),
+            },
+            cancellation_token,
+        )
+        .with_active_hours(Some(active_hours_starting_in(0)?));
+
+        tokio::time::timeout(Duration::from_secs(5), timer.run())
+            .await
+            .map_err(|_| anyhow::anyhow!("timer didn't stop"))?;
+
+        assert!(called_at.lock().unwrap().is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_timer_skips_updates_outside_of_the_active_hours() -> Result<()> {
+        let cancellation_token = CancellationToken::new();
+        let called_at = Arc::new(std::sync::Mutex::new(None));
+        let timer = UpdateTimer::new(
+            RecordingUpdateHandler {
+                called_at: called_at.clone(),
+                cancellation_token: cancellation_token.clone(),
+            },
+            cancellation_token.clone(),
+        )
+        .with_active_hours(Some(active_hours_starting_in(6)?));
+
+        let run = tokio::spawn(async move { timer.run().await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        cancellation_token.cancel();
+        tokio::time::timeout(Duration::from_secs(5), run)
+            .await
+            .map_err(|_| anyhow::anyhow!("timer didn't stop"))?
+            .map_err(|err| anyhow::anyhow!(err))?;
+
+        assert!(called_at.lock().unwrap().is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn repeats_until_cancelled() -> Result<()> {
+        let cancellation_token = CancellationToken::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let repeat = {
+            let cancellation_token = cancellation_token.clone();
+            let calls = calls.clone();
+            tokio::spawn(async move {
+                repeat_until_cancelled(Duration::from_millis(10), &cancellation_token, || {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    async { Ok(()) }
+                })
+                .await
+            })
+        };
+        while calls.load(Ordering::SeqCst) < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        cancellation_token.cancel();
+
+        tokio::time::timeout(Duration::from_secs(5), repeat)
+            .await
+            .map_err(|_| anyhow::anyhow!("repeating didn't stop"))?
+            .map_err(|err| anyhow::anyhow!(err))??;
+        Ok(())
+    }
+
+    struct NeverEndingHandler;
+
+    #[async_trait]
+    impl CompactDatabaseHandler for NeverEndingHandler {
+        async fn handle(&self) -> Result<u64> {
+            std::future::pending().await
+        }
+    }
+
+    #[async_trait]
+    impl DiscoverCowsHandler for NeverEndingHandler {
+        async fn handle(&self) -> Result<usize> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn compact_database_and_discover_cows_timers_stop_when_cancelled() -> Result<()> {
+        let cancellation_token = CancellationToken::new();
+        let compact_database_timer = CompactDatabaseTimer::new(
+            NeverEndingHandler,
+            Duration::from_secs(60 * 60),
+            cancellation_token.clone(),
+        );
+        let discover_cows_timer =
+            DiscoverCowsTimer::new(NeverEndingHandler, cancellation_token.clone());
+
+        let run = tokio::spawn(async move {
+            tokio::join!(compact_database_timer.run(), discover_cows_timer.run())
+        });
+        cancellation_token.cancel();
+
+        tokio::time::timeout(Duration::from_secs(5), run)
+            .await
+            .map_err(|_| anyhow::anyhow!("timers didn't stop"))?
+            .map_err(|err| anyhow::anyhow!(err))?;
+        Ok(())
     }
 }